@@ -48,7 +48,7 @@ function resetLayout() {
     }
 }
 
-const DEFAULT_CONTENT = `<svg>
+let DEFAULT_CONTENT = `<svg>
   <!-- Example svgdx document -->
   <rect id="in" wh="20 10" text="input" class="d-softshadow d-fill-azure"/>
   <!-- Try changing the '|h 10' below to '|v 30' or '|V 5' -->
@@ -277,6 +277,22 @@ const textViewer = CodeMirror(document.getElementById('text-output'), {
         update();
     }
 
+    // svgdx-server may provide a different default document (`--assets`)
+    if (window.svgdx_use_server) {
+        fetch('example.svg')
+            .then((response) => response.ok ? response.text() : null)
+            .then((example) => {
+                if (example) {
+                    DEFAULT_CONTENT = example;
+                    if (!savedValue) {
+                        editor.setValue(example);
+                        update();
+                    }
+                }
+            })
+            .catch(() => {});
+    }
+
     editor.on('change', rateLimited(update));
 
     function activeTab() {
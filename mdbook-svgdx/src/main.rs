@@ -0,0 +1,189 @@
+//! mdBook preprocessor rendering svgdx code blocks as inline SVG.
+//!
+//! Installed with `cargo install --path mdbook-svgdx` from the svgdx repository,
+//! and enabled in a book's `book.toml` with:
+//!
+//! ```toml
+//! [preprocessor.svgdx]
+//! # optional settings, named as for `<config>`, applied to every block
+//! theme = "dark"
+//! ```
+//!
+//! Each ```` ```svgdx ```` code block is replaced by the rendered SVG. A
+//! block may start with a comment giving settings for that block only:
+//!
+//! ````markdown
+//! ```svgdx
+//! <!-- svgdx font-size="4" connector-jumps="true" -->
+//! <svg>...</svg>
+//! ```
+//! ````
+//!
+//! As every diagram in a chapter shares the same page, styles are made local
+//! to each diagram (`use-local-styles`) unless configured otherwise. Imports
+//! are relative to the chapter's source file.
+
+use serde_json::Value;
+use svgdx::{filter_markdown, markdown_html, transform_str, TransformConfig};
+
+use std::error::Error;
+use std::io::{stdin, stdout, Read, Write};
+use std::path::Path;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Keys in the preprocessor table of `book.toml` used by mdBook itself.
+const MDBOOK_KEYS: &[&str] = &["command", "renderers", "before", "after", "optional"];
+
+/// Transform config for the book, from the `[preprocessor.svgdx]` table.
+fn book_config(context: &Value) -> Result<TransformConfig> {
+    let mut config = TransformConfig {
+        use_local_styles: true,
+        ..Default::default()
+    };
+    let table = context["config"]["preprocessor"]["svgdx"].as_object();
+    for (key, value) in table.into_iter().flatten() {
+        if MDBOOK_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        config
+            .set(key, &value)
+            .map_err(|e| format!("preprocessor.svgdx.{key}: {e}"))?;
+    }
+    Ok(config)
+}
+
+/// Render the svgdx blocks in each chapter of a list of book items.
+fn process_items(items: &mut [Value], config: &TransformConfig, src_dir: &Path) -> Result<()> {
+    for item in items {
+        let Some(chapter) = item.get_mut("Chapter") else {
+            continue;
+        };
+        let path = chapter["path"].as_str().map(Path::new);
+        let name = path
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|| chapter["name"].as_str().unwrap_or_default().to_string());
+        let mut chapter_config = config.clone();
+        chapter_config.import_dir = Some(
+            src_dir
+                .join(path.and_then(Path::parent).unwrap_or(Path::new("")))
+                .to_string_lossy()
+                .into_owned(),
+        );
+        chapter_config.source_file = Some(name.clone());
+        if let Some(content) = chapter["content"].as_str() {
+            let rendered = filter_markdown(content, |block| {
+                let mut config = chapter_config.clone();
+                let source = block.apply_config(&mut config)?;
+                let svg = transform_str(source, &config)?;
+                Ok(markdown_html(&svg))
+            })
+            .map_err(|e| format!("{name}: {e}"))?;
+            chapter["content"] = Value::String(rendered);
+        }
+        if let Some(sub_items) = chapter.get_mut("sub_items").and_then(Value::as_array_mut) {
+            process_items(sub_items, config, src_dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// Process a book, as given to an mdBook preprocessor.
+///
+/// `input` is the JSON `[context, book]` pair from mdBook, and the updated
+/// book is written to `output` as JSON.
+fn run(input: &mut dyn Read, output: &mut dyn Write) -> Result<()> {
+    let (context, mut book): (Value, Value) = serde_json::from_reader(input)?;
+    let config = book_config(&context)?;
+    let src_dir = Path::new(context["root"].as_str().unwrap_or("."))
+        .join(context["config"]["book"]["src"].as_str().unwrap_or("src"));
+    // mdBook 0.4 uses `sections`; later versions use `items`
+    for key in ["sections", "items"] {
+        if let Some(items) = book.get_mut(key).and_then(Value::as_array_mut) {
+            process_items(items, &config, &src_dir)?;
+        }
+    }
+    serde_json::to_writer(output, &book)?;
+    Ok(())
+}
+
+fn main() {
+    // `mdbook-svgdx supports <renderer>`: output is inline SVG within Markdown,
+    // which only the html renderer displays; others keep the code blocks.
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("supports") {
+        if args.next().as_deref() != Some("html") {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Err(e) = run(&mut stdin().lock(), &mut stdout().lock()) {
+        eprintln!("mdbook-svgdx: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chapter(content: &str, sub_items: Value) -> Value {
+        json!({ "Chapter": {
+            "name": "Intro",
+            "content": content,
+            "path": "intro.md",
+            "sub_items": sub_items,
+        }})
+    }
+
+    fn process(context: Value, book: Value) -> Result<Value> {
+        let input = serde_json::to_vec(&json!([context, book])).unwrap();
+        let mut output = Vec::new();
+        run(&mut input.as_slice(), &mut output)?;
+        Ok(serde_json::from_slice(&output).unwrap())
+    }
+
+    #[test]
+    fn test_preprocess_book() {
+        let context = json!({
+            "root": "/book",
+            "config": {
+                "book": { "src": "src" },
+                "preprocessor": { "svgdx": { "command": "mdbook-svgdx", "border": 2 } },
+            },
+        });
+        let book = json!({ "sections": [
+            chapter(
+                "# Intro\n\n```svgdx\n<svg><rect wh=\"10\"/></svg>\n```\n",
+                json!([chapter(
+                    "```svgdx\n<!-- svgdx border=\"0\" -->\n<svg><rect wh=\"10\"/></svg>\n```\n",
+                    json!([]),
+                )]),
+            ),
+            "Separator",
+        ]});
+        let book = process(context.clone(), book).unwrap();
+        let intro = &book["sections"][0]["Chapter"];
+        let content = intro["content"].as_str().unwrap();
+        assert!(content.starts_with("# Intro\n\n<svg"));
+        assert!(content.contains(r#"viewBox="-2 -2 14 14""#));
+        let sub = intro["sub_items"][0]["Chapter"]["content"]
+            .as_str()
+            .unwrap();
+        assert!(sub.contains(r#"viewBox="0 0 10 10""#));
+        assert_eq!(book["sections"][1], "Separator");
+        assert!(book.get("items").is_none());
+
+        let book = json!({ "items": [chapter("```svgdx\n<rect xy=\"#x\"/>\n```\n", json!([]))] });
+        let err = process(context.clone(), book).unwrap_err();
+        assert!(err.to_string().starts_with("intro.md: "));
+
+        let context = json!({ "config": { "preprocessor": { "svgdx": { "nope": 1 } } } });
+        assert!(process(context, json!({ "sections": [] })).is_err());
+    }
+}
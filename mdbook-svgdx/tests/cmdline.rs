@@ -0,0 +1,31 @@
+use assert_cmd::{crate_name, Command};
+
+#[test]
+fn test_cmdline_supports() {
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["supports", "html"]).assert().success();
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["supports", "epub"]).assert().failure().code(1);
+}
+
+#[test]
+fn test_cmdline_preprocess() {
+    let input = r#"[
+        {"root": "/book", "config": {"book": {"src": "src"}, "preprocessor": {"svgdx": {}}}},
+        {"items": [{"Chapter": {"name": "Intro", "path": "intro.md", "sub_items": [],
+            "content": "```svgdx\n<svg><rect wh=\"10\"/></svg>\n```\n"}}]}
+    ]"#;
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let output = String::from_utf8(output.stdout).expect("non-UTF8");
+    assert!(output.starts_with(r#"{"items":[{"Chapter":"#));
+    assert!(output.contains("<svg"));
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.write_stdin("not json").assert().failure().code(1);
+}
@@ -0,0 +1,250 @@
+//! Alignment and distribution of existing elements.
+//!
+//! `<align>` and `<distribute>` elements adjust the positions of the elements
+//! listed in their `refs` attribute, which must each have an `id`:
+//!
+//! ```xml
+//! <align refs="#a #b #c" mode="cy"/>
+//! <distribute refs="#a #b #c" dir="h" gap="10"/>
+//! ```
+//!
+//! As referenced elements may already have been rendered - and used to
+//! position other elements - the adjustments are only collected as the
+//! document is processed. If there are any, the document is then processed
+//! again with each referenced element translated as it is positioned, so
+//! connectors and relatively positioned elements follow the moved elements.
+//! Adjustments are always derived from the positions in the first pass.
+
+use crate::context::{ElementMap, TransformerContext};
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::OutputList;
+use crate::expression::eval_attr;
+use crate::position::BoundingBox;
+use crate::transform::EventGen;
+use crate::types::{attr_split, strp, ElRef};
+
+use std::str::FromStr;
+
+/// Edge or centre line along which elements are aligned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AlignMode {
+    X1,
+    Cx,
+    X2,
+    Y1,
+    Cy,
+    Y2,
+}
+
+impl FromStr for AlignMode {
+    type Err = SvgdxError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "x1" => Ok(Self::X1),
+            "cx" => Ok(Self::Cx),
+            "x2" => Ok(Self::X2),
+            "y1" => Ok(Self::Y1),
+            "cy" => Ok(Self::Cy),
+            "y2" => Ok(Self::Y2),
+            _ => Err(SvgdxError::InvalidData(format!(
+                "mode must be one of x1, cx, x2, y1, cy, y2, not '{value}'"
+            ))),
+        }
+    }
+}
+
+impl AlignMode {
+    /// Coordinate of the edge or centre of `bb`.
+    fn value(self, bb: &BoundingBox) -> f32 {
+        let (cx, cy) = bb.center();
+        match self {
+            Self::X1 => bb.x1,
+            Self::Cx => cx,
+            Self::X2 => bb.x2,
+            Self::Y1 => bb.y1,
+            Self::Cy => cy,
+            Self::Y2 => bb.y2,
+        }
+    }
+
+    /// Translation moving `bb` so its edge or centre is at `target`.
+    fn delta(self, bb: &BoundingBox, target: f32) -> (f32, f32) {
+        let delta = target - self.value(bb);
+        match self {
+            Self::X1 | Self::Cx | Self::X2 => (delta, 0.),
+            Self::Y1 | Self::Cy | Self::Y2 => (0., delta),
+        }
+    }
+}
+
+/// Ids and bounding boxes of the elements in the `refs` attribute of `el`.
+fn ref_bboxes(el: &SvgElement, context: &TransformerContext) -> Result<Vec<(String, BoundingBox)>> {
+    let refs = el
+        .get_attr("refs")
+        .ok_or_else(|| SvgdxError::MissingAttribute("refs".to_string()))?;
+    let mut result = Vec::new();
+    for elref in attr_split(&eval_attr(&refs, context)) {
+        let elref: ElRef = elref.parse().map_err(|e: SvgdxError| e.in_attr("refs"))?;
+        let target = context
+            .get_element(&elref)
+            .ok_or_else(|| SvgdxError::ReferenceError(elref.clone()))?;
+        let id = target
+            .get_attr("id")
+            .map(|id| eval_attr(&id, context))
+            .ok_or_else(|| {
+                SvgdxError::InvalidData(format!("{}: element {elref} has no id", el.name))
+            })?;
+        // referenced elements must be positioned before they can be adjusted
+        if !context.is_processed(&id) {
+            return Err(SvgdxError::ReferenceError(elref));
+        }
+        let bb = context
+            .get_element_bbox(target)?
+            .ok_or_else(|| SvgdxError::MissingBoundingBox(elref.to_string()))?;
+        result.push((id, bb));
+    }
+    Ok(result)
+}
+
+/// Translations along one axis placing items of the given `(start, end)`
+/// extents in sequence from the start of the first, separated by `gap`.
+///
+/// Without a `gap`, the first and last items are unchanged and the others are
+/// evenly spaced between them.
+fn distribute(extents: &[(f32, f32)], gap: Option<f32>) -> Vec<f32> {
+    let Some(&(first, _)) = extents.first() else {
+        return Vec::new();
+    };
+    let gap = gap.unwrap_or_else(|| {
+        let last = extents.last().map_or(first, |e| e.1);
+        let total: f32 = extents.iter().map(|(start, end)| end - start).sum();
+        (last - first - total) / (extents.len() as f32 - 1.).max(1.)
+    });
+    let mut pos = first;
+    extents
+        .iter()
+        .map(|(start, end)| {
+            let delta = pos - start;
+            pos += end - start + gap;
+            delta
+        })
+        .collect()
+}
+
+/// Generate events for an `<align>` or `<distribute>` element.
+pub(crate) fn align_events(
+    element: &SvgElement,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    if element.name == "align" {
+        AlignElement(element.clone()).generate_events(context)
+    } else {
+        DistributeElement(element.clone()).generate_events(context)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AlignElement(SvgElement);
+
+impl EventGen for AlignElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        if context.applying_adjustments() {
+            return Ok((OutputList::new(), None));
+        }
+        let mode: AlignMode = self
+            .0
+            .get_attr("mode")
+            .ok_or_else(|| SvgdxError::MissingAttribute("mode".to_string()))
+            .and_then(|v| eval_attr(&v, context).parse())
+            .map_err(|e| e.in_attr("mode"))?;
+        let refs = ref_bboxes(&self.0, context)?;
+        // elements are aligned with the first
+        if let Some((_, first)) = refs.first() {
+            let target = mode.value(first);
+            for (id, bb) in &refs {
+                let (dx, dy) = mode.delta(bb, target);
+                context.add_adjustment(id, dx, dy);
+            }
+        }
+        Ok((OutputList::new(), None))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DistributeElement(SvgElement);
+
+impl EventGen for DistributeElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        if context.applying_adjustments() {
+            return Ok((OutputList::new(), None));
+        }
+        let horizontal = match self.0.get_attr("dir").map(|v| eval_attr(&v, context)) {
+            None => true,
+            Some(dir) if dir == "h" => true,
+            Some(dir) if dir == "v" => false,
+            Some(dir) => {
+                return Err(
+                    SvgdxError::InvalidData(format!("dir must be 'h' or 'v', not '{dir}'"))
+                        .in_attr("dir"),
+                )
+            }
+        };
+        let gap = self
+            .0
+            .get_attr("gap")
+            .map(|v| strp(&eval_attr(&v, context)))
+            .transpose()
+            .map_err(|e| e.in_attr("gap"))?;
+        let refs = ref_bboxes(&self.0, context)?;
+        let extents: Vec<_> = refs
+            .iter()
+            .map(|(_, bb)| {
+                if horizontal {
+                    (bb.x1, bb.x2)
+                } else {
+                    (bb.y1, bb.y2)
+                }
+            })
+            .collect();
+        for ((id, _), delta) in refs.iter().zip(distribute(&extents, gap)) {
+            if horizontal {
+                context.add_adjustment(id, delta, 0.);
+            } else {
+                context.add_adjustment(id, 0., delta);
+            }
+        }
+        Ok((OutputList::new(), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_delta() {
+        let bb = BoundingBox::new(10., 20., 30., 60.);
+        assert_eq!(AlignMode::X1.delta(&bb, 0.), (-10., 0.));
+        assert_eq!(AlignMode::Cx.delta(&bb, 0.), (-20., 0.));
+        assert_eq!(AlignMode::Y2.delta(&bb, 100.), (0., 40.));
+        assert!("middle".parse::<AlignMode>().is_err());
+    }
+
+    #[test]
+    fn test_distribute() {
+        let extents = [(0., 10.), (12., 16.), (40., 50.)];
+        assert_eq!(distribute(&extents, Some(5.)), [0., 3., -16.]);
+        // (50 - 24) / 2 = 13 between each
+        assert_eq!(distribute(&extents, None), [0., 11., 0.]);
+        assert_eq!(distribute(&extents[..1], None), [0.]);
+        assert!(distribute(&[], None).is_empty());
+    }
+}
@@ -0,0 +1,252 @@
+//! Layout of group content using named template areas.
+//!
+//! A `<g template-areas="...">` element divides its area into a grid of rows
+//! (separated by `/`) and columns (separated by whitespace), with each cell
+//! naming the area it belongs to, similar to CSS `grid-template-areas`. A `.`
+//! cell belongs to no area. For example:
+//!
+//! ```xml
+//! <g template-areas="header header / nav main" gap="2">
+//!   <rect area="header" wh="40 5"/>
+//!   <rect area="nav" wh="10 20"/>
+//!   <rect area="main" wh="30 30"/>
+//! </g>
+//! ```
+//!
+//! Direct children with an `area` attribute are centred in the named area.
+//! Each column is as wide as the widest element in it, and each row as tall as
+//! the tallest; elements spanning several columns or rows widen or heighten
+//! those tracks evenly if needed. Tracks are separated by the optional `gap`.
+//! The grid's top-left corner is at the group's origin.
+
+use crate::context::TransformerContext;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{tagify_events, InputList, OutputList, Tag};
+use crate::expression::eval_attr;
+use crate::layout::{element_size, POSITION_ATTRS};
+use crate::position::BoundingBox;
+use crate::transform::group_events;
+use crate::types::{fstr, strp};
+
+/// Attributes of the group which are not present in the output.
+const AREAS_ATTRS: &[&str] = &["template-areas", "gap"];
+
+/// A named rectangular region of the grid, in units of rows and columns.
+#[derive(Clone, Debug, PartialEq)]
+struct Area {
+    name: String,
+    row: usize,
+    col: usize,
+    rows: usize,
+    cols: usize,
+}
+
+/// Parse a `template-areas` value into its areas and the number of rows and
+/// columns in the grid.
+fn parse_template(value: &str) -> Result<(Vec<Area>, usize, usize)> {
+    let cells: Vec<Vec<&str>> = value
+        .split('/')
+        .map(|row| row.split_whitespace().collect())
+        .collect();
+    let cols = cells.first().map_or(0, |r| r.len());
+    if cols == 0 || cells.iter().any(|r| r.len() != cols) {
+        return Err(SvgdxError::InvalidData(format!(
+            "template-areas rows must have the same number of cells: '{value}'"
+        )));
+    }
+    let mut areas: Vec<Area> = Vec::new();
+    for (row, cells_row) in cells.iter().enumerate() {
+        for (col, &name) in cells_row.iter().enumerate() {
+            if name == "." || areas.iter().any(|a| a.name == name) {
+                continue;
+            }
+            let cols = cells_row[col..].iter().take_while(|&&c| c == name).count();
+            let rows = cells[row..].iter().take_while(|r| r[col] == name).count();
+            areas.push(Area {
+                name: name.to_string(),
+                row,
+                col,
+                rows,
+                cols,
+            });
+        }
+    }
+    // each area must be exactly the rectangle found above
+    for (row, cells_row) in cells.iter().enumerate() {
+        for (col, &name) in cells_row.iter().enumerate() {
+            let inside = |a: &&Area| {
+                (a.row..a.row + a.rows).contains(&row) && (a.col..a.col + a.cols).contains(&col)
+            };
+            if let Some(area) = areas.iter().find(|a| a.name == name) {
+                if !inside(&area) {
+                    return Err(SvgdxError::InvalidData(format!(
+                        "template-areas area '{name}' is not rectangular"
+                    )));
+                }
+            }
+            if areas.iter().filter(inside).any(|a| a.name != name) {
+                return Err(SvgdxError::InvalidData(format!(
+                    "template-areas area '{name}' is not rectangular"
+                )));
+            }
+        }
+    }
+    Ok((areas, cells.len(), cols))
+}
+
+/// Size tracks (rows or columns) to fit the given `(start, span, size)` items,
+/// returning the size of each track.
+fn track_sizes(count: usize, items: &[(usize, usize, f32)], gap: f32) -> Vec<f32> {
+    let mut sizes = vec![0.; count];
+    let mut items = items.to_vec();
+    items.sort_by_key(|&(_, span, _)| span);
+    for (start, span, size) in items {
+        let tracks = &mut sizes[start..start + span];
+        let current: f32 = tracks.iter().sum::<f32>() + gap * (span - 1) as f32;
+        if size > current {
+            let extra = (size - current) / span as f32;
+            tracks.iter_mut().for_each(|t| *t += extra);
+        }
+    }
+    sizes
+}
+
+/// Offset of the start of each track, followed by the total size.
+fn track_offsets(sizes: &[f32], gap: f32) -> Vec<f32> {
+    let mut offsets = vec![0.];
+    for (idx, size) in sizes.iter().enumerate() {
+        let gap = if idx + 1 < sizes.len() { gap } else { 0. };
+        offsets.push(offsets[idx] + size + gap);
+    }
+    offsets
+}
+
+/// Size of `el` once its attributes are resolved, ignoring any position.
+fn unpositioned_size(el: &SvgElement, context: &TransformerContext) -> Result<(f32, f32)> {
+    let mut el = el.clone();
+    for attr in POSITION_ATTRS {
+        el.pop_attr(attr);
+    }
+    element_size(&el, context)
+}
+
+/// Position `area` children of the given group content according to the
+/// `template-areas` of `element`, generating a group containing the result.
+pub(crate) fn areas_events(
+    element: &SvgElement,
+    content: Option<InputList>,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    let template = element
+        .get_attr("template-areas")
+        .map(|v| eval_attr(&v, context))
+        .unwrap_or_default();
+    let (areas, rows, cols) = parse_template(&template).map_err(|e| e.in_attr("template-areas"))?;
+    let gap = element
+        .get_attr("gap")
+        .map(|v| strp(&eval_attr(&v, context)))
+        .transpose()
+        .map_err(|e| e.in_attr("gap"))?
+        .unwrap_or(0.);
+
+    let content = if let Some(content) = content {
+        let mut placed = Vec::new();
+        for el in tagify_events(content.clone())?
+            .iter()
+            .filter_map(Tag::get_element)
+        {
+            let (Some(name), Some((start, _))) = (el.get_attr("area"), el.event_range) else {
+                continue;
+            };
+            let name = eval_attr(&name, context);
+            let area = areas
+                .iter()
+                .find(|a| a.name == name)
+                .ok_or_else(|| {
+                    SvgdxError::InvalidData(format!("no template area named '{name}'"))
+                        .in_attr("area")
+                })?
+                .clone();
+            let size = unpositioned_size(&el, context)?;
+            placed.push((start, el.name.clone(), area, size));
+        }
+
+        let col_items: Vec<_> = placed
+            .iter()
+            .map(|(_, _, a, (w, _))| (a.col, a.cols, *w))
+            .collect();
+        let row_items: Vec<_> = placed
+            .iter()
+            .map(|(_, _, a, (_, h))| (a.row, a.rows, *h))
+            .collect();
+        let col_offsets = track_offsets(&track_sizes(cols, &col_items, gap), gap);
+        let row_offsets = track_offsets(&track_sizes(rows, &row_items, gap), gap);
+        // centre of the span from the start of the first track to the end of the last
+        let centre = |offsets: &[f32], start: usize, count: usize| {
+            let end = offsets[start + count]
+                - if start + count < offsets.len() - 1 {
+                    gap
+                } else {
+                    0.
+                };
+            (offsets[start] + end) / 2.
+        };
+
+        let mut events = content.events;
+        for (index, name, area, _) in placed {
+            let cx = centre(&col_offsets, area.col, area.cols);
+            let cy = centre(&row_offsets, area.row, area.rows);
+            if let Some(ev) = events.iter_mut().find(|ev| ev.index == index) {
+                *ev = ev
+                    .renamed(&name, &["area"])
+                    .with_attr("cxy", &format!("{} {}", fstr(cx), fstr(cy)));
+            }
+        }
+        Some(InputList { events })
+    } else {
+        None
+    };
+
+    let mut group = element.clone();
+    for attr in AREAS_ATTRS {
+        group.pop_attr(attr);
+    }
+    group_events(&group, content, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        let (areas, rows, cols) = parse_template("a a b / c . b").unwrap();
+        assert_eq!((rows, cols), (2, 3));
+        let spans: Vec<_> = areas
+            .iter()
+            .map(|a| (a.name.as_str(), a.row, a.col, a.rows, a.cols))
+            .collect();
+        assert_eq!(
+            spans,
+            [("a", 0, 0, 1, 2), ("b", 0, 2, 2, 1), ("c", 1, 0, 1, 1)]
+        );
+
+        assert!(parse_template("").is_err());
+        assert!(parse_template("a b / c").is_err());
+        assert!(parse_template("a b a").is_err());
+        assert!(parse_template("a a / a b").is_err());
+        assert!(parse_template("a b / b b").is_err());
+    }
+
+    #[test]
+    fn test_track_sizes() {
+        assert_eq!(track_sizes(2, &[(0, 1, 10.), (1, 1, 5.)], 2.), [10., 5.]);
+        // spanning item widens both tracks evenly
+        assert_eq!(
+            track_sizes(2, &[(0, 2, 22.), (0, 1, 10.), (1, 1, 6.)], 2.),
+            [12., 8.]
+        );
+        assert_eq!(track_offsets(&[12., 8.], 2.), [0., 14., 22.]);
+    }
+}
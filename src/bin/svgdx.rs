@@ -1,9 +1,8 @@
-use svgdx::Result;
-
 use svgdx::cli::{get_config, run};
 
-fn main() -> Result<()> {
-    run(get_config()?)?;
-
-    Ok(())
+fn main() {
+    if let Err(e) = get_config().and_then(run) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
 }
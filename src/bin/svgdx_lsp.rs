@@ -0,0 +1,8 @@
+use std::io::{stdin, stdout};
+
+fn main() {
+    if let Err(e) = svgdx::lsp::run(&mut stdin().lock(), &mut stdout().lock()) {
+        eprintln!("svgdx-lsp: {e}");
+        std::process::exit(1);
+    }
+}
@@ -1,4 +1,5 @@
 use std::net::IpAddr;
+use std::path::PathBuf;
 
 use svgdx::server;
 
@@ -20,11 +21,25 @@ struct Arguments {
     /// Open browser on startup
     #[arg(long)]
     open: bool,
+
+    /// Directory of files overriding the built-in editor assets
+    ///
+    /// Files are matched by path relative to the server root, e.g. `index.html`
+    /// or `static/svgdx-editor.css`. An `example.svg` file in this directory is
+    /// used as the editor's default document.
+    #[arg(long, value_name = "DIR")]
+    assets: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Arguments::parse();
+    if let Some(assets) = &args.assets {
+        if !assets.is_dir() {
+            eprintln!("Asset directory not found: {}", assets.display());
+            std::process::exit(1);
+        }
+    }
     let address = if args.address.is_ipv6() {
         format!("[{}]:{}", args.address, args.port)
     } else {
@@ -47,5 +62,5 @@ async fn main() {
             }
         });
     }
-    server::start_server(Some(&address), tx).await;
+    server::start_server(Some(&address), args.assets, tx).await;
 }
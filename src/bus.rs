@@ -0,0 +1,160 @@
+//! Bus lines for block diagrams.
+//!
+//! A `<bus>` element is drawn as a thick horizontal or vertical line, given
+//! in the same way as a `<line>` element. Elements listed in its `taps`
+//! attribute are connected to the bus with stubs from the middle of the side
+//! of the element facing the bus, meeting the bus at right angles at evenly
+//! spaced points along its length. Taps whose position is not known (e.g.
+//! references other than `#id`) are connected with a straight connector. An optional `signals` attribute
+//! annotates the bus with a slash and the number of signals it carries.
+//!
+//! ```xml
+//! <bus xy1="0 30" xy2="100 30" taps="#cpu #mem #io" signals="8"/>
+//! ```
+
+use crate::context::{ElementMap, TransformerContext};
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{InputEvent, InputList, OutputEvent, OutputList};
+use crate::expression::eval_attr;
+use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::transform::{process_events, EventGen};
+use crate::types::{attr_split, extract_elref, fstr};
+
+/// Attributes of `<bus>` which are not present on the generated line.
+const BUS_ATTRS: &[&str] = &["taps", "signals"];
+/// Class giving the bus line its thickness
+const BUS_CLASS: &str = "d-thicker";
+/// Distance from the start of the bus to the `signals` annotation
+const SLASH_OFFSET: f32 = 4.;
+/// Half the length of the `signals` slash in each of x and y
+const SLASH_SIZE: f32 = 1.5;
+
+#[derive(Debug, Clone)]
+pub struct BusElement(pub SvgElement);
+
+impl BusElement {
+    /// Elements to draw once the position of the bus line is known.
+    ///
+    /// `taps` are the tapped element references, with their bounding boxes if known.
+    fn bus_elements(
+        &self,
+        bus_bb: &BoundingBox,
+        taps: &[(String, Option<BoundingBox>)],
+    ) -> Vec<SvgElement> {
+        let horizontal = bus_bb.width() >= bus_bb.height();
+        let (cx, cy) = bus_bb.center();
+        // Point at fraction `t` along the bus
+        let bus_point = |t: f32| {
+            if horizontal {
+                (bus_bb.x1 + bus_bb.width() * t, cy)
+            } else {
+                (cx, bus_bb.y1 + bus_bb.height() * t)
+            }
+        };
+
+        let mut elements = Vec::new();
+        let count = taps.len() as f32;
+        for (idx, (tap, tap_bb)) in taps.iter().enumerate() {
+            let (x, y) = bus_point((idx as f32 + 0.5) / count);
+            let stub = match tap_bb {
+                Some(bb) => {
+                    let (tap_x, tap_y) = bb.center();
+                    // start of the stub, and its midpoint along the perpendicular axis
+                    let points = if horizontal {
+                        let edge_y = if tap_y < cy { bb.y2 } else { bb.y1 };
+                        let mid_y = (edge_y + cy) / 2.;
+                        [(tap_x, edge_y), (tap_x, mid_y), (x, mid_y), (x, y)]
+                    } else {
+                        let edge_x = if tap_x < cx { bb.x2 } else { bb.x1 };
+                        let mid_x = (edge_x + cx) / 2.;
+                        [(edge_x, tap_y), (mid_x, tap_y), (mid_x, y), (x, y)]
+                    };
+                    let points: Vec<String> = points
+                        .iter()
+                        .map(|(px, py)| format!("{} {}", fstr(*px), fstr(*py)))
+                        .collect();
+                    let mut stub = SvgElement::new("polyline", &[]);
+                    stub.set_attr("points", &points.join(", "));
+                    stub
+                }
+                None => {
+                    let mut stub = SvgElement::new("line", &[]);
+                    stub.set_attr("start", tap);
+                    stub.set_attr("end", &format!("{} {}", fstr(x), fstr(y)));
+                    stub
+                }
+            };
+            elements.push(stub);
+        }
+
+        if let Some(signals) = self.0.get_attr("signals") {
+            let length = bus_bb.width().max(bus_bb.height());
+            let (x, y) = bus_point((SLASH_OFFSET / length).min(0.5));
+            let mut slash = SvgElement::new("line", &[]);
+            slash.set_attr("x1", &fstr(x - SLASH_SIZE));
+            slash.set_attr("y1", &fstr(y + SLASH_SIZE));
+            slash.set_attr("x2", &fstr(x + SLASH_SIZE));
+            slash.set_attr("y2", &fstr(y - SLASH_SIZE));
+            let mut label = SvgElement::new("text", &[]);
+            label.set_attr(
+                "xy",
+                &format!("{} {}", fstr(x + SLASH_SIZE), fstr(y - SLASH_SIZE)),
+            );
+            label.set_attr("text", &signals);
+            label.set_attr("text-loc", if horizontal { "tr" } else { "br" });
+            elements.push(slash);
+            elements.push(label);
+        }
+        elements
+    }
+}
+
+impl EventGen for BusElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let mut line = self.0.clone();
+        line.name = "line".to_string();
+        for attr in BUS_ATTRS {
+            line.pop_attr(attr);
+        }
+        line.add_class(BUS_CLASS);
+        let mut bus_events = InputList::new();
+        bus_events.push(InputEvent::from(OutputEvent::Empty(line)));
+        let (mut events, bus_bb) = process_events(bus_events, context)?;
+        let bus_bb = bus_bb.ok_or_else(|| SvgdxError::MissingBoundingBox(self.0.to_string()))?;
+
+        let mut taps = Vec::new();
+        for tap in attr_split(&eval_attr(
+            &self.0.get_attr("taps").unwrap_or_default(),
+            context,
+        )) {
+            // Element bounding boxes are only available for bare references
+            // to elements which have already been processed.
+            let tap_bb = match extract_elref(&tap) {
+                Ok((elref, "")) => match context.get_element(&elref) {
+                    Some(el) => context.get_element_bbox(el)?,
+                    None => None,
+                },
+                _ => None,
+            };
+            taps.push((tap, tap_bb));
+        }
+        let mut extra = InputList::new();
+        for el in self.bus_elements(&bus_bb, &taps) {
+            extra.push(InputEvent::from(OutputEvent::Text("\n".to_string())));
+            extra.push(InputEvent::from(OutputEvent::Empty(el)));
+        }
+        let (extra_events, extra_bb) = process_events(extra, context)?;
+        events.extend(&extra_events);
+
+        let mut bbox = BoundingBoxBuilder::new();
+        bbox.extend(bus_bb);
+        if let Some(bb) = extra_bb {
+            bbox.extend(bb);
+        }
+        Ok((events, bbox.build()))
+    }
+}
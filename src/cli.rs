@@ -1,17 +1,35 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use notify::RecursiveMode;
+use notify::{RecursiveMode, Watcher};
 use notify_debouncer_mini::new_debouncer;
-use std::{path::Path, sync::mpsc::channel, time::Duration};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::{Duration, SystemTime};
 
-use crate::errors::{Result, SvgdxError};
+use crate::errors::{explain, Result, SvgdxError};
+use crate::events::InputList;
+use crate::formatter::format_source;
+use crate::import::{import_path, import_sources};
+use crate::markdown::markdown_html;
+use crate::migrate::migrate_source;
+use crate::stats::TransformStats;
+#[cfg(feature = "png")]
+use crate::svg_png;
 use crate::themes::ThemeType;
-use crate::{transform_file, TransformConfig};
+use crate::{
+    filter_markdown, input_reader, svg_compose, svg_data_uri, svg_diff, svg_geometry,
+    svg_html_fragment, svg_outline, svg_skeleton, transform_str_full, validate_svg, write_output,
+    ComposeLayout, TransformConfig,
+};
 
 /// Command line arguments
 #[derive(Parser)]
-#[command(author, version, about, long_about=None)] // Read from Cargo.toml
+#[command(author, version, about, long_about=None, args_conflicts_with_subcommands = true)] // Read from Cargo.toml
 struct Arguments {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// File to process ('-' for stdin)
     #[arg(default_value = "-")]
     file: String,
@@ -20,7 +38,26 @@ struct Arguments {
     #[arg(short, long, default_value = "-")]
     output: String,
 
+    /// Config file listing further outputs [default: svgdx.toml alongside FILE, if present]
+    ///
+    /// Each `[[output]]` table gives an output `file`, an optional `format` (by default
+    /// derived from the file extension), and any settings which should override those
+    /// from the command line, e.g. `theme = "dark"`. All outputs are regenerated
+    /// together with `-w`.
+    #[arg(long, value_name = "FILE")]
+    config_file: Option<String>,
+
+    /// Write one output file per build step
+    ///
+    /// Elements with a `step` attribute are hidden in the outputs for earlier steps.
+    /// The step number is appended to the output file name, e.g. `-o slides.svg`
+    /// writes `slides-1.svg`, `slides-2.svg` and so on.
+    #[arg(long)]
+    steps: bool,
+
     /// Watch file for changes; update output on change. (FILE must be given)
+    ///
+    /// Files imported with `<import>` elements are also watched.
     #[arg(short, long, requires = "file")]
     watch: bool,
 
@@ -28,6 +65,85 @@ struct Arguments {
     #[arg(long)]
     debug: bool,
 
+    /// Print a summary of processing statistics to stderr
+    ///
+    /// Includes counts of elements processed, connectors routed and styles
+    /// generated, and timing of each processing phase.
+    #[arg(long)]
+    stats: bool,
+
+    /// Write phase timings to the given file as Chrome trace-event JSON
+    #[cfg(feature = "profiling")]
+    #[arg(long, value_name = "FILE")]
+    trace_file: Option<String>,
+
+    /// Print a description of the given error code (e.g. E0101) and exit
+    #[arg(long, value_name = "CODE")]
+    explain: Option<String>,
+
+    /// Name of the file being read from stdin
+    ///
+    /// Used in error messages and metadata, and as the base for relative imports,
+    /// e.g. when an editor pipes an unsaved buffer to svgdx.
+    #[arg(long, value_name = "PATH")]
+    stdin_filename: Option<String>,
+
+    /// Check generated output against the SVG element and attribute model
+    ///
+    /// Any issues (e.g. unknown attributes, misplaced child elements) are reported
+    /// to stderr, and result in a non-zero exit status. Output is still written.
+    #[arg(long)]
+    validate: bool,
+
+    /// Treat input as a document of the given format, rendering embedded svgdx blocks
+    ///
+    /// With `markdown`, fenced code blocks with the info string `svgdx` (or `{.svgdx}`)
+    /// are replaced by inline SVG, or by an HTML figure or image link according to
+    /// `--format`, e.g. `svgdx --filter markdown README.src.md -o README.md`.
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with_all = ["steps", "geometry"])]
+    filter: Option<InputFilter>,
+
+    /// Output format
+    ///
+    /// `html-fragment` wraps the SVG in a `<figure>` element (with class `svgdx-figure`)
+    /// suitable for embedding in HTML pages, captioned with any document `<title>`.
+    /// `datauri` and `datauri-base64` output a `data:` URI, e.g. for Markdown images.
+    /// `png` (with the `png` feature) renders the SVG as an image.
+    #[arg(long, value_enum, default_value = "svg", conflicts_with = "geometry")]
+    format: OutputFormat,
+
+    /// Write auto-style CSS to FILE rather than embedding it in the output
+    ///
+    /// The output references FILE with an `xml-stylesheet` processing instruction;
+    /// use `--css-href` if it should be referenced by a different URL.
+    #[arg(long, value_name = "FILE")]
+    extract_css: Option<String>,
+
+    /// URL by which the `--extract-css` stylesheet is referenced
+    #[arg(long, value_name = "URL", requires = "extract_css")]
+    css_href: Option<String>,
+
+    /// Also write a low-detail placeholder SVG (boxes only, no text) to FILE
+    ///
+    /// This is suitable as a 'blur-up' image while the full SVG loads in web pages.
+    #[arg(long, value_name = "FILE")]
+    skeleton: Option<String>,
+
+    /// Also write a JSON outline of the document to FILE
+    ///
+    /// The outline gives the hierarchy of containers and elements with ids, along
+    /// with titles and text content, e.g. for search indexes over many diagrams.
+    #[arg(long, value_name = "FILE")]
+    outline: Option<String>,
+
+    /// Output resolved element geometry as text rather than SVG
+    ///
+    /// Each element with an id, and each line or polyline (e.g. connectors), is
+    /// listed with its bounding box and any vertices, ignoring all styling. This is
+    /// useful for detecting layout regressions.
+    #[arg(long)]
+    geometry: bool,
+
     /// Scale of user-units to mm for root svg element width/height
     #[arg(long, default_value = "1.0")]
     scale: f32,
@@ -74,6 +190,16 @@ struct Arguments {
     #[arg(long, default_value = "100")]
     depth_limit: u32,
 
+    /// Limit on number of elements processed
+    ///
+    /// This protects against runaway output, e.g. from nested reuse.
+    #[arg(long, default_value = "100000")]
+    element_limit: u32,
+
+    /// Limit on output size in bytes
+    #[arg(long, default_value = "16777216")]
+    output_limit: u32,
+
     /// Default font-size (in user-units)
     ///
     /// Text size classes (such as d-text-smaller) are based on this value.
@@ -91,6 +217,206 @@ struct Arguments {
     /// Optional style to apply to SVG root element
     #[arg(long)]
     svg_style: Option<String>,
+
+    /// Transform to apply to all content, e.g. "rotate 90" or "translate(10, 5)"
+    ///
+    /// The viewBox is computed from the transformed content.
+    #[arg(long, allow_hyphen_values = true)]
+    content_transform: Option<String>,
+
+    /// Remove scripts, event handlers and external references from input and output
+    #[arg(long)]
+    sanitize: bool,
+
+    /// Allow the `env()` expression function to read environment variables
+    #[arg(long)]
+    allow_env: bool,
+
+    /// Skip malformed segments in path data rather than failing
+    ///
+    /// Skipped segments are reported on stderr, and only affect the computed
+    /// bounding box; the path data is output unchanged.
+    #[arg(long)]
+    path_recover: bool,
+
+    /// Draw a small hop where a connector crosses an earlier connector
+    #[arg(long)]
+    connector_jumps: bool,
+
+    /// Document title, added as a `<title>` element
+    ///
+    /// With `--add-metadata`, this is also included in Dublin Core RDF metadata.
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Document description, added as a `<desc>` element
+    ///
+    /// With `--add-metadata`, this is also included in Dublin Core RDF metadata.
+    #[arg(long)]
+    desc: Option<String>,
+
+    /// Author to include in an attribution comment in the output
+    #[arg(long)]
+    author: Option<String>,
+
+    /// License (e.g. "CC-BY-4.0") to include in an attribution comment in the output
+    #[arg(long)]
+    license: Option<String>,
+
+    /// Source URL to include in an attribution comment in the output
+    #[arg(long, value_name = "URL")]
+    source_url: Option<String>,
+
+    /// Watermark text (e.g. "DRAFT") to place across the output
+    #[arg(long)]
+    watermark: Option<String>,
+
+    /// Watermark image to place across the output, scaled to fit
+    #[arg(long, value_name = "URL")]
+    watermark_image: Option<String>,
+
+    /// Opacity of watermark
+    #[arg(long, default_value = "0.15")]
+    watermark_opacity: f32,
+
+    /// Rotation of watermark in degrees
+    #[arg(long, default_value = "-30", allow_hyphen_values = true)]
+    watermark_angle: f32,
+
+    /// Place watermark above rather than behind content
+    #[arg(long)]
+    watermark_above: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Reformat svgdx source into a canonical form
+    ///
+    /// Normalises indentation, attribute order and quoting, and merges simple
+    /// attribute pairs (e.g. `x` and `y`) into compound attributes (e.g. `xy`).
+    Fmt {
+        /// File to format ('-' for stdin)
+        #[arg(default_value = "-")]
+        file: String,
+
+        /// Target output file ('-' for stdout)
+        #[arg(short, long, default_value = "-")]
+        output: String,
+
+        /// Don't write output; fail if FILE is not already formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Rewrite svgdx source using syntax from earlier releases
+    ///
+    /// Updates deprecated elref syntax such as `#abc:h` (now `#abc|h`) and
+    /// `#abc.w` (now `#abc~w`), and renamed attributes such as `text-inset`.
+    Migrate {
+        /// File to migrate ('-' for stdin)
+        #[arg(default_value = "-")]
+        file: String,
+
+        /// Target output file ('-' for stdout)
+        #[arg(short, long, default_value = "-")]
+        output: String,
+
+        /// Don't write output; fail if FILE uses deprecated syntax
+        #[arg(long)]
+        check: bool,
+    },
+    /// Compare two svgdx documents visually
+    ///
+    /// Renders both documents, matches elements by id, and writes an overlay
+    /// SVG of NEW highlighting added, removed and moved elements. A summary of
+    /// changed ids is printed to stderr.
+    Diff {
+        /// Original svgdx document
+        old: String,
+
+        /// Updated svgdx document
+        new: String,
+
+        /// Target output file ('-' for stdout)
+        #[arg(short, long, default_value = "-")]
+        output: String,
+    },
+    /// Arrange multiple svgdx documents into a single SVG
+    ///
+    /// Renders each document and places them side by side (or one above
+    /// another) at a consistent scale, e.g. for comparison figures.
+    Compose {
+        /// svgdx documents to compose, in order
+        #[arg(required = true)]
+        files: Vec<String>,
+
+        /// Target output file ('-' for stdout)
+        #[arg(short, long, default_value = "-")]
+        output: String,
+
+        /// Arrange documents horizontally (h) or vertically (v)
+        #[arg(long, value_enum, default_value = "h")]
+        layout: ComposeLayout,
+
+        /// Gap between documents, in user units
+        #[arg(long, default_value = "10")]
+        gap: f32,
+    },
+}
+
+/// Format of documents containing svgdx blocks, for `--filter`.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum InputFilter {
+    /// Markdown, with svgdx in fenced code blocks
+    Markdown,
+}
+
+/// Format of transformed output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// SVG document
+    #[default]
+    Svg,
+    /// HTML `<figure>` element containing inline SVG
+    HtmlFragment,
+    /// `data:image/svg+xml` URI
+    #[value(name = "datauri")]
+    DataUri,
+    /// `data:image/svg+xml` URI with base64 encoding
+    #[value(name = "datauri-base64")]
+    DataUriBase64,
+    /// PNG image
+    #[cfg(feature = "png")]
+    Png,
+}
+
+impl OutputFormat {
+    /// Format implied by the extension of the given output path.
+    fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            #[cfg(feature = "png")]
+            Some(ext) if ext.eq_ignore_ascii_case("png") => Self::Png,
+            _ => Self::Svg,
+        }
+    }
+}
+
+/// Action taken by the `svgdx` program.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Transform input to SVG
+    Transform,
+    /// Reformat input source
+    Format,
+    /// Check input source is formatted
+    FormatCheck,
+    /// Migrate input source to current syntax
+    Migrate,
+    /// Check input source does not use deprecated syntax
+    MigrateCheck,
+    /// Compare input with another document
+    Diff,
+    /// Arrange input and other documents into one
+    Compose,
 }
 
 /// Top-level configuration used by the `svgdx` command-line process.
@@ -102,43 +428,167 @@ struct Arguments {
 /// ('back-end') settings are stored in the embedded `TransformConfig` struct.
 #[derive(Clone)]
 pub struct Config {
+    /// What to do with the input
+    pub mode: Mode,
     /// Path to input file, or '-' for stdin
     pub input_path: String,
     /// Path to output file, or '-' for stdout
     pub output_path: String,
+    /// Paths to further input documents, for `Mode::Diff` and `Mode::Compose`
+    pub other_paths: Vec<String>,
+    /// Arrangement of documents for `Mode::Compose`
+    pub compose_layout: ComposeLayout,
+    /// Gap between documents for `Mode::Compose`
+    pub compose_gap: f32,
+    /// Config file giving further outputs, e.g. `svgdx.toml`
+    pub config_file: Option<PathBuf>,
+    /// Further outputs generated from the same input, each with their own config
+    pub outputs: Vec<Config>,
     /// Stay monitoring `input_path` for changes (Requires input_path is not stdin)
     pub watch: bool,
+    /// Write one output per build step (Requires output_path is not stdout)
+    pub steps: bool,
+    /// Report any validation issues in generated output
+    pub validate: bool,
+    /// Output geometry as text rather than SVG
+    pub geometry: bool,
+    /// Format of output
+    pub format: OutputFormat,
+    /// Format of a document containing svgdx blocks to render, rather than svgdx input
+    pub filter: Option<InputFilter>,
+    /// File to write a placeholder 'skeleton' SVG to
+    pub skeleton: Option<String>,
+    /// File to write a JSON outline of the document to
+    pub outline: Option<String>,
+    /// File to write auto-style CSS to, rather than embedding it in the output
+    pub extract_css: Option<String>,
+    /// Print processing statistics to stderr
+    pub stats: bool,
+    /// File to write Chrome trace-event JSON timings to
+    #[cfg(feature = "profiling")]
+    pub trace_file: Option<String>,
+    /// Error code to describe rather than processing input
+    pub explain: Option<String>,
+    /// Name of the input file when `input_path` is stdin, used in diagnostics
+    pub stdin_filename: Option<String>,
     /// transform config options
     pub transform: TransformConfig,
 }
 
 impl Config {
-    fn from_args(args: Arguments) -> Result<Self> {
+    /// Name of the input for use in messages; `-` for stdin if no
+    /// `stdin_filename` is given.
+    fn input_name(&self) -> &str {
+        match self.input_path.as_str() {
+            "-" => self.stdin_filename.as_deref().unwrap_or("-"),
+            path => path,
+        }
+    }
+
+    fn from_args(mut args: Arguments) -> Result<Self> {
+        let mut mode = Mode::Transform;
+        let mut other_paths = Vec::new();
+        let mut compose_layout = ComposeLayout::default();
+        let mut compose_gap = 0.;
+        match args.command.take() {
+            Some(Command::Fmt {
+                file,
+                output,
+                check,
+            }) => {
+                mode = if check {
+                    Mode::FormatCheck
+                } else {
+                    Mode::Format
+                };
+                args.file = file;
+                args.output = output;
+            }
+            Some(Command::Migrate {
+                file,
+                output,
+                check,
+            }) => {
+                mode = if check {
+                    Mode::MigrateCheck
+                } else {
+                    Mode::Migrate
+                };
+                args.file = file;
+                args.output = output;
+            }
+            Some(Command::Diff { old, new, output }) => {
+                mode = Mode::Diff;
+                args.file = old;
+                args.output = output;
+                other_paths.push(new);
+            }
+            Some(Command::Compose {
+                files,
+                output,
+                layout,
+                gap,
+            }) => {
+                mode = Mode::Compose;
+                (args.file, other_paths) = files
+                    .split_first()
+                    .map(|(first, rest)| (first.clone(), rest.to_vec()))
+                    .unwrap_or_default();
+                args.output = output;
+                compose_layout = layout;
+                compose_gap = gap;
+            }
+            None => {}
+        }
         if args.watch && args.file == "-" {
             // Should already be enforced by clap validation
             return Err(SvgdxError::from(
                 "A non-stdin file must be provided with -w/--watch argument",
             ));
         }
-        if args.file != "-" && args.output != "-" {
-            // Arguably creating this struct shouldn't do any IO, but this is a
-            // deliberate UX safety restriction on the CLI which is worth keeping
-            // as high-level as possible to keep the lower level API cleaner.
-            let in_path = Path::new(&args.file);
-            let out_path = Path::new(&args.output);
-            if out_path.exists()
-                && out_path.canonicalize().map_err(SvgdxError::from_err)?
-                    == in_path.canonicalize().map_err(SvgdxError::from_err)?
-            {
-                return Err(SvgdxError::from(
-                    "Output path must not refer to the same file as the input file.",
-                ));
-            }
+        if args.steps && args.output == "-" {
+            return Err(SvgdxError::from(
+                "An output file must be provided with the --steps argument",
+            ));
         }
-        Ok(Self {
+        check_output_path(&args.file, &args.output)?;
+        let input_name = match args.file.as_str() {
+            "-" => args.stdin_filename.clone(),
+            file => Some(file.to_string()),
+        };
+        // imports are relative to the input document
+        let import_dir = match input_name.as_deref().map(Path::new).and_then(Path::parent) {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy().into_owned(),
+            _ => ".".to_string(),
+        };
+        let config_file = match (&args.config_file, args.file.as_str()) {
+            (Some(path), _) => Some(PathBuf::from(path)),
+            (None, "-") => None,
+            (None, _) => Some(Path::new(&import_dir).join(CONFIG_FILE_NAME)).filter(|p| p.exists()),
+        };
+        let mut config = Self {
+            mode,
             input_path: args.file,
             output_path: args.output,
+            other_paths,
+            compose_layout,
+            compose_gap,
+            config_file,
+            outputs: Vec::new(),
             watch: args.watch,
+            steps: args.steps,
+            validate: args.validate,
+            geometry: args.geometry,
+            format: args.format,
+            filter: args.filter,
+            skeleton: args.skeleton,
+            outline: args.outline,
+            extract_css: args.extract_css.clone(),
+            stats: args.stats,
+            #[cfg(feature = "profiling")]
+            trace_file: args.trace_file,
+            explain: args.explain,
+            stdin_filename: args.stdin_filename,
             transform: TransformConfig {
                 debug: args.debug,
                 scale: args.scale,
@@ -151,12 +601,119 @@ impl Config {
                 loop_limit: args.loop_limit,
                 var_limit: args.var_limit,
                 depth_limit: args.depth_limit,
+                element_limit: args.element_limit,
+                output_limit: args.output_limit,
                 font_size: args.font_size,
                 font_family: args.font_family,
                 theme: args.theme,
+                custom_theme: None,
                 svg_style: args.svg_style,
+                content_transform: args.content_transform,
+                sanitize: args.sanitize,
+                allow_env: args.allow_env,
+                path_recover: args.path_recover,
+                connector_jumps: args.connector_jumps,
+                css_href: args.css_href.or(args.extract_css),
+                title: args.title,
+                desc: args.desc,
+                author: args.author,
+                license: args.license,
+                source_url: args.source_url,
+                watermark: args.watermark,
+                watermark_image: args.watermark_image,
+                watermark_opacity: args.watermark_opacity,
+                watermark_angle: args.watermark_angle,
+                watermark_above: args.watermark_above,
+                step: None,
+                import_dir: Some(import_dir),
+                documents: None,
+                source_file: input_name,
             },
-        })
+        };
+        config.load_outputs()?;
+        Ok(config)
+    }
+
+    /// Read the `[[output]]` tables of `config_file` (if any) into `outputs`.
+    ///
+    /// Each output is based on this config, with the output path, format and
+    /// any settings given in its table. Relative output paths are relative to
+    /// the config file.
+    fn load_outputs(&mut self) -> Result<()> {
+        self.outputs.clear();
+        let Some(path) = &self.config_file else {
+            return Ok(());
+        };
+        let name = path.to_string_lossy();
+        let table: toml::Table = std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| SvgdxError::InvalidData(format!("{name}: {e}")))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let mut outputs = Vec::new();
+        for (key, value) in table {
+            let (true, toml::Value::Array(tables)) = (key == "output", value) else {
+                return Err(SvgdxError::InvalidData(format!(
+                    "{name}: unexpected setting '{key}'"
+                )));
+            };
+            for output in tables {
+                let toml::Value::Table(output) = output else {
+                    return Err(SvgdxError::InvalidData(format!(
+                        "{name}: 'output' must be an array of tables, e.g. [[output]]"
+                    )));
+                };
+                outputs.push(self.output_config(output, base_dir).map_err(|e| {
+                    SvgdxError::InvalidData(format!("{name}: invalid [[output]]: {e}"))
+                })?);
+            }
+        }
+        self.outputs = outputs;
+        Ok(())
+    }
+
+    /// Config for a single `[[output]]` table of a config file.
+    fn output_config(&self, table: toml::Table, base_dir: &Path) -> Result<Config> {
+        let mut config = self.clone();
+        // Extra files are only written for the main output
+        config.outputs.clear();
+        config.skeleton = None;
+        config.outline = None;
+        config.extract_css = None;
+        config.stats = false;
+        #[cfg(feature = "profiling")]
+        {
+            config.trace_file = None;
+        }
+        let mut format = None;
+        let mut file = None;
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Integer(n) => n.to_string(),
+                toml::Value::Float(n) => n.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => {
+                    return Err(SvgdxError::InvalidData(format!(
+                        "'{key}' must be a string, number or boolean"
+                    )))
+                }
+            };
+            match key.as_str() {
+                "file" => file = Some(base_dir.join(value).to_string_lossy().into_owned()),
+                "format" => {
+                    format = Some(
+                        <OutputFormat as clap::ValueEnum>::from_str(&value, false)
+                            .map_err(SvgdxError::InvalidData)?,
+                    )
+                }
+                _ => config.transform.set(&key, &value)?,
+            }
+        }
+        let file = file.ok_or_else(|| SvgdxError::from("'file' must be given"))?;
+        check_output_path(&config.input_path, &file)?;
+        config.format = format.unwrap_or_else(|| OutputFormat::from_path(&file));
+        config.output_path = file;
+        Ok(config)
     }
 
     /// Create a `Config` object set up given a command line string.
@@ -170,41 +727,358 @@ impl Config {
     }
 }
 
+/// Name of the config file read from the input file's directory, if present.
+const CONFIG_FILE_NAME: &str = "svgdx.toml";
+
+/// Check `output` (if not stdout) doesn't refer to the `input` file.
+fn check_output_path(input: &str, output: &str) -> Result<()> {
+    if input != "-" && output != "-" {
+        // Arguably creating a `Config` shouldn't do any IO, but this is a
+        // deliberate UX safety restriction on the CLI which is worth keeping
+        // as high-level as possible to keep the lower level API cleaner.
+        let in_path = Path::new(input);
+        let out_path = Path::new(output);
+        if out_path.exists()
+            && out_path.canonicalize().map_err(SvgdxError::from_err)?
+                == in_path.canonicalize().map_err(SvgdxError::from_err)?
+        {
+            return Err(SvgdxError::from(
+                "Output path must not refer to the same file as the input file.",
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// Create a `Config` object from process arguments.
 pub fn get_config() -> Result<Config> {
     let args = Arguments::parse();
     Config::from_args(args)
 }
 
+/// Transform and write output for the given `Config`, validating the
+/// result if required.
+///
+/// Errors associated with specific input elements are rendered with
+/// annotated snippets of the input source.
+fn process(config: &Config) -> Result<()> {
+    let mut source = String::new();
+    input_reader(&config.input_path)?.read_to_string(&mut source)?;
+    // With configured outputs, the main output is only written if given
+    let main = (config.outputs.is_empty() || config.output_path != "-").then_some(config);
+    for config in main.into_iter().chain(&config.outputs) {
+        if config.filter.is_some() {
+            process_markdown(config, &source)?;
+        } else if config.steps {
+            process_steps(config, &source)?;
+        } else {
+            process_source(config, &source)?;
+        }
+    }
+    Ok(())
+}
+
+/// Path of the output for the given build step, e.g. `out.svg` -> `out-2.svg`.
+fn step_path(path: &str, step: u32) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}-{step}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{step}"),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Write an output for each build step given by `step` attributes in the source.
+fn process_steps(config: &Config, source: &str) -> Result<()> {
+    let mut step = 1;
+    loop {
+        let mut step_config = config.clone();
+        step_config.output_path = step_path(&config.output_path, step);
+        step_config.transform.step = Some(step);
+        let stats = process_source(&step_config, source)?;
+        if step >= stats.steps {
+            return Ok(());
+        }
+        step += 1;
+    }
+}
+
+/// Report element errors with annotated snippets of the input source,
+/// which is identified by `name` if given.
+fn report_errors(e: SvgdxError, source: &str, name: Option<&str>) -> SvgdxError {
+    match e {
+        SvgdxError::MultiError(ref errors) => {
+            eprint!("{}", e.render_with_source_file(source, name));
+            if let Some(code) = errors.values().find_map(|(_, err)| err.code()) {
+                eprintln!("For more information about an error, try `svgdx --explain {code}`.");
+            }
+            SvgdxError::MessageError(format!(
+                "{} element(s) could not be processed",
+                errors.len()
+            ))
+        }
+        e => e,
+    }
+}
+
+fn process_source(config: &Config, source: &str) -> Result<TransformStats> {
+    let (output, stats, css) = transform_str_full(source, &config.transform)
+        .map_err(|e| report_errors(e, source, config.transform.source_file.as_deref()))?;
+    for warning in &stats.warnings {
+        eprintln!("warning: {warning}");
+    }
+    if let Some(skeleton_path) = &config.skeleton {
+        write_output(skeleton_path, svg_skeleton(&output)?.as_bytes())?;
+    }
+    if let Some(outline_path) = &config.outline {
+        write_output(outline_path, svg_outline(&output)?.as_bytes())?;
+    }
+    if let (Some(css_path), Some(css)) = (&config.extract_css, css) {
+        write_output(css_path, css.as_bytes())?;
+    }
+    if config.geometry {
+        write_output(&config.output_path, svg_geometry(&output)?.as_bytes())?;
+    } else {
+        let data = match config.format {
+            OutputFormat::Svg => output.clone().into_bytes(),
+            OutputFormat::HtmlFragment => svg_html_fragment(&output)?.into_bytes(),
+            OutputFormat::DataUri => svg_data_uri(&output, false).into_bytes(),
+            OutputFormat::DataUriBase64 => svg_data_uri(&output, true).into_bytes(),
+            #[cfg(feature = "png")]
+            OutputFormat::Png => svg_png(&output)?,
+        };
+        write_output(&config.output_path, &data)?;
+    }
+    if config.stats {
+        eprint!("{stats}");
+    }
+    #[cfg(feature = "profiling")]
+    if let Some(trace_file) = &config.trace_file {
+        std::fs::write(trace_file, stats.chrome_trace())?;
+    }
+    if !config.validate {
+        return Ok(stats);
+    }
+    let issues = validate_svg(&output)?;
+    for issue in &issues {
+        eprintln!("{issue}");
+    }
+    if issues.is_empty() {
+        Ok(stats)
+    } else {
+        Err(SvgdxError::MessageError(format!(
+            "{} validation issue(s) found",
+            issues.len()
+        )))
+    }
+}
+
+/// Render the svgdx blocks in a Markdown document, writing the updated document.
+///
+/// Errors are reported relative to the block in which they occur.
+fn process_markdown(config: &Config, source: &str) -> Result<()> {
+    let output = filter_markdown(source, |block| {
+        let svg = transform_str_full(block.source.as_str(), &config.transform)
+            .map(|(svg, _, _)| svg)
+            .map_err(|e| {
+                let name = format!(
+                    "{} (svgdx block at line {})",
+                    config.input_name(),
+                    block.line
+                );
+                let err = report_errors(e, &block.source, Some(&name));
+                SvgdxError::MessageError(format!("svgdx block at line {}: {err}", block.line))
+            })?;
+        Ok(match config.format {
+            OutputFormat::Svg => markdown_html(&svg),
+            OutputFormat::HtmlFragment => markdown_html(&svg_html_fragment(&svg)?),
+            OutputFormat::DataUri | OutputFormat::DataUriBase64 => {
+                let base64 = config.format == OutputFormat::DataUriBase64;
+                let alt = block.info.replace('[', "\\[").replace(']', "\\]");
+                format!("![{alt}]({})", svg_data_uri(&svg, base64))
+            }
+            #[cfg(feature = "png")]
+            OutputFormat::Png => {
+                return Err(SvgdxError::from(
+                    "PNG output is not supported for Markdown documents",
+                ))
+            }
+        })
+    })?;
+    write_output(&config.output_path, output.as_bytes())
+}
+
+/// Reformat or migrate the input source, or check it is already up to date.
+fn rewrite(config: &Config) -> Result<()> {
+    let mut source = String::new();
+    input_reader(&config.input_path)?.read_to_string(&mut source)?;
+    let (rewritten, problem) = match config.mode {
+        Mode::Format | Mode::FormatCheck => (format_source(&source)?, "is not formatted"),
+        _ => (migrate_source(&source)?, "uses deprecated syntax"),
+    };
+    if matches!(config.mode, Mode::FormatCheck | Mode::MigrateCheck) {
+        return if rewritten == source {
+            Ok(())
+        } else {
+            Err(SvgdxError::MessageError(format!(
+                "{} {problem}",
+                config.input_name()
+            )))
+        };
+    }
+    write_output(&config.output_path, rewritten.as_bytes())
+}
+
+/// Transform two documents and write an overlay highlighting their differences.
+fn diff(config: &Config) -> Result<()> {
+    let [old, new] = &transform_inputs(config)?[..] else {
+        return Err(SvgdxError::from(
+            "Exactly two documents must be provided to compare",
+        ));
+    };
+    let (output, summary) = svg_diff(old, new)?;
+    eprint!("{summary}");
+    write_output(&config.output_path, output.as_bytes())
+}
+
+/// Transform several documents and arrange them into a single output.
+///
+/// Each document uses local styles, so styles don't apply across documents.
+fn compose(config: &Config) -> Result<()> {
+    let mut config = config.clone();
+    config.transform.use_local_styles = true;
+    let outputs = transform_inputs(&config)?;
+    let output = svg_compose(&outputs, config.compose_layout, config.compose_gap)?;
+    write_output(&config.output_path, output.as_bytes())
+}
+
+/// Transform `input_path` and each of `other_paths`, returning the outputs.
+fn transform_inputs(config: &Config) -> Result<Vec<String>> {
+    let mut outputs = Vec::new();
+    for path in std::iter::once(&config.input_path).chain(&config.other_paths) {
+        let mut source = String::new();
+        input_reader(path)?.read_to_string(&mut source)?;
+        let name = match path.as_str() {
+            "-" => config.stdin_filename.as_deref(),
+            path => Some(path),
+        };
+        let mut transform = config.transform.clone();
+        transform.source_file = name.map(str::to_string);
+        let (output, _, _) =
+            transform_str_full(&source, &transform).map_err(|e| report_errors(e, &source, name))?;
+        outputs.push(output);
+    }
+    Ok(outputs)
+}
+
+/// Canonical paths of the input document, the files it imports, and any
+/// config file, i.e. the files which affect the outputs.
+fn dependencies(config: &Config) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(&config.input_path)];
+    paths.extend(config.config_file.clone());
+    let mut source = String::new();
+    let imports = input_reader(&config.input_path)
+        .and_then(|mut reader| Ok(reader.read_to_string(&mut source)?))
+        .and_then(|_| source.parse::<InputList>());
+    if let (Ok(input), Some(dir)) = (imports, &config.transform.import_dir) {
+        paths.extend(
+            import_sources(&input)
+                .into_iter()
+                .filter_map(|src| import_path(&src, dir).ok()),
+        );
+    }
+    paths
+        .into_iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect()
+}
+
+/// Modification time of the given file, if available.
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Update the set of watched files (with their modification times) to the
+/// current dependencies of the input.
+fn update_watches(
+    watcher: &mut dyn Watcher,
+    watched: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    config: &Config,
+) {
+    let deps = dependencies(config);
+    for (path, _) in watched.iter().filter(|(p, _)| !deps.contains(p)) {
+        watcher.unwatch(path).ok();
+    }
+    for path in &deps {
+        // files replaced on save (rather than modified) must be watched again
+        watcher.unwatch(path).ok();
+        if let Err(e) = watcher.watch(path, RecursiveMode::NonRecursive) {
+            eprintln!("Could not watch {}: {e}", path.to_string_lossy());
+        }
+    }
+    *watched = deps
+        .into_iter()
+        .map(|path| {
+            let mtime = modified(&path);
+            (path, mtime)
+        })
+        .collect();
+}
+
 /// Run the `svgdx` program with a given `Config`.
-pub fn run(config: Config) -> Result<()> {
+pub fn run(mut config: Config) -> Result<()> {
+    if let Some(code) = &config.explain {
+        let text = explain(code)
+            .ok_or_else(|| SvgdxError::InvalidData(format!("Unknown error code '{code}'")))?;
+        println!("{text}");
+        return Ok(());
+    }
+    match config.mode {
+        Mode::Diff => return diff(&config),
+        Mode::Compose => return compose(&config),
+        _ => {}
+    }
+    if config.mode != Mode::Transform {
+        return rewrite(&config);
+    }
     if !config.watch {
-        transform_file(&config.input_path, &config.output_path, &config.transform)?;
+        process(&config)?;
     } else if config.input_path != "-" {
-        let watch = config.input_path;
+        let watch = config.input_path.clone();
         let (tx, rx) = channel();
         let mut watcher =
             new_debouncer(Duration::from_millis(250), tx).expect("Could not create watcher");
-        let watch_path = Path::new(&watch);
         watcher
             .watcher()
             .watch(Path::new(&watch), RecursiveMode::NonRecursive)
             .map_err(SvgdxError::from_err)?;
-        transform_file(&watch, &config.output_path, &config.transform).unwrap_or_else(|e| {
-            eprintln!("transform failed: {e:?}");
+        // Imported files are also watched; these may change as the input changes.
+        let mut watched = Vec::new();
+        update_watches(watcher.watcher(), &mut watched, &config);
+        process(&config).unwrap_or_else(|e| {
+            eprintln!("transform failed: {e}");
         });
         eprintln!("Watching {watch} for changes");
         loop {
             match rx.recv() {
-                Ok(Ok(events)) => {
-                    for event in events {
-                        if event.path.canonicalize()? == watch_path.canonicalize()? {
-                            eprintln!("{} changed", event.path.to_string_lossy());
-                            transform_file(&watch, &config.output_path, &config.transform)
-                                .unwrap_or_else(|e| {
-                                    eprintln!("transform failed: {e:?}");
-                                });
-                        }
+                Ok(Ok(_)) => {
+                    // Events are also generated by reading files, so only
+                    // modified files are considered changed.
+                    let changed = watched
+                        .iter()
+                        .find(|(path, mtime)| modified(path) != *mtime)
+                        .map(|(path, _)| path.clone());
+                    if let Some(path) = changed {
+                        eprintln!("{} changed", path.to_string_lossy());
+                        update_watches(watcher.watcher(), &mut watched, &config);
+                        // outputs may have changed if the config file has
+                        config
+                            .load_outputs()
+                            .and_then(|_| process(&config))
+                            .unwrap_or_else(|e| {
+                                eprintln!("transform failed: {e}");
+                            });
                     }
                 }
                 Ok(Err(e)) => eprintln!("Watch error {e:?}"),
@@ -0,0 +1,195 @@
+//! Composition of multiple SVG documents into a single image.
+//!
+//! Each document (typically the output of `transform_str()`) is embedded as a
+//! nested `<svg>` element, with documents arranged in a row or column separated
+//! by a gap, and centred on the cross axis. Documents retain their `viewBox`
+//! and are placed at a scale of one user unit per user unit, so diagrams drawn
+//! at the same scale remain directly comparable.
+//!
+//! Element ids within each document are prefixed (e.g. `p1-`, `p2-`) so they
+//! remain unique; documents should be rendered with `use_local_styles` so
+//! their styles don't apply to other documents.
+
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::geometry::{root_start, split_root, view_box};
+use crate::position::BoundingBox;
+use crate::types::fstr;
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Root attributes which are replaced when documents are nested.
+const ROOT_ATTRS: &[&str] = &["version", "xmlns", "x", "y", "width", "height"];
+
+/// Arrangement of composed documents.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ComposeLayout {
+    /// Documents are placed left to right
+    #[default]
+    #[cfg_attr(feature = "cli", value(name = "h"))]
+    Horizontal,
+    /// Documents are placed top to bottom
+    #[cfg_attr(feature = "cli", value(name = "v"))]
+    Vertical,
+}
+
+impl FromStr for ComposeLayout {
+    type Err = SvgdxError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "h" => Ok(Self::Horizontal),
+            "v" => Ok(Self::Vertical),
+            _ => Err(SvgdxError::InvalidData(format!(
+                "Unknown layout '{s}' (expected 'h' or 'v')"
+            ))),
+        }
+    }
+}
+
+/// Arrange the given SVG documents into a single SVG document.
+///
+/// The size and units of the result are derived from the first document.
+pub fn svg_compose(svgs: &[String], layout: ComposeLayout, gap: f32) -> Result<String> {
+    let mut docs = Vec::new();
+    for svg in svgs {
+        let (root, content) = split_root(svg)?;
+        let vb = view_box(&root)?.ok_or_else(|| SvgdxError::MissingAttribute("viewBox".into()))?;
+        docs.push((root, content, vb));
+    }
+    let Some((first_root, _, _)) = docs.first() else {
+        return Err(SvgdxError::from("No documents to compose"));
+    };
+
+    let horizontal = layout == ComposeLayout::Horizontal;
+    let cross = |vb: &BoundingBox| if horizontal { vb.height() } else { vb.width() };
+    let cross_size = docs.iter().map(|(_, _, vb)| cross(vb)).fold(0., f32::max);
+
+    let mut nested = String::new();
+    let mut offset = 0.;
+    for (idx, (root, content, vb)) in docs.iter().enumerate() {
+        let mut root = root.clone();
+        let content = scope_ids(&mut root, content, &format!("p{}-", idx + 1));
+        let centred = (cross_size - cross(vb)) / 2.;
+        let (x, y) = if horizontal {
+            (offset, centred)
+        } else {
+            (centred, offset)
+        };
+        let mut el = SvgElement::new("svg", &[]);
+        el.set_attr("x", &fstr(x));
+        el.set_attr("y", &fstr(y));
+        el.set_attr("width", &fstr(vb.width()));
+        el.set_attr("height", &fstr(vb.height()));
+        for (key, value) in &root.attrs {
+            if !ROOT_ATTRS.contains(&key.as_str()) {
+                el.set_attr(key, value);
+            }
+        }
+        el.classes = root.classes.clone();
+        nested.push_str(&format!("{el}{content}</svg>\n"));
+        offset += if horizontal { vb.width() } else { vb.height() } + gap;
+    }
+    let main_size = (offset - gap).max(0.);
+    let view = if horizontal {
+        BoundingBox::new(0., 0., main_size, cross_size)
+    } else {
+        BoundingBox::new(0., 0., cross_size, main_size)
+    };
+
+    let mut output = root_start(first_root, &view);
+    output.push('\n');
+    output.push_str(&nested);
+    output.push_str("</svg>\n");
+    Ok(output)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Prefix the `id` of the given root element and of elements within `content`,
+/// updating `#id` references (e.g. in `href`, `url(#id)` and CSS selectors)
+/// to match.
+fn scope_ids(root: &mut SvgElement, content: &str, prefix: &str) -> String {
+    const ID_ATTR: &str = "id=\"";
+    let mut ids = HashSet::new();
+    if let Some(id) = root.get_attr("id") {
+        root.set_attr("id", &format!("{prefix}{id}"));
+        ids.insert(id);
+    }
+    for (pos, _) in content.match_indices(ID_ATTR) {
+        if !content[..pos].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let value = &content[pos + ID_ATTR.len()..];
+        if let Some(end) = value.find('"') {
+            ids.insert(value[..end].to_string());
+        }
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut prev = None;
+    let mut rest = content;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(ID_ATTR) && prev.is_some_and(char::is_whitespace) {
+            output.push_str(ID_ATTR);
+            output.push_str(prefix);
+            rest = &rest[ID_ATTR.len()..];
+            prev = Some('"');
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        output.push(c);
+        // '&#' introduces a character reference rather than an id reference
+        if c == '#' && prev != Some('&') {
+            let end = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            if ids.contains(&rest[..end]) {
+                output.push_str(prefix);
+            }
+        }
+        prev = Some(c);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_compose() {
+        let a = r#"<svg version="1.1" width="20mm" height="10mm" viewBox="-5 -5 20 10"><rect id="a" width="10" height="10"/></svg>"#.to_string();
+        let b = r#"<svg width="10mm" height="20mm" viewBox="0 0 10 20"><circle r="5"/></svg>"#
+            .to_string();
+        let svgs = [a, b];
+
+        let h = svg_compose(&svgs, ComposeLayout::Horizontal, 5.).unwrap();
+        assert!(h.starts_with(r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="35mm" height="20mm" viewBox="0 0 35 20">"#));
+        assert!(h.contains(r#"<svg x="0" y="5" width="20" height="10" viewBox="-5 -5 20 10"><rect id="p1-a" width="10" height="10"/></svg>"#));
+        assert!(h.contains(
+            r#"<svg x="25" y="0" width="10" height="20" viewBox="0 0 10 20"><circle r="5"/></svg>"#
+        ));
+
+        let v = svg_compose(&svgs, ComposeLayout::Vertical, 5.).unwrap();
+        assert!(v.contains(r#"viewBox="0 0 20 35""#));
+        assert!(v.contains(r#"x="5" y="15" width="10" height="20""#));
+
+        assert!(svg_compose(&[], ComposeLayout::Horizontal, 0.).is_err());
+        assert!(svg_compose(&["<svg/>".to_string()], ComposeLayout::Horizontal, 0.).is_err());
+    }
+
+    #[test]
+    fn test_scope_ids() {
+        let mut root = SvgElement::new("svg", &[("id".to_string(), "doc".to_string())]);
+        let content = r##"<style>#doc .a { fill: #abc; } #a { fill: red; }</style><rect id="a" data-id="x" fill="url(#a)"/><use href="#a"/><text>&#65;</text>"##;
+        let scoped = scope_ids(&mut root, content, "p2-");
+        assert_eq!(root.get_attr("id").as_deref(), Some("p2-doc"));
+        assert_eq!(
+            scoped,
+            r##"<style>#p2-doc .a { fill: #abc; } #p2-a { fill: red; }</style><rect id="p2-a" data-id="x" fill="url(#p2-a)"/><use href="#p2-a"/><text>&#65;</text>"##
+        );
+    }
+}
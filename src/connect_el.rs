@@ -0,0 +1,348 @@
+//! Generation of connectors from a table of edges.
+//!
+//! The content of a `<connect>` element is a comma-separated table, with a
+//! header row naming the columns, e.g.
+//!
+//! ```xml
+//! <connect class="d-arrow">
+//! from, to, label, class
+//! a, b, uses,
+//! b, c, , d-dashed
+//! </connect>
+//! ```
+//!
+//! Each row generates a connector from the element in the `from` column to the
+//! element in the `to` column, with optional `label` text and additional `class`
+//! values. Other attributes of the `<connect>` element are applied to every
+//! connector.
+//!
+//! Values of another column may be mapped to classes with the `class-from` and
+//! `class-map` attributes, e.g. `class-from="status"` with
+//! `class-map="failed: d-red; optional: d-dash"`.
+//!
+//! Alternatively a `<connect>` element with `start` and `end` attributes (and
+//! no table) connects one element to several others as a tree, e.g.
+//!
+//! ```xml
+//! <connect start="#ceo@b" end="#cto #cfo #coo"/>
+//! ```
+//!
+//! Each connector is a polyline from `start`, sharing a trunk and a bus line
+//! at a common distance (`corner-offset`) from the start element, and then
+//! dropping to the facing side of each `end` element.
+
+use crate::context::{ElementMap, TransformerContext};
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{InputEvent, InputList, OutputEvent, OutputList};
+use crate::expression::eval_attr;
+use crate::position::{split_el_loc, strp_length, BoundingBox, Length, LocSpec};
+use crate::transform::{process_events, EventGen};
+use crate::types::{attr_split, fstr, ElRef};
+
+/// Attributes of `<connect>` which are not copied to generated connectors,
+/// with their defaults where applicable.
+const FROM_COL: (&str, &str) = ("from-col", "from");
+const TO_COL: (&str, &str) = ("to-col", "to");
+const LABEL_COL: (&str, &str) = ("label-col", "label");
+const CLASS_COL: (&str, &str) = ("class-col", "class");
+const CONNECTOR: (&str, &str) = ("connector", "line");
+const CLASS_FROM: (&str, &str) = ("class-from", "");
+const CLASS_MAP: (&str, &str) = ("class-map", "");
+/// Distance from the start element to the bus of a tree, relative to the
+/// distance to the nearest `end` element.
+const TREE_OFFSET: (&str, &str) = ("corner-offset", "50%");
+
+/// Distance from the start element to the bus of a tree when element
+/// positions are not known.
+const DEFAULT_TREE_OFFSET: f32 = 5.;
+
+/// Parse a class mapping of the form `value: class1 class2; value2: class3`.
+fn parse_class_map(value: &str) -> Result<Vec<(String, String)>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .split_once(':')
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .ok_or_else(|| {
+                    SvgdxError::InvalidData(format!("connect: invalid class-map entry '{entry}'"))
+                })
+        })
+        .collect()
+}
+
+/// Element reference for a table cell; ids may be given without a `#` prefix.
+pub(crate) fn cell_elref(cell: &str) -> String {
+    if cell.starts_with(['#', '^']) {
+        cell.to_string()
+    } else {
+        format!("#{cell}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectElement(pub SvgElement);
+
+impl ConnectElement {
+    fn attr_or(&self, (attr, default): (&str, &str)) -> String {
+        self.0.get_attr(attr).unwrap_or(default.to_string())
+    }
+
+    /// Build a connector element for each row of the edge table, or for
+    /// each branch of a tree if `start` is given.
+    pub(crate) fn connectors(&self, context: &TransformerContext) -> Result<Vec<SvgElement>> {
+        // Note the table is kept escaped, as cell values become (raw) attribute values
+        let mut table = String::new();
+        for ev in self.0.inner_events(context).unwrap_or_default().iter() {
+            if let Some(text) = ev.text_string().or_else(|| ev.cdata_string()) {
+                table.push_str(&text);
+            }
+        }
+        if let Some(start) = self.0.get_attr("start") {
+            if !table.trim().is_empty() {
+                return Err(SvgdxError::InvalidData(
+                    "connect: an edge table cannot be used with 'start'".to_string(),
+                ));
+            }
+            return self.tree_connectors(&eval_attr(&start, context), context);
+        }
+        self.table_connectors(&table)
+    }
+
+    /// Build a polyline connector from `start` to each element in `end`,
+    /// with a shared trunk and bus.
+    fn tree_connectors(
+        &self,
+        start: &str,
+        context: &TransformerContext,
+    ) -> Result<Vec<SvgElement>> {
+        let end = self
+            .0
+            .get_attr("end")
+            .ok_or_else(|| SvgdxError::MissingAttribute("end".to_string()))?;
+        let ends: Vec<_> = attr_split(&eval_attr(&end, context)).collect();
+        if ends.is_empty() {
+            return Err(SvgdxError::InvalidData(
+                "connect: 'end' must list at least one element".to_string(),
+            )
+            .in_attr("end"));
+        }
+
+        // Element bounding boxes are only available for elements which have
+        // already been processed.
+        let bbox = |elref: &ElRef| -> Result<Option<BoundingBox>> {
+            match context.get_element(elref) {
+                Some(el) => context.get_element_bbox(el),
+                None => Ok(None),
+            }
+        };
+        let (start_ref, start_loc) = split_el_loc(start).map_err(|e| e.in_attr("start"))?;
+        let start_bb = bbox(&start_ref)?;
+        // The start location may be a port of the start element
+        let start_loc = match (start_loc, context.get_element(&start_ref)) {
+            (Some(loc), Some(el)) => Some(el.port_loc(loc)),
+            (Some(loc), None) => Some(loc.parse()),
+            (None, _) => None,
+        }
+        .transpose()
+        .map_err(|e| e.in_attr("start"))?;
+        let mut end_locs = Vec::new();
+        for end in &ends {
+            let (end_ref, end_loc) = split_el_loc(end).map_err(|e| e.in_attr("end"))?;
+            end_locs.push((end_loc, bbox(&end_ref)?));
+        }
+
+        let side =
+            match start_loc {
+                Some(LocSpec::Top | LocSpec::TopEdge(_)) => LocSpec::Top,
+                Some(LocSpec::Bottom | LocSpec::BottomEdge(_)) => LocSpec::Bottom,
+                Some(LocSpec::Left | LocSpec::LeftEdge(_)) => LocSpec::Left,
+                Some(LocSpec::Right | LocSpec::RightEdge(_)) => LocSpec::Right,
+                None => {
+                    // Towards the average position of the end elements
+                    let centers: Vec<_> = end_locs
+                        .iter()
+                        .filter_map(|(_, bb)| bb.map(|bb| bb.center()))
+                        .collect();
+                    match (start_bb, centers.len()) {
+                        (Some(bb), n) if n > 0 => {
+                            let (sx, sy) = bb.center();
+                            let dx = centers.iter().map(|c| c.0 - sx).sum::<f32>();
+                            let dy = centers.iter().map(|c| c.1 - sy).sum::<f32>();
+                            match (dx.abs() > dy.abs(), dx > 0., dy < 0.) {
+                                (true, true, _) => LocSpec::Right,
+                                (true, false, _) => LocSpec::Left,
+                                (false, _, true) => LocSpec::Top,
+                                (false, _, false) => LocSpec::Bottom,
+                            }
+                        }
+                        _ => LocSpec::Bottom,
+                    }
+                }
+                Some(_) => return Err(SvgdxError::InvalidData(
+                    "connect: tree start location must be on an edge, e.g. 't', 'b', 'l' or 'r'"
+                        .to_string(),
+                )
+                .in_attr("start")),
+            };
+        let (start_name, end_name, facing) = match side {
+            LocSpec::Top => ("t", "b", LocSpec::Bottom),
+            LocSpec::Left => ("l", "r", LocSpec::Right),
+            LocSpec::Right => ("r", "l", LocSpec::Left),
+            _ => ("b", "t", LocSpec::Top),
+        };
+
+        // The bus is placed relative to the nearest end element
+        let offset =
+            strp_length(&self.attr_or(TREE_OFFSET)).map_err(|e| e.in_attr("corner-offset"))?;
+        let horizontal = matches!(side, LocSpec::Left | LocSpec::Right);
+        let gap = start_bb.and_then(|start_bb| {
+            let (sx, sy) = start_bb.locspec(side);
+            end_locs
+                .iter()
+                .filter_map(|(_, bb)| {
+                    let (ex, ey) = (*bb)?.locspec(facing);
+                    Some(if horizontal { ex - sx } else { ey - sy }.abs())
+                })
+                .min_by(f32::total_cmp)
+        });
+        let offset = match (gap, offset) {
+            (Some(gap), offset) => offset.calc_offset(0., gap),
+            (None, Length::Absolute(abs)) => abs,
+            (None, _) => DEFAULT_TREE_OFFSET,
+        };
+
+        let mut template = self.0.clone();
+        template.name = "polyline".to_string();
+        for (attr, _) in [CONNECTOR, TREE_OFFSET] {
+            template.pop_attr(attr);
+        }
+        template.set_attr(
+            "start",
+            &if start_loc.is_some() {
+                start.to_string()
+            } else {
+                format!("{start}@{start_name}")
+            },
+        );
+        template.set_attr("corner-offset", &fstr(offset));
+
+        Ok(ends
+            .iter()
+            .zip(end_locs)
+            .map(|(end, (end_loc, _))| {
+                let mut el = template.clone();
+                if end_loc.is_some() {
+                    el.set_attr("end", end);
+                } else {
+                    el.set_attr("end", &format!("{end}@{end_name}"));
+                }
+                el
+            })
+            .collect())
+    }
+
+    fn table_connectors(&self, table: &str) -> Result<Vec<SvgElement>> {
+        let connector = self.attr_or(CONNECTOR);
+        if !matches!(connector.as_str(), "line" | "polyline") {
+            return Err(SvgdxError::InvalidData(format!(
+                "connect: connector must be 'line' or 'polyline', not '{connector}'"
+            )));
+        }
+        let mut rows = table
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| line.split(',').map(str::trim).collect::<Vec<_>>());
+        let header = rows
+            .next()
+            .ok_or_else(|| SvgdxError::InvalidData("connect: missing edge table".to_string()))?;
+        let column = |spec| {
+            let name = self.attr_or(spec);
+            header.iter().position(|h| *h == name)
+        };
+        let (from_idx, to_idx) = match (column(FROM_COL), column(TO_COL)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                return Err(SvgdxError::InvalidData(format!(
+                    "connect: edge table must have '{}' and '{}' columns",
+                    self.attr_or(FROM_COL),
+                    self.attr_or(TO_COL)
+                )))
+            }
+        };
+        let (label_idx, class_idx) = (column(LABEL_COL), column(CLASS_COL));
+        let class_map = parse_class_map(&self.attr_or(CLASS_MAP))?;
+        let class_from_idx = match self.attr_or(CLASS_FROM).as_str() {
+            "" => None,
+            name => Some(header.iter().position(|h| *h == name).ok_or_else(|| {
+                SvgdxError::InvalidData(format!("connect: edge table has no '{name}' column"))
+            })?),
+        };
+
+        let mut template = self.0.clone();
+        template.name = connector;
+        for (attr, _) in [
+            FROM_COL, TO_COL, LABEL_COL, CLASS_COL, CONNECTOR, CLASS_FROM, CLASS_MAP,
+        ] {
+            template.pop_attr(attr);
+        }
+
+        let mut connectors = Vec::new();
+        for row in rows {
+            let cell = |idx: Option<usize>| {
+                idx.and_then(|i| row.get(i))
+                    .filter(|c| !c.is_empty())
+                    .map(|c| c.to_string())
+            };
+            let (Some(from), Some(to)) = (cell(Some(from_idx)), cell(Some(to_idx))) else {
+                return Err(SvgdxError::InvalidData(format!(
+                    "connect: missing '{}' or '{}' value in row '{}'",
+                    self.attr_or(FROM_COL),
+                    self.attr_or(TO_COL),
+                    row.join(", ")
+                )));
+            };
+            let mut el = template.clone();
+            el.set_attr("start", &cell_elref(&from));
+            el.set_attr("end", &cell_elref(&to));
+            if let Some(label) = cell(label_idx) {
+                el.set_attr("text", &label);
+            }
+            let mapped = cell(class_from_idx).and_then(|value| {
+                class_map
+                    .iter()
+                    .find(|(k, _)| *k == value)
+                    .map(|(_, classes)| classes.clone())
+            });
+            for classes in [cell(class_idx), mapped].into_iter().flatten() {
+                for class in classes.split_whitespace() {
+                    el.classes.insert(class.to_string());
+                }
+            }
+            connectors.push(el);
+        }
+        Ok(connectors)
+    }
+}
+
+impl EventGen for ConnectElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        // Generated connectors are positioned at the `<connect>` element, so
+        // diagnostics refer to the table they were defined in.
+        let mut events = InputList::new();
+        for (idx, el) in self.connectors(context)?.into_iter().enumerate() {
+            if idx > 0 {
+                events.push(InputEvent::from(OutputEvent::Text("\n".to_string())));
+            }
+            events.push(InputEvent::from(OutputEvent::Empty(el)).at_position_of_element(&self.0));
+        }
+        process_events(events, context)
+    }
+}
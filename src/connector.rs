@@ -1,7 +1,9 @@
-use crate::context::ElementMap;
+use crate::context::{ElementMap, Segment, TransformerContext};
 use crate::element::SvgElement;
 use crate::errors::{Result, SvgdxError};
-use crate::position::{parse_el_loc, strp_length, Length, LocSpec, ScalarSpec};
+use crate::path::{element_polylines, outline_crossings};
+use crate::position::{split_el_loc, strp_length, BoundingBox, Length, LocSpec, ScalarSpec};
+use crate::route::{route, simplify, RouteEnd};
 use crate::types::{attr_split, fstr, strp};
 
 #[derive(Clone, Copy, Debug)]
@@ -12,6 +14,34 @@ enum Direction {
     Left,
 }
 
+impl Direction {
+    const fn vector(self) -> (f32, f32) {
+        match self {
+            Self::Up => (0., -1.),
+            Self::Right => (1., 0.),
+            Self::Down => (0., 1.),
+            Self::Left => (-1., 0.),
+        }
+    }
+}
+
+/// Default absolute offset, e.g. for U-shaped connectors and route margins
+const DEFAULT_ABS_OFFSET: f32 = 3.;
+/// Spacing between connectors joining the same pair of elements
+const PARALLEL_SPACING: f32 = 2.;
+
+/// Element types whose connector endpoints are moved from their bounding box
+/// onto their outline.
+const OUTLINE_SHAPES: &[&str] = &["circle", "ellipse", "polygon", "path"];
+
+/// Element types which are treated as obstacles when routing connectors.
+/// Only elements with an `id` have a known position once processed.
+/// Radius of the hop drawn where a connector crosses an earlier connector
+/// when `connector-jumps` is enabled.
+const JUMP_RADIUS: f32 = 1.5;
+
+const ROUTE_OBSTACLES: &str = "rect, circle, ellipse, polygon, image, use";
+
 #[derive(Clone, Copy, Debug)]
 struct Endpoint {
     origin: (f32, f32),
@@ -30,6 +60,10 @@ pub enum ConnectionType {
     Vertical,
     Corner,
     Straight,
+    /// Orthogonal route avoiding other elements
+    Route,
+    /// Cubic bezier curve following the endpoint directions
+    Curve,
 }
 
 impl ConnectionType {
@@ -37,6 +71,8 @@ impl ConnectionType {
         match s {
             "h" | "horizontal" => Self::Horizontal,
             "v" | "vertical" => Self::Vertical,
+            "route" => Self::Route,
+            "curve" => Self::Curve,
             _ => Self::Straight,
         }
     }
@@ -46,7 +82,7 @@ fn edge_locations(ctype: ConnectionType) -> Vec<LocSpec> {
     match ctype {
         ConnectionType::Horizontal => vec![LocSpec::Left, LocSpec::Right],
         ConnectionType::Vertical => vec![LocSpec::Top, LocSpec::Bottom],
-        ConnectionType::Corner => {
+        ConnectionType::Corner | ConnectionType::Route | ConnectionType::Curve => {
             vec![LocSpec::Top, LocSpec::Right, LocSpec::Bottom, LocSpec::Left]
         }
         ConnectionType::Straight => vec![
@@ -71,6 +107,136 @@ pub struct Connector {
     end: Endpoint,
     conn_type: ConnectionType,
     offset: Option<Length>,
+    /// Intermediate points the connector passes through
+    via: Vec<(f32, f32)>,
+    /// Distance the connector is moved to the left of its direction of travel
+    lateral: f32,
+    /// Distances by which the connector is shortened at its start and end
+    margins: (f32, f32),
+    /// Radii of rounded corners, one per corner with the last repeated
+    corner_radius: Vec<Length>,
+}
+
+/// Key identifying connectors of the same type joining the same elements
+/// (and explicit locations), in either direction, and whether `el` runs in
+/// the reverse direction to the key.
+fn connector_key(el: &SvgElement, ctx: &impl ElementMap) -> Option<(String, bool)> {
+    let id = |attr| {
+        let value = el.get_attr(attr)?;
+        let (elref, loc) = split_el_loc(&value).ok()?;
+        let id = ctx.get_element(&elref)?.get_attr("id")?;
+        Some(format!("{id}{loc:?}"))
+    };
+    let (start, end) = (id("start")?, id("end")?);
+    let reversed = start > end;
+    let (a, b) = if reversed { (end, start) } else { (start, end) };
+    let edge_type = el.get_attr("edge-type").unwrap_or_default();
+    Some((format!("{} {edge_type} {a} {b}", el.name), reversed))
+}
+
+/// Give connector `el` a default `offset` separating it from any connectors
+/// already rendered between the same pair of elements, returning the key to
+/// record once `el` is rendered.
+///
+/// Successive connectors are offset by 0, +1, -1, +2, ... times the spacing,
+/// relative to the direction of the first.
+pub fn parallel_offset(el: &mut SvgElement, ctx: &TransformerContext) -> Option<String> {
+    let (key, reversed) = connector_key(el, ctx)?;
+    let count = ctx.connector_count(&key);
+    let steps = count.div_ceil(2) as f32;
+    let offset = if count % 2 == 1 { steps } else { -steps } * PARALLEL_SPACING;
+    if offset != 0. {
+        el.set_default_attr("offset", &fstr(if reversed { -offset } else { offset }));
+    }
+    Some(key)
+}
+
+/// Points of a polyline parallel to `points`, at a distance `d` to the left
+/// of its direction of travel.
+fn offset_points(points: &[(f32, f32)], d: f32) -> Vec<(f32, f32)> {
+    let normals: Vec<(f32, f32)> = points
+        .windows(2)
+        .map(|s| {
+            let (dx, dy) = (s[1].0 - s[0].0, s[1].1 - s[0].1);
+            let len = dx.hypot(dy);
+            if len == 0. {
+                (0., 0.)
+            } else {
+                (dy / len, -dx / len)
+            }
+        })
+        .collect();
+    points
+        .iter()
+        .enumerate()
+        .map(|(idx, &(x, y))| {
+            let before = idx.checked_sub(1).and_then(|i| normals.get(i));
+            let (nx, ny) = match (before, normals.get(idx)) {
+                // corners are moved to the intersection of the offset segments
+                (Some(a), Some(b)) if a.0 * b.0 + a.1 * b.1 > -0.99 => {
+                    let scale = 1. + a.0 * b.0 + a.1 * b.1;
+                    ((a.0 + b.0) / scale, (a.1 + b.1) / scale)
+                }
+                (Some(n), _) | (None, Some(n)) => *n,
+                (None, None) => (0., 0.),
+            };
+            (x + nx * d, y + ny * d)
+        })
+        .collect()
+}
+
+/// Parse a `via` attribute value, consisting of element references (with
+/// optional locations; the centre by default) and coordinate pairs.
+fn via_points(value: &str, context: &impl ElementMap) -> Result<Vec<(f32, f32)>> {
+    let mut points = Vec::new();
+    let mut coords = Vec::new();
+    for part in attr_split(value) {
+        if let Ok((elref, loc)) = split_el_loc(&part) {
+            if !coords.is_empty() {
+                break;
+            }
+            let el = context
+                .get_element(&elref)
+                .ok_or_else(|| SvgdxError::ReferenceError(elref.clone()))?;
+            let loc = loc.map(|loc| el.port_loc(loc)).transpose()?;
+            points.push(element_loc(el, loc.unwrap_or(LocSpec::Center), context)?);
+        } else {
+            coords.push(strp(&part)?);
+            if let [x, y] = coords[..] {
+                points.push((x, y));
+                coords.clear();
+            }
+        }
+    }
+    if !coords.is_empty() {
+        return Err(SvgdxError::InvalidData(format!(
+            "via coordinates must be in pairs: '{value}'"
+        )));
+    }
+    Ok(points)
+}
+
+/// Orthogonal points passing through each of `points` in turn, leaving the
+/// first in `start_dir` and arriving at the last against `end_dir` (i.e. into
+/// an element at that side) where given.
+fn elbow_points(
+    points: &[(f32, f32)],
+    start_dir: Option<Direction>,
+    end_dir: Option<Direction>,
+) -> Vec<(f32, f32)> {
+    let is_horizontal = |d: Direction| matches!(d, Direction::Left | Direction::Right);
+    // each leg starts along the same axis, so waypoints are corners
+    let mut horizontal = start_dir.is_none_or(is_horizontal);
+    let mut result = vec![points[0]];
+    for (idx, leg) in points.windows(2).enumerate() {
+        let ((x1, y1), (x2, y2)) = (leg[0], leg[1]);
+        if let (true, Some(end_dir)) = (idx + 2 == points.len(), end_dir) {
+            horizontal = !is_horizontal(end_dir);
+        }
+        result.push(if horizontal { (x2, y1) } else { (x1, y2) });
+        result.push((x2, y2));
+    }
+    result
 }
 
 fn closest_loc(
@@ -98,6 +264,15 @@ fn closest_loc(
     Ok(min_loc)
 }
 
+/// Resolve `loc` on the given element, using its rendered geometry where
+/// available (e.g. the midpoint or corners of another connector).
+fn element_loc(el: &SvgElement, loc: LocSpec, context: &impl ElementMap) -> Result<(f32, f32)> {
+    let bb = context
+        .get_element_bbox(el)?
+        .ok_or_else(|| SvgdxError::MissingBoundingBox(el.to_string()))?;
+    el.loc_point(loc, &bb)
+}
+
 fn shortest_link(
     this: &SvgElement,
     that: &SvgElement,
@@ -131,6 +306,291 @@ fn shortest_link(
     Ok((this_min_loc, that_min_loc))
 }
 
+/// Apply any `dir` attribute to a connector element, setting its arrow class.
+///
+/// Returns `true` if the connector should be drawn from `end` to `start`.
+fn apply_dir(element: &mut SvgElement) -> Result<bool> {
+    let Some(dir) = element.pop_attr("dir") else {
+        return Ok(false);
+    };
+    let (class, reverse) = match dir.as_str() {
+        "forward" => (Some("d-arrow"), false),
+        "back" => (Some("d-arrow"), true),
+        "both" => (Some("d-arrow-both"), false),
+        "none" => (None, false),
+        _ => {
+            return Err(SvgdxError::InvalidData(format!(
+                "dir must be one of forward, back, both or none; got '{dir}'"
+            ))
+            .in_attr("dir"));
+        }
+    };
+    for arrow in ["d-arrow", "d-arrow-start", "d-arrow-both", "d-biarrow"] {
+        element.pop_class(arrow);
+    }
+    if let Some(class) = class {
+        element.add_class(class);
+    }
+    Ok(reverse)
+}
+
+/// Parse a non-negative connector margin attribute, removing it from `element`.
+fn pop_margin(element: &mut SvgElement, attr: &str) -> Result<f32> {
+    match element.pop_attr(attr) {
+        Some(value) => {
+            let margin = strp(&value).map_err(|e| e.in_attr(attr))?;
+            if margin < 0. {
+                return Err(
+                    SvgdxError::InvalidData(format!("{attr} must not be negative")).in_attr(attr),
+                );
+            }
+            Ok(margin)
+        }
+        None => Ok(0.),
+    }
+}
+
+/// Remove the given distance from the start of a polyline.
+fn trim_start(points: &mut Vec<(f32, f32)>, mut dist: f32) {
+    while dist > 0. && points.len() >= 2 {
+        let (a, b) = (points[0], points[1]);
+        let len = (b.0 - a.0).hypot(b.1 - a.1);
+        if len > dist {
+            let t = dist / len;
+            points[0] = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+            return;
+        }
+        points.remove(0);
+        dist -= len;
+    }
+}
+
+/// Shorten a polyline by the given distances at its start and end.
+fn trim_points(points: &[(f32, f32)], (start, end): (f32, f32)) -> Result<Vec<(f32, f32)>> {
+    let length: f32 = points
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+        .sum();
+    if start + end >= length {
+        return Err(SvgdxError::InvalidData(format!(
+            "connector margins ({start} + {end}) exceed its length ({length})"
+        )));
+    }
+    let mut points = points.to_vec();
+    trim_start(&mut points, start);
+    points.reverse();
+    trim_start(&mut points, end);
+    points.reverse();
+    Ok(points)
+}
+
+/// Path data for a polyline with rounded corners.
+///
+/// Each corner takes the next radius from `radii`, with the last repeated for
+/// any remaining corners; ratio lengths are relative to the shortest segment.
+/// Radii are reduced where necessary so the curve at each corner takes no more
+/// than half of either adjoining segment. Returns `None` if there are no corners.
+fn points_to_path(points: &[(f32, f32)], radii: &[Length]) -> Option<String> {
+    let points: Vec<_> = points
+        .iter()
+        .enumerate()
+        .filter(|&(idx, p)| idx == 0 || points[idx - 1] != *p)
+        .map(|(_, p)| *p)
+        .collect();
+    if points.len() < 3 || radii.is_empty() {
+        return None;
+    }
+    let seg_len = |a: (f32, f32), b: (f32, f32)| (b.0 - a.0).hypot(b.1 - a.1);
+    let shortest = points
+        .windows(2)
+        .map(|w| seg_len(w[0], w[1]))
+        .fold(f32::INFINITY, f32::min);
+    let mut d = vec![format!("M {} {}", fstr(points[0].0), fstr(points[0].1))];
+    for (idx, w) in points.windows(3).enumerate() {
+        let (a, p, b) = (w[0], w[1], w[2]);
+        let (len_in, len_out) = (seg_len(a, p), seg_len(p, b));
+        let u_in = ((p.0 - a.0) / len_in, (p.1 - a.1) / len_in);
+        let u_out = ((b.0 - p.0) / len_out, (b.1 - p.1) / len_out);
+        let cross = u_in.0 * u_out.1 - u_in.1 * u_out.0;
+        // half the angle between the two segments at the corner
+        let half_angle = (-(u_in.0 * u_out.0 + u_in.1 * u_out.1))
+            .clamp(-1., 1.)
+            .acos()
+            / 2.;
+        let radius = radii[idx.min(radii.len() - 1)].evaluate(shortest);
+        if radius <= 0. || cross.abs() < 1e-6 {
+            d.push(format!("L {} {}", fstr(p.0), fstr(p.1)));
+            continue;
+        }
+        // distance from the corner at which the curve meets each segment
+        let tangent = (radius / half_angle.tan())
+            .min(len_in / 2.)
+            .min(len_out / 2.);
+        let radius = tangent * half_angle.tan();
+        let (x1, y1) = (p.0 - u_in.0 * tangent, p.1 - u_in.1 * tangent);
+        let (x2, y2) = (p.0 + u_out.0 * tangent, p.1 + u_out.1 * tangent);
+        d.push(format!("L {} {}", fstr(x1), fstr(y1)));
+        d.push(format!(
+            "A {r} {r} 0 0 {} {} {}",
+            u8::from(cross > 0.),
+            fstr(x2),
+            fstr(y2),
+            r = fstr(radius)
+        ));
+    }
+    let last = points[points.len() - 1];
+    d.push(format!("L {} {}", fstr(last.0), fstr(last.1)));
+    Some(d.join(" "))
+}
+
+/// Update the coordinates of a line or polyline element.
+fn set_vertices(el: &mut SvgElement, points: &[(f32, f32)]) {
+    if el.name == "line" {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        el.set_attr("x1", &fstr(first.0));
+        el.set_attr("y1", &fstr(first.1));
+        el.set_attr("x2", &fstr(last.0));
+        el.set_attr("y2", &fstr(last.1));
+    } else {
+        let points: Vec<_> = points
+            .iter()
+            .map(|(x, y)| format!("{} {}", fstr(*x), fstr(*y)))
+            .collect();
+        el.set_attr("points", &points.join(", "));
+    }
+}
+
+/// Line or polyline element through the given points.
+fn points_element(points: Vec<(f32, f32)>, source: &SvgElement) -> SvgElement {
+    if points.len() == 2 {
+        SvgElement::new(
+            "line",
+            &[
+                ("x1".to_string(), fstr(points[0].0)),
+                ("y1".to_string(), fstr(points[0].1)),
+                ("x2".to_string(), fstr(points[1].0)),
+                ("y2".to_string(), fstr(points[1].1)),
+            ],
+        )
+        .with_attrs_from(source)
+    } else {
+        SvgElement::new(
+            "polyline",
+            &[(
+                "points".to_string(),
+                points
+                    .into_iter()
+                    .map(|(px, py)| format!("{} {}", fstr(px), fstr(py)))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )],
+        )
+        .with_attrs_from(source)
+    }
+}
+
+/// Fraction along the segment `a`-`b` at which it crosses the segment `p`-`q`,
+/// if the two cross other than at their ends.
+fn segment_crossing(a: (f32, f32), b: (f32, f32), p: (f32, f32), q: (f32, f32)) -> Option<f32> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (ex, ey) = (q.0 - p.0, q.1 - p.1);
+    let denom = dx * ey - dy * ex;
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let (fx, fy) = (p.0 - a.0, p.1 - a.1);
+    let t = (fx * ey - fy * ex) / denom;
+    let u = (fx * dy - fy * dx) / denom;
+    let inside = |v: f32| v > 1e-4 && v < 1. - 1e-4;
+    (inside(t) && inside(u)).then_some(t)
+}
+
+/// Path data for the given vertices, with a semicircular hop of radius `r`
+/// wherever a segment crosses one of `others`.
+///
+/// Hops are omitted where there is insufficient room, i.e. within `r` of a
+/// vertex or overlapping a previous hop. Returns `None` if there are no hops.
+fn jump_path(points: &[(f32, f32)], others: &[Segment], r: f32) -> Option<String> {
+    let mut d = vec![format!("M {} {}", fstr(points[0].0), fstr(points[0].1))];
+    let mut hops = 0;
+    for (&a, &b) in points.iter().zip(points.iter().skip(1)) {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let len = dx.hypot(dy);
+        if len == 0. {
+            continue;
+        }
+        let (ux, uy) = (dx / len, dy / len);
+        let mut crossings: Vec<f32> = others
+            .iter()
+            .filter_map(|&(p, q)| segment_crossing(a, b, p, q))
+            .map(|t| t * len)
+            .collect();
+        crossings.sort_by(f32::total_cmp);
+        // hops go 'over' the crossed connector, i.e. upwards or leftwards
+        let sweep = if dx.abs() > dy.abs() {
+            dx > 0.
+        } else {
+            dy < 0.
+        };
+        let mut last = 0.;
+        for dist in crossings {
+            if dist - r < last || dist + r > len {
+                continue;
+            }
+            let (x1, y1) = (a.0 + ux * (dist - r), a.1 + uy * (dist - r));
+            let (x2, y2) = (a.0 + ux * (dist + r), a.1 + uy * (dist + r));
+            d.push(format!("L {} {}", fstr(x1), fstr(y1)));
+            d.push(format!(
+                "A {r} {r} 0 0 {} {} {}",
+                u8::from(sweep),
+                fstr(x2),
+                fstr(y2),
+                r = fstr(r)
+            ));
+            last = dist + r;
+            hops += 1;
+        }
+        d.push(format!("L {} {}", fstr(b.0), fstr(b.1)));
+    }
+    (hops > 0).then(|| d.join(" "))
+}
+
+/// If `connector-jumps` is enabled, replace a rendered line or polyline
+/// connector with a path which hops over any earlier connectors it crosses.
+///
+/// The geometry of each connector is recorded (in document coordinates,
+/// allowing for enclosing group translations) for use by later connectors.
+pub fn connector_jumps(el: &mut SvgElement, ctx: &mut TransformerContext) -> Result<()> {
+    if !ctx.config.connector_jumps {
+        return Ok(());
+    }
+    let Ok(polylines) = element_polylines(el) else {
+        return Ok(());
+    };
+    let (ox, oy) = ctx.group_offset();
+    let to_doc = |(x, y): (f32, f32)| (x + ox, y + oy);
+    let segments: Vec<_> = polylines
+        .iter()
+        .flat_map(|p| p.iter().zip(p.iter().skip(1)))
+        .map(|(&a, &b)| (to_doc(a), to_doc(b)))
+        .collect();
+    if let Some(points) = el.vertices().filter(|_| el.name != "polygon") {
+        let others: Vec<_> = ctx
+            .connector_segments()
+            .iter()
+            .map(|&(a, b)| ((a.0 - ox, a.1 - oy), (b.0 - ox, b.1 - oy)))
+            .collect();
+        if let Some(d) = jump_path(&points, &others, JUMP_RADIUS) {
+            let source = ["x1", "y1", "x2", "y2", "points"]
+                .iter()
+                .fold(el.clone(), |src, attr| src.without_attr(attr));
+            *el = SvgElement::new("path", &[("d".to_string(), d)]).with_attrs_from(&source);
+        }
+    }
+    ctx.add_connector_segments(segments);
+    Ok(())
+}
+
 impl Connector {
     fn loc_to_dir(loc: LocSpec) -> Option<Direction> {
         match loc {
@@ -142,18 +602,126 @@ impl Connector {
         }
     }
 
+    /// Direction of a connector endpoint at `loc` on `el`. Connectors joined
+    /// to the start or end of a line-like element continue in the direction of
+    /// that line where it is horizontal or vertical.
+    /// Parse an endpoint location, which may be the name of a port of the
+    /// endpoint element.
+    fn resolve_loc(el: Option<&SvgElement>, loc: &str) -> Result<LocSpec> {
+        match el {
+            Some(el) => el.port_loc(loc),
+            None => loc.parse(),
+        }
+    }
+
+    fn endpoint_dir(el: Option<&SvgElement>, loc: LocSpec) -> Option<Direction> {
+        let points = el.and_then(SvgElement::vertices);
+        let (a, b) = match (loc, points.as_deref()) {
+            (LocSpec::Start, Some([a, b, ..])) => (*b, *a),
+            (LocSpec::End, Some([.., a, b])) => (*a, *b),
+            _ => return Self::loc_to_dir(loc),
+        };
+        match (b.0 - a.0, b.1 - a.1) {
+            (dx, dy) if dx == 0. && dy < 0. => Some(Direction::Up),
+            (dx, dy) if dx > 0. && dy == 0. => Some(Direction::Right),
+            (dx, dy) if dx == 0. && dy > 0. => Some(Direction::Down),
+            (dx, dy) if dx < 0. && dy == 0. => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    /// Whether endpoints at `loc` on `el` are moved onto its outline.
+    fn clips_to_outline(el: &SvgElement, loc: LocSpec) -> bool {
+        OUTLINE_SHAPES.contains(&el.name.as_str())
+            && !el.has_attr("transform")
+            && !matches!(
+                loc,
+                LocSpec::Center | LocSpec::Mid | LocSpec::Corner(_) | LocSpec::Start | LocSpec::End
+            )
+    }
+
+    /// Move endpoint `ep` at `loc` on `el` from its bounding box onto its
+    /// outline, where `el` is a non-rectangular shape.
+    ///
+    /// If `towards` is given (for automatically placed straight connectors)
+    /// the endpoint is where the line from that point to the centre of `el`
+    /// meets the outline. Otherwise endpoints with a direction are moved
+    /// inwards along it, and others towards the centre of `el`.
+    fn clip_endpoint(
+        ep: Endpoint,
+        el: &SvgElement,
+        loc: LocSpec,
+        towards: Option<(f32, f32)>,
+        ctx: &impl ElementMap,
+    ) -> Result<Endpoint> {
+        if !Self::clips_to_outline(el, loc) {
+            return Ok(ep);
+        }
+        let bb = ctx
+            .get_element_bbox(el)?
+            .ok_or_else(|| SvgdxError::MissingBoundingBox(el.to_string()))?;
+        let centre = bb.locspec(LocSpec::Center);
+        let reach = bb.width() + bb.height();
+        let (from, to) = match (towards, ep.dir) {
+            (Some(point), _) => (point, centre),
+            (None, Some(dir)) => {
+                let (dx, dy) = dir.vector();
+                (
+                    ep.origin,
+                    (ep.origin.0 - dx * reach, ep.origin.1 - dy * reach),
+                )
+            }
+            (None, None) => (ep.origin, centre),
+        };
+        // the first crossing reached from outside the shape
+        Ok(match outline_crossings(el, from, to)?.first() {
+            Some(&t) => Endpoint::new(
+                (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t),
+                ep.dir,
+            ),
+            None => ep,
+        })
+    }
+
     pub fn from_element(
         element: &SvgElement,
         elem_map: &impl ElementMap,
         conn_type: ConnectionType,
     ) -> Result<Self> {
         let mut element = element.clone();
-        let start_ref = element
+        let mut start_ref = element
             .pop_attr("start")
             .ok_or_else(|| SvgdxError::MissingAttribute("start".to_string()))?;
-        let end_ref = element
+        let mut end_ref = element
             .pop_attr("end")
             .ok_or_else(|| SvgdxError::MissingAttribute("end".to_string()))?;
+        let reverse = apply_dir(&mut element)?;
+        if reverse {
+            std::mem::swap(&mut start_ref, &mut end_ref);
+        }
+        let via = match element.pop_attr("via") {
+            Some(via) => via_points(&via, elem_map).map_err(|e| e.in_attr("via"))?,
+            None => Vec::new(),
+        };
+        let lateral = match element.pop_attr("offset") {
+            Some(o) => strp(&o).map_err(|e| e.in_attr("offset"))?,
+            None => 0.,
+        };
+        let mut margins = (
+            pop_margin(&mut element, "margin-start")?,
+            pop_margin(&mut element, "margin-end")?,
+        );
+        if reverse {
+            // margins stay with the element given in `start` / `end`
+            margins = (margins.1, margins.0);
+        }
+        let corner_radius = match element.pop_attr("corner-radius") {
+            Some(radii) => attr_split(&radii)
+                .map(|r| strp_length(&r))
+                .collect::<Result<Vec<_>>>()
+                .map_err(|e| e.in_attr("corner-radius"))?,
+            None => Vec::new(),
+        };
         let offset = if let Some(o_inner) = element.pop_attr("corner-offset") {
             Some(
                 strp_length(&o_inner)
@@ -175,14 +743,16 @@ impl Connector {
         let mut end_point: Option<(f32, f32)> = None;
         let mut start_dir: Option<Direction> = None;
         let mut end_dir: Option<Direction> = None;
+        let straight = matches!(conn_type, ConnectionType::Straight);
 
         // Example: "#thing@tl" => top left coordinate of element id="thing"
-        if let Ok((elref, loc)) = parse_el_loc(&start_ref) {
+        if let Ok((elref, loc)) = split_el_loc(&start_ref) {
+            start_el = elem_map.get_element(&elref);
             if let Some(loc) = loc {
-                start_dir = Self::loc_to_dir(loc);
+                let loc = Self::resolve_loc(start_el, loc)?;
+                start_dir = Self::endpoint_dir(start_el, loc);
                 start_loc = Some(loc);
             }
-            start_el = elem_map.get_element(&elref);
         } else {
             let mut parts = attr_split(&start_ref).map_while(|v| strp(&v).ok());
             start_point = Some((
@@ -194,12 +764,13 @@ impl Connector {
                 })?,
             ));
         }
-        if let Ok((elref, loc)) = parse_el_loc(&end_ref) {
+        if let Ok((elref, loc)) = split_el_loc(&end_ref) {
+            end_el = elem_map.get_element(&elref);
             if let Some(loc) = loc {
-                end_dir = Self::loc_to_dir(loc);
+                let loc = Self::resolve_loc(end_el, loc)?;
+                end_dir = Self::endpoint_dir(end_el, loc);
                 end_loc = Some(loc);
             }
-            end_el = elem_map.get_element(&elref);
         } else {
             let mut parts = attr_split(&end_ref).map_while(|v| strp(&v).ok());
             end_point = Some((
@@ -212,7 +783,46 @@ impl Connector {
             ));
         }
 
-        let (start, end) = match (start_point, end_point) {
+        if let (Some(&first), Some(&last)) = (via.first(), via.last()) {
+            // endpoints are independent, each facing the nearest waypoint
+            let endpoint = |point: Option<(f32, f32)>,
+                            el: Option<&SvgElement>,
+                            loc: Option<LocSpec>,
+                            towards| {
+                if let Some(point) = point {
+                    return Ok(Endpoint::new(point, None));
+                }
+                let el = el.ok_or_else(|| SvgdxError::InternalLogicError("no el".to_owned()))?;
+                let (loc, towards) = match loc {
+                    Some(loc) => (loc, None),
+                    None => (
+                        closest_loc(el, towards, conn_type, elem_map)?,
+                        Some(towards).filter(|_| straight),
+                    ),
+                };
+                let ep = Endpoint::new(
+                    element_loc(el, loc, elem_map)?,
+                    Self::endpoint_dir(Some(el), loc),
+                );
+                Self::clip_endpoint(ep, el, loc, towards, elem_map)
+            };
+            return Ok(Self {
+                start: endpoint(start_point, start_el, start_loc, first)?,
+                end: endpoint(end_point, end_el, end_loc, last)?,
+                source_element: element,
+                start_el: start_el.cloned(),
+                end_el: end_el.cloned(),
+                conn_type,
+                offset,
+                via,
+                lateral,
+                margins,
+                corner_radius,
+            });
+        }
+
+        let (start_auto, end_auto) = (start_loc.is_none(), end_loc.is_none());
+        let (mut start, mut end) = match (start_point, end_point) {
             (Some(start_point), Some(end_point)) => (
                 Endpoint::new(start_point, start_dir),
                 Endpoint::new(end_point, end_dir),
@@ -225,10 +835,8 @@ impl Connector {
                     end_loc = Some(eloc);
                     end_dir = Self::loc_to_dir(eloc);
                 }
-                let end_coord = elem_map
-                    .get_element_bbox(end_el)?
-                    .ok_or_else(|| SvgdxError::MissingBoundingBox(end_el.to_string()))?
-                    .locspec(end_loc.expect("Set from closest_loc"));
+                let end_coord =
+                    element_loc(end_el, end_loc.expect("Set from closest_loc"), elem_map)?;
                 (
                     Endpoint::new(start_point, start_dir),
                     Endpoint::new(end_coord, end_dir),
@@ -242,10 +850,8 @@ impl Connector {
                     start_loc = Some(sloc);
                     start_dir = Self::loc_to_dir(sloc);
                 }
-                let start_coord = elem_map
-                    .get_element_bbox(start_el)?
-                    .ok_or_else(|| SvgdxError::MissingBoundingBox(start_el.to_string()))?
-                    .locspec(start_loc.expect("Set from closest_loc"));
+                let start_coord =
+                    element_loc(start_el, start_loc.expect("Set from closest_loc"), elem_map)?;
                 (
                     Endpoint::new(start_coord, start_dir),
                     Endpoint::new(end_point, end_dir),
@@ -264,36 +870,44 @@ impl Connector {
                     start_dir = Self::loc_to_dir(sloc);
                     end_dir = Self::loc_to_dir(eloc);
                 } else if start_loc.is_none() {
-                    let end_coord = elem_map
-                        .get_element_bbox(end_el)?
-                        .ok_or_else(|| SvgdxError::MissingBoundingBox(end_el.to_string()))?
-                        .locspec(end_loc.expect("Not both None"));
+                    let end_coord = element_loc(end_el, end_loc.expect("Not both None"), elem_map)?;
                     let sloc = closest_loc(start_el, end_coord, conn_type, elem_map)?;
                     start_loc = Some(sloc);
                     start_dir = Self::loc_to_dir(sloc);
                 } else if end_loc.is_none() {
-                    let start_coord = elem_map
-                        .get_element_bbox(start_el)?
-                        .ok_or_else(|| SvgdxError::MissingBoundingBox(start_el.to_string()))?
-                        .locspec(start_loc.expect("Not both None"));
+                    let start_coord =
+                        element_loc(start_el, start_loc.expect("Not both None"), elem_map)?;
                     let eloc = closest_loc(end_el, start_coord, conn_type, elem_map)?;
                     end_loc = Some(eloc);
                     end_dir = Self::loc_to_dir(eloc);
                 }
-                let start_coord = elem_map
-                    .get_element_bbox(start_el)?
-                    .ok_or_else(|| SvgdxError::MissingBoundingBox(start_el.to_string()))?
-                    .locspec(start_loc.expect("Set above"));
-                let end_coord = elem_map
-                    .get_element_bbox(end_el)?
-                    .ok_or_else(|| SvgdxError::MissingBoundingBox(end_el.to_string()))?
-                    .locspec(end_loc.expect("Set above"));
+                let start_coord = element_loc(start_el, start_loc.expect("Set above"), elem_map)?;
+                let end_coord = element_loc(end_el, end_loc.expect("Set above"), elem_map)?;
                 (
                     Endpoint::new(start_coord, start_dir),
                     Endpoint::new(end_coord, end_dir),
                 )
             }
         };
+
+        // Automatically placed straight connectors join non-rectangular
+        // shapes along the line towards their centres.
+        let reference = |ep: &Endpoint, el: Option<&SvgElement>, loc, auto: bool| match (el, loc) {
+            (Some(el), Some(loc)) if straight && auto && Self::clips_to_outline(el, loc) => {
+                Ok(element_loc(el, LocSpec::Center, elem_map)?)
+            }
+            _ => Ok::<_, SvgdxError>(ep.origin),
+        };
+        let start_ref = reference(&start, start_el, start_loc, start_auto)?;
+        let end_ref = reference(&end, end_el, end_loc, end_auto)?;
+        if let (Some(el), Some(loc)) = (start_el, start_loc) {
+            let towards = Some(end_ref).filter(|_| straight && start_auto);
+            start = Self::clip_endpoint(start, el, loc, towards, elem_map)?;
+        }
+        if let (Some(el), Some(loc)) = (end_el, end_loc) {
+            let towards = Some(start_ref).filter(|_| straight && end_auto);
+            end = Self::clip_endpoint(end, el, loc, towards, elem_map)?;
+        }
         Ok(Self {
             source_element: element,
             start,
@@ -302,12 +916,86 @@ impl Connector {
             end_el: end_el.cloned(),
             conn_type,
             offset,
+            via,
+            lateral,
+            margins,
+            corner_radius,
         })
     }
 
     pub fn render(&self, ctx: &impl ElementMap) -> Result<SvgElement> {
+        let mut conn_element = if self.via.is_empty() {
+            if matches!(self.conn_type, ConnectionType::Curve) && self.margins != (0., 0.) {
+                // curves have no vertices to trim, so are drawn between
+                // endpoints moved outwards by the margins instead.
+                self.with_curve_margins().render_direct(ctx)?
+            } else {
+                self.render_direct(ctx)?
+            }
+        } else {
+            self.render_via(ctx, Length::Absolute(DEFAULT_ABS_OFFSET))?
+        };
+        if self.lateral != 0. {
+            if let Some(points) = conn_element.vertices() {
+                conn_element =
+                    points_element(offset_points(&points, self.lateral), &self.source_element);
+            }
+        }
+        if self.margins != (0., 0.) {
+            if let Some(points) = conn_element.vertices() {
+                // Trimming follows the rendered route, so elbow connectors
+                // are shortened along their first and last legs.
+                set_vertices(&mut conn_element, &trim_points(&points, self.margins)?);
+            }
+        }
+        if !self.corner_radius.is_empty() {
+            if let Some(d) = conn_element
+                .vertices()
+                .and_then(|points| points_to_path(&points, &self.corner_radius))
+            {
+                let source = ["x1", "y1", "x2", "y2", "points"]
+                    .iter()
+                    .fold(conn_element.clone(), |src, attr| src.without_attr(attr));
+                conn_element =
+                    SvgElement::new("path", &[("d".to_string(), d)]).with_attrs_from(&source);
+            }
+        }
+        Ok(conn_element)
+    }
+
+    /// Copy of this (curve) connector with each endpoint moved by its margin
+    /// in the direction the connector leaves it, or towards the other
+    /// endpoint if it has no direction.
+    fn with_curve_margins(&self) -> Self {
+        let moved = |ep: &Endpoint, other: &Endpoint, margin: f32| {
+            let (dx, dy) = match ep.dir {
+                Some(dir) => dir.vector(),
+                None => {
+                    let (dx, dy) = (other.origin.0 - ep.origin.0, other.origin.1 - ep.origin.1);
+                    let len = dx.hypot(dy);
+                    if len == 0. {
+                        (0., 0.)
+                    } else {
+                        (dx / len, dy / len)
+                    }
+                }
+            };
+            Endpoint::new(
+                (ep.origin.0 + dx * margin, ep.origin.1 + dy * margin),
+                ep.dir,
+            )
+        };
+        Self {
+            start: moved(&self.start, &self.end, self.margins.0),
+            end: moved(&self.end, &self.start, self.margins.1),
+            ..self.clone()
+        }
+    }
+
+    /// Render the connector directly between its endpoints.
+    fn render_direct(&self, ctx: &impl ElementMap) -> Result<SvgElement> {
         let default_ratio_offset = Length::Ratio(0.5);
-        let default_abs_offset = Length::Absolute(3.);
+        let default_abs_offset = Length::Absolute(DEFAULT_ABS_OFFSET);
 
         let (x1, y1) = self.start.origin;
         let (x2, y2) = self.end.origin;
@@ -481,33 +1169,150 @@ impl Connector {
                     points = vec![(x1, y1), (x2, y2)];
                 }
                 // TODO: remove repeated points.
-                if points.len() == 2 {
-                    SvgElement::new(
-                        "line",
-                        &[
-                            ("x1".to_string(), fstr(points[0].0)),
-                            ("y1".to_string(), fstr(points[0].1)),
-                            ("x2".to_string(), fstr(points[1].0)),
-                            ("y2".to_string(), fstr(points[1].1)),
-                        ],
-                    )
-                    .with_attrs_from(&self.source_element)
-                } else {
-                    SvgElement::new(
-                        "polyline",
-                        &[(
-                            "points".to_string(),
-                            points
-                                .into_iter()
-                                .map(|(px, py)| format!("{} {}", fstr(px), fstr(py)))
-                                .collect::<Vec<String>>()
-                                .join(", "),
-                        )],
+                points_element(points, &self.source_element)
+            }
+            ConnectionType::Route => match self.route_points(ctx, default_abs_offset)? {
+                Some(points) => points_element(points, &self.source_element),
+                // fall back to a standard elbow connector
+                None => Self {
+                    conn_type: ConnectionType::Corner,
+                    ..self.clone()
+                }
+                .render_direct(ctx)?,
+            },
+            ConnectionType::Curve => self.render_curve(default_ratio_offset),
+        };
+        Ok(conn_element)
+    }
+
+    /// Cubic bezier path between the endpoints, with each control point
+    /// extending from its endpoint in that endpoint's direction. The distance
+    /// to the control points is given by `corner-offset`, relative to the
+    /// distance between the endpoints.
+    fn render_curve(&self, default_offset: Length) -> SvgElement {
+        let (start, end) = (self.start.origin, self.end.origin);
+        let dist = (end.0 - start.0).hypot(end.1 - start.1);
+        let reach = self.offset.unwrap_or(default_offset).calc_offset(0., dist);
+        let control = |ep: &Endpoint| match ep.dir {
+            Some(dir) => {
+                let (dx, dy) = dir.vector();
+                (ep.origin.0 + dx * reach, ep.origin.1 + dy * reach)
+            }
+            None => ep.origin,
+        };
+        let mut points = vec![start, control(&self.start), control(&self.end), end];
+        if self.lateral != 0. {
+            // offsetting the control polygon gives an approximately parallel curve
+            points = offset_points(&points, self.lateral);
+        }
+        let coords: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{} {}", fstr(*x), fstr(*y)))
+            .collect();
+        SvgElement::new(
+            "path",
+            &[(
+                "d".to_string(),
+                format!("M {} C {}", coords[0], coords[1..].join(", ")),
+            )],
+        )
+        .with_attrs_from(&self.source_element)
+    }
+
+    /// Minimum distance between routed connectors and other elements.
+    fn route_margin(&self, default_offset: Length) -> Result<f32> {
+        self.offset
+            .unwrap_or(default_offset)
+            .absolute()
+            .ok_or_else(|| {
+                SvgdxError::InvalidData("Route type requires absolute offset".to_owned())
+            })
+    }
+
+    /// Bounding boxes of elements to be avoided by a route through `points`.
+    fn route_obstacles(
+        &self,
+        ctx: &impl ElementMap,
+        points: &[(f32, f32)],
+    ) -> Result<Vec<BoundingBox>> {
+        let touches = |bb: &BoundingBox, (x, y): (f32, f32)| {
+            bb.x1 <= x && x <= bb.x2 && bb.y1 <= y && y <= bb.y2
+        };
+        let mut obstacles = Vec::new();
+        for el in ctx.get_elements_matching(ROUTE_OBSTACLES) {
+            if let Some(bb) = ctx.get_element_bbox(el)? {
+                // the connected elements (and anything containing them) aren't obstacles
+                if !points.iter().any(|&p| touches(&bb, p)) {
+                    obstacles.push(bb);
+                }
+            }
+        }
+        Ok(obstacles)
+    }
+
+    /// Points of an orthogonal route between the endpoints avoiding other
+    /// elements, or `None` if there is no such route.
+    fn route_points(
+        &self,
+        ctx: &impl ElementMap,
+        default_offset: Length,
+    ) -> Result<Option<Vec<(f32, f32)>>> {
+        let margin = self.route_margin(default_offset)?;
+        let (start, end) = (self.start.origin, self.end.origin);
+        let obstacles = self.route_obstacles(ctx, &[start, end])?;
+        Ok(route(
+            RouteEnd {
+                point: start,
+                dir: self.start.dir.map(Direction::vector),
+            },
+            RouteEnd {
+                point: end,
+                dir: self.end.dir.map(Direction::vector),
+            },
+            &obstacles,
+            margin,
+        ))
+    }
+
+    /// Render a connector passing through each of its `via` points in turn.
+    fn render_via(&self, ctx: &impl ElementMap, default_offset: Length) -> Result<SvgElement> {
+        let mut points = vec![self.start.origin];
+        points.extend(&self.via);
+        points.push(self.end.origin);
+        let points = match self.conn_type {
+            ConnectionType::Straight => points,
+            ConnectionType::Corner => elbow_points(&points, self.start.dir, self.end.dir),
+            ConnectionType::Route => {
+                let margin = self.route_margin(default_offset)?;
+                let obstacles = self.route_obstacles(ctx, &points)?;
+                let last = points.len() - 2;
+                let mut routed = vec![self.start.origin];
+                for (idx, leg) in points.windows(2).enumerate() {
+                    let start_dir = if idx == 0 { self.start.dir } else { None };
+                    let end_dir = if idx == last { self.end.dir } else { None };
+                    let leg_points = route(
+                        RouteEnd {
+                            point: leg[0],
+                            dir: start_dir.map(Direction::vector),
+                        },
+                        RouteEnd {
+                            point: leg[1],
+                            dir: end_dir.map(Direction::vector),
+                        },
+                        &obstacles,
+                        margin,
                     )
-                    .with_attrs_from(&self.source_element)
+                    .unwrap_or_else(|| elbow_points(leg, start_dir, end_dir));
+                    routed.extend(&leg_points[1..]);
                 }
+                routed
+            }
+            ConnectionType::Horizontal | ConnectionType::Vertical | ConnectionType::Curve => {
+                return Err(SvgdxError::InvalidData(
+                    "via is not supported for horizontal, vertical or curve connectors".to_owned(),
+                ))
             }
         };
-        Ok(conn_element)
+        Ok(points_element(simplify(points), &self.source_element))
     }
 }
@@ -2,13 +2,18 @@ use crate::element::SvgElement;
 use crate::errors::{Result, SvgdxError};
 use crate::events::InputEvent;
 use crate::expression::eval_attr;
+use crate::pack::PackState;
 use crate::position::BoundingBox;
-use crate::types::{attr_split, strp, AttrMap, ClassList, ElRef};
+use crate::reuse::{apply_overrides, Override};
+use crate::stats::{PhaseTimer, TransformStats};
+use crate::transform_attr::TransformAttr;
+use crate::types::{attr_split, attr_split_cycle, fnv1a, fstr, strp, AttrMap, ClassList, ElRef};
 use crate::TransformConfig;
 
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::mem;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use rand::prelude::*;
 use rand_pcg::Pcg32;
@@ -85,6 +90,7 @@ impl From<&SvgElement> for ElementMatch {
 struct Scope {
     vars: HashMap<String, String>,
     defaults: Vec<(ElementMatch, SvgElement)>,
+    overrides: Vec<Override>,
 }
 
 impl Scope {
@@ -96,25 +102,61 @@ impl Scope {
     }
 }
 
+/// Line segment between two points
+pub type Segment = ((f32, f32), (f32, f32));
+
 pub struct TransformerContext {
     /// Current state of given element; may be updated as processing continues
     elem_map: HashMap<String, SvgElement>,
     /// Original state of given element; used for `reuse` elements
     original_map: HashMap<String, SvgElement>,
+    /// All elements processed so far, in processing order
+    processed: Vec<SvgElement>,
+    /// Placement state for regions with elements packed `inside` them
+    pack_state: HashMap<String, PackState>,
+    /// Number of connectors rendered between each pair of elements
+    connector_counts: HashMap<String, usize>,
+    /// Segments of connectors rendered so far, used for `connector-jumps`
+    connector_segments: Vec<Segment>,
+    /// Accumulated translation of enclosing `<g>` elements, innermost last
+    group_offsets: Vec<(f32, f32)>,
+    /// Accumulated group translation of elements defined within translated groups
+    element_offsets: HashMap<String, (f32, f32)>,
+    /// Translation of elements by id from `<align>` and `<distribute>` elements
+    adjustments: HashMap<String, (f32, f32)>,
+    /// Whether `adjustments` are being applied, rather than collected
+    applying_adjustments: bool,
     /// Stack of elements which have been started but not yet ended
     ///
     /// Note empty elements are normally not pushed onto the stack,
     /// but `<reuse>` elements are an exception during processing of
     /// the referenced element.
     element_stack: Vec<SvgElement>,
-    /// The element which `^` refers to; some elements are ignored as 'previous'
-    prev_element: Option<SvgElement>,
+    /// Stack of enclosing container elements, used to resolve percentage
+    /// sizes against the parent element size
+    parent_stack: Vec<SvgElement>,
+    /// Iteration index and (where known) iteration count of enclosing loops,
+    /// innermost last; provides `$loop_index` and `$loop_count`
+    loop_stack: Vec<(u32, Option<u32>)>,
+    /// Elements which `^`, `^2` etc refer to, most recent last; some elements
+    /// are ignored as 'previous'
+    prev_elements: Vec<SvgElement>,
     /// Stack of scoped variables etc
     scope_stack: Vec<Scope>,
     /// Pcg32 is used as it is both seedable and portable.
     rng: RefCell<Pcg32>,
+    /// Number of RNG sub-streams derived so far for each element
+    /// Position of the element started by each input event, counting only
+    /// elements (not text or comments); used to seed per-element random streams
+    element_positions: Vec<usize>,
     /// Current recursion depth
     current_depth: u32,
+    /// Number of elements processed so far
+    element_count: u32,
+    /// Whether a deferred resolution pass is in progress
+    resolving: bool,
+    /// Total time spent in (outermost) deferred resolution passes
+    resolve_time: Duration,
     /// Is this a 'real' SVG doc, or just a fragment?
     pub real_svg: bool,
     /// Are we in a <specs> block?
@@ -125,6 +167,10 @@ pub struct TransformerContext {
     pub local_style_id: Option<String>,
     /// Config of transformer processing; updated by <config> elements
     pub config: TransformConfig,
+    /// Counts and timings of processing
+    pub stats: TransformStats,
+    /// Auto-style CSS omitted from output when `config.css_href` is set
+    pub extracted_css: Option<String>,
 }
 
 impl Default for TransformerContext {
@@ -132,16 +178,32 @@ impl Default for TransformerContext {
         Self {
             elem_map: HashMap::new(),
             original_map: HashMap::new(),
+            processed: Vec::new(),
+            pack_state: HashMap::new(),
+            connector_counts: HashMap::new(),
+            connector_segments: Vec::new(),
+            group_offsets: Vec::new(),
+            element_offsets: HashMap::new(),
+            adjustments: HashMap::new(),
+            applying_adjustments: false,
             element_stack: Vec::new(),
-            prev_element: None,
+            parent_stack: Vec::new(),
+            loop_stack: Vec::new(),
+            prev_elements: Vec::new(),
             scope_stack: Vec::new(),
             rng: RefCell::new(Pcg32::seed_from_u64(0)),
+            element_positions: Vec::new(),
             local_style_id: None,
             current_depth: 0,
+            element_count: 0,
+            resolving: false,
+            resolve_time: Duration::ZERO,
             real_svg: false,
             in_specs: false,
             events: Vec::new(),
             config: TransformConfig::default(),
+            stats: TransformStats::default(),
+            extracted_css: None,
         }
     }
 }
@@ -149,11 +211,19 @@ impl Default for TransformerContext {
 pub trait ElementMap {
     fn get_element(&self, elref: &ElRef) -> Option<&SvgElement>;
     fn get_element_bbox(&self, el: &SvgElement) -> Result<Option<BoundingBox>>;
+    fn get_parent_size(&self) -> Option<(f32, f32)>;
+    /// All processed elements matching the given selector, in processing order.
+    fn get_elements_matching(&self, selector: &str) -> Vec<&SvgElement>;
 }
 
 pub trait VariableMap {
     fn get_var(&self, name: &str) -> Option<String>;
     fn get_rng(&self) -> &RefCell<Pcg32>;
+    fn get_seed(&self) -> u64;
+    /// Output scale, in mm per user unit
+    fn get_scale(&self) -> f32;
+    /// Value of an environment variable, if set. Errors unless enabled.
+    fn get_env(&self, name: &str) -> Result<Option<String>>;
 }
 
 pub trait ContextView: ElementMap + VariableMap {}
@@ -162,7 +232,8 @@ impl ElementMap for TransformerContext {
     fn get_element(&self, elref: &ElRef) -> Option<&SvgElement> {
         match elref {
             ElRef::Id(id) => self.elem_map.get(id),
-            ElRef::Prev => self.prev_element.as_ref(),
+            ElRef::Prev => self.prev_elements.last(),
+            ElRef::PrevN(n) => self.prev_elements.iter().rev().nth(*n - 1),
         }
     }
 
@@ -191,7 +262,20 @@ impl ElementMap for TransformerContext {
                     .get_element(&elref)
                     .ok_or_else(|| SvgdxError::ReferenceError(elref))?;
                 // recurse to get bbox of the target
-                inner(target_el, ctx, already)?
+                let target_bbox = inner(target_el, ctx, already)?;
+                // a sized `use` element scales the target to that size
+                match (el.name.as_str(), target_bbox) {
+                    ("use", Some(bbox)) => match el.use_scale(target_el, &bbox) {
+                        Some((sx, sy)) => Some(BoundingBox::new(
+                            bbox.x1,
+                            bbox.y1,
+                            bbox.x1 + bbox.width() * sx,
+                            bbox.y1 + bbox.height() * sy,
+                        )),
+                        None => Some(bbox),
+                    },
+                    _ => target_bbox,
+                }
             } else {
                 el.bbox()?
             };
@@ -208,10 +292,67 @@ impl ElementMap for TransformerContext {
                     }
                 }
             }
+            if let ("use", Some(xfrm), Some(bbox)) =
+                (el.name.as_str(), el.get_attr("transform"), &el_bbox)
+            {
+                let xfrm: TransformAttr = xfrm.parse()?;
+                el_bbox = Some(xfrm.apply(bbox));
+            }
             Ok(el_bbox)
         }
         let mut already_seen = Vec::new();
-        inner(el, self, &mut already_seen)
+        let bbox = inner(el, self, &mut already_seen)?;
+        // Elements within translated groups are positioned relative to their
+        // group; convert to the coordinates of the current group.
+        let offset = el
+            .get_attr("id")
+            .and_then(|id| self.element_offsets.get(&eval_attr(&id, self)));
+        match (bbox, offset) {
+            (Some(bbox), Some((dx, dy))) => {
+                let (cur_dx, cur_dy) = self.group_offset();
+                Ok(Some(bbox.translated(dx - cur_dx, dy - cur_dy)))
+            }
+            _ => Ok(bbox),
+        }
+    }
+
+    /// Size of the content area of the enclosing element, if it has one.
+    ///
+    /// This is derived from `viewBox` if present, otherwise `wh` or the
+    /// `width` and `height` attributes.
+    fn get_parent_size(&self) -> Option<(f32, f32)> {
+        let parent = self.parent_stack.last()?;
+        let attr = |name: &str| parent.get_attr(name).map(|v| eval_attr(&v, self));
+        if let Some(view_box) = attr("viewBox") {
+            let parts: Vec<f32> = attr_split(&view_box)
+                .map(|v| strp(&v))
+                .collect::<Result<_>>()
+                .ok()?;
+            if let [_, _, w, h] = parts[..] {
+                return Some((w, h));
+            }
+            return None;
+        }
+        let (w, h) = if let Some(wh) = attr("wh") {
+            let mut parts = attr_split_cycle(&wh);
+            (parts.next(), parts.next())
+        } else {
+            (attr("width"), attr("height"))
+        };
+        Some((strp(&w?).ok()?, strp(&h?).ok()?))
+    }
+
+    fn get_elements_matching(&self, selector: &str) -> Vec<&SvgElement> {
+        self.processed
+            .iter()
+            .map(|el| {
+                // prefer the latest state of elements which can be referenced by id
+                el.get_attr("id")
+                    .and_then(|id| self.elem_map.get(&eval_attr(&id, self)))
+                    .unwrap_or(el)
+            })
+            .filter(|el| el.matches_selector(selector))
+            .collect()
     }
 }
 
@@ -228,12 +369,30 @@ impl VariableMap for TransformerContext {
                 return Some(value.to_string());
             }
         }
-        None
+        self.get_implicit_var(name)
     }
 
     fn get_rng(&self) -> &RefCell<Pcg32> {
         &self.rng
     }
+
+    fn get_seed(&self) -> u64 {
+        self.config.seed
+    }
+
+    fn get_scale(&self) -> f32 {
+        self.config.scale
+    }
+
+    fn get_env(&self, name: &str) -> Result<Option<String>> {
+        // never available in sanitized mode, e.g. when serving untrusted input
+        if !self.config.allow_env || self.config.sanitize {
+            return Err(SvgdxError::InvalidData(format!(
+                "env('{name}'): environment access is not enabled"
+            )));
+        }
+        Ok(std::env::var(name).ok())
+    }
 }
 
 impl ContextView for TransformerContext {}
@@ -270,13 +429,63 @@ impl TransformerContext {
     }
 
     pub fn set_events(&mut self, events: Vec<InputEvent>) {
+        let mut position = 0;
+        self.element_positions = events
+            .iter()
+            .map(|ev| {
+                let pos = position;
+                if ev.is_element_start() {
+                    position += 1;
+                }
+                pos
+            })
+            .collect();
         self.events = events;
     }
 
     pub fn get_original_element(&self, elref: &ElRef) -> Option<&SvgElement> {
         match elref {
             ElRef::Id(id) => self.original_map.get(id),
-            ElRef::Prev => self.prev_element.as_ref(),
+            ElRef::Prev => self.prev_elements.last(),
+            ElRef::PrevN(n) => self.prev_elements.iter().rev().nth(*n - 1),
+        }
+    }
+
+    /// Variables derived from document context rather than explicitly set.
+    ///
+    /// These are only consulted if no variable of the same name is in scope.
+    fn get_implicit_var(&self, name: &str) -> Option<String> {
+        match name {
+            "doc_theme" => Some(self.config.theme.to_string()),
+            "loop_index" => self.loop_stack.last().map(|(idx, _)| idx.to_string()),
+            "loop_count" => self
+                .loop_stack
+                .last()
+                .and_then(|(_, count)| *count)
+                .map(|count| count.to_string()),
+            "parent_width" | "parent_height" => {
+                // Nearest enclosing element with a known size; this is either
+                // from its bounding box or an explicit width / height attribute.
+                let (attr, is_width) = if name == "parent_width" {
+                    ("width", true)
+                } else {
+                    ("height", false)
+                };
+                self.element_stack.iter().rev().find_map(|el| {
+                    if let Ok(Some(bbox)) = self.get_element_bbox(el) {
+                        Some(fstr(if is_width {
+                            bbox.width()
+                        } else {
+                            bbox.height()
+                        }))
+                    } else {
+                        el.get_attr(attr)
+                            .and_then(|v| strp(&eval_attr(&v, self)).ok())
+                            .map(fstr)
+                    }
+                })
+            }
+            _ => None,
         }
     }
 
@@ -284,6 +493,34 @@ impl TransformerContext {
         self.rng = RefCell::new(Pcg32::seed_from_u64(seed));
     }
 
+    /// Reseed the RNG with a sub-stream specific to the given element.
+    ///
+    /// The stream is derived from the global seed and the element's `id`, or
+    /// its position among the document's elements if it has no `id`, so
+    /// `random()` results for an element are independent of how many random
+    /// values other elements consumed, and of any text or comments. The
+    /// iteration of any enclosing loops and the enclosing `<reuse>` elements
+    /// are also included, so repeated elements get a new stream each time.
+    pub fn seed_element_rng(&mut self, el: &SvgElement) {
+        let mut data = self.config.seed.to_le_bytes().to_vec();
+        let reuses = self.element_stack.iter().filter(|el| el.name == "reuse");
+        for el in reuses.chain([el]) {
+            if let Some(id) = el.get_attr("id") {
+                data.extend(b"#");
+                data.extend(id.as_bytes());
+            } else {
+                let idx = el.order_index.iter().next().unwrap_or_default();
+                let pos = self.element_positions.get(idx).copied().unwrap_or(idx);
+                data.extend((pos as u64).to_le_bytes());
+            }
+        }
+        for (idx, _) in &self.loop_stack {
+            data.extend(idx.to_le_bytes());
+        }
+        let hash = fnv1a(&data);
+        self.seed_rng(hash);
+    }
+
     fn ensure_scope(&mut self) -> &mut Scope {
         if self.scope_stack.is_empty() {
             let scope = Scope::default();
@@ -305,6 +542,20 @@ impl TransformerContext {
         scope.defaults.push((el_match, mod_el));
     }
 
+    pub fn set_element_overrides(&mut self, overrides: Vec<Override>) {
+        let scope = self.ensure_scope();
+        scope.overrides = overrides;
+    }
+
+    /// Apply `override` attribute values from any enclosing `<reuse>` elements.
+    ///
+    /// Outer scopes are applied last, so the outermost instance has priority.
+    pub fn apply_overrides(&self, el: &mut SvgElement) {
+        for scope in self.scope_stack.iter().rev() {
+            apply_overrides(el, &scope.overrides);
+        }
+    }
+
     pub fn apply_defaults(&mut self, el: &mut SvgElement) {
         // Build up the default element we're going to apply until
         // we hit a `final` match.
@@ -386,6 +637,35 @@ impl TransformerContext {
         self.element_stack.pop()
     }
 
+    pub fn push_parent(&mut self, el: &SvgElement) {
+        self.parent_stack.push(el.clone());
+    }
+
+    pub fn pop_parent(&mut self) -> Option<SvgElement> {
+        self.parent_stack.pop()
+    }
+
+    /// Run `f` as the body of a loop with the given iteration count, if known.
+    ///
+    /// Loop variables are only available within `f`, and refer to the
+    /// innermost loop even if its count is unknown.
+    pub fn with_loop<T>(
+        &mut self,
+        count: Option<u32>,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.loop_stack.push((0, count));
+        let res = f(self);
+        self.loop_stack.pop();
+        res
+    }
+
+    pub fn set_loop_index(&mut self, index: u32) {
+        if let Some((idx, _)) = self.loop_stack.last_mut() {
+            *idx = index;
+        }
+    }
+
     pub fn inc_depth(&mut self) -> Result<()> {
         self.current_depth += 1;
         if self.current_depth > self.config.depth_limit {
@@ -397,6 +677,113 @@ impl TransformerContext {
         Ok(())
     }
 
+    pub fn element_count(&self) -> u32 {
+        self.element_count
+    }
+
+    pub fn get_pack_state(&self, region: &str) -> Option<&PackState> {
+        self.pack_state.get(region)
+    }
+
+    pub fn set_pack_state(&mut self, region: &str, state: PackState) {
+        self.pack_state.insert(region.to_owned(), state);
+    }
+
+    pub fn connector_count(&self, key: &str) -> usize {
+        self.connector_counts.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn add_connector(&mut self, key: String) {
+        *self.connector_counts.entry(key).or_default() += 1;
+    }
+
+    pub fn connector_segments(&self) -> &[Segment] {
+        &self.connector_segments
+    }
+
+    pub fn add_connector_segments(&mut self, segments: impl IntoIterator<Item = Segment>) {
+        self.connector_segments.extend(segments);
+    }
+
+    /// Record a successfully processed element for later selector lookups.
+    pub fn add_processed(&mut self, el: &SvgElement) {
+        self.processed.push(el.clone());
+    }
+
+    /// Whether the element with the given id has been processed.
+    pub fn is_processed(&self, id: &str) -> bool {
+        self.processed.iter().any(|el| {
+            el.get_attr("id")
+                .is_some_and(|el_id| eval_attr(&el_id, self) == id)
+        })
+    }
+
+    /// Record a translation of the element with the given id, to be applied
+    /// when the document is processed again.
+    pub fn add_adjustment(&mut self, id: &str, dx: f32, dy: f32) {
+        let adj = self.adjustments.entry(id.to_string()).or_default();
+        *adj = (adj.0 + dx, adj.1 + dy);
+    }
+
+    /// Translation to apply to the element with the given id, if any.
+    pub fn get_adjustment(&self, id: &str) -> Option<(f32, f32)> {
+        if self.applying_adjustments {
+            self.adjustments
+                .get(id)
+                .copied()
+                .filter(|&(dx, dy)| dx != 0. || dy != 0.)
+        } else {
+            None
+        }
+    }
+
+    pub fn applying_adjustments(&self) -> bool {
+        self.applying_adjustments
+    }
+
+    /// Remove and return the adjustments collected so far.
+    pub fn take_adjustments(&mut self) -> HashMap<String, (f32, f32)> {
+        std::mem::take(&mut self.adjustments)
+    }
+
+    /// Apply the given adjustments to elements as they are positioned.
+    pub fn apply_adjustments(&mut self, adjustments: HashMap<String, (f32, f32)>) {
+        self.adjustments = adjustments;
+        self.applying_adjustments = true;
+    }
+
+    /// Start timing a deferred resolution pass, unless already within one.
+    pub fn start_resolve(&mut self) -> Option<PhaseTimer> {
+        if self.resolving {
+            return None;
+        }
+        self.resolving = true;
+        Some(PhaseTimer::start("resolve"))
+    }
+
+    pub fn finish_resolve(&mut self, timer: Option<PhaseTimer>) {
+        if let Some(timer) = timer {
+            self.resolving = false;
+            self.resolve_time += timer.finish().1;
+        }
+    }
+
+    /// Time spent in deferred resolution passes since the last call.
+    pub fn take_resolve_time(&mut self) -> Duration {
+        mem::take(&mut self.resolve_time)
+    }
+
+    pub fn inc_element_count(&mut self) -> Result<()> {
+        self.element_count += 1;
+        if self.element_count > self.config.element_limit {
+            return Err(SvgdxError::ElementLimitError(
+                self.element_count,
+                self.config.element_limit,
+            ));
+        }
+        Ok(())
+    }
+
     pub fn dec_depth(&mut self) -> Result<()> {
         if self.current_depth > 0 {
             self.current_depth -= 1;
@@ -411,15 +798,43 @@ impl TransformerContext {
     }
 
     pub fn set_prev_element(&mut self, el: &SvgElement) {
-        self.prev_element = Some(el.clone());
+        self.prev_elements.push(el.clone());
+    }
+
+    /// Replace the most recent previous element, e.g. after it has been moved.
+    pub fn update_prev_element(&mut self, el: &SvgElement) {
+        if let Some(prev) = self.prev_elements.last_mut() {
+            *prev = el.clone();
+        }
     }
 
     pub fn update_element(&mut self, el: &SvgElement) {
         if let Some(id) = el.get_attr("id") {
             let id = eval_attr(&id, self);
+            let offset = self.group_offset();
+            if offset == (0., 0.) {
+                self.element_offsets.remove(&id);
+            } else {
+                self.element_offsets.insert(id.clone(), offset);
+            }
             if self.elem_map.insert(id.clone(), el.clone()).is_none() {
                 self.original_map.insert(id, el.clone());
             }
         }
     }
+
+    /// Accumulated translation of the enclosing `<g>` elements.
+    pub fn group_offset(&self) -> (f32, f32) {
+        self.group_offsets.last().copied().unwrap_or((0., 0.))
+    }
+
+    /// Enter a `<g>` element translated by `(dx, dy)` relative to its parent.
+    pub fn push_group_offset(&mut self, dx: f32, dy: f32) {
+        let (x, y) = self.group_offset();
+        self.group_offsets.push((x + dx, y + dy));
+    }
+
+    pub fn pop_group_offset(&mut self) {
+        self.group_offsets.pop();
+    }
 }
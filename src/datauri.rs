@@ -0,0 +1,150 @@
+//! Encoding of SVG output as a `data:` URI, and decoding of `data:` URIs.
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode base64 `data`, ignoring whitespace. Returns `None` if invalid.
+fn base64_decode(data: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let mut n = 0u32;
+    let mut bits = 0;
+    for c in data.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            break;
+        }
+        let v = BASE64_CHARS.iter().position(|&b| b == c)? as u32;
+        n = n << 6 | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits & 0xff) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Content of a `data:` URI, or `None` if `uri` is not a valid `data:` URI.
+pub(crate) fn data_uri_content(uri: &str) -> Option<Vec<u8>> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    if header.ends_with(";base64") {
+        base64_decode(data)
+    } else {
+        Some(percent_decode(data))
+    }
+}
+
+/// Percent-decode `data`, leaving any invalid escapes unchanged.
+fn percent_decode(data: &str) -> Vec<u8> {
+    let bytes = data.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = bytes
+            .get(idx + 1..idx + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[idx], hex) {
+            (b'%', Some(v)) => {
+                out.push(v);
+                idx += 3;
+            }
+            (c, _) => {
+                out.push(c);
+                idx += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Percent-encode `data`, leaving characters which are safe in URIs (and in
+/// Markdown / CSS `url()` contexts) unchanged.
+fn percent_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len());
+    for &c in data {
+        if c.is_ascii_alphanumeric() || b"-._~!$&'*+,;=:@/?".contains(&c) {
+            out.push(c as char);
+        } else {
+            out.push_str(&format!("%{c:02X}"));
+        }
+    }
+    out
+}
+
+/// Encode an SVG document, typically the output of `transform_str()`, as a
+/// `data:image/svg+xml` URI, optionally using base64 encoding.
+///
+/// The result may be used directly e.g. in Markdown image links or CSS `url()`
+/// values.
+pub fn svg_data_uri(svg: &str, base64: bool) -> String {
+    if base64 {
+        format!(
+            "data:image/svg+xml;base64,{}",
+            base64_encode(svg.as_bytes())
+        )
+    } else {
+        format!("data:image/svg+xml,{}", percent_encode(svg.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(base64_encode(&[0xff, 0xfe, 0xfd]), "//79");
+    }
+
+    #[test]
+    fn test_data_uri_content() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", &[0xff, 0xfe, 0xfd]] {
+            let uri = format!("data:x;base64,{}", base64_encode(data));
+            assert_eq!(data_uri_content(&uri).as_deref(), Some(data));
+        }
+        let svg = r##"<svg><rect fill="#f00"/></svg>"##;
+        assert_eq!(
+            data_uri_content(&svg_data_uri(svg, false)).as_deref(),
+            Some(svg.as_bytes())
+        );
+        assert_eq!(data_uri_content("data:x;base64,%%"), None);
+        assert_eq!(data_uri_content("file.png"), None);
+    }
+
+    #[test]
+    fn test_svg_data_uri() {
+        let svg = r##"<svg><rect fill="#f00"/></svg>"##;
+        assert_eq!(
+            svg_data_uri(svg, false),
+            "data:image/svg+xml,%3Csvg%3E%3Crect%20fill=%22%23f00%22/%3E%3C/svg%3E"
+        );
+        assert_eq!(
+            svg_data_uri("<svg/>", true),
+            "data:image/svg+xml;base64,PHN2Zy8+"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! Visual comparison of two SVG documents.
+//!
+//! Elements are matched by `id` between an 'old' and 'new' document (typically
+//! the output of `transform_str()` for two versions of a diagram), and an
+//! overlay SVG is generated showing the new document with changes highlighted:
+//!
+//! - added elements (only in the new document) are outlined in green
+//! - removed elements (only in the old document) are outlined in red
+//! - moved or resized elements are outlined in orange, with a dashed outline
+//!   at their old position and a line from old to new.
+
+use crate::errors::Result;
+use crate::geometry::{rendered_elements, root_start, split_root, view_box};
+use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::types::fstr;
+
+/// Gap between an element and its highlight outline
+const DIFF_PADDING: f32 = 1.;
+/// Opacity of the new document content beneath highlights
+const DIFF_CONTENT_OPACITY: f32 = 0.4;
+
+const DIFF_STYLE: &str = r#"<style>
+.diff-added, .diff-removed, .diff-moved, .diff-moved-from { fill: none; stroke-width: 0.5; }
+.diff-added { stroke: #2a2; }
+.diff-removed { stroke: #d22; }
+.diff-moved, .diff-moved-from { stroke: #e80; }
+.diff-moved-from { stroke-dasharray: 1 1; }
+line.diff-moved { stroke-width: 0.25; marker-end: url(#diff-arrow); }
+</style>
+<defs>
+<marker id="diff-arrow" refX="1" refY="0.5" orient="auto" markerWidth="6" markerHeight="5" viewBox="0 0 1 1">
+<path d="M 0 0 1 0.5 0 1" style="stroke-width: 0.2; stroke: #e80; fill: none;"/>
+</marker>
+</defs>"#;
+
+/// Summary of differences between two documents.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DiffSummary {
+    /// Ids of elements only present in the new document
+    pub added: Vec<String>,
+    /// Ids of elements only present in the old document
+    pub removed: Vec<String>,
+    /// Ids of elements whose bounding box has changed
+    pub moved: Vec<String>,
+}
+
+impl DiffSummary {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+impl std::fmt::Display for DiffSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (prefix, ids) in [("+", &self.added), ("-", &self.removed), ("~", &self.moved)] {
+            for id in ids {
+                writeln!(f, "{prefix} #{id}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Elements with an `id` in document order, with their bounding boxes.
+fn id_bboxes(svg: &str) -> Result<Vec<(String, Option<BoundingBox>)>> {
+    Ok(rendered_elements(svg)?
+        .into_iter()
+        .filter_map(|(el, bbox)| el.get_attr("id").map(|id| (id, bbox)))
+        .collect())
+}
+
+/// Whether two bounding boxes differ once formatted for output.
+fn bbox_changed(a: &BoundingBox, b: &BoundingBox) -> bool {
+    [a.x1, a.y1, a.x2, a.y2]
+        .iter()
+        .zip([b.x1, b.y1, b.x2, b.y2])
+        .any(|(a, b)| fstr(*a) != fstr(b))
+}
+
+fn outline(bb: &BoundingBox, class: &str) -> String {
+    format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" class="{class}"/>"#,
+        fstr(bb.x1 - DIFF_PADDING),
+        fstr(bb.y1 - DIFF_PADDING),
+        fstr(bb.width() + DIFF_PADDING * 2.),
+        fstr(bb.height() + DIFF_PADDING * 2.)
+    )
+}
+
+/// Compare two SVG documents, returning an overlay SVG of the new document
+/// with added, removed and moved elements highlighted, and a summary of the
+/// differences.
+pub fn svg_diff(old: &str, new: &str) -> Result<(String, DiffSummary)> {
+    let old_els = id_bboxes(old)?;
+    let new_els = id_bboxes(new)?;
+    let (old_root, _) = split_root(old)?;
+    let (new_root, new_content) = split_root(new)?;
+
+    let mut summary = DiffSummary::default();
+    let mut highlights = Vec::new();
+    let mut extent = BoundingBoxBuilder::new();
+    for (id, new_bb) in &new_els {
+        match old_els.iter().find(|(old_id, _)| old_id == id) {
+            None => {
+                summary.added.push(id.clone());
+                if let Some(bb) = new_bb {
+                    highlights.push(outline(bb, "diff-added"));
+                }
+            }
+            Some((_, old_bb)) => {
+                if let (Some(old_bb), Some(new_bb)) = (old_bb, new_bb) {
+                    if bbox_changed(old_bb, new_bb) {
+                        summary.moved.push(id.clone());
+                        highlights.push(outline(old_bb, "diff-moved-from"));
+                        highlights.push(outline(new_bb, "diff-moved"));
+                        let ((x1, y1), (x2, y2)) = (old_bb.center(), new_bb.center());
+                        if (x1, y1) != (x2, y2) {
+                            highlights.push(format!(
+                                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" class="diff-moved"/>"#,
+                                fstr(x1),
+                                fstr(y1),
+                                fstr(x2),
+                                fstr(y2)
+                            ));
+                        }
+                        extent.extend(*old_bb);
+                    }
+                }
+            }
+        }
+    }
+    for (id, old_bb) in &old_els {
+        if !new_els.iter().any(|(new_id, _)| new_id == id) {
+            summary.removed.push(id.clone());
+            if let Some(bb) = old_bb {
+                highlights.push(outline(bb, "diff-removed"));
+                extent.extend(*bb);
+            }
+        }
+    }
+
+    // The overlay covers both documents, and any highlights of old positions
+    let mut view = BoundingBoxBuilder::new();
+    for vb in [view_box(&old_root)?, view_box(&new_root)?]
+        .into_iter()
+        .flatten()
+    {
+        view.extend(vb);
+    }
+    if let Some(bb) = extent.build() {
+        let mut bb = bb;
+        view.extend(*bb.expand(DIFF_PADDING * 2., DIFF_PADDING * 2.));
+    }
+    // retain the scale of the new document, e.g. user units to mm
+    let view = view.build().unwrap_or(BoundingBox::new(0., 0., 0., 0.));
+    let mut output = root_start(&new_root, &view);
+    output.push('\n');
+    output.push_str(DIFF_STYLE);
+    output.push_str(&format!(
+        "\n<g opacity=\"{}\">{new_content}</g>\n",
+        fstr(DIFF_CONTENT_OPACITY)
+    ));
+    for highlight in highlights {
+        output.push_str(&highlight);
+        output.push('\n');
+    }
+    output.push_str("</svg>\n");
+    Ok((output, summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_diff() {
+        let old = r#"<svg viewBox="0 0 30 10" width="30mm" height="10mm">
+<rect id="a" width="10" height="10"/>
+<rect id="b" x="20" width="10" height="10"/>
+<rect id="c" x="10" width="5" height="5"/>
+</svg>"#;
+        let new = r#"<svg viewBox="0 0 40 10" width="40mm" height="10mm">
+<rect id="a" width="10" height="10"/>
+<rect id="b" x="30" width="10" height="10"/>
+<circle id="d" cx="20" cy="5" r="2"/>
+</svg>"#;
+        let (svg, summary) = svg_diff(old, new).unwrap();
+        assert_eq!(
+            summary,
+            DiffSummary {
+                added: vec!["d".to_string()],
+                removed: vec!["c".to_string()],
+                moved: vec!["b".to_string()],
+            }
+        );
+        assert_eq!(summary.to_string(), "+ #d\n- #c\n~ #b\n");
+        assert!(svg.contains(r#"width="40mm" height="14mm" viewBox="0 -2 40 14""#));
+        assert!(svg.contains(r#"<rect x="17" y="2" width="6" height="6" class="diff-added"/>"#));
+        assert!(svg.contains(r#"<rect x="9" y="-1" width="7" height="7" class="diff-removed"/>"#));
+        assert!(svg.contains(r#"<line x1="25" y1="5" x2="35" y2="5" class="diff-moved"/>"#));
+        assert!(svg.contains(r#"<g opacity="0.4">"#));
+        assert!(svg.contains(r#"<circle id="d" cx="20" cy="5" r="2"/>"#));
+
+        let (_, summary) = svg_diff(new, new).unwrap();
+        assert!(summary.is_empty());
+    }
+}
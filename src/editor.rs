@@ -0,0 +1,434 @@
+//! Support for editor front-ends.
+//!
+//! Given a document and a cursor position (as a byte offset), these functions
+//! provide completion candidates and hover information, as building blocks for
+//! smarter editors and language servers.
+
+use crate::constants::{ELREF_ID_PREFIX, ELREF_PREVIOUS, LOCSPEC_SEP};
+use crate::context::ElementMap;
+use crate::errors::Result;
+use crate::themes::auto_style_classes;
+use crate::transform::Transformer;
+use crate::types::ElRef;
+use crate::validate::element_attrs;
+use crate::TransformConfig;
+
+use std::io::Cursor;
+
+/// Attributes handled by svgdx itself, in addition to any SVG attributes
+const SVGDX_ATTRS: &[(&str, &str)] = &[
+    ("id", "Element identifier"),
+    ("class", "Space-separated class names"),
+    ("xy", "Position of the element"),
+    ("cxy", "Position of the element's center"),
+    ("xy-loc", "Location on the element which `xy` refers to"),
+    ("dx", "Horizontal offset"),
+    ("dy", "Vertical offset"),
+    ("dxy", "Horizontal and vertical offset"),
+    ("wh", "Width and height"),
+    ("dw", "Width adjustment"),
+    ("dh", "Height adjustment"),
+    ("dwh", "Width and height adjustment"),
+    ("surround", "Elements to surround"),
+    ("inside", "Region to fill"),
+    ("pack", "Arrangement of elements inside a region"),
+    ("margin", "Margin for `surround` or `inside`"),
+    ("xy1", "Start point of a line"),
+    ("xy2", "End point of a line"),
+    ("start", "Start of a connector"),
+    ("end", "End of a connector"),
+    ("edge-type", "Connector routing type"),
+    ("corner-offset", "Offset of a connector's corner"),
+    ("corner-radius", "Radius of a connector's rounded corners"),
+    ("dir", "Connector arrow direction"),
+    (
+        "margin-start",
+        "Distance by which a connector is shortened at its start",
+    ),
+    (
+        "margin-end",
+        "Distance by which a connector is shortened at its end",
+    ),
+    ("text", "Text label"),
+    ("text-loc", "Location of the text label"),
+    ("text-offset", "Offset of the text label from its location"),
+    ("text-pre", "Preformatted text label"),
+    ("style", "Inline CSS style"),
+    ("transform", "Transformation to apply"),
+];
+
+const LOCSPECS: &[(&str, &str)] = &[
+    ("tl", "top-left"),
+    ("t", "top"),
+    ("tr", "top-right"),
+    ("r", "right"),
+    ("br", "bottom-right"),
+    ("b", "bottom"),
+    ("bl", "bottom-left"),
+    ("l", "left"),
+    ("c", "center"),
+    ("m", "midpoint of a line"),
+];
+
+/// The type of a completion candidate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompletionKind {
+    ElementId,
+    Attribute,
+    Class,
+    LocSpec,
+}
+
+/// A single completion candidate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    /// Full text of the candidate, e.g. `d-red`
+    pub label: String,
+    /// Short description of the candidate
+    pub detail: String,
+}
+
+/// Resolved geometry of an element, for display when hovering.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HoverInfo {
+    pub id: String,
+    /// Element name, after any transformation (e.g. `polyline` for a connector)
+    pub element: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Convert a 1-based `line` and `col` (in characters) into a byte offset.
+pub fn cursor_offset(input: &str, line: usize, col: usize) -> Option<usize> {
+    let mut offset = 0;
+    for (idx, text) in input.split_inclusive('\n').enumerate() {
+        if idx + 1 == line {
+            let text = text.trim_end_matches('\n');
+            let col_offset = text
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .nth(col.checked_sub(1)?)?;
+            return Some(offset + col_offset);
+        }
+        offset += text.len();
+    }
+    (line == 1 && input.is_empty() && col == 1).then_some(0)
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// All `id` values in the document, with the name of the corresponding element.
+fn document_ids(input: &str) -> Vec<(String, String)> {
+    let mut ids: Vec<(String, String)> = Vec::new();
+    let mut search = 0;
+    while let Some(idx) = input[search..].find("id=") {
+        let start = search + idx;
+        search = start + 3;
+        let preceded_by_ws = input[..start].ends_with(char::is_whitespace);
+        let quote = input[search..].chars().next();
+        if !preceded_by_ws || !matches!(quote, Some('"') | Some('\'')) {
+            continue;
+        }
+        let value_start = search + 1;
+        let Some(len) = input[value_start..].find(quote.expect("checked above")) else {
+            break;
+        };
+        let id = &input[value_start..value_start + len];
+        let element = input[..start]
+            .rfind('<')
+            .map(|lt| {
+                input[lt + 1..start]
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+        if !id.is_empty() && !ids.iter().any(|(i, _)| i == id) {
+            ids.push((id.to_owned(), element.to_owned()));
+        }
+    }
+    ids
+}
+
+/// The state of the cursor within an element's start tag.
+enum TagPosition<'a> {
+    /// Within an attribute name (possibly empty)
+    AttrName { element: &'a str, prefix: &'a str },
+    /// Within an attribute value
+    AttrValue { attr: &'a str, prefix: &'a str },
+}
+
+fn tag_position(before: &str) -> Option<TagPosition<'_>> {
+    let lt = before.rfind('<')?;
+    if before[lt..].contains('>') {
+        return None;
+    }
+    let tag = &before[lt + 1..];
+    let name_end = tag.find(char::is_whitespace)?;
+    let element = &tag[..name_end];
+
+    let mut quote: Option<char> = None;
+    let mut value_start = 0;
+    for (idx, c) in tag.char_indices().skip(name_end) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                value_start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    if quote.is_some() {
+        let attr = tag[..value_start - 1]
+            .trim_end()
+            .strip_suffix('=')?
+            .trim_end()
+            .rsplit(char::is_whitespace)
+            .next()?;
+        Some(TagPosition::AttrValue {
+            attr,
+            prefix: &tag[value_start..],
+        })
+    } else {
+        let prefix = tag.rsplit(char::is_whitespace).next().unwrap_or_default();
+        if prefix.contains(['=', '"', '\'']) {
+            return None;
+        }
+        Some(TagPosition::AttrName { element, prefix })
+    }
+}
+
+/// Determine completion candidates for the cursor at byte `offset` in `input`.
+///
+/// Candidates are element ids (following `#`), locspecs (following `#id@`),
+/// attribute names, and auto-style `d-*` classes within a `class` attribute.
+pub fn complete(input: &str, offset: usize) -> Vec<Completion> {
+    let Some(before) = input.get(..offset) else {
+        return Vec::new();
+    };
+    match tag_position(before) {
+        Some(TagPosition::AttrName { element, prefix }) => {
+            let el_attrs = element_attrs(element).unwrap_or_default();
+            SVGDX_ATTRS
+                .iter()
+                .copied()
+                .chain(el_attrs.iter().map(|a| (*a, "SVG attribute")))
+                .filter(|(a, _)| a.starts_with(prefix))
+                .fold(Vec::new(), |mut acc: Vec<Completion>, (a, detail)| {
+                    if !acc.iter().any(|c| c.label == a) {
+                        acc.push(Completion {
+                            kind: CompletionKind::Attribute,
+                            label: a.to_owned(),
+                            detail: detail.to_owned(),
+                        });
+                    }
+                    acc
+                })
+        }
+        Some(TagPosition::AttrValue { attr, prefix }) => {
+            let word = prefix
+                .rsplit(char::is_whitespace)
+                .next()
+                .unwrap_or_default();
+            if attr == "class" {
+                return auto_style_classes(&TransformConfig::default())
+                    .into_iter()
+                    .filter(|c| c.name.starts_with(word))
+                    .map(|c| Completion {
+                        kind: CompletionKind::Class,
+                        label: c.name,
+                        detail: c.description,
+                    })
+                    .collect();
+            }
+            // find the start of any element reference, e.g. `#abc@t` or `^@`
+            let ref_start = word
+                .rfind(|c: char| !(is_id_char(c) || c == LOCSPEC_SEP))
+                .map(|idx| &word[idx..])
+                .unwrap_or(word);
+            if !ref_start.starts_with([ELREF_ID_PREFIX, ELREF_PREVIOUS]) {
+                return Vec::new();
+            }
+            if let Some((_, loc)) = ref_start.split_once(LOCSPEC_SEP) {
+                LOCSPECS
+                    .iter()
+                    .filter(|(ls, _)| ls.starts_with(loc))
+                    .map(|(ls, detail)| Completion {
+                        kind: CompletionKind::LocSpec,
+                        label: ls.to_string(),
+                        detail: detail.to_string(),
+                    })
+                    .collect()
+            } else if let Some(id_prefix) = ref_start.strip_prefix(ELREF_ID_PREFIX) {
+                document_ids(input)
+                    .into_iter()
+                    .filter(|(id, _)| id.starts_with(id_prefix))
+                    .map(|(id, element)| Completion {
+                        kind: CompletionKind::ElementId,
+                        label: id,
+                        detail: format!("<{element}>"),
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Find the element id at byte `offset` in `input`, either within a `#id`
+/// reference or an `id` attribute value. The flag is true for a reference.
+fn id_at(input: &str, offset: usize) -> Option<(&str, bool)> {
+    let (before, after) = (input.get(..offset)?, input.get(offset..)?);
+    let start = before
+        .rfind(|c: char| !is_id_char(c))
+        .map(|idx| idx + before[idx..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(0);
+    let end = offset + after.find(|c: char| !is_id_char(c)).unwrap_or(after.len());
+    let id = &input[start..end];
+    let prefix = &input[..start];
+    let is_ref = prefix.ends_with(ELREF_ID_PREFIX);
+    let is_id_attr = prefix.ends_with("id=\"") || prefix.ends_with("id='");
+    (!id.is_empty() && (is_ref || is_id_attr)).then_some((id, is_ref))
+}
+
+/// Determine the source position (1-based line and column) of the element
+/// defining the `#id` reference at byte `offset` in `input`.
+///
+/// This uses the transformer's record of elements, so references to elements
+/// created by e.g. `<reuse>` or `<loop>` resolve to their source positions.
+pub fn definition(input: &str, offset: usize, config: &TransformConfig) -> Option<(usize, usize)> {
+    let (id, true) = id_at(input, offset)? else {
+        return None;
+    };
+    let mut t = Transformer::from_config(config);
+    // any errors are ignored; elements processed successfully are still available
+    let _ = t.transform(&mut Cursor::new(input), &mut Vec::new());
+    let el = t.context.get_element(&ElRef::Id(id.to_owned()))?;
+    (el.src_line > 0).then_some((el.src_line, el.src_col.max(1)))
+}
+
+/// Determine the element referenced at byte `offset` in `input`, either by a
+/// `#id` reference or an `id` attribute, and return its resolved geometry.
+///
+/// Returns `Ok(None)` if there is no reference at the cursor or the referenced
+/// element has no bounding box.
+pub fn hover(input: &str, offset: usize, config: &TransformConfig) -> Result<Option<HoverInfo>> {
+    let Some((id, _)) = id_at(input, offset) else {
+        return Ok(None);
+    };
+
+    let mut t = Transformer::from_config(config);
+    t.transform(&mut Cursor::new(input), &mut Vec::new())?;
+    let Some(el) = t.context.get_element(&ElRef::Id(id.to_owned())) else {
+        return Ok(None);
+    };
+    Ok(t.context.get_element_bbox(el)?.map(|bb| HoverInfo {
+        id: id.to_owned(),
+        element: el.name.clone(),
+        x: bb.x1,
+        y: bb.y1,
+        width: bb.width(),
+        height: bb.height(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(completions: Vec<Completion>) -> Vec<String> {
+        completions.into_iter().map(|c| c.label).collect()
+    }
+
+    #[test]
+    fn test_cursor_offset() {
+        let input = "ab\ncdé\nf";
+        assert_eq!(cursor_offset(input, 1, 1), Some(0));
+        assert_eq!(cursor_offset(input, 2, 2), Some(4));
+        assert_eq!(cursor_offset(input, 2, 4), Some(7));
+        assert_eq!(cursor_offset(input, 3, 2), Some(9));
+        assert_eq!(cursor_offset(input, 2, 5), None);
+        assert_eq!(cursor_offset(input, 4, 1), None);
+    }
+
+    #[test]
+    fn test_complete_ids() {
+        let input = r##"<rect id="abc"/><circle id="abd"/><rect id="x"/><line start="#ab"##;
+        let completions = complete(input, input.len());
+        assert_eq!(completions.len(), 2);
+        assert_eq!(completions[0].label, "abc");
+        assert_eq!(completions[0].detail, "<rect>");
+        assert_eq!(completions[1].kind, CompletionKind::ElementId);
+
+        let input = r##"<rect id="abc"/><line start="#abc@t"##;
+        assert_eq!(labels(complete(input, input.len())), vec!["tl", "t", "tr"]);
+        let input = r##"<rect id="abc"/><line start="^@"##;
+        assert_eq!(complete(input, input.len()).len(), LOCSPECS.len());
+        // not within a reference
+        let input = r##"<rect id="abc"/><line start="10 2"##;
+        assert!(complete(input, input.len()).is_empty());
+    }
+
+    #[test]
+    fn test_complete_attrs_classes() {
+        let input = r#"<rect"#;
+        assert!(complete(input, input.len()).is_empty());
+        let input = r#"<rect xy="1" x"#;
+        assert_eq!(
+            labels(complete(input, input.len())),
+            vec!["xy", "xy-loc", "xy1", "xy2", "x"]
+        );
+        let input = r#"<rect class="d-red d-fill-bl"#;
+        let completions = complete(input, input.len());
+        assert!(completions.iter().all(|c| c.kind == CompletionKind::Class));
+        assert!(labels(completions).contains(&"d-fill-blue".to_string()));
+        // outside a tag
+        let input = r#"<rect class="d-red"/> d-"#;
+        assert!(complete(input, input.len()).is_empty());
+    }
+
+    #[test]
+    fn test_hover() {
+        let input =
+            r##"<svg><rect id="abc" xy="5" wh="10 4"/><line start="#abc" end="20 20"/></svg>"##;
+        let cfg = TransformConfig::default();
+        let expected = Some(HoverInfo {
+            id: "abc".to_owned(),
+            element: "rect".to_owned(),
+            x: 5.,
+            y: 5.,
+            width: 10.,
+            height: 4.,
+        });
+        let offset = input.find("#abc").unwrap() + 2;
+        assert_eq!(hover(input, offset, &cfg).unwrap(), expected);
+        let offset = input.find("abc").unwrap();
+        assert_eq!(hover(input, offset, &cfg).unwrap(), expected);
+        let offset = input.find("wh").unwrap();
+        assert_eq!(hover(input, offset, &cfg).unwrap(), None);
+    }
+
+    #[test]
+    fn test_definition() {
+        let input =
+            "<svg>\n  <rect id=\"abc\" wh=\"5\"/>\n<line start=\"#abc\" end=\"#nope\"/>\n</svg>";
+        let cfg = TransformConfig::default();
+        let offset = input.find("#abc").unwrap() + 1;
+        assert_eq!(definition(input, offset, &cfg), Some((2, 3)));
+        // errors elsewhere in the document don't prevent resolution
+        let offset = input.find("#nope").unwrap() + 1;
+        assert_eq!(definition(input, offset, &cfg), None);
+        // not a reference
+        let offset = input.find("abc").unwrap();
+        assert_eq!(definition(input, offset, &cfg), None);
+    }
+}
@@ -7,14 +7,17 @@ use crate::context::{ContextView, ElementMap, TransformerContext};
 use crate::errors::{Result, SvgdxError};
 use crate::events::{InputList, OutputEvent};
 use crate::expression::eval_attr;
+use crate::geometry::view_box;
+use crate::image_size::image_size;
 use crate::path::path_bbox;
 use crate::position::{
-    strp_length, BoundingBox, DirSpec, LocSpec, Position, ScalarSpec, TrblLength,
+    split_locspec, strp_length, BoundingBox, DirSpec, Length, LocSpec, Position, ScalarSpec,
+    TrblLength,
 };
 use crate::text::process_text_attr;
 use crate::transform_attr::TransformAttr;
 use crate::types::{
-    attr_split, attr_split_cycle, extract_elref, fstr, strp, AttrMap, ClassList, OrderIndex,
+    attr_split, attr_split_cycle, extract_elref, fstr, strp, AttrMap, ClassList, ElRef, OrderIndex,
 };
 
 use core::fmt::Display;
@@ -22,6 +25,9 @@ use std::collections::HashMap;
 use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
 use std::str::FromStr;
 
+/// Largest tangent of a `skew-x` / `skew-y` angle (about 89.94°)
+const MAX_SKEW_TAN: f32 = 1000.;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct SvgElement {
     pub name: String,
@@ -33,6 +39,8 @@ pub struct SvgElement {
     pub order_index: OrderIndex,
     pub indent: usize,
     pub src_line: usize,
+    /// Column (1-based) of the element's start in the source line
+    pub src_col: usize,
     pub event_range: Option<(usize, usize)>,
     pub content_bbox: Option<BoundingBox>,
 }
@@ -118,9 +126,11 @@ fn expand_relspec(value: &str, ctx: &impl ElementMap) -> String {
 }
 
 fn expand_single_relspec(value: &str, ctx: &impl ElementMap) -> String {
-    let elem_loc = |elem: &SvgElement, loc: LocSpec| {
-        ctx.get_element_bbox(elem)
-            .map(|bb| bb.map(|bb| bb.locspec(loc)))
+    let elem_loc = |elem: &SvgElement, loc: LocSpec| -> Result<Option<(f32, f32)>> {
+        match ctx.get_element_bbox(elem)? {
+            Some(bb) => elem.loc_point(loc, &bb).map(Some),
+            None => Ok(None),
+        }
     };
     if let Ok((Some(elem), rest)) = split_relspec(value, ctx) {
         if rest.is_empty() && elem.name == "point" {
@@ -170,6 +180,7 @@ impl SvgElement {
             order_index: OrderIndex::default(),
             indent: 0,
             src_line: 0,
+            src_col: 0,
             event_range: None,
             content_bbox: None,
         }
@@ -190,6 +201,10 @@ impl SvgElement {
             ) {
                 // replace with rendered connection element
                 *self = conn.render(ctx)?.without_attr("edge-type");
+                if self.has_attr("text") {
+                    // connector labels are placed along the connector
+                    self.set_default_attr("text-loc", "mid");
+                }
             } else {
                 return Err(SvgdxError::InvalidData(
                     "Cannot create connector".to_owned(),
@@ -342,15 +357,16 @@ impl SvgElement {
 
         // Need size before can evaluate relative position
         self.expand_compound_size();
+        self.eval_parent_ratios(ctx);
         self.eval_rel_attributes(ctx)?;
         self.resolve_size_delta();
 
         // ensure relatively-positioned text elements have appropriate anchors
         if self.name == "text" && self.has_attr("text") {
-            self.eval_text_anchor(ctx)?;
+            self.eval_text_anchor(ctx).map_err(|e| e.in_attr("xy"))?;
         }
 
-        self.eval_rel_position(ctx)?;
+        self.eval_rel_position(ctx).map_err(|e| e.in_attr("xy"))?;
         // Compound attributes, e.g. xy="#o 2" -> x="#o 2", y="#o 2"
         self.expand_compound_pos();
         self.eval_rel_attributes(ctx)?;
@@ -367,6 +383,258 @@ impl SvgElement {
         let p = Position::from(self as &SvgElement);
         p.set_position_attrs(self);
 
+        self.resolve_scale(ctx)?;
+        self.resolve_skew(ctx)?;
+        self.resolve_transform_origin(ctx)?;
+
+        if self.name == "use" {
+            self.resolve_use_scale(ctx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Convert any `scale` attribute into a `transform`.
+    ///
+    /// The element is scaled by one (uniform) or two (x and y) factors about
+    /// its centre, or about its `transform-origin` if given.
+    fn resolve_scale(&mut self, ctx: &impl ContextView) -> Result<()> {
+        // `scale` is a standard attribute of `<feDisplacementMap>`
+        if self.name.starts_with("fe") {
+            return Ok(());
+        }
+        let Some(scale) = self.pop_attr("scale") else {
+            return Ok(());
+        };
+        let factors = attr_split(&scale)
+            .map(|v| strp(&v))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| e.in_attr("scale"))?;
+        let xfrm = match factors.as_slice() {
+            [s] => format!("scale({})", fstr(*s)),
+            [sx, sy] => format!("scale({}, {})", fstr(*sx), fstr(*sy)),
+            _ => {
+                return Err(SvgdxError::InvalidData(format!(
+                    "scale must have one or two values: '{scale}'"
+                )))
+            }
+        };
+        self.append_transform(ctx, &xfrm)
+    }
+
+    /// Convert any `skew-x` / `skew-y` attributes (angles in degrees) into a
+    /// `transform` about the element's centre, or its `transform-origin`.
+    fn resolve_skew(&mut self, ctx: &impl ContextView) -> Result<()> {
+        for (attr, func) in [("skew-x", "skewX"), ("skew-y", "skewY")] {
+            if let Some(angle) = self.pop_attr(attr) {
+                let angle = strp(&angle).map_err(|e| e.in_attr(attr))?;
+                // angles near ±90° would skew the element to (near) infinity
+                let tan = angle.to_radians().tan();
+                if !tan.is_finite() || tan.abs() > MAX_SKEW_TAN {
+                    return Err(SvgdxError::InvalidData(format!(
+                        "skew angle {} is too close to 90 degrees",
+                        fstr(angle)
+                    ))
+                    .in_attr(attr));
+                }
+                self.append_transform(ctx, &format!("{func}({})", fstr(angle)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the given transform to the element, applied before any existing
+    /// `transform`.
+    ///
+    /// Unless the element has a `transform-origin`, the transform is applied
+    /// about the centre of the element's untransformed bbox.
+    fn append_transform(&mut self, ctx: &impl ContextView, xfrm: &str) -> Result<()> {
+        let existing = self.get_attr("transform");
+        let xfrm = if self.has_attr("transform-origin") {
+            xfrm.to_string()
+        } else {
+            let mut untransformed = self.clone();
+            untransformed.pop_attr("transform");
+            let bbox = ctx
+                .get_element_bbox(&untransformed)?
+                .ok_or_else(|| SvgdxError::MissingBoundingBox(format!("{xfrm} requires a bbox")))?;
+            let (cx, cy) = bbox.center();
+            format!(
+                "translate({}, {}) {xfrm} translate({}, {})",
+                fstr(cx),
+                fstr(cy),
+                fstr(-cx),
+                fstr(-cy)
+            )
+        };
+        let xfrm = match existing {
+            Some(existing) => format!("{existing} {xfrm}"),
+            None => xfrm,
+        };
+        self.set_attr("transform", &xfrm);
+        Ok(())
+    }
+
+    /// Fold any `transform-origin` attribute into the element's `transform`.
+    ///
+    /// The origin may be a locspec (or `center`) or a pair of percentages
+    /// relative to the element's untransformed bbox, or absolute coordinates.
+    /// Making this explicit in the `transform` ensures bbox calculations match
+    /// the rendered element, and supports SVG 1.1 renderers.
+    fn resolve_transform_origin(&mut self, ctx: &impl ContextView) -> Result<()> {
+        let Some(origin) = self.pop_attr("transform-origin") else {
+            return Ok(());
+        };
+        let Some(transform) = self.get_attr("transform") else {
+            return Ok(());
+        };
+        let mut untransformed = self.clone();
+        untransformed.pop_attr("transform");
+        let bbox = ctx.get_element_bbox(&untransformed)?;
+        let (ox, oy) =
+            transform_origin(&origin, bbox.as_ref()).map_err(|e| e.in_attr("transform-origin"))?;
+        if ox != 0. || oy != 0. {
+            self.set_attr(
+                "transform",
+                &format!(
+                    "translate({}, {}) {transform} translate({}, {})",
+                    fstr(ox),
+                    fstr(oy),
+                    fstr(-ox),
+                    fstr(-oy)
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// For a `use` element with numeric `width` and `height`, the scale factors
+    /// required to fit the target element's bbox to that size.
+    ///
+    /// Aspect ratio is preserved unless `preserveAspectRatio="none"` is given.
+    /// Returns `None` for `<symbol>` targets with a `viewBox`, as SVG renderers
+    /// handle scaling of those natively.
+    pub fn use_scale(&self, target: &SvgElement, target_bbox: &BoundingBox) -> Option<(f32, f32)> {
+        if target.name == "symbol" && target.has_attr("viewBox") {
+            return None;
+        }
+        let width = strp(&self.get_attr("width")?).ok()?;
+        let height = strp(&self.get_attr("height")?).ok()?;
+        if target_bbox.width() == 0. || target_bbox.height() == 0. {
+            return None;
+        }
+        let sx = width / target_bbox.width();
+        let sy = height / target_bbox.height();
+        if self.get_attr("preserveAspectRatio").as_deref() == Some("none") {
+            Some((sx, sy))
+        } else {
+            let s = sx.min(sy);
+            Some((s, s))
+        }
+    }
+
+    /// Replace an `auto` width or height (or part of `wh`) on a `use` or
+    /// `image` element with the value which preserves the aspect ratio of the
+    /// referenced element or image. If both are `auto`, the intrinsic size is
+    /// used.
+    pub fn resolve_auto_aspect(&mut self, ctx: &TransformerContext) -> Result<()> {
+        if !matches!(self.name.as_str(), "use" | "image") {
+            return Ok(());
+        }
+        if let Some(wh) = self.get_attr("wh") {
+            let (w, h) = Self::split_compound_attr(&eval_attr(&wh, ctx));
+            if w == "auto" || h == "auto" {
+                self.pop_attr("wh");
+                self.set_attr("width", &w);
+                self.set_attr("height", &h);
+            }
+        }
+        let is_auto = |attr| self.get_attr(attr).as_deref() == Some("auto");
+        let (auto_w, auto_h) = (is_auto("width"), is_auto("height"));
+        if !auto_w && !auto_h {
+            return Ok(());
+        }
+        let href = self
+            .get_attr("href")
+            .or_else(|| self.get_attr("xlink:href"))
+            .ok_or_else(|| SvgdxError::MissingAttribute("href".to_string()))?;
+        let (width, height) = if self.name == "image" {
+            image_size(&href, ctx.config.import_dir.as_deref()).map_err(|e| e.in_attr("href"))?
+        } else {
+            let elref: ElRef = href.parse()?;
+            let target = ctx
+                .get_element(&elref)
+                .ok_or_else(|| SvgdxError::ReferenceError(elref.clone()))?;
+            let bbox = match view_box(target)? {
+                Some(vb) if target.name == "symbol" => Some(vb),
+                _ => ctx.get_element_bbox(target)?,
+            };
+            bbox.filter(|bb| bb.width() > 0. && bb.height() > 0.)
+                .map(|bb| (bb.width(), bb.height()))
+                .ok_or_else(|| SvgdxError::MissingBoundingBox(target.to_string()))?
+        };
+        let value = |attr: &str| {
+            let value = self.get_attr(attr).unwrap_or_default();
+            strp(&eval_attr(&value, ctx)).map_err(|e| e.in_attr(attr))
+        };
+        let (width, height) = match (auto_w, auto_h) {
+            (true, true) => (width, height),
+            (true, false) => {
+                let h = value("height")?;
+                (h * width / height, h)
+            }
+            _ => {
+                let w = value("width")?;
+                (w, w * height / width)
+            }
+        };
+        self.set_attr("width", &fstr(width));
+        self.set_attr("height", &fstr(height));
+        Ok(())
+    }
+
+    /// Replace `width` / `height` on a `use` element with a transform
+    /// scaling the target to that size.
+    fn resolve_use_scale(&mut self, ctx: &impl ContextView) -> Result<()> {
+        let elref: ElRef = match self.get_attr("href") {
+            Some(href) => href.parse()?,
+            None => return Ok(()),
+        };
+        let Some(target) = ctx.get_element(&elref) else {
+            return Ok(());
+        };
+        let Some(bbox) = ctx.get_element_bbox(target)? else {
+            return Ok(());
+        };
+        if let Some((sx, sy)) = self.use_scale(target, &bbox) {
+            if fstr(sx) == "1" && fstr(sy) == "1" {
+                // already at the requested size; leave as-is
+                return Ok(());
+            }
+            let x = strp(&self.pop_attr("x").unwrap_or("0".to_owned()))?;
+            let y = strp(&self.pop_attr("y").unwrap_or("0".to_owned()))?;
+            self.pop_attr("width");
+            self.pop_attr("height");
+            self.pop_attr("preserveAspectRatio");
+            let scale = if sx == sy {
+                format!("scale({})", fstr(sx))
+            } else {
+                format!("scale({}, {})", fstr(sx), fstr(sy))
+            };
+            // scale about the target's top-left, then move to the use position
+            let xfrm = format!(
+                "translate({}, {}) {scale} translate({}, {})",
+                fstr(bbox.x1 + x),
+                fstr(bbox.y1 + y),
+                fstr(-bbox.x1),
+                fstr(-bbox.y1)
+            );
+            let xfrm = match self.get_attr("transform") {
+                Some(existing) => format!("{existing} {xfrm}"),
+                None => xfrm,
+            };
+            self.set_attr("transform", &xfrm);
+        }
         Ok(())
     }
 
@@ -378,6 +646,10 @@ impl SvgElement {
         self.src_line = line;
     }
 
+    pub fn set_src_col(&mut self, col: usize) {
+        self.src_col = col;
+    }
+
     pub fn set_order_index(&mut self, order_index: &OrderIndex) {
         self.order_index = order_index.clone();
     }
@@ -407,6 +679,37 @@ impl SvgElement {
         self.classes.contains(class)
     }
 
+    /// Check whether this element matches a simple CSS-like selector.
+    ///
+    /// Selectors are of the form `name`, `.class`, `#id` or a combination
+    /// such as `rect.a.b`; comma-separated selectors match if any part matches.
+    pub fn matches_selector(&self, selector: &str) -> bool {
+        selector.split(',').map(str::trim).any(|sel| {
+            if sel.is_empty() {
+                return false;
+            }
+            let name_end = sel.find(['.', '#']).unwrap_or(sel.len());
+            let (name, mut rest) = sel.split_at(name_end);
+            if !(name.is_empty() || name == "*" || name == self.name) {
+                return false;
+            }
+            while !rest.is_empty() {
+                let end = rest[1..].find(['.', '#']).map_or(rest.len(), |i| i + 1);
+                let (part, tail) = rest.split_at(end);
+                let ok = if let Some(class) = part.strip_prefix('.') {
+                    self.has_class(class)
+                } else {
+                    self.get_attr("id").as_deref() == part.strip_prefix('#')
+                };
+                if !ok {
+                    return false;
+                }
+                rest = tail;
+            }
+            true
+        })
+    }
+
     /// Remove a class from the element, returning `true` if the class was present
     pub fn pop_class(&mut self, class: &str) -> bool {
         self.classes.remove(class)
@@ -484,6 +787,31 @@ impl SvgElement {
         self.attrs.to_vec().into_iter().collect()
     }
 
+    /// Convert any `fade` attribute into an `opacity` attribute.
+    ///
+    /// The fade is given as a fraction or percentage, where `fade="100%"` is
+    /// fully transparent; any existing `opacity` is reduced accordingly.
+    pub fn resolve_fade(&mut self) -> Result<()> {
+        let Some(fade) = self.pop_attr("fade") else {
+            return Ok(());
+        };
+        let fade = match strp_length(&fade).map_err(|e| e.in_attr("fade"))? {
+            Length::Absolute(v) | Length::Ratio(v) if (0. ..=1.).contains(&v) => v,
+            _ => {
+                return Err(SvgdxError::InvalidData(format!(
+                    "fade must be between 0 and 100%: '{fade}'"
+                ))
+                .in_attr("fade"))
+            }
+        };
+        let opacity = match self.get_attr("opacity") {
+            Some(opacity) => strp(&opacity).map_err(|e| e.in_attr("opacity"))?,
+            None => 1.,
+        };
+        self.set_attr("opacity", &fstr(opacity * (1. - fade)));
+        Ok(())
+    }
+
     /// Resolve any expressions in attributes. Note attributes are unchanged on failure.
     pub fn eval_attributes(&mut self, ctx: &impl ContextView) {
         // Resolve any attributes
@@ -806,7 +1134,75 @@ impl SvgElement {
         Ok(el_bbox)
     }
 
-    fn translated(&self, dx: f32, dy: f32) -> Result<Self> {
+    /// Vertices of a line-like element, in order.
+    pub(crate) fn vertices(&self) -> Option<Vec<(f32, f32)>> {
+        match self.name.as_str() {
+            "line" => {
+                let coord = |a| self.get_attr(a).and_then(|v| strp(&v).ok());
+                Some(vec![
+                    (coord("x1")?, coord("y1")?),
+                    (coord("x2")?, coord("y2")?),
+                ])
+            }
+            "polyline" | "polygon" => {
+                let values = attr_split(&self.get_attr("points")?)
+                    .map(|v| strp(&v))
+                    .collect::<Result<Vec<_>>>()
+                    .ok()?;
+                let mut points: Vec<_> = values.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+                if self.name == "polygon" {
+                    points.push(*points.first()?);
+                }
+                (points.len() >= 2).then_some(points)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse a locspec relative to this element, which may be the name of
+    /// one of the element's `ports`.
+    pub fn port_loc(&self, loc: &str) -> Result<LocSpec> {
+        LocSpec::with_ports(loc, self.get_attr("ports").as_deref())
+    }
+
+    /// Determine the point given by `loc` on this element, which has the
+    /// given bounding box.
+    ///
+    /// For line-like elements (including rendered connectors) `Mid` is the
+    /// point half-way along the line, `Corner(n)` is the n'th vertex between
+    /// the start and end points, and `Start` and `End` are those points.
+    /// Other elements use `bbox.locspec()`.
+    pub fn loc_point(&self, loc: LocSpec, bbox: &BoundingBox) -> Result<(f32, f32)> {
+        match (loc, self.vertices()) {
+            (LocSpec::Mid, Some(points)) => {
+                let seg_len = |(a, b): ((f32, f32), (f32, f32))| (b.0 - a.0).hypot(b.1 - a.1);
+                let segments = || points.iter().copied().zip(points.iter().copied().skip(1));
+                let mut remain = segments().map(seg_len).sum::<f32>() / 2.;
+                for (a, b) in segments() {
+                    let len = seg_len((a, b));
+                    if remain <= len && len > 0. {
+                        let t = remain / len;
+                        return Ok((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+                    }
+                    remain -= len;
+                }
+                Ok(points[0])
+            }
+            (LocSpec::Corner(n), Some(points)) => {
+                // a polygon's corners include its first point (repeated at the end)
+                let skip = if self.name == "polygon" { 0 } else { 1 };
+                points[skip..points.len() - 1]
+                    .get(n - 1)
+                    .copied()
+                    .ok_or_else(|| SvgdxError::InvalidData(format!("No corner {n} on {self}")))
+            }
+            (LocSpec::Start, Some(points)) => Ok(points[0]),
+            (LocSpec::End, Some(points)) => Ok(points[points.len() - 1]),
+            _ => Ok(bbox.locspec(loc)),
+        }
+    }
+
+    pub fn translated(&self, dx: f32, dy: f32) -> Result<Self> {
         let mut new_elem = self.clone();
         for (key, value) in &self.attrs {
             match key.as_str() {
@@ -867,6 +1263,21 @@ impl SvgElement {
                 self.attrs.insert("rx", fstr(rx));
                 self.attrs.insert("ry", fstr(ry));
             }
+            "text" | "point" => {
+                // position at the point given by any `text-loc`, so e.g. a label
+                // with `text-loc="tl"` sits in the top-left corner of the region.
+                let loc = self
+                    .get_attr("text-loc")
+                    .and_then(|loc| loc.parse().ok())
+                    .unwrap_or(LocSpec::Center);
+                let (x, y) = bb.locspec(loc);
+                self.attrs.insert("x", fstr(x));
+                self.attrs.insert("y", fstr(y));
+                if inscribe && self.name == "text" {
+                    // anchor text towards the inside of the region
+                    self.classes.insert("d-text-inside".to_string());
+                }
+            }
             _ => {}
         }
     }
@@ -897,7 +1308,9 @@ impl SvgElement {
                             ss.into()
                         };
                         // position attributes handle dx/dy within eval_pos_helper
-                        if let Ok(Some((x, y))) = self.eval_pos_helper(remain, &bbox, anchor) {
+                        if let Some((x, y)) =
+                            self.eval_pos_helper(remain, Some(el), &bbox, anchor)?
+                        {
                             use ScalarSpec::*;
                             v = match ss {
                                 Minx | Maxx | Cx => x,
@@ -933,16 +1346,23 @@ impl SvgElement {
     fn eval_pos_helper(
         &self,
         remain: &str,
+        ref_el: Option<&SvgElement>,
         bbox: &BoundingBox,
         anchor: LocSpec,
     ) -> Result<Option<(f32, f32)>> {
         if let Some((x, y)) = if remain.starts_with(LOCSPEC_SEP) {
-            let (loc_str, dxy) = remain.split_once(' ').unwrap_or((remain, ""));
-            if let Some(loc) = loc_str
-                .strip_prefix(LOCSPEC_SEP)
-                .and_then(|ls| ls.parse().ok())
-            {
-                let (x, y) = bbox.locspec(loc);
+            let (loc_str, dxy) = split_locspec(remain);
+            if let Some(loc) = loc_str.strip_prefix(LOCSPEC_SEP).and_then(|ls| {
+                match ref_el {
+                    Some(ref_el) => ref_el.port_loc(ls),
+                    None => ls.parse(),
+                }
+                .ok()
+            }) {
+                let (x, y) = match ref_el {
+                    Some(ref_el) => ref_el.loc_point(loc, bbox)?,
+                    None => bbox.locspec(loc),
+                };
                 let (dx, dy) = self.extract_dx_dy(dxy)?;
                 {
                     Some((x + dx, y + dy))
@@ -993,7 +1413,9 @@ impl SvgElement {
                     "x" | "y" | "cx" | "cy" | "x1" | "y1" | "x2" | "y2",
                 )
             ) {
-                let computed = self.eval_rel_attr(&key, &value, ctx)?;
+                let computed = self
+                    .eval_rel_attr(&key, &value, ctx)
+                    .map_err(|e| e.in_attr(&key))?;
                 if strp(&computed).is_ok() {
                     self.attrs.insert(key.clone(), computed);
                 }
@@ -1008,7 +1430,7 @@ impl SvgElement {
         let input = self.attrs.get("xy");
         if let Some(input) = input {
             let (_, rel_loc) = split_relspec(input, ctx)?;
-            let rel_loc = rel_loc.split_whitespace().next().unwrap_or_default();
+            let (rel_loc, _) = split_locspec(rel_loc);
             if let Some(rel) = rel_loc.strip_prefix(RELPOS_SEP) {
                 match rel.parse()? {
                     DirSpec::Above => self.set_default_attr("text-loc", "t"),
@@ -1032,6 +1454,9 @@ impl SvgElement {
                         LocSpec::BottomEdge(_) => self.set_default_attr("text-loc", "b"),
                         LocSpec::LeftEdge(_) => self.set_default_attr("text-loc", "l"),
                         LocSpec::RightEdge(_) => self.set_default_attr("text-loc", "r"),
+                        LocSpec::Mid | LocSpec::Corner(_) | LocSpec::Start | LocSpec::End => {
+                            self.set_default_attr("text-loc", "c")
+                        }
                     }
                 } else {
                     return Err(SvgdxError::InvalidData(format!(
@@ -1132,9 +1557,8 @@ impl SvgElement {
         // wh="#thing 50%" -> width="#thing 50%", height="#thing 50%"
         // wh="#thing 10 20" -> width="#thing 10", height="#thing 20"
         if value.starts_with([ELREF_ID_PREFIX, ELREF_PREVIOUS]) {
-            let mut parts = value.splitn(2, char::is_whitespace);
-            let prefix = parts.next().expect("nonempty");
-            if let Some(remain) = parts.next() {
+            let (prefix, remain) = split_locspec(value);
+            if !remain.is_empty() {
                 let mut parts = attr_split_cycle(remain);
                 let x_suffix = parts.next().unwrap_or_default();
                 let y_suffix = parts.next().unwrap_or_default();
@@ -1171,6 +1595,26 @@ impl SvgElement {
         }
     }
 
+    /// Resolve percentage `width` / `height` values against the size of the
+    /// parent element, e.g. `width="50%"` within `<g wh="40 20">` becomes
+    /// `width="20"`. If the parent has no known size, values are unchanged.
+    fn eval_parent_ratios(&mut self, ctx: &impl ElementMap) {
+        if !matches!(
+            self.name.as_str(),
+            "rect" | "use" | "image" | "svg" | "foreignObject" | "box"
+        ) {
+            return;
+        }
+        if let Some((parent_w, parent_h)) = ctx.get_parent_size() {
+            for (attr, base) in [("width", parent_w), ("height", parent_h)] {
+                if let Some(Ok(Length::Ratio(ratio))) = self.get_attr(attr).map(|v| strp_length(&v))
+                {
+                    self.set_attr(attr, &fstr(base * ratio));
+                }
+            }
+        }
+    }
+
     fn resolve_size_delta(&mut self) {
         // assumes "width"/"height"/"r"/"rx"/"ry" are numeric if present
         let (w, h) = match self.name.as_str() {
@@ -1250,6 +1694,41 @@ impl SvgElement {
     }
 }
 
+/// Absolute coordinates of a `transform-origin` value for an element with
+/// the given (untransformed) bbox.
+fn transform_origin(value: &str, bbox: Option<&BoundingBox>) -> Result<(f32, f32)> {
+    let need_bbox = || {
+        bbox.ok_or_else(|| {
+            SvgdxError::MissingBoundingBox(format!("transform-origin '{value}' requires a bbox"))
+        })
+    };
+    let parts: Vec<_> = attr_split(value).collect();
+    match parts.as_slice() {
+        [loc] => {
+            let loc = if loc == "center" { "c" } else { loc };
+            Ok(need_bbox()?.locspec(loc.parse()?))
+        }
+        [x, y] => {
+            // percentages are relative to the bbox; plain numbers are absolute
+            let (x, y) = (strp_length(x)?, strp_length(y)?);
+            match (x, y) {
+                (Length::Absolute(x), Length::Absolute(y)) => Ok((x, y)),
+                _ => {
+                    let bb = need_bbox()?;
+                    let coord = |len: Length, start: f32, size: f32| match len {
+                        Length::Absolute(v) => v,
+                        _ => start + len.evaluate(size),
+                    };
+                    Ok((coord(x, bb.x1, bb.width()), coord(y, bb.y1, bb.height())))
+                }
+            }
+        }
+        _ => Err(SvgdxError::InvalidData(format!(
+            "Invalid transform-origin '{value}'"
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -1258,6 +1737,19 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_transform_origin() {
+        let bb = BoundingBox::new(10., 20., 30., 60.);
+        assert_eq!(transform_origin("center", Some(&bb)).unwrap(), (20., 40.));
+        assert_eq!(transform_origin("br", Some(&bb)).unwrap(), (30., 60.));
+        assert_eq!(transform_origin("25% 50%", Some(&bb)).unwrap(), (15., 40.));
+        assert_eq!(transform_origin("5 50%", Some(&bb)).unwrap(), (5., 40.));
+        assert_eq!(transform_origin("1 2", None).unwrap(), (1., 2.));
+        assert!(transform_origin("c", None).is_err());
+        assert!(transform_origin("50% 1", None).is_err());
+        assert!(transform_origin("x", Some(&bb)).is_err());
+    }
+
     #[test]
     fn test_spread_attr() {
         let (w, h) = SvgElement::split_compound_attr("10");
@@ -1293,27 +1785,27 @@ mod tests {
         let bbox = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
 
         // Test with edge positioning
-        let result = element.eval_pos_helper("@t:25%", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@t:25%", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((25., 0.)));
 
-        let result = element.eval_pos_helper("@t:25% -4", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@t:25% -4", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((21., -4.)));
 
-        let result = element.eval_pos_helper("@r:200%", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@r:200%", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((100., 200.)));
 
-        let result = element.eval_pos_helper("@l:-1", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@l:-1", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((0., 99.)));
 
-        let result = element.eval_pos_helper("@l:37", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@l:37", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((0., 37.)));
 
-        let result = element.eval_pos_helper("@l:37 3 5", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@l:37 3 5", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((3., 42.)));
     }
@@ -1324,15 +1816,15 @@ mod tests {
         let bbox = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
 
         // Test with location positioning
-        let result = element.eval_pos_helper("@tr", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@tr", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((100., 0.)));
 
-        let result = element.eval_pos_helper("@bl", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@bl", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((0., 100.)));
 
-        let result = element.eval_pos_helper("@c", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("@c", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((50., 50.)));
     }
@@ -1343,11 +1835,11 @@ mod tests {
         let bbox = BoundingBox::new(0.0, 0.0, 100.0, 100.0);
         // Test with invalid input
 
-        let result = element.eval_pos_helper("invalid", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("invalid", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), None);
 
-        let result = element.eval_pos_helper("30 20", &bbox, LocSpec::TopLeft);
+        let result = element.eval_pos_helper("30 20", None, &bbox, LocSpec::TopLeft);
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Some((30., 20.)));
     }
@@ -1368,6 +1860,14 @@ mod tests {
         fn get_element_bbox(&self, el: &SvgElement) -> Result<Option<BoundingBox>> {
             el.bbox()
         }
+
+        fn get_parent_size(&self) -> Option<(f32, f32)> {
+            None
+        }
+
+        fn get_elements_matching(&self, _selector: &str) -> Vec<&SvgElement> {
+            Vec::new()
+        }
     }
 
     impl TestContext {
@@ -1400,4 +1900,22 @@ mod tests {
         let out = expand_relspec("1 2 #abc@t 3 4 #abc~h 5 6 #abc@c", &ctx);
         assert_eq!(out, "1 2 5 0 3 4 20 5 6 5 10");
     }
+
+    #[test]
+    fn test_matches_selector() {
+        let mut el = SvgElement::new("rect", &[("id".to_string(), "r1".to_string())]);
+        el.add_class("a");
+        el.add_class("b");
+        assert!(el.matches_selector("rect"));
+        assert!(el.matches_selector(".a"));
+        assert!(el.matches_selector("rect.a.b"));
+        assert!(el.matches_selector("#r1"));
+        assert!(el.matches_selector("rect#r1.b"));
+        assert!(el.matches_selector("circle, .b"));
+        assert!(el.matches_selector("*"));
+        assert!(!el.matches_selector("circle"));
+        assert!(!el.matches_selector(".a.c"));
+        assert!(!el.matches_selector("#r2"));
+        assert!(!el.matches_selector(""));
+    }
 }
@@ -22,9 +22,12 @@ pub enum SvgdxError {
     VarLimitError(String, usize, u32),
     LoopLimitError(u32, u32),
     DepthLimitExceeded(u32, u32),
+    ElementLimitError(u32, u32),
+    OutputLimitError(usize, u32),
     CircularRefError(String),
     DocumentError(String),
     MissingAttribute(String),
+    AttrError(String, Box<SvgdxError>),
     MissingBoundingBox(String),
     MessageError(String),
     InternalLogicError(String),
@@ -32,6 +35,19 @@ pub enum SvgdxError {
     OtherError(Box<dyn std::error::Error>),
 }
 
+/// Location of an error within the source document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorSpan {
+    /// Line number (1-based)
+    pub line: usize,
+    /// Column number (1-based, in characters)
+    pub col: usize,
+    /// Length of the relevant source text, in characters
+    pub len: usize,
+    pub code: Option<&'static str>,
+    pub message: String,
+}
+
 impl fmt::Display for SvgdxError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -52,11 +68,18 @@ impl fmt::Display for SvgdxError {
             SvgdxError::DepthLimitExceeded(depth, limit) => {
                 write!(f, "Depth {} exceeded limit {}", depth, limit)
             }
+            SvgdxError::ElementLimitError(count, limit) => {
+                write!(f, "Element count {} exceeded limit {}", count, limit)
+            }
+            SvgdxError::OutputLimitError(size, limit) => {
+                write!(f, "Output size {} bytes exceeded limit {}", size, limit)
+            }
             SvgdxError::CircularRefError(reason) => {
                 write!(f, "Circular reference error: {}", reason)
             }
             SvgdxError::DocumentError(reason) => write!(f, "Document error: {}", reason),
             SvgdxError::MissingAttribute(attr) => write!(f, "Element missing attribute '{}'", attr),
+            SvgdxError::AttrError(attr, err) => write!(f, "{} (attribute '{}')", err, attr),
             SvgdxError::MissingBoundingBox(reason) => write!(f, "Missing bounding box: {}", reason),
             SvgdxError::MessageError(reason) => write!(f, "{}", reason),
             SvgdxError::InternalLogicError(reason) => write!(f, "Internal logic error: {}", reason),
@@ -81,9 +104,12 @@ impl Error for SvgdxError {
             SvgdxError::VarLimitError(_, _, _) => None,
             SvgdxError::LoopLimitError(_, _) => None,
             SvgdxError::DepthLimitExceeded(_, _) => None,
+            SvgdxError::ElementLimitError(_, _) => None,
+            SvgdxError::OutputLimitError(_, _) => None,
             SvgdxError::CircularRefError(_) => None,
             SvgdxError::DocumentError(_) => None,
             SvgdxError::MissingAttribute(_) => None,
+            SvgdxError::AttrError(_, err) => Some(&**err),
             SvgdxError::MissingBoundingBox(_) => None,
             SvgdxError::MessageError(_) => None,
             SvgdxError::InternalLogicError(_) => None,
@@ -100,6 +126,138 @@ impl SvgdxError {
     {
         SvgdxError::OtherError(Box::new(err))
     }
+
+    /// Stable error code for this error, which may be passed to `svgdx --explain`.
+    ///
+    /// Generic errors (e.g. `MessageError`) and aggregates of other errors
+    /// (`MultiError`) do not have a code.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            SvgdxError::ReferenceError(_) => Some("E0101"),
+            SvgdxError::MissingAttribute(_) => Some("E0102"),
+            SvgdxError::MissingBoundingBox(_) => Some("E0103"),
+            SvgdxError::DocumentError(_) => Some("E0201"),
+            SvgdxError::DepthLimitExceeded(_, _) => Some("E0202"),
+            SvgdxError::CircularRefError(_) => Some("E0203"),
+            SvgdxError::LoopLimitError(_, _) => Some("E0204"),
+            SvgdxError::VarLimitError(_, _, _) => Some("E0205"),
+            SvgdxError::ElementLimitError(_, _) => Some("E0206"),
+            SvgdxError::OutputLimitError(_, _) => Some("E0207"),
+            SvgdxError::ParseError(_) => Some("E0301"),
+            SvgdxError::InvalidData(_) => Some("E0302"),
+            SvgdxError::IoError(_) => Some("E0901"),
+            SvgdxError::InternalLogicError(_) => Some("E0902"),
+            SvgdxError::AttrError(_, err) => err.code(),
+            SvgdxError::MessageError(_) | SvgdxError::MultiError(_) | SvgdxError::OtherError(_) => {
+                None
+            }
+        }
+    }
+
+    /// Annotate this error with the attribute being processed when it occurred.
+    ///
+    /// Errors which already refer to an attribute are returned unchanged.
+    pub fn in_attr(self, attr: &str) -> Self {
+        match self {
+            SvgdxError::AttrError(..) | SvgdxError::MultiError(_) => self,
+            _ => SvgdxError::AttrError(attr.to_owned(), Box::new(self)),
+        }
+    }
+
+    /// Render the error with caret-annotated snippets from the `source` document.
+    ///
+    /// Only errors associated with specific elements (i.e. `MultiError`) have
+    /// snippets; other errors are rendered as normal.
+    pub fn render_with_source(&self, source: &str) -> String {
+        self.render_with_source_file(source, None)
+    }
+
+    /// Render the error as `render_with_source()`, with error positions
+    /// given as `file:line:column` for the given source file name.
+    pub fn render_with_source_file(&self, source: &str, file: Option<&str>) -> String {
+        if !matches!(self, SvgdxError::MultiError(_)) {
+            return self.to_string();
+        }
+        let lines: Vec<&str> = source.lines().collect();
+        let mut out = String::new();
+        for span in self.spans(source) {
+            match span.code {
+                Some(code) => out.push_str(&format!("error[{}]: {}\n", code, span.message)),
+                None => out.push_str(&format!("error: {}\n", span.message)),
+            }
+            let (line, col) = (span.line, span.col);
+            match file {
+                Some(file) => out.push_str(&format!("  --> {}:{}:{}\n", file, line, col)),
+                None => out.push_str(&format!("  --> line {}, column {}\n", line, col)),
+            }
+            if let Some(text) = lines.get(line.wrapping_sub(1)) {
+                let gutter = line.to_string().len();
+                out.push_str(&format!("{:gutter$} |\n", ""));
+                out.push_str(&format!("{} | {}\n", line, text));
+                out.push_str(&format!(
+                    "{:gutter$} | {}{}\n",
+                    "",
+                    " ".repeat(col.saturating_sub(1)),
+                    "^".repeat(span.len.max(1))
+                ));
+            }
+        }
+        out
+    }
+
+    /// Source positions of the individual errors making up this error.
+    ///
+    /// Errors not associated with specific elements are positioned at the
+    /// start of the document.
+    pub fn spans(&self, source: &str) -> Vec<ErrorSpan> {
+        let SvgdxError::MultiError(errors) = self else {
+            return vec![ErrorSpan {
+                line: 1,
+                col: 1,
+                len: 0,
+                code: self.code(),
+                message: self.to_string(),
+            }];
+        };
+        let lines: Vec<&str> = source.lines().collect();
+        errors
+            .iter()
+            .sorted_by(|a, b| a.0.cmp(b.0))
+            .map(|(_, (el, err))| {
+                let (line, col, len) = Self::snippet_pos(&lines, el, err);
+                ErrorSpan {
+                    line,
+                    col,
+                    len,
+                    code: err.code(),
+                    message: err.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Position (line, column, length) to highlight for an error on `el`;
+    /// the offending attribute if known, otherwise the element name.
+    fn snippet_pos(lines: &[&str], el: &SvgElement, err: &SvgdxError) -> (usize, usize, usize) {
+        let (line, col) = (el.src_line, el.src_col.max(1));
+        if let SvgdxError::AttrError(attr, _) = err {
+            let needle = format!(" {attr}=");
+            // Attributes may be on subsequent lines to the element start
+            for (idx, text) in lines.iter().enumerate().skip(line.saturating_sub(1)) {
+                let chars: Vec<char> = text.chars().collect();
+                let skip = if idx + 1 == line { col - 1 } else { 0 };
+                let tail: String = chars.iter().skip(skip).collect();
+                if let Some(pos) = tail.find(&needle) {
+                    let pos = tail[..pos].chars().count() + skip + 1;
+                    return (idx + 1, pos + 1, attr.chars().count());
+                }
+                if tail.contains('>') {
+                    break;
+                }
+            }
+        }
+        (line, col, el.name.chars().count() + 1)
+    }
 }
 
 impl From<std::io::Error> for SvgdxError {
@@ -137,3 +295,176 @@ impl From<&str> for SvgdxError {
         SvgdxError::MessageError(err.to_string())
     }
 }
+
+/// Longer descriptions of errors, keyed by error code.
+#[cfg(feature = "cli")]
+const ERROR_EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "E0101",
+        r##"A referenced element could not be found.
+
+Elements are referenced by `#id` (or `^` for the previous element) in
+attributes such as `xy`, `start`, `end` and `href`. This error occurs if no
+element with the given id exists in the document.
+
+Erroneous example:
+
+    <rect id="a" wh="10"/>
+    <rect xy="#b|h" wh="10"/>   <!-- no element with id 'b' -->
+
+Check the id is spelt correctly and that the target element exists."##,
+    ),
+    (
+        "E0102",
+        r##"A required attribute is missing.
+
+Some elements require particular attributes; for example a `<reuse>` element
+requires an `href` attribute, and connectors require `start` and `end`.
+
+Erroneous example:
+
+    <reuse x="10"/>   <!-- missing href -->"##,
+    ),
+    (
+        "E0103",
+        r##"An element's bounding box could not be determined.
+
+Positioning relative to another element requires that element to have a
+known size and position. Elements such as `<g>` with no sized content, or
+elements with missing geometry attributes, have no bounding box.
+
+Erroneous example:
+
+    <g id="empty"/>
+    <rect xy="#empty|h" wh="10"/>"##,
+    ),
+    (
+        "E0201",
+        r##"The document structure is invalid.
+
+This covers structural problems such as nested `<specs>` elements, or
+elements which could not be extracted from the input.
+
+Erroneous example:
+
+    <specs><specs><rect id="a" wh="1"/></specs></specs>"##,
+    ),
+    (
+        "E0202",
+        r##"The recursion depth limit was exceeded.
+
+Nested `<reuse>` elements and nested groups increase the processing depth;
+processing is abandoned if this exceeds `depth-limit` (default 100), which
+typically indicates a `<reuse>` element referring (directly or indirectly)
+to itself.
+
+Erroneous example:
+
+    <g id="a"><reuse href="#a"/></g>
+
+The limit may be changed with `--depth-limit` or `<config depth-limit="..."/>`."##,
+    ),
+    (
+        "E0203",
+        r##"A circular reference was detected.
+
+This occurs when a `<use>` or `<reuse>` element refers (directly or via other
+`<use>` elements) to itself when determining its bounding box, or when a
+variable's value refers to itself during evaluation.
+
+Erroneous example:
+
+    <use id="a" href="#b"/>
+    <use id="b" href="#a"/>"##,
+    ),
+    (
+        "E0204",
+        r##"The loop iteration limit was exceeded.
+
+`<loop>` and `<for>` elements are limited to `loop-limit` (default 1000)
+iterations; this is primarily to catch infinite loops from `while` or
+`until` conditions which never change.
+
+Erroneous example:
+
+    <var i="0"/>
+    <loop while="lt($i, 10)"><rect wh="1"/></loop>   <!-- $i never changes -->
+
+The limit may be changed with `--loop-limit` or `<config loop-limit="..."/>`,
+or for an individual loop with a `limit` attribute, e.g. `<loop limit="5000" ...>`."##,
+    ),
+    (
+        "E0205",
+        r##"A variable value exceeded the length limit.
+
+Variable values are limited to `var-limit` (default 1024) characters, which
+prevents runaway growth of values built up in loops.
+
+The limit may be changed with `--var-limit` or `<config var-limit="..."/>`."##,
+    ),
+    (
+        "E0206",
+        r##"The element count limit was exceeded.
+
+The number of elements processed is limited by `--element-limit` (default
+100000). This guards against documents whose output grows exponentially,
+such as nested `<reuse>` elements each instancing the previous level
+several times.
+
+This limit cannot be changed from within a document."##,
+    ),
+    (
+        "E0207",
+        r##"The output size limit was exceeded.
+
+The size of generated output is limited by `--output-limit` (default 16MiB).
+
+This limit cannot be changed from within a document."##,
+    ),
+    (
+        "E0301",
+        r##"A value could not be parsed.
+
+This occurs when an attribute value or expression is not of the expected
+form, for example a non-numeric value where a number is required, or an
+invalid expression.
+
+Erroneous example:
+
+    <rect wh="ten"/>
+    <var x="{{1 +}}"/>"##,
+    ),
+    (
+        "E0302",
+        r##"A value was syntactically valid but not usable.
+
+For example an unknown `<config>` setting, or an argument outside the
+valid range of a function.
+
+Erroneous example:
+
+    <config no-such-setting="1"/>"##,
+    ),
+    (
+        "E0901",
+        r##"An I/O error occurred reading input or writing output.
+
+Check that input files exist and that output locations are writable."##,
+    ),
+    (
+        "E0902",
+        r##"An internal error occurred.
+
+This indicates a bug in svgdx; please report it, including the input
+document if possible."##,
+    ),
+];
+
+/// Longer description of the given error code (e.g. "E0101"), if known.
+#[cfg(feature = "cli")]
+pub fn explain(code: &str) -> Option<&'static str> {
+    ERROR_EXPLANATIONS
+        .iter()
+        .find(|(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|(_, text)| *text)
+}
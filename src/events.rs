@@ -1,5 +1,6 @@
 use crate::element::SvgElement;
 use crate::errors::{Result, SvgdxError};
+use crate::sanitize::{SanitizeAction, Sanitizer};
 use crate::types::OrderIndex;
 
 use std::io::{BufRead, BufReader, Cursor, Write};
@@ -14,6 +15,8 @@ pub struct InputEvent {
     event: Event<'static>,
     pub index: usize,
     line: usize,
+    /// Column (1-based, in characters) at which the event starts
+    col: usize,
     indent: usize,
     pub alt_idx: Option<usize>,
 }
@@ -26,6 +29,66 @@ impl InputEvent {
         }
     }
 
+    /// Copy of this (start or empty) event with an additional attribute,
+    /// retaining its position within the source document.
+    pub fn with_attr(&self, key: &str, value: &str) -> Self {
+        let mut ev = self.clone();
+        if let Event::Start(e) | Event::Empty(e) = &mut ev.event {
+            e.push_attribute((key, value));
+        }
+        ev
+    }
+
+    /// Whether this event starts an element, i.e. is a Start or Empty event.
+    pub fn is_element_start(&self) -> bool {
+        matches!(self.event, Event::Start(_) | Event::Empty(_))
+    }
+
+    /// Copy of this event at the source position of `other`, e.g. for events
+    /// derived from other files.
+    pub fn at_position_of(&self, other: &InputEvent) -> Self {
+        let mut ev = self.clone();
+        ev.line = other.line;
+        ev.col = other.col;
+        ev.indent = other.indent;
+        ev
+    }
+
+    /// Copy of this event at the source position of `el`, e.g. for events
+    /// generated from the element's content.
+    pub fn at_position_of_element(&self, el: &SvgElement) -> Self {
+        let mut ev = self.clone();
+        ev.line = el.src_line;
+        ev.col = el.src_col;
+        ev.indent = el.indent;
+        ev
+    }
+
+    /// Copy of this (start, empty or end) event with a different element name,
+    /// and without the given attributes.
+    pub fn renamed(&self, name: &str, remove_attrs: &[&str]) -> Self {
+        let mut ev = self.clone();
+        match &mut ev.event {
+            Event::Start(e) | Event::Empty(e) => {
+                // attribute values are retained as-is, without unescaping
+                let attrs: Vec<(Vec<u8>, Vec<u8>)> = e
+                    .attributes()
+                    .flatten()
+                    .filter(|a| !remove_attrs.iter().any(|r| a.key.as_ref() == r.as_bytes()))
+                    .map(|a| (a.key.as_ref().to_vec(), a.value.to_vec()))
+                    .collect();
+                let mut bs = BytesStart::new(name.to_string());
+                for (k, v) in &attrs {
+                    bs.push_attribute(Attribute::from((k.as_slice(), v.as_slice())));
+                }
+                *e = bs;
+            }
+            Event::End(e) => *e = BytesEnd::new(name.to_string()),
+            _ => (),
+        }
+        ev
+    }
+
     pub fn cdata_string(&self) -> Option<String> {
         match &self.event {
             Event::CData(c) => Some(String::from_utf8(c.to_vec()).expect("utf8")),
@@ -40,6 +103,7 @@ impl From<Event<'_>> for InputEvent {
             event: value.into_owned(),
             index: 0,
             line: 0,
+            col: 0,
             indent: 0,
             alt_idx: None,
         }
@@ -66,6 +130,7 @@ impl From<&[InputEvent]> for InputList {
                     event: v.event.clone(),
                     index: v.index,
                     line: v.line,
+                    col: v.col,
                     indent: v.indent,
                     alt_idx: v.alt_idx,
                 })
@@ -120,7 +185,10 @@ impl InputList {
     }
 
     pub fn from_reader(reader: &mut dyn BufRead) -> Result<Self> {
-        let mut reader = Reader::from_reader(reader);
+        // The entire input is read up-front so event start columns can be determined.
+        let mut source = Vec::new();
+        reader.read_to_end(&mut source)?;
+        let mut reader = Reader::from_reader(source.as_slice());
 
         let mut events = Vec::new();
         let mut buf = Vec::new();
@@ -132,6 +200,15 @@ impl InputList {
         let mut indent = 0;
         let mut index = 0;
         loop {
+            let ev_start = reader.buffer_position() as usize;
+            let line_start = source[..ev_start]
+                .iter()
+                .rposition(|&c| c == b'\n')
+                .map_or(0, |p| p + 1);
+            let col = String::from_utf8_lossy(&source[line_start..ev_start])
+                .chars()
+                .count()
+                + 1;
             let ev = reader.read_event_into(&mut buf);
             let event_lines = if let Ok(ok_ev) = ev.clone() {
                 ok_ev.as_ref().iter().filter(|&c| *c == b'\n').count()
@@ -151,6 +228,7 @@ impl InputList {
                         event: ev.expect("match").into_owned(),
                         index,
                         line: src_line,
+                        col,
                         indent,
                         alt_idx: None,
                     });
@@ -160,6 +238,7 @@ impl InputList {
                         event: ev.expect("match").into_owned(),
                         index,
                         line: src_line,
+                        col,
                         indent,
                         alt_idx: None,
                     });
@@ -174,6 +253,7 @@ impl InputList {
                         event: ev.expect("match").into_owned(),
                         index,
                         line: src_line,
+                        col,
                         indent,
                         alt_idx: start_idx,
                     });
@@ -182,12 +262,13 @@ impl InputList {
                     event: e.clone().into_owned(),
                     index,
                     line: src_line,
+                    col,
                     indent,
                     alt_idx: None,
                 }),
                 Err(e) => {
                     return Err(SvgdxError::ParseError(format!(
-                        "XML error near line {src_line}: {e:?}"
+                        "XML error near line {src_line}, column {col}: {e:?}"
                     )))
                 }
             }
@@ -205,6 +286,48 @@ impl InputList {
             events: self.events[start..end].to_vec(),
         }
     }
+
+    /// Remove potentially unsafe content from the list.
+    ///
+    /// Source lines are retained, but events are re-indexed.
+    pub fn sanitized(self) -> Self {
+        let mut sanitizer = Sanitizer::new();
+        let mut events: Vec<InputEvent> = Vec::new();
+        for mut ev in self.events {
+            let mut out_ev = OutputEvent::from(ev.clone());
+            match sanitizer.check(&mut out_ev) {
+                SanitizeAction::Drop => continue,
+                SanitizeAction::Modified => ev.event = Event::from(out_ev).into_owned(),
+                SanitizeAction::Keep => (),
+            }
+            events.push(ev);
+        }
+        Self::reindexed(events)
+    }
+
+    /// List of the given events, with indices (and those of matching
+    /// start / end events) updated to their position in the list.
+    pub fn reindexed(source: Vec<InputEvent>) -> Self {
+        let mut events: Vec<InputEvent> = Vec::new();
+        let mut event_idx_stack = Vec::new();
+        for mut ev in source {
+            let index = events.len();
+            ev.index = index;
+            ev.alt_idx = None;
+            match ev.event {
+                Event::Start(_) => event_idx_stack.push(index),
+                Event::End(_) => {
+                    if let Some(start_idx) = event_idx_stack.pop() {
+                        events[start_idx].alt_idx = Some(index);
+                        ev.alt_idx = Some(start_idx);
+                    }
+                }
+                _ => (),
+            }
+            events.push(ev);
+        }
+        Self { events }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -458,6 +581,20 @@ impl OutputList {
         Ok(())
     }
 
+    /// Remove potentially unsafe content from the list.
+    pub fn sanitized(self) -> Self {
+        let mut sanitizer = Sanitizer::new();
+        Self {
+            events: self
+                .events
+                .into_iter()
+                .filter_map(|mut ev| {
+                    (sanitizer.check(&mut ev) != SanitizeAction::Drop).then_some(ev)
+                })
+                .collect(),
+        }
+    }
+
     /// Split an `OutputList` into (up to) 3 parts: before, pivot, after.
     pub fn partition(&self, name: &str) -> (Self, Option<OutputEvent>, Self) {
         let mut before = vec![];
@@ -566,6 +703,7 @@ impl TryFrom<InputEvent> for SvgElement {
                 element.original = String::from_utf8(e.to_owned().to_vec()).expect("utf8");
                 element.set_indent(ev.indent);
                 element.set_src_line(ev.line);
+                element.set_src_col(ev.col);
                 element.set_order_index(&OrderIndex::new(ev.index));
                 Ok(element)
             }
@@ -612,6 +750,27 @@ mod tests {
         assert_eq!(el.events[2].event, Event::End(BytesEnd::new("svg")));
     }
 
+    #[test]
+    fn test_eventlist_col() {
+        let input = "<svg><rect/>\n  <circle/>\n<!-- é --><g></g></svg>";
+        let el = InputList::from_str(input).unwrap();
+        let cols: Vec<_> = el.events.iter().map(|e| (e.line, e.col)).collect();
+        assert_eq!(
+            cols,
+            vec![
+                (1, 1),  // <svg>
+                (1, 6),  // <rect/>
+                (1, 13), // text
+                (2, 3),  // <circle/>
+                (2, 12), // text
+                (3, 1),  // comment
+                (3, 11), // <g>
+                (3, 14), // </g>
+                (3, 18), // </svg>
+            ]
+        );
+    }
+
     #[test]
     fn test_outputlist_write_to() {
         let input = r#"<svg><rect width="100" height="100"/></svg>"#;
@@ -8,6 +8,7 @@ use crate::constants::{
     VAR_PREFIX,
 };
 use crate::context::{ContextView, VariableMap};
+use crate::element::SvgElement;
 use crate::errors::{Result, SvgdxError};
 use crate::functions::{eval_function, Function};
 use crate::position::parse_el_scalar;
@@ -500,11 +501,14 @@ impl<'a> EvalState<'a> {
     /// l - the x coordinate of the left of the element
     /// w - the width of the element
     /// h - the height of the element
-    fn element_ref(&self, v: &str) -> Result<ExprValue> {
+    pub fn element_ref(&self, v: &str) -> Result<ExprValue> {
         // TODO: perhaps this should be in the SvgElement impl, so it can
         // be re-used by other single-value attribute references, e.g.
         // <line x1="#abc~l" .../>
-        if let Ok((elref, Some(scalar))) = parse_el_scalar(v) {
+        if let Ok((_, None)) = parse_el_scalar(v) {
+            // A bare reference is passed as-is, e.g. to `pathlen(#abc)`
+            Ok(ExprValue::String(v.to_owned()))
+        } else if let Ok((elref, Some(scalar))) = parse_el_scalar(v) {
             if let Some(elem) = self.context.get_element(&elref) {
                 if let Some(bb) = self.context.get_element_bbox(elem)? {
                     Ok(bb.scalarspec(scalar).into())
@@ -518,6 +522,17 @@ impl<'a> EvalState<'a> {
             Err(SvgdxError::ParseError(format!("Invalid element_ref: {v}")))
         }
     }
+
+    /// The element referenced by `v`, e.g. `#abc` or `^`.
+    pub fn element(&self, v: &str) -> Result<&SvgElement> {
+        match parse_el_scalar(v) {
+            Ok((elref, None)) => self
+                .context
+                .get_element(&elref)
+                .ok_or(SvgdxError::ReferenceError(elref)),
+            _ => Err(SvgdxError::ParseError(format!("Invalid element_ref: {v}"))),
+        }
+    }
 }
 
 fn evaluate(
@@ -792,7 +807,7 @@ pub fn eval_list(value: &str, context: &impl ContextView) -> Result<Vec<String>>
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{HashMap, HashSet};
 
     use crate::context::{ElementMap, VariableMap};
     use crate::element::SvgElement;
@@ -808,6 +823,7 @@ mod tests {
     struct TestContext {
         vars: HashMap<String, String>,
         rng: RefCell<Pcg32>,
+        env: Option<HashMap<String, String>>,
     }
 
     impl TestContext {
@@ -815,6 +831,7 @@ mod tests {
             Self {
                 vars: HashMap::new(),
                 rng: RefCell::new(Pcg32::seed_from_u64(0)),
+                env: None,
             }
         }
 
@@ -825,6 +842,7 @@ mod tests {
                     .map(|(k, v)| (k.to_string(), v.to_string()))
                     .collect(),
                 rng: RefCell::new(Pcg32::seed_from_u64(0)),
+                env: None,
             }
         }
     }
@@ -837,6 +855,14 @@ mod tests {
         fn get_element_bbox(&self, el: &SvgElement) -> Result<Option<BoundingBox>> {
             el.bbox()
         }
+
+        fn get_parent_size(&self) -> Option<(f32, f32)> {
+            None
+        }
+
+        fn get_elements_matching(&self, _selector: &str) -> Vec<&SvgElement> {
+            Vec::new()
+        }
     }
 
     impl VariableMap for TestContext {
@@ -847,6 +873,21 @@ mod tests {
         fn get_rng(&self) -> &RefCell<Pcg32> {
             &self.rng
         }
+
+        fn get_seed(&self) -> u64 {
+            0
+        }
+
+        fn get_scale(&self) -> f32 {
+            2.
+        }
+
+        fn get_env(&self, name: &str) -> Result<Option<String>> {
+            self.env
+                .as_ref()
+                .map(|env| env.get(name).cloned())
+                .ok_or_else(|| SvgdxError::InvalidData("env not enabled".to_string()))
+        }
     }
 
     impl ContextView for TestContext {}
@@ -1093,6 +1134,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_func_map() {
+        let ctx = TestContext::new();
+        for (expr, expected) in [
+            ("maplin(5, 0, 10, 100, 200)", 150.),
+            ("maplin(0, 0, 10, 100, 200)", 100.),
+            ("maplin(15, 0, 10, 100, 200)", 250.),
+            ("maplin(2, 0, 10, 100, 0)", 80.),
+            ("maplin(-1, 1, -1, 0, 20)", 20.),
+            ("maplog(1, 1, 1000, 0, 300)", 0.),
+            ("maplog(10, 1, 1000, 0, 300)", 100.),
+            ("maplog(1000, 1, 1000, 0, 300)", 300.),
+            ("maplog(100, 1000, 1, 0, 300)", 100.),
+        ] {
+            assert_in_delta!(
+                evaluate_one(tokenize(expr).expect("test"), &ctx)
+                    .ok()
+                    .unwrap(),
+                expected,
+                0.0001
+            );
+        }
+        for expr in [
+            "maplin(1, 2, 3, 4)",
+            "maplin(1, 2, 2, 0, 10)",
+            "maplog(0, 1, 10, 0, 10)",
+            "maplog(5, -1, 10, 0, 10)",
+            "maplog(5, 10, 10, 0, 10)",
+        ] {
+            assert!(
+                evaluate_one(tokenize(expr).expect("test"), &ctx).is_err(),
+                "Should have failed: {expr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_func_units() {
+        // TestContext has a scale of 2mm per user unit
+        let ctx = TestContext::with_vars(&[("list", "1, 2")]);
+        for (expr, expected) in [
+            ("mm(10)", 5.),
+            ("mm(-3)", -1.5),
+            ("in(1)", 12.7),
+            ("in(0.5)", 6.35),
+            ("pt(72)", 12.7),
+            ("pt(36) + mm(2)", 7.35),
+            // membership test with more than one argument
+            ("in(2, $list)", 1.),
+            ("in(3, $list)", 0.),
+        ] {
+            assert_in_delta!(
+                evaluate_one(tokenize(expr).expect("test"), &ctx)
+                    .ok()
+                    .unwrap(),
+                expected,
+                0.0001
+            );
+        }
+    }
+
     #[test]
     fn test_func_trig() {
         let ctx = TestContext::new();
@@ -1174,6 +1276,93 @@ mod tests {
         assert_lt!(count_b, 100);
     }
 
+    #[test]
+    fn test_func_choice() {
+        let ctx = TestContext::new();
+        let mut seen = HashSet::new();
+        for _ in 0..100 {
+            seen.insert(eval_attr("{{choice('a', 'b', 'c')}}", &ctx));
+        }
+        assert_eq!(seen.len(), 3);
+        assert!(seen
+            .iter()
+            .all(|s| ["'a'", "'b'", "'c'"].contains(&s.as_str())));
+
+        // Zero weights are never selected
+        for _ in 0..100 {
+            assert_eq!(
+                eval_attr("{{wchoice(0, 1, 0, 'a', 'b', 'c')}}", &ctx),
+                "'b'"
+            );
+        }
+        let mut count_a = 0;
+        for _ in 0..1000 {
+            if eval_attr("{{wchoice(9, 1, 'a', 'b')}}", &ctx) == "'a'" {
+                count_a += 1;
+            }
+        }
+        assert_in_delta!(count_a as f32, 900., 50.);
+
+        for expr in [
+            "choice()",
+            "wchoice(1, 'a', 'b')",
+            "wchoice(0, 0, 'a', 'b')",
+            "wchoice(sqrt(-1), 1, 'a', 'b')",
+            "wchoice(3e38, 3e38, 'a', 'b')",
+        ] {
+            let tokens = tokenize(expr).unwrap();
+            assert!(expr_check(tokens, &ctx).is_err(), "'{expr}' should fail");
+        }
+    }
+
+    #[test]
+    fn test_func_randn() {
+        let ctx = TestContext::new();
+        let tokens = tokenize("randn(10, 2)").unwrap();
+        let samples = (0..1000)
+            .map(|_| evaluate_one(tokens.clone(), &ctx).unwrap())
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        let var = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+        assert_in_delta!(mean, 10., 0.2);
+        assert_in_delta!(var.sqrt(), 2., 0.2);
+
+        let tokens = tokenize("randn(1, -1)").unwrap();
+        assert!(evaluate_one(tokens, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_func_noise() {
+        let ctx = TestContext::new();
+        for expr in [
+            "noise(0.5)",
+            "noise(12.25)",
+            "noise(1.5, 2.5)",
+            "noise(-3.7, 8)",
+        ] {
+            let tokens = tokenize(expr).unwrap();
+            let a = evaluate_one(tokens.clone(), &ctx).unwrap();
+            let b = evaluate_one(tokens, &ctx).unwrap();
+            // noise is a function of its inputs, not the RNG state
+            assert_eq!(a, b, "{expr}");
+            assert!((0. ..1.).contains(&a), "{expr}: {a}");
+        }
+        // Values change smoothly between lattice points
+        let mut prev: Option<f32> = None;
+        for i in 0..=100 {
+            let expr = format!("noise({})", i as f32 / 10.);
+            let v = evaluate_one(tokenize(&expr).unwrap(), &ctx).unwrap();
+            if let Some(p) = prev {
+                assert_lt!((v - p).abs(), 0.2);
+            }
+            prev = Some(v);
+        }
+        // 1D noise is the y=0 slice of 2D noise
+        let a = evaluate_one(tokenize("noise(3.3)").unwrap(), &ctx).unwrap();
+        let b = evaluate_one(tokenize("noise(3.3, 0)").unwrap(), &ctx).unwrap();
+        assert_eq!(a, b);
+    }
+
     #[test]
     fn test_func_comparison() {
         let ctx = TestContext::new();
@@ -1230,6 +1419,13 @@ mod tests {
             ("mean(2,2.5,3,4,2.25)", Some(2.75)),
             ("mean(1,sum(2,3,4,5),6)", Some(7.)),
             ("mean()", None),
+            ("median(3)", Some(3.)),
+            ("median(5,1,3)", Some(3.)),
+            ("median(4,1,3,2)", Some(2.5)),
+            ("median()", None),
+            ("stddev(7)", Some(0.)),
+            ("stddev(2,4,4,4,5,5,7,9)", Some(2.)),
+            ("stddev()", None),
         ] {
             assert_eq!(
                 evaluate_one(tokenize(expr).expect("test"), &ctx).ok(),
@@ -1473,6 +1669,20 @@ mod tests {
             ("{{scalev(0, 123)}}", "0"),
             ("{{scalev(0.5, 123)}}", "61.5"),
             ("{{scalev(0.5, 1,2,3)}}", "0.5, 1, 1.5"),
+            ("{{minv(3, 1, 4, 1, 5)}}", "1, 1"),
+            ("{{maxv(3, 5, 4, 5, 1)}}", "5, 1"),
+            ("{{maxv(-2)}}", "-2, 0"),
+            ("{{cumsum(1, 2, 3, 4)}}", "1, 3, 6, 10"),
+            ("{{cumsum(5)}}", "5"),
+            ("{{rotatev(90, 10, 0)}}", "0, 10"),
+            ("{{rotatev(180, 10, 5, 5, 5)}}", "0, 5"),
+            ("{{rotatev(-90, 3, 4)}}", "4, -3"),
+            ("{{normalize(3, 4)}}", "0.6, 0.8"),
+            ("{{normalize(0, -2)}}", "0, -1"),
+            ("{{dot(1, 2, 3, 4)}}", "11"),
+            ("{{dot(1, 0, 0, 1)}}", "0"),
+            ("{{cross(1, 0, 0, 1)}}", "1"),
+            ("{{cross(0, 1, 1, 0)}}", "-1"),
         ] {
             assert_eq!(eval_attr(expr, &ctx), expected);
         }
@@ -1487,6 +1697,8 @@ mod tests {
             ("{{addv(1, $list, 3, 4, 5)}}", "4, 5, 7"),
             ("{{$double}}", "1, 2, 1, 2"),
             ("{{scalev(2, $double)}}", "2, 4, 2, 4"),
+            ("{{cumsum($double)}}", "1, 3, 4, 6"),
+            ("{{head(tail(maxv($double)))}}", "1"),
         ] {
             assert_eq!(eval_attr(expr, &ctx), expected);
         }
@@ -1584,6 +1796,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_env_function() {
+        let mut ctx = TestContext::new();
+        // disabled unless explicitly enabled
+        assert_eq!(eval_attr("{{env('VERSION')}}", &ctx), "env('VERSION')");
+        ctx.env = Some(HashMap::from([(
+            "VERSION".to_string(),
+            "1.2.3".to_string(),
+        )]));
+        for (expr, expected) in [
+            ("{{env('VERSION')}}", "'1.2.3'"),
+            ("{{env('VERSION', 'dev')}}", "'1.2.3'"),
+            ("{{env('HOSTNAME', 'localhost')}}", "'localhost'"),
+            ("{{env('WIDTH', 10) * 2}}", "20"),
+            ("v{{_(env('VERSION'))}}", "v1.2.3"),
+            ("{{env('HOSTNAME')}}", "env('HOSTNAME')"),
+            ("{{env(1)}}", "env(1)"),
+        ] {
+            assert_eq!(eval_attr(expr, &ctx), expected, "'{expr}' != '{expected}'");
+        }
+    }
+
     #[test]
     fn test_string_escape() {
         let ctx = TestContext::new();
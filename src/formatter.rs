@@ -0,0 +1,305 @@
+//! Canonical formatting of svgdx source documents.
+//!
+//! Formatting normalises indentation, attribute order and quoting, and merges
+//! simple coordinate pairs into compound attributes (e.g. `x` / `y` into `xy`).
+//! Comments, text content and single blank lines between elements are kept,
+//! and the transformed output of a formatted document is unchanged.
+
+use crate::errors::{Result, SvgdxError};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::ops::Range;
+
+const INDENT: &str = "  ";
+
+/// Elements whose attributes may be reordered. Attribute order is significant
+/// for others, e.g. `<var>` where later attributes may refer to earlier ones.
+const REORDER_ELEMENTS: &[&str] = &[
+    "rect", "circle", "ellipse", "line", "polyline", "polygon", "path", "text", "point", "box",
+    "g", "use", "image", "symbol",
+];
+
+/// Canonical attribute order: identity, position, size, style, then svgdx-specific.
+/// Attributes not listed here follow in their original order.
+const ATTR_ORDER: &[&str] = &[
+    "id",
+    // position
+    "x",
+    "y",
+    "xy",
+    "cx",
+    "cy",
+    "cxy",
+    "x1",
+    "y1",
+    "xy1",
+    "x2",
+    "y2",
+    "xy2",
+    "xy-loc",
+    "dx",
+    "dy",
+    "dxy",
+    "points",
+    "d",
+    "start",
+    "end",
+    // size
+    "width",
+    "height",
+    "wh",
+    "r",
+    "rx",
+    "ry",
+    "dw",
+    "dh",
+    "dwh",
+    // style
+    "class",
+    "style",
+    "transform",
+    // svgdx-specific
+    "surround",
+    "inside",
+    "pack",
+    "margin",
+    "edge-type",
+    "corner-offset",
+    "corner-radius",
+    "dir",
+    "margin-start",
+    "margin-end",
+    "text",
+    "text-loc",
+    "text-offset",
+    "text-pre",
+];
+
+/// Pairs of attributes which may be merged into a compound attribute, for the
+/// given elements.
+const COMPOUND_ATTRS: &[(&str, &str, &str, &[&str])] = &[
+    (
+        "x",
+        "y",
+        "xy",
+        &["rect", "text", "use", "image", "point", "box"],
+    ),
+    ("width", "height", "wh", &["rect", "use", "image", "box"]),
+    ("cx", "cy", "cxy", &["circle", "ellipse"]),
+    ("x1", "y1", "xy1", &["line"]),
+    ("x2", "y2", "xy2", &["line"]),
+];
+
+fn format_attrs(e: &BytesStart, merge: bool) -> Result<Vec<(String, String)>> {
+    let name = String::from_utf8(e.name().as_ref().to_vec())?;
+    let mut attrs = Vec::new();
+    for a in e.attributes().with_checks(false) {
+        let a = a.map_err(SvgdxError::from_err)?;
+        let key = String::from_utf8(a.key.as_ref().to_vec())?;
+        let value = String::from_utf8(a.value.to_vec())?;
+        attrs.push((key, value));
+    }
+    if !REORDER_ELEMENTS.contains(&name.as_str()) {
+        return Ok(attrs);
+    }
+
+    // only plain numbers are merged; e.g. `x="#a"` means something different
+    // to the first value of `xy="#a 0"`.
+    let simple = |v: &str| v.parse::<f32>().is_ok();
+    for (a1, a2, compound, elements) in COMPOUND_ATTRS {
+        if !merge || !elements.contains(&name.as_str()) || attrs.iter().any(|(k, _)| k == compound)
+        {
+            continue;
+        }
+        let pos1 = attrs.iter().position(|(k, v)| k == a1 && simple(v));
+        let pos2 = attrs.iter().position(|(k, v)| k == a2 && simple(v));
+        if let (Some(pos1), Some(pos2)) = (pos1, pos2) {
+            let value = format!("{} {}", attrs[pos1].1, attrs[pos2].1);
+            attrs[pos1] = (compound.to_string(), value);
+            attrs.remove(pos2);
+        }
+    }
+
+    // stable sort, so unknown attributes retain their original order
+    attrs.sort_by_key(|(k, _)| {
+        ATTR_ORDER
+            .iter()
+            .position(|a| a == k)
+            .unwrap_or(ATTR_ORDER.len())
+    });
+    Ok(attrs)
+}
+
+fn format_tag(e: &BytesStart, empty: bool, merge: bool) -> Result<String> {
+    let mut tag = format!("<{}", String::from_utf8(e.name().as_ref().to_vec())?);
+    for (key, value) in format_attrs(e, merge)? {
+        // values are kept as-is (i.e. still escaped), so only quoting may change
+        let quote = if value.contains('"') { '\'' } else { '"' };
+        tag.push_str(&format!(" {key}={quote}{value}{quote}"));
+    }
+    tag.push_str(if empty { "/>" } else { ">" });
+    Ok(tag)
+}
+
+/// Reformat svgdx source `input` into a canonical form.
+pub fn format_source(input: &str) -> Result<String> {
+    let mut reader = Reader::from_str(input);
+    let mut events: Vec<(Event, Range<usize>)> = Vec::new();
+    loop {
+        let start = reader.buffer_position() as usize;
+        let ev = reader.read_event().map_err(|e| {
+            let line = input[..start].matches('\n').count() + 1;
+            SvgdxError::ParseError(format!("XML error near line {line}: {e:?}"))
+        })?;
+        if let Event::Eof = ev {
+            break;
+        }
+        events.push((ev, start..reader.buffer_position() as usize));
+    }
+
+    // Index of the matching end event for each start event
+    let mut end_idx = vec![None; events.len()];
+    let mut stack = Vec::new();
+    for (idx, (ev, _)) in events.iter().enumerate() {
+        match ev {
+            Event::Start(_) => stack.push(idx),
+            Event::End(_) => {
+                if let Some(start) = stack.pop() {
+                    end_idx[start] = Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Documents with an `xmlns` root are passed through by svgdx as plain SVG,
+    // where compound attributes have no meaning.
+    let merge = !events.iter().any(|(ev, _)| match ev {
+        Event::Start(e) | Event::Empty(e) => {
+            e.name().as_ref() == b"svg" && e.try_get_attribute("xmlns").ok().flatten().is_some()
+        }
+        _ => false,
+    });
+
+    let is_ws = |ev: &Event| matches!(ev, Event::Text(t) if t.iter().all(u8::is_ascii_whitespace));
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth = 0;
+    let mut idx = 0;
+    while idx < events.len() {
+        let (ev, range) = &events[idx];
+        let indent = INDENT.repeat(depth);
+        match ev {
+            Event::Text(t) if is_ws(ev) => {
+                // keep (at most one) blank line between elements
+                let after_start =
+                    matches!(events.get(idx.wrapping_sub(1)), Some((Event::Start(_), _)));
+                let before_end = matches!(events.get(idx + 1), Some((Event::End(_), _)) | None);
+                if t.iter().filter(|&&c| c == b'\n').count() > 1
+                    && !lines.is_empty()
+                    && !after_start
+                    && !before_end
+                {
+                    lines.push(String::new());
+                }
+            }
+            Event::Start(e) => {
+                let end = end_idx[idx];
+                // only direct children are considered
+                let mut nesting = 0;
+                let mixed = end.is_some_and(|end| {
+                    events[idx + 1..end].iter().any(|(ev, _)| {
+                        match ev {
+                            Event::Start(_) => nesting += 1,
+                            Event::End(_) => nesting -= 1,
+                            _ => {}
+                        }
+                        nesting == 0 && matches!(ev, Event::Text(_) | Event::CData(_)) && !is_ws(ev)
+                    })
+                });
+                if let (true, Some(end)) = (mixed, end) {
+                    // elements with text content are kept on a single 'line',
+                    // with the content unchanged.
+                    let content = &input[range.end..events[end].1.start];
+                    let close = &input[events[end].1.clone()];
+                    lines.push(format!(
+                        "{indent}{}{content}{close}",
+                        format_tag(e, false, merge)?
+                    ));
+                    idx = end + 1;
+                    continue;
+                }
+                lines.push(format!("{indent}{}", format_tag(e, false, merge)?));
+                depth += 1;
+            }
+            Event::Empty(e) => lines.push(format!("{indent}{}", format_tag(e, true, merge)?)),
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                lines.push(format!("{}{}", INDENT.repeat(depth), &input[range.clone()]));
+            }
+            _ => {
+                // comments, declarations, stray text etc. are kept as-is
+                lines.push(format!("{indent}{}", input[range.clone()].trim()));
+            }
+        }
+        idx += 1;
+    }
+    let mut output = lines.join("\n");
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_attrs() {
+        let input = r#"<rect class="a" height="5" width="4" y="2" x="1" id="r"/>"#;
+        assert_eq!(
+            format_source(input).unwrap(),
+            "<rect id=\"r\" xy=\"1 2\" wh=\"4 5\" class=\"a\"/>\n"
+        );
+        // non-numeric values are not merged
+        let input = r##"<rect y="2" x="#a" wh="3"/>"##;
+        assert_eq!(
+            format_source(input).unwrap(),
+            "<rect x=\"#a\" y=\"2\" wh=\"3\"/>\n"
+        );
+        // plain SVG documents are not given compound attributes
+        let input = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect y="2" x="1"/></svg>"#;
+        assert!(format_source(input)
+            .unwrap()
+            .contains(r#"<rect x="1" y="2"/>"#));
+        // attribute order is significant for `var`
+        let input = r#"<var b="1" a='"$b"'/>"#;
+        assert_eq!(format_source(input).unwrap(), "<var b=\"1\" a='\"$b\"'/>\n");
+    }
+
+    #[test]
+    fn test_format_layout() {
+        let input = r#"<svg>
+    <!-- boxes -->
+       <rect   wh="1"/>
+
+
+ <g><circle r="1" cx="2" cy="3"/></g>
+<text xy="0">  some   <tspan>text</tspan></text>
+</svg>"#;
+        let expected = r#"<svg>
+  <!-- boxes -->
+  <rect wh="1"/>
+
+  <g>
+    <circle cxy="2 3" r="1"/>
+  </g>
+  <text xy="0">  some   <tspan>text</tspan></text>
+</svg>
+"#;
+        let output = format_source(input).unwrap();
+        assert_eq!(output, expected);
+        // formatting is idempotent
+        assert_eq!(format_source(&output).unwrap(), expected);
+    }
+}
@@ -1,10 +1,14 @@
+use crate::constants::{ELREF_PREVIOUS, SCALARSPEC_SEP};
 use crate::errors::{Result, SvgdxError};
 use crate::expression::{EvalState, ExprValue};
+use crate::path::{path_length, path_point_at};
 
 use itertools::Itertools;
 use rand::Rng;
 use std::str::FromStr;
 
+const MM_PER_INCH: f32 = 25.4;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Function {
     /// abs(x) - absolute value of x
@@ -43,6 +47,14 @@ pub enum Function {
     Random,
     /// randint(min, max) - generate uniform random integer in range min..max
     RandInt,
+    /// randn(mean, sd) - generate normally distributed random number
+    RandN,
+    /// noise(x[, y]) - smooth value noise in range 0..1 at the given 1D or 2D point
+    Noise,
+    /// choice(a, ...) - select one of the given values at random
+    Choice,
+    /// wchoice(w1, w2, ..., wN, a1, a2, ..., aN) - weighted random selection of a value
+    WChoice,
     /// min(a, ...) - minimum of values
     Min,
     /// max(a, ...) - maximum of values
@@ -53,10 +65,24 @@ pub enum Function {
     Product,
     /// mean(a, ...) - mean of values
     Mean,
+    /// median(a, ...) - median of values
+    Median,
+    /// stddev(a, ...) - population standard deviation of values
+    StdDev,
+    /// minv(a, ...) - (value, index) of the minimum value
+    MinV,
+    /// maxv(a, ...) - (value, index) of the maximum value
+    MaxV,
+    /// cumsum(a, ...) - running totals of values
+    CumSum,
     /// clamp(x, min, max) - return x, clamped between min and max
     Clamp,
     /// mix(start, end, amount) - linear interpolation between start and end
     Mix,
+    /// maplin(x, in_min, in_max, out_min, out_max) - map x linearly from input to output range
+    MapLin,
+    /// maplog(x, in_min, in_max, out_min, out_max) - map x logarithmically from input to output range
+    MapLog,
     /// eq(a, b) - 1 if a == b, 0 otherwise
     Equal,
     /// ne(a, b) - 1 if a != b, 0 otherwise
@@ -93,6 +119,14 @@ pub enum Function {
     Subv,
     /// scalev(s, a1, a2, ..., aN) - scale vector by s
     Scalev,
+    /// rotatev(angle, x, y[, cx, cy]) - rotate point (x, y) by angle degrees about (cx, cy)
+    Rotatev,
+    /// normalize(x, y) - unit vector in the direction of (x, y)
+    Normalize,
+    /// dot(a1, a2, ..., aN, b1, b2, ...bN) - vector dot product
+    Dot,
+    /// cross(x1, y1, x2, y2) - z component of the cross product of two 2D vectors
+    Cross,
     /// head(a, ...) - first element of list
     Head,
     /// tail(a, ...) - all but the first element of list
@@ -101,6 +135,22 @@ pub enum Function {
     Empty,
     /// count(a, ...) - number of elements in list
     Count,
+    /// pathlen(ref) - length of a referenced path or line-like element
+    PathLen,
+    /// pointat(ref, t) - point at fraction t of the length along a referenced path
+    PointAt,
+    /// count_of(sel) - number of elements processed so far matching selector sel
+    CountOf,
+    /// elref(ref[, scalar]) - scalar value of a referenced element, e.g. elref('^2', 'x2')
+    ElRef,
+    /// prev(scalar[, n]) - scalar value of the (nth) previous element
+    Prev,
+    /// mm(x) - convert x millimetres to user units
+    Mm,
+    /// in(x) - convert x inches to user units (see also `In`)
+    Inch,
+    /// pt(x) - convert x points (1/72 inch) to user units
+    Pt,
     /// in(x, a, ...) - 1 if x is in list, 0 otherwise
     In,
     /// split(sep, a) - split string a into list of substrings using sep
@@ -113,6 +163,9 @@ pub enum Function {
     Join,
     /// _(a) - return a as text
     Text,
+    /// env(name, default) - value of environment variable `name`, or `default`
+    /// if unset; only available if enabled with `allow_env`
+    Env,
 }
 
 impl FromStr for Function {
@@ -138,13 +191,24 @@ impl FromStr for Function {
             "atan" => Self::Atan,
             "random" => Self::Random,
             "randint" => Self::RandInt,
+            "randn" => Self::RandN,
+            "noise" => Self::Noise,
+            "choice" => Self::Choice,
+            "wchoice" => Self::WChoice,
             "min" => Self::Min,
             "max" => Self::Max,
             "sum" => Self::Sum,
             "product" => Self::Product,
             "mean" => Self::Mean,
+            "median" => Self::Median,
+            "stddev" => Self::StdDev,
+            "minv" => Self::MinV,
+            "maxv" => Self::MaxV,
+            "cumsum" => Self::CumSum,
             "clamp" => Self::Clamp,
             "mix" => Self::Mix,
+            "maplin" => Self::MapLin,
+            "maplog" => Self::MapLog,
             "eq" => Self::Equal,
             "ne" => Self::NotEqual,
             "lt" => Self::LessThan,
@@ -163,16 +227,28 @@ impl FromStr for Function {
             "addv" => Self::Addv,
             "subv" => Self::Subv,
             "scalev" => Self::Scalev,
+            "rotatev" => Self::Rotatev,
+            "normalize" => Self::Normalize,
+            "dot" => Self::Dot,
+            "cross" => Self::Cross,
             "head" => Self::Head,
             "tail" => Self::Tail,
             "empty" => Self::Empty,
             "count" => Self::Count,
+            "count_of" => Self::CountOf,
+            "pathlen" => Self::PathLen,
+            "pointat" => Self::PointAt,
+            "elref" => Self::ElRef,
+            "prev" => Self::Prev,
+            "mm" => Self::Mm,
+            "pt" => Self::Pt,
             "in" => Self::In,
             "split" => Self::Split,
             "splitw" => Self::Splitw,
             "trim" => Self::Trim,
             "join" => Self::Join,
             "_" => Self::Text,
+            "env" => Self::Env,
             _ => return Err(SvgdxError::ParseError(format!("Unknown function: {value}"))),
         })
     }
@@ -238,6 +314,87 @@ pub fn eval_function(
             }
             return Ok(result.into());
         }
+        Function::Rotatev => {
+            let (angle, x, y, cx, cy) = match args.number_list()?[..] {
+                [angle, x, y] => (angle, x, y, 0., 0.),
+                [angle, x, y, cx, cy] => (angle, x, y, cx, cy),
+                _ => {
+                    return Err(SvgdxError::ParseError(
+                        "rotatev() requires three or five arguments".to_string(),
+                    ))
+                }
+            };
+            // matches the sense of SVG's `rotate()` transform
+            let (sin, cos) = angle.to_radians().sin_cos();
+            let (dx, dy) = (x - cx, y - cy);
+            return Ok([cx + dx * cos - dy * sin, cy + dx * sin + dy * cos]
+                .as_slice()
+                .into());
+        }
+        Function::Normalize => {
+            let (x, y) = args.number_pair()?;
+            let len = x.hypot(y);
+            if len == 0. {
+                return Err(SvgdxError::InvalidData(
+                    "normalize(x, y) - vector must be non-zero".to_string(),
+                ));
+            }
+            return Ok([x / len, y / len].as_slice().into());
+        }
+        Function::Dot => {
+            let args = args.number_list()?;
+            if args.len() % 2 != 0 {
+                return Err(SvgdxError::ParseError(
+                    "dot() requires an even number of arguments".to_string(),
+                ));
+            }
+            let (a, b) = args.split_at(args.len() / 2);
+            a.iter().zip(b).map(|(a, b)| a * b).sum()
+        }
+        Function::Cross => {
+            let [x1, y1, x2, y2] = args.number_list()?[..] else {
+                return Err(SvgdxError::ParseError(
+                    "cross() requires exactly four arguments".to_string(),
+                ));
+            };
+            x1 * y2 - y1 * x2
+        }
+        Function::MinV | Function::MaxV => {
+            let values = args.number_list()?;
+            let found = if fun == Function::MinV {
+                // min_by returns the first of equal minimum values
+                values.iter().enumerate().min_by(|a, b| a.1.total_cmp(b.1))
+            } else {
+                // ...but max_by returns the last; reverse to keep the first
+                values
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .max_by(|a, b| a.1.total_cmp(b.1))
+            };
+            let (idx, v) = found.ok_or_else(|| {
+                SvgdxError::InvalidData(format!(
+                    "{}() requires at least one argument",
+                    if fun == Function::MinV {
+                        "minv"
+                    } else {
+                        "maxv"
+                    }
+                ))
+            })?;
+            return Ok([*v, idx as f32].as_slice().into());
+        }
+        Function::CumSum => {
+            let result: Vec<f32> = args
+                .number_list()?
+                .into_iter()
+                .scan(0., |acc, v| {
+                    *acc += v;
+                    Some(*acc)
+                })
+                .collect();
+            return Ok(result.into());
+        }
         Function::Head => {
             let args = args.flatten();
             if args.is_empty() {
@@ -260,6 +417,55 @@ pub fn eval_function(
             }
         }
         Function::Count => args.len() as f32,
+        Function::CountOf => {
+            let selector = args.one_string()?;
+            eval_state.context.get_elements_matching(&selector).len() as f32
+        }
+        Function::PathLen => path_length(eval_state.element(&args.one_string()?)?)?,
+        Function::PointAt => {
+            let (elref, t) = match args.flatten().as_slice() {
+                [ExprValue::String(elref), t] => (elref.to_owned(), t.one_number()?),
+                _ => {
+                    return Err(SvgdxError::ParseError(
+                        "pointat() requires an element reference and a number".to_string(),
+                    ))
+                }
+            };
+            let (x, y) = path_point_at(eval_state.element(&elref)?, t)?;
+            return Ok([x, y].as_slice().into());
+        }
+        Function::ElRef => {
+            let elref = match args.string_list()?.as_slice() {
+                [elref] => elref.to_owned(),
+                [elref, scalar] => format!("{elref}{SCALARSPEC_SEP}{scalar}"),
+                _ => {
+                    return Err(SvgdxError::ParseError(
+                        "elref() requires one or two string arguments".to_string(),
+                    ))
+                }
+            };
+            return eval_state.element_ref(&elref);
+        }
+        Function::Prev => {
+            let elref = match args.flatten().as_slice() {
+                [ExprValue::String(scalar)] => format!("{ELREF_PREVIOUS}{SCALARSPEC_SEP}{scalar}"),
+                [ExprValue::String(scalar), n] => {
+                    let n = n.one_number()?;
+                    if n < 1. {
+                        return Err(SvgdxError::InvalidData(
+                            "prev() count must be at least 1".to_string(),
+                        ));
+                    }
+                    format!("{ELREF_PREVIOUS}{}{SCALARSPEC_SEP}{scalar}", n as usize)
+                }
+                _ => {
+                    return Err(SvgdxError::ParseError(
+                        "prev() requires a scalar name and optional count".to_string(),
+                    ))
+                }
+            };
+            return eval_state.element_ref(&elref);
+        }
         Function::Select => {
             let args = args.flatten();
             if args.len() < 2 {
@@ -277,6 +483,14 @@ pub fn eval_function(
                 ));
             }
         }
+        Function::Mm => args.one_number()? / eval_state.context.get_scale(),
+        Function::Inch => args.one_number()? * MM_PER_INCH / eval_state.context.get_scale(),
+        Function::Pt => args.one_number()? * MM_PER_INCH / 72. / eval_state.context.get_scale(),
+        // in() with a single argument is a unit conversion rather than a
+        // (trivially false) membership test
+        Function::In if args.flatten().len() == 1 => {
+            return eval_function(Function::Inch, args, eval_state);
+        }
         Function::In => {
             let args = args.flatten();
             if args.is_empty() {
@@ -340,6 +554,84 @@ pub fn eval_function(
                 .borrow_mut()
                 .random_range(min..=max) as f32
         }
+        Function::RandN => {
+            let (mean, sd) = args.number_pair()?;
+            if sd < 0. {
+                return Err(SvgdxError::InvalidData(
+                    "randn(mean, sd) - `sd` must be >= 0".to_string(),
+                ));
+            }
+            // Box-Muller transform; `1 - u1` avoids ln(0).
+            let mut rng = eval_state.context.get_rng().borrow_mut();
+            let u1 = 1. - rng.random::<f32>();
+            let u2 = rng.random::<f32>();
+            let z = (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos();
+            mean + sd * z
+        }
+        Function::Noise => {
+            let (x, y) = match &args.number_list()?[..] {
+                [x] => (*x, 0.),
+                [x, y] => (*x, *y),
+                _ => {
+                    return Err(SvgdxError::ParseError(
+                        "noise() requires one or two arguments".to_string(),
+                    ))
+                }
+            };
+            value_noise(eval_state.context.get_seed(), x, y)
+        }
+        Function::Choice => {
+            let args = args.flatten();
+            if args.is_empty() {
+                return Err(SvgdxError::ParseError(
+                    "choice() requires at least one argument".to_string(),
+                ));
+            }
+            let n = eval_state
+                .context
+                .get_rng()
+                .borrow_mut()
+                .random_range(0..args.len());
+            return Ok(args[n].to_owned());
+        }
+        Function::WChoice => {
+            let args = args.flatten();
+            if args.is_empty() || args.len() % 2 == 1 {
+                return Err(SvgdxError::ParseError(
+                    "wchoice() requires a non-zero even number of arguments".to_string(),
+                ));
+            }
+            let (weights, values) = args.split_at(args.len() / 2);
+            let weights = weights
+                .iter()
+                .map(|w| w.one_number())
+                .collect::<Result<Vec<_>>>()?;
+            if weights.iter().any(|w| !w.is_finite() || *w < 0.) {
+                return Err(SvgdxError::InvalidData(
+                    "wchoice() weights must be finite and not negative".to_string(),
+                ));
+            }
+            let total: f32 = weights.iter().sum();
+            if !total.is_finite() || total <= 0. {
+                return Err(SvgdxError::InvalidData(
+                    "wchoice() requires at least one positive weight".to_string(),
+                ));
+            }
+            let mut target = eval_state.context.get_rng().borrow_mut().random::<f32>() * total;
+            for (w, v) in weights.iter().zip(values) {
+                if target < *w {
+                    return Ok(v.to_owned());
+                }
+                target -= w;
+            }
+            // Rounding may leave a remainder; pick the last positively weighted value
+            let idx = weights.iter().rposition(|w| *w > 0.).ok_or_else(|| {
+                SvgdxError::InvalidData(
+                    "wchoice() requires at least one positive weight".to_string(),
+                )
+            })?;
+            return Ok(values[idx].to_owned());
+        }
         Function::Max => args
             .number_list()?
             .into_iter()
@@ -365,6 +657,32 @@ pub fn eval_function(
             let n = args.len() as f32;
             args.number_list()?.into_iter().sum::<f32>() / n
         }
+        Function::Median => {
+            let mut values = args.number_list()?;
+            if values.is_empty() {
+                return Err(SvgdxError::ParseError(
+                    "median() requires at least one argument".to_string(),
+                ));
+            }
+            values.sort_by(|a, b| a.total_cmp(b));
+            let mid = values.len() / 2;
+            if values.len() % 2 == 0 {
+                (values[mid - 1] + values[mid]) / 2.
+            } else {
+                values[mid]
+            }
+        }
+        Function::StdDev => {
+            let values = args.number_list()?;
+            if values.is_empty() {
+                return Err(SvgdxError::ParseError(
+                    "stddev() requires at least one argument".to_string(),
+                ));
+            }
+            let n = values.len() as f32;
+            let mean = values.iter().sum::<f32>() / n;
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n).sqrt()
+        }
         Function::Clamp => {
             let (x, min, max) = args.number_triple()?;
             if min > max {
@@ -378,6 +696,35 @@ pub fn eval_function(
             let (a, b, c) = args.number_triple()?;
             a * (1. - c) + b * c
         }
+        Function::MapLin | Function::MapLog => {
+            let name = if fun == Function::MapLin {
+                "maplin"
+            } else {
+                "maplog"
+            };
+            let [x, in_min, in_max, out_min, out_max] = args.number_list()?[..] else {
+                return Err(SvgdxError::ParseError(format!(
+                    "{name}(x, in_min, in_max, out_min, out_max) requires five arguments"
+                )));
+            };
+            let (x, in_min, in_max) = if fun == Function::MapLog {
+                if x <= 0. || in_min <= 0. || in_max <= 0. {
+                    return Err(SvgdxError::InvalidData(
+                        "maplog() - `x`, `in_min` and `in_max` must be > 0".to_string(),
+                    ));
+                }
+                (x.ln(), in_min.ln(), in_max.ln())
+            } else {
+                (x, in_min, in_max)
+            };
+            if in_min == in_max {
+                return Err(SvgdxError::InvalidData(format!(
+                    "{name}() - `in_min` and `in_max` must differ"
+                )));
+            }
+            let t = (x - in_min) / (in_max - in_min);
+            out_min * (1. - t) + out_max * t
+        }
         Function::Equal => {
             let (a, b) = args.pair()?;
             if a == b {
@@ -491,6 +838,24 @@ pub fn eval_function(
             let a = args.one_string()?;
             return Ok(ExprValue::String(a.trim().to_owned()));
         }
+        Function::Env => {
+            let args = args.flatten();
+            let (name, default) = match args.as_slice() {
+                [ExprValue::String(name) | ExprValue::Text(name)] => (name, None),
+                [ExprValue::String(name) | ExprValue::Text(name), default] => (name, Some(default)),
+                _ => {
+                    return Err(SvgdxError::ParseError(
+                        "env() requires a variable name and optional default".to_string(),
+                    ))
+                }
+            };
+            return match eval_state.context.get_env(name)? {
+                Some(value) => Ok(ExprValue::String(value)),
+                None => default.cloned().ok_or_else(|| {
+                    SvgdxError::InvalidData(format!("environment variable '{name}' is not set"))
+                }),
+            };
+        }
         Function::Join => {
             if let Some((sep, rest)) = args.string_list()?.split_first() {
                 let combined = rest.iter().join(sep);
@@ -508,3 +873,36 @@ pub fn eval_function(
     };
     Ok(e.into())
 }
+
+/// Pseudo-random value in range 0..1 for the given lattice point.
+fn lattice_value(seed: u64, x: i64, y: i64) -> f32 {
+    // splitmix64 finalizer over a combination of the inputs
+    let mut z = seed
+        ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    // top 24 bits give a value exactly representable in f32
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// 2D value noise: smoothly interpolated lattice values.
+///
+/// Deterministic for a given seed; integer coordinates return the raw
+/// lattice value, with smoothstep interpolation between them.
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (ix, iy) = (x0 as i64, y0 as i64);
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+    let (sx, sy) = (smooth(x - x0), smooth(y - y0));
+
+    let v00 = lattice_value(seed, ix, iy);
+    let v10 = lattice_value(seed, ix + 1, iy);
+    let v01 = lattice_value(seed, ix, iy + 1);
+    let v11 = lattice_value(seed, ix + 1, iy + 1);
+
+    let top = v00 + (v10 - v00) * sx;
+    let bottom = v01 + (v11 - v01) * sx;
+    top + (bottom - top) * sy
+}
@@ -0,0 +1,392 @@
+//! Canonical text representation of the geometry of an SVG document.
+//!
+//! Each element with an `id`, and each line or polyline (e.g. connectors),
+//! is given as a line of text in document order, e.g.
+//!
+//! ```text
+//! rect #a 0 0 10 10
+//! line #c 10 5 10 0 : 10,5 20,5
+//! ```
+//!
+//! being the element name, id (if any), bounding box `x y width height`, and
+//! (for lines, polylines and polygons) the vertex list. Styling and other
+//! attributes are ignored, so this is suitable for detecting layout changes.
+//!
+//! The same geometry is used to derive a low-detail 'skeleton' SVG, suitable
+//! as a placeholder while the full image loads.
+
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::outline::json_str;
+use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::types::{attr_split, fstr, strp};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Elements whose content is not rendered directly.
+pub(crate) const NON_RENDERED: &[&str] = &[
+    "defs", "symbol", "marker", "pattern", "clipPath", "mask", "style", "script", "metadata",
+];
+
+/// Elements represented by their bounding box in skeleton output.
+const SKELETON_SHAPES: &[&str] = &[
+    "rect",
+    "circle",
+    "ellipse",
+    "polygon",
+    "path",
+    "image",
+    "foreignObject",
+];
+
+/// Fill and stroke colours of skeleton boxes; the stroke distinguishes nested boxes.
+const SKELETON_FILL: &str = "#e0e0e0";
+const SKELETON_STROKE: &str = "#f8f8f8";
+
+fn geometry_line(el: &SvgElement, bbox: Option<BoundingBox>) -> Option<String> {
+    let vertices = el.vertices();
+    let id = el.get_attr("id");
+    if id.is_none() && vertices.is_none() {
+        return None;
+    }
+    let mut line = el.name.clone();
+    if let Some(id) = id {
+        line.push_str(&format!(" #{id}"));
+    }
+    if let Some(bb) = bbox {
+        let values = [bb.x1, bb.y1, bb.width(), bb.height()];
+        for v in values {
+            line.push_str(&format!(" {}", fstr(v)));
+        }
+    }
+    if let Some(vertices) = vertices {
+        line.push_str(" :");
+        for (x, y) in vertices {
+            line.push_str(&format!(" {},{}", fstr(x), fstr(y)));
+        }
+    }
+    Some(line)
+}
+
+/// An open (non-empty) element, whose geometry may depend on its content.
+struct OpenElement {
+    /// Element whose line is emitted once its content is known, with index
+    /// into the output lines
+    pending: Option<(SvgElement, usize)>,
+    /// Combined bbox of content
+    content: BoundingBoxBuilder,
+}
+
+/// Rendered elements of the given SVG document in document order, together
+/// with their bounding boxes. Group bounding boxes are derived from content.
+pub(crate) fn rendered_elements(svg: &str) -> Result<Vec<(SvgElement, Option<BoundingBox>)>> {
+    let mut reader = Reader::from_str(svg);
+    let mut elements: Vec<Option<(SvgElement, Option<BoundingBox>)>> = Vec::new();
+    let mut stack: Vec<OpenElement> = Vec::new();
+    // Depth within the stack at which non-rendered content starts
+    let mut hidden_depth: Option<usize> = None;
+    loop {
+        let ev = reader
+            .read_event()
+            .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?;
+        match &ev {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) => {
+                let el = SvgElement::try_from(e)?;
+                let is_start = matches!(ev, Event::Start(_));
+                if hidden_depth.is_none() && NON_RENDERED.contains(&el.name.as_str()) {
+                    hidden_depth = Some(stack.len());
+                }
+                let mut pending = None;
+                // the root element is not included
+                if hidden_depth.is_none() && !stack.is_empty() {
+                    let bbox = el.bbox()?;
+                    if let Some(bb) = bbox {
+                        for open in &mut stack {
+                            open.content.extend(bb);
+                        }
+                    }
+                    if is_start && bbox.is_none() {
+                        // e.g. a group; bbox is determined from its content
+                        elements.push(None);
+                        pending = Some((el, elements.len() - 1));
+                    } else {
+                        elements.push(Some((el, bbox)));
+                    }
+                }
+                if is_start {
+                    stack.push(OpenElement {
+                        pending,
+                        content: BoundingBoxBuilder::new(),
+                    });
+                } else if hidden_depth == Some(stack.len()) {
+                    hidden_depth = None;
+                }
+            }
+            Event::End(_) => {
+                if let Some(OpenElement {
+                    pending: Some((el, idx)),
+                    content,
+                }) = stack.pop()
+                {
+                    elements[idx] = Some((el, content.build()));
+                }
+                if hidden_depth == Some(stack.len()) {
+                    hidden_depth = None;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(elements.into_iter().flatten().collect())
+}
+
+/// Extract the geometry of elements in the given SVG document, typically
+/// the output of `transform_str()`, in a canonical text form.
+pub fn svg_geometry(svg: &str) -> Result<String> {
+    let lines: Vec<_> = rendered_elements(svg)?
+        .iter()
+        .filter_map(|(el, bbox)| geometry_line(el, *bbox))
+        .collect();
+    let mut output = lines.join("\n");
+    output.push('\n');
+    Ok(output)
+}
+
+/// Bounding boxes of elements with an `id` in the given SVG document, as a
+/// JSON object mapping each id to its `x`, `y`, `width` and `height`, e.g.
+///
+/// ```json
+/// {"a": {"x": 0, "y": 0, "width": 10, "height": 10}}
+/// ```
+///
+/// Elements without a bounding box are omitted; if an id is repeated, the
+/// first element with that id is used.
+pub fn svg_element_bboxes(svg: &str) -> Result<String> {
+    let mut seen = std::collections::HashSet::new();
+    let entries: Vec<_> = rendered_elements(svg)?
+        .into_iter()
+        .filter_map(|(el, bbox)| Some((el.get_attr("id")?, bbox?)))
+        .filter(|(id, _)| seen.insert(id.clone()))
+        .map(|(id, bb)| {
+            format!(
+                "{}: {{\"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}}}",
+                json_str(&id),
+                fstr(bb.x1),
+                fstr(bb.y1),
+                fstr(bb.width()),
+                fstr(bb.height())
+            )
+        })
+        .collect();
+    Ok(format!("{{{}}}", entries.join(", ")))
+}
+
+/// The root element of the given document, and its content as source text.
+pub(crate) fn split_root(svg: &str) -> Result<(SvgElement, &str)> {
+    let mut reader = Reader::from_str(svg);
+    let mut root = None;
+    let mut depth = 0;
+    let mut content_start = 0;
+    loop {
+        let pos = reader.buffer_position() as usize;
+        let ev = reader
+            .read_event()
+            .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?;
+        match ev {
+            Event::Eof => break,
+            Event::Start(e) => {
+                if depth == 0 {
+                    root = Some(SvgElement::try_from(&e)?);
+                    content_start = reader.buffer_position() as usize;
+                }
+                depth += 1;
+            }
+            Event::Empty(e) if depth == 0 => {
+                let root = SvgElement::try_from(&e)?;
+                return Ok((root, ""));
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    let root = root.ok_or_else(|| SvgdxError::from("No root element found"))?;
+                    return Ok((root, &svg[content_start..pos]));
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(SvgdxError::from("No root element found"))
+}
+
+/// The `viewBox` of a root element as a bounding box.
+pub(crate) fn view_box(root: &SvgElement) -> Result<Option<BoundingBox>> {
+    let Some(vb) = root.get_attr("viewBox") else {
+        return Ok(None);
+    };
+    let parts = attr_split(&vb)
+        .map(|v| strp(&v))
+        .collect::<Result<Vec<_>>>()?;
+    if let [x, y, w, h] = parts[..] {
+        Ok(Some(BoundingBox::new(x, y, x + w, y + h)))
+    } else {
+        Err(SvgdxError::InvalidData(format!("Invalid viewBox '{vb}'")).in_attr("viewBox"))
+    }
+}
+
+/// Start tag of a root `<svg>` element with the given `viewBox`, with
+/// `width` and `height` retaining the scale (and units) of `root`.
+pub(crate) fn root_start(root: &SvgElement, view: &BoundingBox) -> String {
+    let (scale, unit) = root
+        .get_attr("width")
+        .and_then(|w| {
+            let num_end = w.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(w.len());
+            let width = strp(&w[..num_end]).ok()?;
+            let vb_width = view_box(root).ok()??.width();
+            (vb_width > 0.).then(|| (width / vb_width, w[num_end..].to_string()))
+        })
+        .unwrap_or((1., String::new()));
+    format!(
+        r#"<svg version="1.1" xmlns="http://www.w3.org/2000/svg" width="{}{unit}" height="{}{unit}" viewBox="{} {} {} {}">"#,
+        fstr(view.width() * scale),
+        fstr(view.height() * scale),
+        fstr(view.x1),
+        fstr(view.y1),
+        fstr(view.width()),
+        fstr(view.height())
+    )
+}
+
+/// Derive a low-detail placeholder SVG from an SVG document, typically the
+/// output of `transform_str()`.
+///
+/// Each shape in the document is replaced by a plain box covering its bounding
+/// box, and text, lines and styling are omitted. The root element retains its
+/// size and `viewBox` so the placeholder has the same dimensions as the original.
+pub fn svg_skeleton(svg: &str) -> Result<String> {
+    let mut reader = Reader::from_str(svg);
+    let mut output = String::new();
+    let mut depth = 0;
+    let mut hidden_depth: Option<usize> = None;
+    loop {
+        let ev = reader
+            .read_event()
+            .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?;
+        match &ev {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) => {
+                let el = SvgElement::try_from(e)?;
+                if depth == 0 {
+                    output.push_str("<svg");
+                    for attr in ["version", "xmlns", "width", "height", "viewBox"] {
+                        if let Some(value) = el.get_attr(attr) {
+                            output.push_str(&format!(" {attr}=\"{value}\""));
+                        }
+                    }
+                    output.push_str(&format!(
+                        " fill=\"{SKELETON_FILL}\" stroke=\"{SKELETON_STROKE}\">\n"
+                    ));
+                } else if hidden_depth.is_none() && NON_RENDERED.contains(&el.name.as_str()) {
+                    hidden_depth = Some(depth);
+                } else if hidden_depth.is_none() && SKELETON_SHAPES.contains(&el.name.as_str()) {
+                    if let Some(bb) = el.bbox()? {
+                        output.push_str(&format!(
+                            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                            fstr(bb.x1),
+                            fstr(bb.y1),
+                            fstr(bb.width()),
+                            fstr(bb.height())
+                        ));
+                    }
+                }
+                if matches!(ev, Event::Start(_)) {
+                    depth += 1;
+                } else if hidden_depth == Some(depth) {
+                    hidden_depth = None;
+                }
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if hidden_depth == Some(depth) {
+                    hidden_depth = None;
+                }
+            }
+            _ => {}
+        }
+    }
+    if output.is_empty() {
+        return Err(SvgdxError::from("No root element found"));
+    }
+    output.push_str("</svg>\n");
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_geometry() {
+        let svg = r##"<svg>
+<defs><rect id="hidden" width="5" height="5"/></defs>
+<rect id="a" width="10" height="10" style="fill: red"/>
+<g id="grp"><circle cx="20" cy="20" r="2"/><rect x="30" y="25" width="5" height="5"/></g>
+<line x1="10" y1="5" x2="20" y2="5" class="d-arrow"/>
+<text x="3" y="3">no id</text>
+</svg>"##;
+        assert_eq!(
+            svg_geometry(svg).unwrap(),
+            "rect #a 0 0 10 10\ng #grp 18 18 17 12\nline 10 5 10 0 : 10,5 20,5\n"
+        );
+    }
+
+    #[test]
+    fn test_svg_element_bboxes() {
+        let svg = r##"<svg>
+<defs><rect id="hidden" width="5" height="5"/></defs>
+<rect id="a" x="1.5" width="10" height="10"/>
+<g id="grp"><circle id="c" cx="20" cy="20" r="2"/></g>
+<rect id="a" width="1" height="1"/>
+<line x1="10" y1="5" x2="20" y2="5"/>
+</svg>"##;
+        assert_eq!(
+            svg_element_bboxes(svg).unwrap(),
+            concat!(
+                r#"{"a": {"x": 1.5, "y": 0, "width": 10, "height": 10}, "#,
+                r#""grp": {"x": 18, "y": 18, "width": 4, "height": 4}, "#,
+                r#""c": {"x": 18, "y": 18, "width": 4, "height": 4}}"#
+            )
+        );
+        assert_eq!(svg_element_bboxes("<svg/>").unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_svg_skeleton() {
+        let svg = r##"<svg version="1.1" width="20mm" height="10mm" viewBox="0 0 20 10">
+<style>rect { fill: red; }</style>
+<defs><marker id="m"><path d="M 0 0 h 1"/></marker></defs>
+<rect width="10" height="10" style="fill: red"/>
+<g><circle cx="15" cy="5" r="2"/></g>
+<line x1="10" y1="5" x2="13" y2="5"/>
+<text x="5" y="5">label</text>
+</svg>"##;
+        assert_eq!(
+            svg_skeleton(svg).unwrap(),
+            r##"<svg version="1.1" width="20mm" height="10mm" viewBox="0 0 20 10" fill="#e0e0e0" stroke="#f8f8f8">
+<rect x="0" y="0" width="10" height="10"/>
+<rect x="13" y="3" width="4" height="4"/>
+</svg>
+"##
+        );
+    }
+
+    #[test]
+    fn test_split_root() {
+        let (root, content) =
+            split_root(r#"<?xml version="1.0"?><svg a="1"><g><rect/></g></svg>"#).unwrap();
+        assert_eq!(root.name, "svg");
+        assert_eq!(content, "<g><rect/></g>");
+        assert!(split_root("").is_err());
+    }
+}
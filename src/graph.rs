@@ -0,0 +1,700 @@
+//! Automatic layout of graph nodes.
+//!
+//! A `<graph layout="...">` element is a group whose direct children with
+//! an `id` and no explicit position are treated as graph nodes, and are
+//! positioned according to the edges given by connectors (or `<connect>`
+//! tables) within the graph. Elements which already have a position are left
+//! unchanged and do not take part in the layout.
+//!
+//! As a shorthand, `<node>` children are converted to `<rect>` elements, and
+//! `<edge from="..." to="...">` children are converted to connectors.
+//!
+//! Supported layouts:
+//!
+//! - `force` - a simple force-directed layout, with repulsion between all
+//!   nodes and attraction between connected nodes. The initial placement is
+//!   derived from the document `seed`, so output is deterministic.
+//! - `layered` - nodes of a directed graph are assigned to ranks so edges
+//!   point in the graph's `direction`, with nodes ordered within each rank
+//!   to reduce edge crossings.
+//! - `circular` - nodes are placed evenly around a circle in document order,
+//!   with edges as chords of the circle. With `bundle="f"`, chords are
+//!   replaced by curves pulled towards the centre of the circle by the
+//!   fraction `f`.
+
+use crate::connect_el::{cell_elref, ConnectElement};
+use crate::context::{TransformerContext, VariableMap};
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{tagify_events, InputEvent, InputList, OutputEvent, OutputList, Tag};
+use crate::expression::eval_attr;
+use crate::layout::POSITION_ATTRS;
+use crate::position::BoundingBox;
+use crate::transform::{group_events, EventGen};
+use crate::types::{extract_elref, fstr, strp, ElRef};
+
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+/// Attributes of `<graph>` which are not present on the generated `<g>`.
+const GRAPH_ATTRS: &[&str] = &["layout", "spacing", "iterations", "direction", "bundle"];
+
+const DEFAULT_SPACING: f32 = 40.;
+const DEFAULT_ITERATIONS: u32 = 100;
+/// Force layout work (iterations x nodes^2) allowed per unit of `element_limit`;
+/// this bounds processing time since `iterations` is set by the document.
+const FORCE_WORK_PER_ELEMENT: u64 = 100;
+/// Size of `<node>` elements without an explicit size
+const DEFAULT_NODE_WH: &str = "20 10";
+/// Number of (down and up) sweeps used to order nodes within ranks
+const ORDER_SWEEPS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Layout {
+    Force,
+    Layered,
+    Circular,
+}
+
+impl FromStr for Layout {
+    type Err = SvgdxError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "force" => Ok(Self::Force),
+            "layered" => Ok(Self::Layered),
+            "circular" => Ok(Self::Circular),
+            _ => Err(SvgdxError::InvalidData(format!(
+                "graph: unknown layout '{value}'"
+            ))),
+        }
+    }
+}
+
+/// Direction in which edges point in a layered layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum Direction {
+    /// Top to bottom
+    #[default]
+    TB,
+    /// Bottom to top
+    BT,
+    /// Left to right
+    LR,
+    /// Right to left
+    RL,
+}
+
+impl FromStr for Direction {
+    type Err = SvgdxError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "TB" => Ok(Self::TB),
+            "BT" => Ok(Self::BT),
+            "LR" => Ok(Self::LR),
+            "RL" => Ok(Self::RL),
+            _ => Err(SvgdxError::InvalidData(format!(
+                "direction must be one of TB, BT, LR, RL, not '{value}'"
+            ))),
+        }
+    }
+}
+
+/// Translate positions so the minimum x and y values are zero, rounding
+/// to whole units.
+fn normalise(pos: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let min_x = pos.iter().map(|p| p.0).fold(f32::INFINITY, f32::min);
+    let min_y = pos.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+    pos.iter()
+        .map(|(x, y)| ((x - min_x).round(), (y - min_y).round()))
+        .collect()
+}
+
+/// Replace the events from index `start` to `end` (inclusive) with `new_events`.
+fn splice_events(
+    events: &mut Vec<InputEvent>,
+    start: usize,
+    end: usize,
+    new_events: Vec<InputEvent>,
+) {
+    let from = events.iter().position(|ev| ev.index == start);
+    let to = events.iter().position(|ev| ev.index == end);
+    if let (Some(from), Some(to)) = (from, to) {
+        events.splice(from..=to, new_events);
+    }
+}
+
+/// Side of an element (as a `LocSpec` suffix) facing in the direction `(dx, dy)`.
+fn facing_loc(dx: f32, dy: f32) -> &'static str {
+    match (dx.abs() > dy.abs(), dx > 0., dy > 0.) {
+        (true, true, _) => "r",
+        (true, false, _) => "l",
+        (false, _, true) => "b",
+        (false, _, false) => "t",
+    }
+}
+
+/// Replace `<connect>` children with the connectors they generate, and
+/// connectors between nodes with quadratic curves whose control point is
+/// pulled towards `centre` by the fraction `bundle`.
+fn bundle_edges(
+    content: InputList,
+    nodes: &[(String, (f32, f32))],
+    centre: (f32, f32),
+    bundle: f32,
+    context: &TransformerContext,
+) -> Result<InputList> {
+    let mut events = content.events.clone();
+    let node_pos = |elref: &str| match extract_elref(elref).ok()? {
+        (ElRef::Id(id), _) => nodes.iter().find(|(n, _)| *n == id).map(|(n, p)| (n, *p)),
+        _ => None,
+    };
+    for el in tagify_events(content)?.iter().filter_map(Tag::get_element) {
+        let Some((start, end)) = el.event_range else {
+            continue;
+        };
+        let connectors = if el.name == "connect" {
+            ConnectElement(el).connectors(context)?
+        } else if el.is_connector() {
+            vec![el]
+        } else {
+            continue;
+        };
+        let mut new_events = Vec::new();
+        for (idx, conn) in connectors.into_iter().enumerate() {
+            if idx > 0 {
+                new_events.push(InputEvent::from(OutputEvent::Text("\n".to_string())));
+            }
+            let (conn_start, conn_end) = (conn.get_attr("start"), conn.get_attr("end"));
+            let ev = InputEvent::from(OutputEvent::Empty(conn));
+            let (Some((a, pa)), Some((b, pb))) = (
+                conn_start.as_deref().and_then(node_pos),
+                conn_end.as_deref().and_then(node_pos),
+            ) else {
+                new_events.push(ev);
+                continue;
+            };
+            let mid = ((pa.0 + pb.0) / 2., (pa.1 + pb.1) / 2.);
+            let ctrl = (
+                mid.0 + (centre.0 - mid.0) * bundle,
+                mid.1 + (centre.1 - mid.1) * bundle,
+            );
+            let d = format!(
+                "M #{a}@{} Q {} {} #{b}@{}",
+                facing_loc(ctrl.0 - pa.0, ctrl.1 - pa.1),
+                fstr(ctrl.0),
+                fstr(ctrl.1),
+                facing_loc(ctrl.0 - pb.0, ctrl.1 - pb.1)
+            );
+            new_events.push(
+                ev.renamed("path", &["start", "end", "points"])
+                    .with_attr("d", &d),
+            );
+        }
+        splice_events(&mut events, start, end, new_events);
+    }
+    Ok(InputList { events })
+}
+
+/// Convert `<node>` and `<edge>` children of a graph to standard elements.
+pub(crate) fn expand_shorthand(content: InputList) -> Result<InputList> {
+    let mut events = content.events.clone();
+    let mut replace = |index: usize, f: &dyn Fn(&InputEvent) -> InputEvent| {
+        if let Some(ev) = events.iter_mut().find(|ev| ev.index == index) {
+            *ev = f(ev);
+        }
+    };
+    for el in tagify_events(content)?.iter().filter_map(Tag::get_element) {
+        let Some((start, end)) = el.event_range else {
+            continue;
+        };
+        match el.name.as_str() {
+            "node" => {
+                replace(start, &|ev| {
+                    let ev = ev.renamed("rect", &[]);
+                    if ["wh", "width", "height"].iter().any(|a| el.has_attr(a)) {
+                        ev
+                    } else {
+                        ev.with_attr("wh", DEFAULT_NODE_WH)
+                    }
+                });
+                replace(end, &|ev| ev.renamed("rect", &[]));
+            }
+            "edge" => {
+                let (Some(from), Some(to)) = (el.get_attr("from"), el.get_attr("to")) else {
+                    return Err(SvgdxError::MissingAttribute("from/to".to_string()));
+                };
+                let name = el.get_attr("connector").unwrap_or("line".to_string());
+                if !matches!(name.as_str(), "line" | "polyline") {
+                    return Err(SvgdxError::InvalidData(format!(
+                        "edge: connector must be 'line' or 'polyline', not '{name}'"
+                    )));
+                }
+                replace(start, &|ev| {
+                    ev.renamed(&name, &["from", "to", "connector"])
+                        .with_attr("start", &cell_elref(&from))
+                        .with_attr("end", &cell_elref(&to))
+                });
+                replace(end, &|ev| ev.renamed(&name, &[]));
+            }
+            _ => (),
+        }
+    }
+    Ok(InputList { events })
+}
+
+/// A node in the graph, identified by its index in the graph's content.
+#[derive(Clone, Debug)]
+pub(crate) struct Node {
+    pub id: String,
+    /// Index of the node's start event within the document
+    pub index: usize,
+}
+
+/// Nodes and edges derived from the content of a `<graph>` element.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct GraphModel {
+    pub nodes: Vec<Node>,
+    /// Edges as pairs of indices into `nodes`
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl GraphModel {
+    pub fn from_events(content: &InputList, context: &TransformerContext) -> Result<Self> {
+        let mut nodes = Vec::new();
+        let mut edge_ids = Vec::new();
+        for el in tagify_events(content.clone())?
+            .iter()
+            .filter_map(Tag::get_element)
+        {
+            if el.name == "connect" {
+                for conn in ConnectElement(el).connectors(context)? {
+                    edge_ids.push((conn.get_attr("start"), conn.get_attr("end")));
+                }
+            } else if el.is_connector() {
+                edge_ids.push((el.get_attr("start"), el.get_attr("end")));
+            } else if let (Some(id), Some((start, _))) = (el.get_attr("id"), el.event_range) {
+                if !POSITION_ATTRS.iter().any(|a| el.has_attr(a)) {
+                    nodes.push(Node { id, index: start });
+                }
+            }
+        }
+
+        let node_idx = |elref: Option<String>| {
+            let elref = elref?;
+            match extract_elref(&elref).ok()? {
+                (ElRef::Id(id), _) => nodes.iter().position(|n| n.id == id),
+                _ => None,
+            }
+        };
+        let edges = edge_ids
+            .into_iter()
+            .filter_map(|(start, end)| Some((node_idx(start)?, node_idx(end)?)))
+            .filter(|(a, b)| a != b)
+            .collect();
+        Ok(Self { nodes, edges })
+    }
+
+    /// Fruchterman-Reingold style force-directed layout, returning the centre
+    /// position of each node.
+    fn force_layout(&self, spacing: f32, iterations: u32, seed: u64) -> Vec<(f32, f32)> {
+        let n = self.nodes.len();
+        let k = spacing;
+        let extent = k * (n as f32).sqrt();
+        let mut rng = Pcg32::seed_from_u64(seed);
+        let mut pos: Vec<(f32, f32)> = (0..n)
+            .map(|_| (rng.random_range(0. ..extent), rng.random_range(0. ..extent)))
+            .collect();
+
+        for iter in 0..iterations {
+            // temperature limits the movement of each node, reducing linearly
+            let temp = extent / 10. * (1. - iter as f32 / iterations as f32);
+            let mut disp = vec![(0., 0.); n];
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    let (dx, dy) = (pos[i].0 - pos[j].0, pos[i].1 - pos[j].1);
+                    let dist = dx.hypot(dy).max(0.01);
+                    let force = k * k / dist;
+                    let (fx, fy) = (dx / dist * force, dy / dist * force);
+                    disp[i] = (disp[i].0 + fx, disp[i].1 + fy);
+                    disp[j] = (disp[j].0 - fx, disp[j].1 - fy);
+                }
+            }
+            for &(a, b) in &self.edges {
+                let (dx, dy) = (pos[a].0 - pos[b].0, pos[a].1 - pos[b].1);
+                let dist = dx.hypot(dy).max(0.01);
+                let force = dist * dist / k;
+                let (fx, fy) = (dx / dist * force, dy / dist * force);
+                disp[a] = (disp[a].0 - fx, disp[a].1 - fy);
+                disp[b] = (disp[b].0 + fx, disp[b].1 + fy);
+            }
+            for (i, (dx, dy)) in disp.into_iter().enumerate() {
+                let len = dx.hypot(dy);
+                if len > 0. {
+                    let step = len.min(temp);
+                    pos[i] = (pos[i].0 + dx / len * step, pos[i].1 + dy / len * step);
+                }
+            }
+        }
+
+        normalise(&pos)
+    }
+
+    /// Rank of each node such that (other than edges which would form a
+    /// cycle) every edge goes from a lower to a higher rank.
+    fn ranks(&self) -> Vec<usize> {
+        let n = self.nodes.len();
+        // Remove cycles by ignoring edges back to a node on the DFS stack
+        let mut state = vec![0u8; n]; // 0: unvisited, 1: on stack, 2: done
+        let mut dag = Vec::new();
+        for root in 0..n {
+            if state[root] != 0 {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            state[root] = 1;
+            while let Some((node, edge_idx)) = stack.pop() {
+                let next = self.edges[edge_idx..]
+                    .iter()
+                    .position(|&(a, _)| a == node)
+                    .map(|p| p + edge_idx);
+                if let Some(e) = next {
+                    stack.push((node, e + 1));
+                    let b = self.edges[e].1;
+                    match state[b] {
+                        0 => {
+                            dag.push(self.edges[e]);
+                            state[b] = 1;
+                            stack.push((b, 0));
+                        }
+                        2 => dag.push(self.edges[e]),
+                        _ => (),
+                    }
+                } else {
+                    state[node] = 2;
+                }
+            }
+        }
+
+        // Longest path ranking, visiting nodes in topological order
+        let mut rank = vec![0; n];
+        let mut in_degree = vec![0; n];
+        for &(_, b) in &dag {
+            in_degree[b] += 1;
+        }
+        let mut ready: Vec<usize> = (0..n).filter(|&i| in_degree[i] == 0).rev().collect();
+        while let Some(a) = ready.pop() {
+            for &(_, b) in dag.iter().filter(|(src, _)| *src == a) {
+                rank[b] = rank[b].max(rank[a] + 1);
+                in_degree[b] -= 1;
+                if in_degree[b] == 0 {
+                    ready.push(b);
+                }
+            }
+        }
+        rank
+    }
+
+    /// Nodes in each rank, ordered to reduce edge crossings using the
+    /// barycenter heuristic.
+    fn ordered_layers(&self, rank: &[usize]) -> Vec<Vec<usize>> {
+        let rank_count = rank.iter().max().map_or(0, |r| r + 1);
+        let mut layers = vec![Vec::new(); rank_count];
+        for (node, &r) in rank.iter().enumerate() {
+            layers[r].push(node);
+        }
+
+        let mut order = vec![0.; self.nodes.len()];
+        let update_order = |layers: &[Vec<usize>], order: &mut [f32]| {
+            for layer in layers {
+                for (i, &node) in layer.iter().enumerate() {
+                    order[node] = i as f32;
+                }
+            }
+        };
+        update_order(&layers, &mut order);
+        for sweep in 0..(ORDER_SWEEPS * 2) {
+            let down = sweep % 2 == 0;
+            let ranks: Vec<usize> = if down {
+                (1..rank_count).collect()
+            } else {
+                (0..rank_count.saturating_sub(1)).rev().collect()
+            };
+            for r in ranks {
+                let fixed = |other: usize| {
+                    if down {
+                        rank[other] < r
+                    } else {
+                        rank[other] > r
+                    }
+                };
+                let barycenter = |node: usize| {
+                    let neighbours: Vec<f32> = self
+                        .edges
+                        .iter()
+                        .filter_map(|&(a, b)| match (a == node, b == node) {
+                            (true, _) if fixed(b) => Some(order[b]),
+                            (_, true) if fixed(a) => Some(order[a]),
+                            _ => None,
+                        })
+                        .collect();
+                    if neighbours.is_empty() {
+                        order[node]
+                    } else {
+                        neighbours.iter().sum::<f32>() / neighbours.len() as f32
+                    }
+                };
+                let mut keyed: Vec<(f32, usize)> =
+                    layers[r].iter().map(|&n| (barycenter(n), n)).collect();
+                keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+                layers[r] = keyed.into_iter().map(|(_, n)| n).collect();
+                update_order(&layers[r..=r], &mut order);
+            }
+        }
+        layers
+    }
+
+    /// Layered layout of a directed graph, returning the centre position of
+    /// each node.
+    fn layered_layout(&self, spacing: f32, direction: Direction) -> Vec<(f32, f32)> {
+        let rank = self.ranks();
+        let mut pos = vec![(0., 0.); self.nodes.len()];
+        for (r, layer) in self.ordered_layers(&rank).iter().enumerate() {
+            let offset = (layer.len() as f32 - 1.) / 2.;
+            for (i, &node) in layer.iter().enumerate() {
+                let along = r as f32 * spacing;
+                let across = (i as f32 - offset) * spacing;
+                pos[node] = match direction {
+                    Direction::TB => (across, along),
+                    Direction::BT => (across, -along),
+                    Direction::LR => (along, across),
+                    Direction::RL => (-along, across),
+                };
+            }
+        }
+        normalise(&pos)
+    }
+
+    /// Circular layout with nodes evenly spaced clockwise from the top, returning
+    /// the centre position of each node and the centre of the circle.
+    fn circular_layout(&self, spacing: f32) -> (Vec<(f32, f32)>, (f32, f32)) {
+        let n = self.nodes.len();
+        // radius such that adjacent nodes are `spacing` apart
+        let radius = if n > 1 {
+            spacing / (2. * (PI / n as f32).sin())
+        } else {
+            0.
+        };
+        let mut pos: Vec<(f32, f32)> = (0..n)
+            .map(|i| {
+                let angle = 2. * PI * i as f32 / n as f32 - PI / 2.;
+                (radius * angle.cos(), radius * angle.sin())
+            })
+            .collect();
+        // the centre never affects normalisation, so is included to be translated
+        pos.push((0., 0.));
+        let mut pos = normalise(&pos);
+        let centre = pos.pop().unwrap_or_default();
+        (pos, centre)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphElement(pub SvgElement);
+
+impl EventGen for GraphElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        graph_events(&self.0, self.0.inner_events(context), context)
+    }
+}
+
+/// Lay out the given graph content according to the attributes of `element`,
+/// generating a group containing the result.
+pub(crate) fn graph_events(
+    element: &SvgElement,
+    content: Option<InputList>,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    let layout: Layout = element
+        .get_attr("layout")
+        .ok_or_else(|| SvgdxError::MissingAttribute("layout".to_string()))
+        .and_then(|v| eval_attr(&v, context).parse())?;
+    let spacing = element
+        .get_attr("spacing")
+        .map(|v| strp(&eval_attr(&v, context)))
+        .transpose()?
+        .unwrap_or(DEFAULT_SPACING);
+    let iterations = element
+        .get_attr("iterations")
+        .map(|v| {
+            eval_attr(&v, context)
+                .parse::<u32>()
+                .map_err(|_| SvgdxError::InvalidData(format!("graph: invalid iterations '{v}'")))
+        })
+        .transpose()?
+        .unwrap_or(DEFAULT_ITERATIONS);
+    let direction: Direction = element
+        .get_attr("direction")
+        .map(|v| eval_attr(&v, context).parse())
+        .transpose()?
+        .unwrap_or_default();
+    let bundle = element
+        .get_attr("bundle")
+        .map(|v| strp(&eval_attr(&v, context)))
+        .transpose()?;
+
+    let content = if let Some(content) = content {
+        let content = expand_shorthand(content)?;
+        let model = GraphModel::from_events(&content, context)?;
+        let (positions, centre) = match layout {
+            Layout::Force => {
+                let n = model.nodes.len() as u64;
+                let work = iterations as u64 * n * n;
+                let limit = context.config.element_limit as u64 * FORCE_WORK_PER_ELEMENT;
+                if work > limit {
+                    return Err(SvgdxError::InvalidData(format!(
+                        "graph: force layout of {n} nodes with {iterations} iterations exceeds limit"
+                    ))
+                    .in_attr("iterations"));
+                }
+                (
+                    model.force_layout(spacing, iterations, context.get_seed()),
+                    None,
+                )
+            }
+            Layout::Layered => (model.layered_layout(spacing, direction), None),
+            Layout::Circular => {
+                let (positions, centre) = model.circular_layout(spacing);
+                (positions, Some(centre))
+            }
+        };
+        let mut events = content.events;
+        for (node, &(x, y)) in model.nodes.iter().zip(&positions) {
+            let cxy = format!("{} {}", fstr(x), fstr(y));
+            if let Some(ev) = events.iter_mut().find(|ev| ev.index == node.index) {
+                *ev = ev.with_attr("cxy", &cxy);
+            }
+        }
+        let content = InputList { events };
+        if let (Some(centre), Some(bundle)) = (centre, bundle) {
+            let nodes: Vec<_> = model
+                .nodes
+                .into_iter()
+                .map(|n| n.id)
+                .zip(positions)
+                .collect();
+            Some(bundle_edges(content, &nodes, centre, bundle, context)?)
+        } else {
+            Some(content)
+        }
+    } else {
+        None
+    };
+
+    let mut group = element.clone();
+    group.name = "g".to_string();
+    for attr in GRAPH_ATTRS {
+        group.pop_attr(attr);
+    }
+    group_events(&group, content, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(n: usize, edges: &[(usize, usize)]) -> GraphModel {
+        GraphModel {
+            nodes: (0..n)
+                .map(|i| Node {
+                    id: format!("n{i}"),
+                    index: i,
+                })
+                .collect(),
+            edges: edges.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_force_layout() {
+        // a path of three nodes
+        let gm = model(3, &[(0, 1), (1, 2)]);
+        let pos = gm.force_layout(40., 100, 0);
+        assert_eq!(pos, gm.force_layout(40., 100, 0));
+        assert!(pos.iter().all(|&(x, y)| x >= 0. && y >= 0.));
+        let dist = |a: usize, b: usize| (pos[a].0 - pos[b].0).hypot(pos[a].1 - pos[b].1);
+        // connected nodes are closer than unconnected ones
+        assert!(dist(0, 1) < dist(0, 2));
+        assert!(dist(1, 2) < dist(0, 2));
+    }
+
+    #[test]
+    fn test_layered_ranks() {
+        // diamond with an extra long edge
+        let gm = model(4, &[(0, 1), (0, 2), (1, 3), (2, 3), (0, 3)]);
+        assert_eq!(gm.ranks(), [0, 1, 1, 2]);
+        // cycles are broken
+        let gm = model(3, &[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(gm.ranks(), [0, 1, 2]);
+        // disconnected nodes are at rank 0
+        let gm = model(3, &[(1, 2)]);
+        assert_eq!(gm.ranks(), [0, 0, 1]);
+    }
+
+    #[test]
+    fn test_layered_order() {
+        // edges 0->3 and 1->2 cross unless rank 1 is reordered
+        let gm = model(4, &[(0, 3), (1, 2)]);
+        let rank = gm.ranks();
+        assert_eq!(gm.ordered_layers(&rank), [vec![0, 1], vec![3, 2]]);
+    }
+
+    #[test]
+    fn test_layered_layout() {
+        let gm = model(3, &[(0, 1), (0, 2)]);
+        assert_eq!(
+            gm.layered_layout(10., Direction::TB),
+            [(5., 0.), (0., 10.), (10., 10.)]
+        );
+        assert_eq!(
+            gm.layered_layout(10., Direction::BT),
+            [(5., 10.), (0., 0.), (10., 0.)]
+        );
+        assert_eq!(
+            gm.layered_layout(10., Direction::LR),
+            [(0., 5.), (10., 0.), (10., 10.)]
+        );
+        assert_eq!(
+            gm.layered_layout(10., Direction::RL),
+            [(10., 5.), (0., 0.), (0., 10.)]
+        );
+    }
+
+    #[test]
+    fn test_circular_layout() {
+        let gm = model(4, &[]);
+        let (pos, centre) = gm.circular_layout(10.);
+        // square of side 10, with first node at the top
+        assert_eq!(pos, [(7., 0.), (14., 7.), (7., 14.), (0., 7.)]);
+        assert_eq!(centre, (7., 7.));
+
+        let (pos, centre) = model(1, &[]).circular_layout(10.);
+        assert_eq!(pos, [(0., 0.)]);
+        assert_eq!(centre, (0., 0.));
+    }
+
+    #[test]
+    fn test_facing_loc() {
+        assert_eq!(facing_loc(1., 0.5), "r");
+        assert_eq!(facing_loc(-1., 0.5), "l");
+        assert_eq!(facing_loc(0.5, 1.), "b");
+        assert_eq!(facing_loc(0.5, -1.), "t");
+    }
+}
@@ -0,0 +1,90 @@
+//! Wrapping of SVG output as an HTML fragment, for embedding in web pages.
+
+use crate::errors::{Result, SvgdxError};
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Class of the `<figure>` element wrapping the SVG in an HTML fragment.
+pub const HTML_FIGURE_CLASS: &str = "svgdx-figure";
+
+/// Convert an SVG document, typically the output of `transform_str()`, into an
+/// HTML fragment: a `<figure>` element containing the inline SVG, with a
+/// `<figcaption>` from the document's `<title>` if present.
+///
+/// Any content prior to the root `<svg>` element (e.g. XML declarations or
+/// processing instructions) is omitted, as it is not valid within HTML.
+pub fn svg_html_fragment(svg: &str) -> Result<String> {
+    let mut reader = Reader::from_str(svg);
+    let mut svg_start = None;
+    let mut title = None;
+    let mut depth = 0;
+    loop {
+        let start = reader.buffer_position() as usize;
+        let ev = reader
+            .read_event()
+            .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?;
+        match ev {
+            Event::Eof => break,
+            Event::Start(e) => {
+                if depth == 0 && svg_start.is_none() {
+                    svg_start = Some(start);
+                } else if depth == 1 && title.is_none() && e.name().as_ref() == b"title" {
+                    // title content is kept as-is (escaped), which is also valid HTML
+                    let span = reader
+                        .read_to_end(e.name())
+                        .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?;
+                    title = Some(
+                        svg[span.start as usize..span.end as usize]
+                            .trim()
+                            .to_owned(),
+                    );
+                    continue;
+                }
+                depth += 1;
+            }
+            Event::Empty(_) if depth == 0 && svg_start.is_none() => {
+                svg_start = Some(start);
+            }
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    let svg_start = svg_start.ok_or_else(|| SvgdxError::from("No root element found"))?;
+
+    let mut html = format!("<figure class=\"{HTML_FIGURE_CLASS}\">\n");
+    html.push_str(svg[svg_start..].trim_end());
+    html.push('\n');
+    if let Some(title) = title.filter(|t| !t.is_empty()) {
+        html.push_str(&format!("<figcaption>{title}</figcaption>\n"));
+    }
+    html.push_str("</figure>\n");
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_fragment() {
+        let svg = r#"<?xml-stylesheet type="text/css" href="a.css"?>
+<svg><title> A &amp; B </title><rect width="1" height="1"><title>rect</title></rect></svg>
+"#;
+        assert_eq!(
+            svg_html_fragment(svg).unwrap(),
+            r#"<figure class="svgdx-figure">
+<svg><title> A &amp; B </title><rect width="1" height="1"><title>rect</title></rect></svg>
+<figcaption>A &amp; B</figcaption>
+</figure>
+"#
+        );
+
+        // No caption without a document title
+        let svg = r#"<svg><rect width="1" height="1"><title>rect</title></rect></svg>"#;
+        assert_eq!(
+            svg_html_fragment(svg).unwrap(),
+            format!("<figure class=\"svgdx-figure\">\n{svg}\n</figure>\n")
+        );
+    }
+}
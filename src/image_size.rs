@@ -0,0 +1,147 @@
+//! Intrinsic size of images referenced by `<image>` elements.
+//!
+//! Sizes are read from the headers of PNG, GIF and JPEG images, and from the
+//! root element of SVG images. Images may be given as `data:` URIs, or as
+//! files within `TransformConfig::import_dir`.
+
+use crate::datauri::data_uri_content;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::geometry::view_box;
+use crate::import::import_path;
+use crate::types::strp;
+
+use std::fs::File;
+use std::io::Read;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Maximum number of bytes read from an image file to determine its size;
+/// JPEG metadata segments may precede the frame header.
+const MAX_HEADER_LEN: u64 = 1 << 20;
+
+fn be_u16(data: &[u8], idx: usize) -> Option<f32> {
+    Some(u16::from_be_bytes(data.get(idx..idx + 2)?.try_into().ok()?) as f32)
+}
+
+fn png_size(data: &[u8]) -> Option<(f32, f32)> {
+    let w = u32::from_be_bytes(data.get(16..20)?.try_into().ok()?);
+    let h = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?);
+    Some((w as f32, h as f32))
+}
+
+fn gif_size(data: &[u8]) -> Option<(f32, f32)> {
+    let w = u16::from_le_bytes(data.get(6..8)?.try_into().ok()?);
+    let h = u16::from_le_bytes(data.get(8..10)?.try_into().ok()?);
+    Some((w as f32, h as f32))
+}
+
+fn jpeg_size(data: &[u8]) -> Option<(f32, f32)> {
+    // Walk the segments following the SOI marker to find a start-of-frame
+    let mut idx = 2;
+    while *data.get(idx)? == 0xff {
+        let marker = *data.get(idx + 1)?;
+        if matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc) {
+            return Some((be_u16(data, idx + 7)?, be_u16(data, idx + 5)?));
+        }
+        idx += 2 + be_u16(data, idx + 2)? as usize;
+    }
+    None
+}
+
+fn svg_size(data: &[u8]) -> Option<(f32, f32)> {
+    // Only the root element is needed, and the data may have been truncated
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) => std::str::from_utf8(&data[..e.valid_up_to()]).ok()?,
+    };
+    let mut reader = Reader::from_str(text);
+    let root = loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) | Event::Empty(e) => break SvgElement::try_from(&e).ok()?,
+            Event::Eof => return None,
+            _ => {}
+        }
+    };
+    let length = |attr: &str| root.get_attr(attr).and_then(|v| strp(&v).ok());
+    match (length("width"), length("height")) {
+        (Some(w), Some(h)) => Some((w, h)),
+        _ => view_box(&root)
+            .ok()
+            .flatten()
+            .map(|vb| (vb.width(), vb.height())),
+    }
+}
+
+/// Width and height of the given image data, if in a supported format.
+fn data_size(data: &[u8]) -> Option<(f32, f32)> {
+    if data.starts_with(b"\x89PNG") {
+        png_size(data)
+    } else if data.starts_with(b"GIF8") {
+        gif_size(data)
+    } else if data.starts_with(b"\xff\xd8") {
+        jpeg_size(data)
+    } else {
+        svg_size(data)
+    }
+}
+
+/// Intrinsic width and height of the image at `href`.
+///
+/// Files are resolved within `image_dir`, and are rejected if that is not
+/// set. Only the start of the file is read.
+pub(crate) fn image_size(href: &str, image_dir: Option<&str>) -> Result<(f32, f32)> {
+    let data = if href.starts_with("data:") {
+        data_uri_content(href)
+            .ok_or_else(|| SvgdxError::InvalidData("Invalid data URI".to_string()))?
+    } else {
+        let dir = image_dir.ok_or_else(|| {
+            SvgdxError::InvalidData(format!("reading image '{href}' is not enabled"))
+        })?;
+        let mut data = Vec::new();
+        File::open(import_path(href, dir)?)
+            .and_then(|f| f.take(MAX_HEADER_LEN).read_to_end(&mut data))
+            .map_err(|e| SvgdxError::InvalidData(format!("could not read image '{href}': {e}")))?;
+        data
+    };
+    data_size(&data)
+        .filter(|&(w, h)| w > 0. && h > 0.)
+        .ok_or_else(|| SvgdxError::InvalidData(format!("Could not determine size of '{href}'")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_size() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend([0, 0, 1, 0, 0, 0, 0, 0x80]);
+        assert_eq!(data_size(&png), Some((256., 128.)));
+
+        let gif = b"GIF89a\x20\x00\x10\x00";
+        assert_eq!(data_size(gif), Some((32., 16.)));
+
+        // SOI, APP0 (length 4), SOF0 with height 0x10, width 0x30
+        let jpeg = b"\xff\xd8\xff\xe0\x00\x04\x00\x00\xff\xc0\x00\x11\x08\x00\x10\x00\x30";
+        assert_eq!(data_size(jpeg), Some((48., 16.)));
+
+        assert_eq!(
+            data_size(br#"<svg width="40" height="10"/>"#),
+            Some((40., 10.))
+        );
+        assert_eq!(
+            data_size(br#"<svg width="100%" viewBox="0 0 3 4"><rect/></svg>"#),
+            Some((3., 4.))
+        );
+        assert_eq!(data_size(b"nonsense"), None);
+    }
+
+    #[test]
+    fn test_image_size() {
+        let uri = r#"data:image/svg+xml,%3Csvg%20viewBox=%220%200%2020%2010%22/%3E"#;
+        assert_eq!(image_size(uri, None).unwrap(), (20., 10.));
+        assert!(image_size("a.png", None).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Import of plain SVG files into a document.
+//!
+//! An `<import src="art.svg"/>` element is replaced by a `<g>` containing the
+//! content of the given file's root element, so its elements are processed
+//! (and registered by `id`) as if written inline. This allows annotations and
+//! connectors to reference shapes from externally produced artwork, with the
+//! import typically placed first so it forms a background layer.
+//!
+//! Other attributes of the `<import>` element (e.g. `id`, `transform` or
+//! `opacity`) are retained on the generated group. Imported content is
+//! positioned in user units; any `viewBox` of the imported file is ignored.
+//!
+//! Imports are first looked up by name in `TransformConfig::documents`, then
+//! resolved relative to `TransformConfig::import_dir`; they are rejected if
+//! neither provides the given `src`, or if the resolved path is outside the
+//! import directory.
+
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{InputEvent, InputList, OutputEvent};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Class added to groups generated from `<import>` elements
+const IMPORT_CLASS: &str = "d-import";
+
+/// Events of the content of the root element of the given document.
+fn root_content(imported: InputList) -> Vec<InputEvent> {
+    let mut content = Vec::new();
+    let mut depth = 0;
+    for ev in imported {
+        match OutputEvent::from(ev.clone()) {
+            OutputEvent::Start(_) => {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            }
+            OutputEvent::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        if depth > 0 {
+            content.push(ev);
+        }
+    }
+    content
+}
+
+fn is_import(ev: &InputEvent) -> bool {
+    match OutputEvent::from(ev.clone()) {
+        OutputEvent::Empty(el) => el.name == "import",
+        _ => false,
+    }
+}
+
+/// The `src` of each `<import>` element in the input, relative to the
+/// import directory.
+#[cfg(feature = "cli")]
+pub(crate) fn import_sources(input: &InputList) -> Vec<String> {
+    input
+        .iter()
+        .filter(|ev| is_import(ev))
+        .filter_map(|ev| SvgElement::try_from(ev.clone()).ok()?.get_attr("src"))
+        .collect()
+}
+
+/// Location of the file `src` within `import_dir`.
+///
+/// Paths are canonicalized so neither absolute paths, `..` components nor
+/// symlinks can be used to read files outside the import directory, and
+/// only regular files are allowed.
+pub(crate) fn import_path(src: &str, import_dir: &str) -> Result<PathBuf> {
+    let dir = Path::new(import_dir)
+        .canonicalize()
+        .map_err(SvgdxError::from_err)?;
+    let path = dir
+        .join(src)
+        .canonicalize()
+        .map_err(|e| SvgdxError::InvalidData(format!("could not read '{src}': {e}")))?;
+    if !path.starts_with(&dir) {
+        return Err(SvgdxError::InvalidData(format!(
+            "'{src}' is outside the import directory"
+        )));
+    }
+    if !path.is_file() {
+        return Err(SvgdxError::InvalidData(format!("'{src}' is not a file")));
+    }
+    Ok(path)
+}
+
+/// Source of the document referenced by an `<import>` element.
+fn import_source(
+    src: &str,
+    import_dir: Option<&str>,
+    documents: Option<&HashMap<String, String>>,
+) -> Result<String> {
+    if let Some(doc) = documents.and_then(|docs| docs.get(src)) {
+        return Ok(doc.clone());
+    }
+    let dir = import_dir.ok_or_else(|| {
+        let msg = if documents.is_some() {
+            format!("no document named '{src}'")
+        } else {
+            format!("import of '{src}' is not enabled")
+        };
+        SvgdxError::InvalidData(msg).in_attr("src")
+    })?;
+    let path = import_path(src, dir).map_err(|e| e.in_attr("src"))?;
+    std::fs::read_to_string(path).map_err(|e| {
+        SvgdxError::InvalidData(format!("could not import '{src}': {e}")).in_attr("src")
+    })
+}
+
+/// Replace any `<import>` elements in the input with the content of the
+/// referenced documents.
+pub(crate) fn expand_imports(
+    input: InputList,
+    import_dir: Option<&str>,
+    documents: Option<&HashMap<String, String>>,
+) -> Result<InputList> {
+    if !input.iter().any(is_import) {
+        return Ok(input);
+    }
+    let mut events = Vec::new();
+    for ev in input {
+        if !is_import(&ev) {
+            events.push(ev);
+            continue;
+        }
+        let mut group = SvgElement::try_from(ev.clone())?;
+        let src = group
+            .pop_attr("src")
+            .ok_or_else(|| SvgdxError::MissingAttribute("src".to_string()))?;
+        let source = import_source(&src, import_dir, documents)?;
+        let imported: InputList = source.parse()?;
+
+        group.name = "g".to_string();
+        group.add_class(IMPORT_CLASS);
+        events.push(InputEvent::from(OutputEvent::Start(group)).at_position_of(&ev));
+        for imported_ev in root_content(imported) {
+            events.push(imported_ev.at_position_of(&ev));
+        }
+        events.push(InputEvent::from(OutputEvent::End("g".to_string())).at_position_of(&ev));
+    }
+    Ok(InputList::reindexed(events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_content() {
+        let doc: InputList = r#"<?xml version="1.0"?>
+<!-- artwork -->
+<svg xmlns="http://www.w3.org/2000/svg"><g><rect id="a"/></g><circle/></svg>"#
+            .parse()
+            .unwrap();
+        let content = root_content(doc);
+        let names: Vec<_> = content
+            .into_iter()
+            .map(|ev| match OutputEvent::from(ev) {
+                OutputEvent::Start(el) | OutputEvent::Empty(el) => el.name,
+                OutputEvent::End(name) => format!("/{name}"),
+                _ => "?".to_string(),
+            })
+            .collect();
+        assert_eq!(names, ["g", "rect", "/g", "circle"]);
+    }
+
+    #[test]
+    fn test_import_disabled() {
+        let input: InputList = r#"<svg><import src="a.svg"/></svg>"#.parse().unwrap();
+        assert!(expand_imports(input, None, None).is_err());
+        let input: InputList = r#"<svg><rect/></svg>"#.parse().unwrap();
+        assert_eq!(expand_imports(input.clone(), None, None).unwrap(), input);
+    }
+
+    #[test]
+    fn test_import_documents() {
+        let docs = HashMap::from([(
+            "lib.svg".to_string(),
+            r#"<svg><rect id="a"/></svg>"#.to_string(),
+        )]);
+        let input: InputList = r#"<svg><import src="lib.svg"/></svg>"#.parse().unwrap();
+        let expanded = expand_imports(input, None, Some(&docs)).unwrap();
+        let names: Vec<_> = expanded
+            .into_iter()
+            .filter_map(|ev| match OutputEvent::from(ev) {
+                OutputEvent::Start(el) | OutputEvent::Empty(el) => Some(el.name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, ["svg", "g", "rect"]);
+
+        let input: InputList = r#"<svg><import src="other.svg"/></svg>"#.parse().unwrap();
+        assert!(expand_imports(input, None, Some(&docs)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_import_sources() {
+        let input: InputList = r#"<svg><import src="a.svg"/><rect/><import src="b/c.svg"/></svg>"#
+            .parse()
+            .unwrap();
+        assert_eq!(import_sources(&input), ["a.svg", "b/c.svg"]);
+    }
+}
@@ -0,0 +1,138 @@
+//! Rich output of rendered diagrams for Jupyter-style notebooks.
+//!
+//! Notebook kernels display values as a 'MIME bundle' - a map from MIME type
+//! to a representation of the value - leaving the frontend to choose the best
+//! representation it supports. A [`Diagram`] provides this for svgdx output:
+//!
+//! ```
+//! let diagram = svgdx::Diagram::new(r#"<svg><rect wh="20 10"/></svg>"#).unwrap();
+//! let bundle = diagram.mime_bundle_json();
+//! assert!(bundle.starts_with(r#"{"image/svg+xml": "<svg"#));
+//! ```
+//!
+//! With the `evcxr` feature, `Diagram` also has the `evcxr_display()` method
+//! used by the [evcxr](https://github.com/evcxr/evcxr) Rust kernel, so a
+//! diagram evaluated as the last expression in a cell is rendered inline.
+
+use crate::errors::Result;
+use crate::outline::json_str;
+use crate::{transform_str, TransformConfig};
+
+use std::fmt::{self, Display};
+
+/// The result of rendering an svgdx document, for display in a notebook.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagram {
+    svg: String,
+}
+
+impl Diagram {
+    /// Render `input` with the default `TransformConfig`.
+    pub fn new<T: Into<String>>(input: T) -> Result<Self> {
+        Self::with_config(input, &TransformConfig::default())
+    }
+
+    /// Render `input` with the given `TransformConfig`.
+    pub fn with_config<T: Into<String>>(input: T, config: &TransformConfig) -> Result<Self> {
+        transform_str(input, config).map(|svg| Self { svg })
+    }
+
+    /// The rendered SVG document.
+    pub fn svg(&self) -> &str {
+        &self.svg
+    }
+
+    /// Representations of the diagram as `(mime_type, data)` pairs, in order
+    /// of preference.
+    pub fn mime_bundle(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("image/svg+xml", self.svg.clone()),
+            ("text/html", self.svg.clone()),
+            ("text/plain", self.summary()),
+        ]
+    }
+
+    /// The MIME bundle as a JSON object, e.g. as the `data` of a Jupyter
+    /// `display_data` or `execute_result` message.
+    pub fn mime_bundle_json(&self) -> String {
+        let entries: Vec<_> = self
+            .mime_bundle()
+            .iter()
+            .map(|(mime, data)| format!("{}: {}", json_str(mime), json_str(data)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    /// Display the diagram in an evcxr notebook.
+    ///
+    /// evcxr calls this automatically for the value of a cell; it may also be
+    /// called explicitly to display diagrams from within a cell.
+    #[cfg(feature = "evcxr")]
+    pub fn evcxr_display(&self) {
+        print!("{}", self.evcxr_content());
+    }
+
+    #[cfg(feature = "evcxr")]
+    fn evcxr_content(&self) -> String {
+        self.mime_bundle()
+            .iter()
+            .filter(|(mime, _)| *mime != "text/plain")
+            .map(|(mime, data)| format!("EVCXR_BEGIN_CONTENT {mime}\n{data}\nEVCXR_END_CONTENT\n"))
+            .collect()
+    }
+
+    /// Plain-text description, for frontends unable to display images.
+    fn summary(&self) -> String {
+        let root = self.svg.find("<svg").map(|start| &self.svg[start..]);
+        let attr = |name: &str| {
+            let root = root?;
+            let root = &root[..root.find('>')?];
+            let start = root.find(&format!(" {name}=\""))? + name.len() + 3;
+            root[start..].split('"').next()
+        };
+        match (attr("width"), attr("height")) {
+            (Some(w), Some(h)) => format!("<svgdx diagram {w} x {h}>"),
+            _ => "<svgdx diagram>".to_string(),
+        }
+    }
+}
+
+impl Display for Diagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.svg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mime_bundle() {
+        let diagram = Diagram::new(r#"<svg><rect wh="20 10"/></svg>"#).unwrap();
+        let bundle = diagram.mime_bundle();
+        let mimes: Vec<_> = bundle.iter().map(|(mime, _)| *mime).collect();
+        assert_eq!(mimes, ["image/svg+xml", "text/html", "text/plain"]);
+        assert_eq!(bundle[0].1, diagram.svg());
+        assert_eq!(bundle[2].1, "<svgdx diagram 30mm x 20mm>");
+        assert_eq!(diagram.to_string(), diagram.svg());
+
+        let json = diagram.mime_bundle_json();
+        assert!(json.starts_with(r#"{"image/svg+xml": "<svg"#));
+        assert!(json.contains(r#"width=\"30mm\""#));
+        assert!(json.ends_with(r#""text/plain": "<svgdx diagram 30mm x 20mm>"}"#));
+
+        assert!(Diagram::new("<svg><rect xy=\"#nope\"/></svg>").is_err());
+    }
+
+    #[cfg(feature = "evcxr")]
+    #[test]
+    fn test_evcxr_content() {
+        let diagram = Diagram::new(r#"<svg><rect wh="20 10"/></svg>"#).unwrap();
+        let content = diagram.evcxr_content();
+        assert!(content.starts_with("EVCXR_BEGIN_CONTENT image/svg+xml\n<svg"));
+        assert!(content.contains("</svg>\nEVCXR_END_CONTENT\nEVCXR_BEGIN_CONTENT text/html\n"));
+        assert!(content.ends_with("EVCXR_END_CONTENT\n"));
+        assert!(!content.contains("text/plain"));
+    }
+}
@@ -0,0 +1,322 @@
+//! Keycaps for keyboard shortcut and layout diagrams.
+//!
+//! A `<key>` element is positioned and sized in the same way as a `<rect>`,
+//! and is drawn as a keycap: an outer rounded rect with an inset top face,
+//! labelled with the `label` attribute.
+//!
+//! ```xml
+//! <key label="Ctrl" xy="0" wh="10 8"/>
+//! <key label="C" xy="^|h 1" wh="8"/>
+//! ```
+//!
+//! A `<keyboard>` element lays out rows of keys from its text content, with
+//! one row per line and whitespace-separated key labels. Keys may be widened
+//! with a `:N` suffix giving their width in key units, and `_` leaves a gap.
+//!
+//! ```xml
+//! <keyboard xy="0" key-wh="8">
+//! Esc _ F1 F2 F3 F4
+//! Tab:1.5 Q W E R T
+//! </keyboard>
+//! ```
+
+use crate::context::TransformerContext;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{OutputEvent, OutputList};
+use crate::expression::eval_attr;
+use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::transform::{process_elements, EventGen, FRAME_POSITION_ATTRS};
+use crate::types::{fstr, strp};
+
+/// Attributes of `<keyboard>` which are not present on the generated group.
+const KEYBOARD_ATTRS: &[&str] = &["key-wh", "gap"];
+/// Class for the body of a keycap, unless a `d-fill-*` class is given
+const KEY_CLASS: &str = "d-fill-lightgrey";
+/// Label used for gaps in a `<keyboard>` row
+const GAP_LABEL: &str = "_";
+
+const DEFAULT_KEY_WH: (f32, f32) = (10., 10.);
+const DEFAULT_GAP: f32 = 1.;
+
+/// Generate events for a `<key>` or `<keyboard>` element.
+pub(crate) fn key_events(
+    element: &SvgElement,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    if element.name == "key" {
+        KeyElement(element.clone()).generate_events(context)
+    } else {
+        KeyboardElement(element.clone()).generate_events(context)
+    }
+}
+
+/// The top face of a keycap with the given outer bounding box.
+fn key_face(bb: &BoundingBox) -> SvgElement {
+    // the face is offset upwards, leaving a deeper lower edge
+    let inset = bb.width().min(bb.height()) / 8.;
+    let mut face = SvgElement::new("rect", &[]);
+    face.set_attr("x", &fstr(bb.x1 + inset));
+    face.set_attr("y", &fstr(bb.y1 + inset / 2.));
+    face.set_attr("width", &fstr(bb.width() - inset * 2.));
+    face.set_attr("height", &fstr(bb.height() - inset * 2.5));
+    face.set_attr("rx", &fstr(inset / 2.));
+    face
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyElement(pub SvgElement);
+
+impl EventGen for KeyElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let mut body = self.0.clone();
+        body.name = "rect".to_string();
+        body.event_range = None;
+        let label = body.pop_attr("label");
+        if !body.get_classes().iter().any(|c| c.starts_with("d-fill-")) {
+            body.add_class(KEY_CLASS);
+        }
+        if !body.has_attr("rx") {
+            // round the body to match its face; the size may be relative to
+            // other elements, so resolve it first.
+            let mut frame = body.clone();
+            frame.resolve_position(context)?;
+            if let Some(bb) = frame.bbox()? {
+                body.set_attr("rx", &fstr(bb.width().min(bb.height()) / 8.));
+            }
+        }
+        let (mut events, key_bb) = process_elements(vec![body], context)?;
+        let key_bb = key_bb.ok_or_else(|| SvgdxError::MissingBoundingBox(self.0.to_string()))?;
+
+        let mut face = key_face(&key_bb);
+        if let Some(label) = label {
+            face.set_attr("text", &label);
+        }
+        let (face_events, _) = process_elements(vec![face], context)?;
+        events.extend(&face_events);
+
+        // relative positioning from subsequent elements uses the whole key
+        let mut prev = SvgElement::new("rect", &[]);
+        if let Some(id) = self.0.get_attr("id") {
+            prev.set_attr("id", &id);
+        }
+        prev.set_attr("x", &fstr(key_bb.x1));
+        prev.set_attr("y", &fstr(key_bb.y1));
+        prev.set_attr("width", &fstr(key_bb.width()));
+        prev.set_attr("height", &fstr(key_bb.height()));
+        context.set_prev_element(&prev);
+        Ok((events, Some(key_bb)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyboardElement(pub SvgElement);
+
+/// A key in a `<keyboard>` row: its label (`None` for gaps) and width in key units.
+type RowKey = (Option<String>, f32);
+
+/// Parse `<keyboard>` rows of keys.
+fn parse_rows(text: &str) -> Result<Vec<Vec<RowKey>>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.split_whitespace()
+                .map(|key| {
+                    let (label, width) = match key.rsplit_once(':') {
+                        Some((label, width)) if !label.is_empty() => (label, strp(width)?),
+                        _ => (key, 1.),
+                    };
+                    if !width.is_finite() || width <= 0. {
+                        return Err(SvgdxError::InvalidData(format!(
+                            "keyboard: invalid key width in '{key}'"
+                        )));
+                    }
+                    let label = (label != GAP_LABEL).then(|| label.replace('"', "&quot;"));
+                    Ok((label, width))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl KeyboardElement {
+    /// Key elements for the given rows, with the keyboard at `(x, y)`.
+    fn key_elements(
+        rows: &[Vec<RowKey>],
+        (x, y): (f32, f32),
+        (key_w, key_h): (f32, f32),
+        gap: f32,
+    ) -> Vec<SvgElement> {
+        let mut keys = Vec::new();
+        for (row_idx, row) in rows.iter().enumerate() {
+            let key_y = y + row_idx as f32 * (key_h + gap);
+            let mut key_x = x;
+            for (label, units) in row {
+                // wide keys span the gaps they replace, keeping columns aligned
+                let width = units * key_w + (units - 1.) * gap;
+                if let Some(label) = label {
+                    let mut key = SvgElement::new("key", &[]);
+                    key.set_attr("xy", &format!("{} {}", fstr(key_x), fstr(key_y)));
+                    key.set_attr("wh", &format!("{} {}", fstr(width), fstr(key_h)));
+                    key.set_attr("label", label);
+                    keys.push(key);
+                }
+                key_x += width + gap;
+            }
+        }
+        keys
+    }
+}
+
+impl EventGen for KeyboardElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let (key_w, key_h) = match self.0.get_attr("key-wh") {
+            Some(wh) => {
+                let wh = eval_attr(&wh, context);
+                let mut parts = wh.split_whitespace().map(strp);
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(w), None, None) => {
+                        let w = w?;
+                        (w, w)
+                    }
+                    (Some(w), Some(h), None) => (w?, h?),
+                    _ => {
+                        return Err(SvgdxError::InvalidData(format!(
+                            "keyboard: invalid key-wh '{wh}'"
+                        )))
+                    }
+                }
+            }
+            None => DEFAULT_KEY_WH,
+        };
+        let gap = self
+            .0
+            .get_attr("gap")
+            .map(|v| strp(&eval_attr(&v, context)))
+            .transpose()?
+            .unwrap_or(DEFAULT_GAP);
+
+        // Note text is kept escaped, as key labels become attribute values
+        let mut text = String::new();
+        for ev in self.0.inner_events(context).unwrap_or_default().iter() {
+            if let Some(t) = ev.text_string().or_else(|| ev.cdata_string()) {
+                text.push_str(&t);
+            }
+        }
+        let rows = parse_rows(&text)?;
+
+        // Position the keyboard as if it were a rect covering all the keys
+        let width = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|(_, units)| units * (key_w + gap))
+                    .sum::<f32>()
+                    - gap
+            })
+            .fold(0., f32::max);
+        let height = rows.len() as f32 * (key_h + gap) - gap;
+        let mut frame = SvgElement::new("rect", &[]);
+        for attr in FRAME_POSITION_ATTRS {
+            if let Some(value) = self.0.get_attr(attr) {
+                frame.set_attr(attr, &value);
+            }
+        }
+        frame.set_attr("width", &fstr(width.max(0.)));
+        frame.set_attr("height", &fstr(height.max(0.)));
+        frame.resolve_position(context)?;
+        let x = frame.get_attr("x").map(|x| strp(&x)).transpose()?;
+        let y = frame.get_attr("y").map(|y| strp(&y)).transpose()?;
+        let origin = (x.unwrap_or_default(), y.unwrap_or_default());
+
+        let keys = Self::key_elements(&rows, origin, (key_w, key_h), gap);
+        let (content, content_bb) = process_elements(keys, context)?;
+
+        let mut group = self.0.clone();
+        group.name = "g".to_string();
+        for attr in KEYBOARD_ATTRS.iter().chain(FRAME_POSITION_ATTRS) {
+            group.pop_attr(attr);
+        }
+        let mut events = OutputList::new();
+        events.push(OutputEvent::Start(group.clone()));
+        events.extend(&content);
+        events.push(OutputEvent::Text("\n".to_string()));
+        events.push(OutputEvent::End("g".to_string()));
+
+        group.content_bbox = content_bb;
+        context.update_element(&group);
+        context.set_prev_element(&group);
+
+        let mut bbox = BoundingBoxBuilder::new();
+        if let Some(bb) = content_bb {
+            bbox.extend(bb);
+        }
+        Ok((events, bbox.build()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rows() {
+        let rows = parse_rows("\n  Esc _ F1\nTab:1.5 \"Q\"\n").unwrap();
+        assert_eq!(
+            rows,
+            [
+                vec![
+                    (Some("Esc".to_string()), 1.),
+                    (None, 1.),
+                    (Some("F1".to_string()), 1.)
+                ],
+                vec![
+                    (Some("Tab".to_string()), 1.5),
+                    (Some("&quot;Q&quot;".to_string()), 1.)
+                ],
+            ]
+        );
+        assert_eq!(
+            parse_rows(":").unwrap(),
+            [vec![(Some(":".to_string()), 1.)]]
+        );
+        assert!(parse_rows("A:x").is_err());
+        assert!(parse_rows("A:0").is_err());
+        assert!(parse_rows("A:NaN").is_err());
+        assert!(parse_rows("A:inf").is_err());
+    }
+
+    #[test]
+    fn test_key_elements() {
+        let rows = parse_rows("A:2 B\n_ C").unwrap();
+        let keys = KeyboardElement::key_elements(&rows, (0., 0.), (10., 8.), 1.);
+        let summary: Vec<_> = keys
+            .iter()
+            .map(|k| (k.get_attr("xy").unwrap(), k.get_attr("wh").unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("0 0".to_string(), "21 8".to_string()),
+                ("22 0".to_string(), "10 8".to_string()),
+                ("11 9".to_string(), "10 8".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_key_face() {
+        let face = key_face(&BoundingBox::new(0., 0., 16., 8.));
+        assert_eq!(face.get_attr("x").unwrap(), "1");
+        assert_eq!(face.get_attr("y").unwrap(), "0.5");
+        assert_eq!(face.get_attr("width").unwrap(), "14");
+        assert_eq!(face.get_attr("height").unwrap(), "5.5");
+    }
+}
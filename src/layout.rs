@@ -0,0 +1,190 @@
+//! Sequential layout of elements in a row or column.
+//!
+//! `<row>` and `<col>` elements are groups which place their graphical
+//! children one after another, horizontally or vertically, similar to a CSS
+//! flexbox. For example:
+//!
+//! ```xml
+//! <row gap="5" align="center">
+//!   <rect wh="20 10"/>
+//!   <circle r="8"/>
+//!   <rect wh="10"/>
+//! </row>
+//! ```
+//!
+//! Children are separated by `gap` (default 0), and aligned across the row or
+//! column according to `align` (`start`, `center` or `end`; default `start`).
+//! With `equal="true"` each child is centred in a slot as large as the largest
+//! child, so they are evenly spaced. Children with an explicit position, and
+//! connectors, are left unchanged. The start of the row or column is at the
+//! group's origin, which may be moved with a `transform`.
+
+use crate::context::TransformerContext;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{tagify_events, InputList, OutputList, Tag};
+use crate::expression::eval_attr;
+use crate::position::BoundingBox;
+use crate::transform::group_events;
+use crate::types::{fstr, strp};
+
+use std::str::FromStr;
+
+/// Attributes of `<row>` / `<col>` which are not present on the generated `<g>`.
+const LAYOUT_ATTRS: &[&str] = &["gap", "align", "equal"];
+
+/// Attributes which give an element an explicit position.
+pub(crate) const POSITION_ATTRS: &[&str] = &[
+    "x", "y", "xy", "cx", "cy", "cxy", "x1", "y1", "xy1", "x2", "y2", "xy2", "surround", "inside",
+];
+
+/// Alignment of children across a row or column.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+impl FromStr for Align {
+    type Err = SvgdxError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "start" => Ok(Self::Start),
+            "center" => Ok(Self::Center),
+            "end" => Ok(Self::End),
+            _ => Err(SvgdxError::InvalidData(format!(
+                "align must be one of start, center, end, not '{value}'"
+            ))),
+        }
+    }
+}
+
+/// Centre of each item, given as `(main, cross)` sizes, relative to the start
+/// of the row or column and also as `(main, cross)`.
+fn place_items(sizes: &[(f32, f32)], gap: f32, align: Align, equal: bool) -> Vec<(f32, f32)> {
+    let max_main = sizes.iter().map(|s| s.0).fold(0., f32::max);
+    let max_cross = sizes.iter().map(|s| s.1).fold(0., f32::max);
+    let mut offset = 0.;
+    sizes
+        .iter()
+        .map(|&(main, cross)| {
+            let slot = if equal { max_main } else { main };
+            let centre_main = offset + slot / 2.;
+            offset += slot + gap;
+            let centre_cross = match align {
+                Align::Start => cross / 2.,
+                Align::Center => max_cross / 2.,
+                Align::End => max_cross - cross / 2.,
+            };
+            (centre_main, centre_cross)
+        })
+        .collect()
+}
+
+/// Size of `el` once its attributes are resolved.
+pub(crate) fn element_size(el: &SvgElement, context: &TransformerContext) -> Result<(f32, f32)> {
+    let mut el = el.clone();
+    el.resolve_position(context)?;
+    Ok(el
+        .bbox()?
+        .map_or((0., 0.), |bb: BoundingBox| (bb.width(), bb.height())))
+}
+
+/// Lay out the children of a `<row>` or `<col>` element, generating a group
+/// containing the result.
+pub(crate) fn layout_events(
+    element: &SvgElement,
+    content: Option<InputList>,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    let horizontal = element.name == "row";
+    let gap = element
+        .get_attr("gap")
+        .map(|v| strp(&eval_attr(&v, context)))
+        .transpose()
+        .map_err(|e| e.in_attr("gap"))?
+        .unwrap_or(0.);
+    let align: Align = element
+        .get_attr("align")
+        .map(|v| eval_attr(&v, context).parse())
+        .transpose()
+        .map_err(|e: SvgdxError| e.in_attr("align"))?
+        .unwrap_or_default();
+    let equal = element
+        .get_attr("equal")
+        .map(|v| eval_attr(&v, context).parse::<bool>())
+        .transpose()
+        .map_err(|e| SvgdxError::from(e).in_attr("equal"))?
+        .unwrap_or(false);
+
+    let content = if let Some(content) = content {
+        let mut items = Vec::new();
+        for el in tagify_events(content.clone())?
+            .iter()
+            .filter_map(Tag::get_element)
+        {
+            let Some((start, _)) = el.event_range else {
+                continue;
+            };
+            if !el.is_graphics_element()
+                || el.is_connector()
+                || POSITION_ATTRS.iter().any(|a| el.has_attr(a))
+            {
+                continue;
+            }
+            let (w, h) = element_size(&el, context)?;
+            let size = if horizontal { (w, h) } else { (h, w) };
+            items.push((start, size));
+        }
+
+        let sizes: Vec<_> = items.iter().map(|&(_, size)| size).collect();
+        let mut events = content.events;
+        for ((index, _), (main, cross)) in items.iter().zip(place_items(&sizes, gap, align, equal))
+        {
+            let (cx, cy) = if horizontal {
+                (main, cross)
+            } else {
+                (cross, main)
+            };
+            if let Some(ev) = events.iter_mut().find(|ev| ev.index == *index) {
+                *ev = ev.with_attr("cxy", &format!("{} {}", fstr(cx), fstr(cy)));
+            }
+        }
+        Some(InputList { events })
+    } else {
+        None
+    };
+
+    let mut group = element.clone();
+    group.name = "g".to_string();
+    for attr in LAYOUT_ATTRS {
+        group.pop_attr(attr);
+    }
+    group_events(&group, content, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_items() {
+        let sizes = [(10., 4.), (20., 10.), (6., 6.)];
+        assert_eq!(
+            place_items(&sizes, 2., Align::Start, false),
+            [(5., 2.), (22., 5.), (37., 3.)]
+        );
+        assert_eq!(
+            place_items(&sizes, 0., Align::Center, false),
+            [(5., 5.), (20., 5.), (33., 5.)]
+        );
+        assert_eq!(
+            place_items(&sizes, 0., Align::End, true),
+            [(10., 8.), (30., 5.), (50., 7.)]
+        );
+        assert!("middle".parse::<Align>().is_err());
+    }
+}
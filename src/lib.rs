@@ -26,7 +26,6 @@
 //! println!("{output}");
 //! ```
 
-use themes::ThemeType;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
@@ -35,36 +34,92 @@ use std::fs::{self, File};
 #[cfg(feature = "cli")]
 use std::io::{BufReader, IsTerminal, Read};
 
+use std::collections::HashMap;
 use std::io::{BufRead, Cursor, Write};
+use std::sync::Arc;
 
 #[cfg(feature = "cli")]
 use tempfile::NamedTempFile;
 
+mod align;
+mod areas;
+mod bus;
 #[cfg(feature = "cli")]
 pub mod cli;
 mod colours;
+mod compose;
+mod connect_el;
 mod connector;
 mod constants;
 mod context;
+mod datauri;
+mod diff;
+mod editor;
 mod element;
 mod errors;
 mod events;
 mod expression;
+#[cfg(feature = "cli")]
+mod formatter;
 mod functions;
+mod geometry;
+mod graph;
+mod html;
+mod image_size;
+mod import;
+mod jupyter;
+mod key;
+mod layout;
+mod live;
 mod loop_el;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod markdown;
+#[cfg(feature = "cli")]
+mod migrate;
+mod outline;
+mod pack;
 mod path;
 mod position;
+#[cfg(feature = "png")]
+mod raster;
 mod reuse;
+mod route;
+mod sanitize;
+mod schematic;
 #[cfg(feature = "server")]
 pub mod server;
+mod statemachine;
+mod stats;
 mod text;
 mod themes;
 mod transform;
 mod transform_attr;
+mod tree;
 mod types;
+mod validate;
+mod wave;
 
-pub use errors::Result;
+pub use compose::{svg_compose, ComposeLayout};
+pub use datauri::svg_data_uri;
+pub use diff::{svg_diff, DiffSummary};
+pub use editor::{
+    complete, cursor_offset, definition, hover, Completion, CompletionKind, HoverInfo,
+};
+use errors::SvgdxError;
+pub use errors::{ErrorSpan, Result};
+pub use geometry::{svg_element_bboxes, svg_geometry, svg_skeleton};
+pub use html::{svg_html_fragment, HTML_FIGURE_CLASS};
+pub use jupyter::Diagram;
+pub use live::LiveDocument;
+pub use markdown::{filter_markdown, markdown_html, MarkdownBlock};
+pub use outline::svg_outline;
+#[cfg(feature = "png")]
+pub use raster::svg_png;
+pub use stats::TransformStats;
+pub use themes::{auto_style_classes, AutoStyleClass, CustomTheme, Theme, ThemeBuilder, ThemeType};
 use transform::Transformer;
+pub use validate::{validate_svg, ValidationIssue};
 
 // Allow users of this as a library to easily retrieve the version of svgdx being used
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -94,6 +149,10 @@ pub struct TransformConfig {
     pub var_limit: u32,
     /// Maximum depth of recursion
     pub depth_limit: u32,
+    /// Maximum number of elements processed
+    pub element_limit: u32,
+    /// Maximum size of output (bytes)
+    pub output_limit: u32,
     /// Add source metadata to output
     pub add_metadata: bool,
     /// Default font-size (in user-units)
@@ -102,10 +161,57 @@ pub struct TransformConfig {
     pub font_family: String,
     /// Theme to use (default "default")
     pub theme: ThemeType,
+    /// User-provided theme, used in place of `theme` if set
+    pub custom_theme: Option<Arc<dyn Theme>>,
     /// Make styles local to this document
     pub use_local_styles: bool,
     /// Optional style to apply to SVG root element
     pub svg_style: Option<String>,
+    /// Transform applied to all content before the viewBox is computed,
+    /// e.g. `rotate(90)`; a single transform may also be given as `rotate 90`
+    pub content_transform: Option<String>,
+    /// Remove scripts, event handlers and external references from input and output
+    pub sanitize: bool,
+    /// Allow the `env()` expression function to read environment variables;
+    /// ignored if `sanitize` is set. Not available via `<config>`.
+    pub allow_env: bool,
+    /// Skip malformed path data segments rather than failing
+    pub path_recover: bool,
+    /// Draw a small hop where a connector crosses an earlier connector
+    pub connector_jumps: bool,
+    /// Reference auto-style CSS at this URL rather than embedding it
+    pub css_href: Option<String>,
+    /// Document title, added as a `<title>` element (and metadata if `add_metadata`)
+    pub title: Option<String>,
+    /// Document description, added as a `<desc>` element (and metadata if `add_metadata`)
+    pub desc: Option<String>,
+    /// Author, added to an attribution comment (and metadata if `add_metadata`)
+    pub author: Option<String>,
+    /// License, added to an attribution comment (and metadata if `add_metadata`)
+    pub license: Option<String>,
+    /// Source URL, added to an attribution comment (and metadata if `add_metadata`)
+    pub source_url: Option<String>,
+    /// Watermark text, placed across the canvas
+    pub watermark: Option<String>,
+    /// Watermark image URL, scaled to fit the canvas
+    pub watermark_image: Option<String>,
+    /// Opacity of watermark (default 0.15)
+    pub watermark_opacity: f32,
+    /// Rotation of watermark in degrees (default -30)
+    pub watermark_angle: f32,
+    /// Place watermark above rather than behind content
+    pub watermark_above: bool,
+    /// Build step to render; elements with a higher `step` are hidden
+    pub step: Option<u32>,
+    /// Directory from which `<import>` elements (and `<image>` elements sized
+    /// with `auto`) load files; these are rejected if this is `None`
+    pub import_dir: Option<String>,
+    /// In-memory documents available to `<import>` elements by name; these
+    /// take precedence over files in `import_dir`
+    pub documents: Option<Arc<HashMap<String, String>>>,
+    /// Name of the input document, used in diagnostics and recorded as a
+    /// `data-src-file` attribute on the root element if `add_metadata` is set
+    pub source_file: Option<String>,
 }
 
 impl Default for TransformConfig {
@@ -120,13 +226,81 @@ impl Default for TransformConfig {
             loop_limit: 1000,
             var_limit: 1024,
             depth_limit: 100,
+            element_limit: 100_000,
+            output_limit: 16 * 1024 * 1024,
             add_metadata: false,
             font_size: 3.0,
             font_family: "sans-serif".to_owned(),
             theme: ThemeType::default(),
+            custom_theme: None,
             use_local_styles: false,
             svg_style: None,
+            content_transform: None,
+            sanitize: false,
+            allow_env: false,
+            path_recover: false,
+            connector_jumps: false,
+            css_href: None,
+            title: None,
+            desc: None,
+            author: None,
+            license: None,
+            source_url: None,
+            watermark: None,
+            watermark_image: None,
+            watermark_opacity: 0.15,
+            watermark_angle: -30.,
+            watermark_above: false,
+            step: None,
+            import_dir: None,
+            documents: None,
+            source_file: None,
+        }
+    }
+}
+
+impl TransformConfig {
+    /// Update a setting from its `<config>` attribute name and value,
+    /// e.g. `("font-size", "4")`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "scale" => self.scale = value.parse()?,
+            "debug" => self.debug = value.parse()?,
+            "add-auto-styles" => self.add_auto_styles = value.parse()?,
+            "use-local-styles" => self.use_local_styles = value.parse()?,
+            "border" => self.border = value.parse()?,
+            "background" => self.background = value.to_string(),
+            "loop-limit" => self.loop_limit = value.parse()?,
+            "var-limit" => self.var_limit = value.parse()?,
+            "depth-limit" => self.depth_limit = value.parse()?,
+            "font-size" => self.font_size = value.parse()?,
+            "font-family" => self.font_family = value.to_string(),
+            "seed" => self.seed = value.parse()?,
+            "theme" => self.theme = value.parse()?,
+            "svg-style" => self.svg_style = Some(value.to_string()),
+            "content-transform" => self.content_transform = Some(value.to_string()),
+            "path-recover" => self.path_recover = value.parse()?,
+            "connector-jumps" => self.connector_jumps = value.parse()?,
+            "title" => self.title = Some(value.to_string()),
+            "desc" => self.desc = Some(value.to_string()),
+            "author" => self.author = Some(value.to_string()),
+            "license" => self.license = Some(value.to_string()),
+            "source-url" => self.source_url = Some(value.to_string()),
+            "watermark" => self.watermark = Some(value.to_string()),
+            "watermark-image" => self.watermark_image = Some(value.to_string()),
+            "watermark-opacity" => self.watermark_opacity = value.parse()?,
+            "watermark-angle" => self.watermark_angle = value.parse()?,
+            "watermark-above" => self.watermark_above = value.parse()?,
+            "step" => self.step = Some(value.parse()?),
+            // Note element-limit and output-limit are deliberately not settable
+            // here, as they protect the host from untrusted documents.
+            _ => {
+                return Err(SvgdxError::InvalidData(format!(
+                    "Unknown config setting {key}"
+                )))
+            }
         }
+        Ok(())
     }
 }
 
@@ -150,7 +324,26 @@ pub fn transform_stream(
 /// The transform can be modified by providing a suitable `TransformConfig` value.
 #[cfg(feature = "cli")]
 pub fn transform_file(input: &str, output: &str, cfg: &TransformConfig) -> Result<()> {
-    let mut in_reader = if input == "-" {
+    let mut in_reader = input_reader(input)?;
+
+    if output == "-" {
+        transform_stream(&mut in_reader, &mut std::io::stdout(), cfg)?;
+    } else {
+        let mut out_temp = NamedTempFile::new()?;
+        transform_stream(&mut in_reader, &mut out_temp, cfg)?;
+        // Copy content rather than rename (by .persist()) since this
+        // could cross filesystems; some apps (e.g. eog) also fail to
+        // react to 'moved-over' files.
+        fs::copy(out_temp.path(), output)?;
+    }
+
+    Ok(())
+}
+
+/// Get a buffered reader for the given `input` file ('-' for stdin).
+#[cfg(feature = "cli")]
+pub(crate) fn input_reader(input: &str) -> Result<Box<dyn BufRead>> {
+    Ok(if input == "-" {
         let mut stdin = std::io::stdin().lock();
         if stdin.is_terminal() {
             // This is unpleasant; at least on Mac, a single Ctrl-D is not otherwise
@@ -168,19 +361,20 @@ pub fn transform_file(input: &str, output: &str, cfg: &TransformConfig) -> Resul
         }
     } else {
         Box::new(BufReader::new(File::open(input)?)) as Box<dyn BufRead>
-    };
+    })
+}
 
+/// Write `data` to the given `output` file ('-' for stdout).
+#[cfg(feature = "cli")]
+pub(crate) fn write_output(output: &str, data: &[u8]) -> Result<()> {
     if output == "-" {
-        transform_stream(&mut in_reader, &mut std::io::stdout(), cfg)?;
+        std::io::stdout().write_all(data)?;
     } else {
+        // As in `transform_file`, copy content rather than rename.
         let mut out_temp = NamedTempFile::new()?;
-        transform_stream(&mut in_reader, &mut out_temp, cfg)?;
-        // Copy content rather than rename (by .persist()) since this
-        // could cross filesystems; some apps (e.g. eog) also fail to
-        // react to 'moved-over' files.
+        out_temp.write_all(data)?;
         fs::copy(out_temp.path(), output)?;
     }
-
     Ok(())
 }
 
@@ -196,6 +390,25 @@ pub fn transform_string(input: String, add_metadata: bool) -> core::result::Resu
     transform_str(input, &cfg).map_err(|e| e.to_string())
 }
 
+/// Transform `input` as for `transform_string`, also returning the bounding
+/// box of each element with an `id`.
+///
+/// The result is a JSON object `{"svg": "...", "elements": {...}}`, where
+/// `elements` is as given by `svg_element_bboxes`, allowing front-ends to
+/// implement hit-testing and navigation without parsing the output SVG.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn transform_string_geometry(
+    input: String,
+    add_metadata: bool,
+) -> core::result::Result<String, String> {
+    let svg = transform_string(input, add_metadata)?;
+    let elements = svg_element_bboxes(&svg).map_err(|e| e.to_string())?;
+    Ok(format!(
+        "{{\"svg\": {}, \"elements\": {elements}}}",
+        outline::json_str(&svg)
+    ))
+}
+
 pub fn transform_str<T: Into<String>>(input: T, cfg: &TransformConfig) -> Result<String> {
     let input = input.into();
 
@@ -207,6 +420,51 @@ pub fn transform_str<T: Into<String>>(input: T, cfg: &TransformConfig) -> Result
     Ok(String::from_utf8(output).expect("Non-UTF8 output generated"))
 }
 
+/// Transform `input` provided as a string, returning the result as a string
+/// along with statistics of the transformation.
+///
+/// The transform can be modified by providing a suitable `TransformConfig` value.
+pub fn transform_str_with_stats<T: Into<String>>(
+    input: T,
+    cfg: &TransformConfig,
+) -> Result<(String, TransformStats)> {
+    transform_str_full(input, cfg).map(|(output, stats, _)| (output, stats))
+}
+
+/// Transform `input`, returning the output, statistics, and any CSS extracted
+/// due to `cfg.css_href` being set.
+pub(crate) fn transform_str_full<T: Into<String>>(
+    input: T,
+    cfg: &TransformConfig,
+) -> Result<(String, TransformStats, Option<String>)> {
+    let mut input = Cursor::new(input.into());
+    let mut output: Vec<u8> = vec![];
+
+    let mut t = Transformer::from_config(cfg);
+    t.transform(&mut input, &mut output)?;
+
+    Ok((
+        String::from_utf8(output).expect("Non-UTF8 output generated"),
+        t.context.stats,
+        t.context.extracted_css,
+    ))
+}
+
+/// Transform `input` provided as a string, returning the result along with
+/// the auto-style CSS as a separate stylesheet.
+///
+/// The output references the stylesheet at `cfg.css_href` (which must be set)
+/// rather than embedding the CSS.
+pub fn transform_str_extract_css<T: Into<String>>(
+    input: T,
+    cfg: &TransformConfig,
+) -> Result<(String, String)> {
+    if cfg.css_href.is_none() {
+        return Err(SvgdxError::from("css_href must be set to extract CSS"));
+    }
+    transform_str_full(input, cfg).map(|(output, _, css)| (output, css.unwrap_or_default()))
+}
+
 /// Transform the provided `input` string using default config, returning the result string.
 ///
 /// Uses default `TransformConfig` settings.
@@ -0,0 +1,167 @@
+//! Live document API for in-browser editing.
+//!
+//! A `LiveDocument` holds the source of a document between calls, so an
+//! editor can send just the edited range on each change rather than the
+//! whole document, and caches the most recent result so repeated renders of
+//! an unchanged document (e.g. when toggling views) don't re-transform it.
+//!
+//! This is not an incremental renderer: rendering a changed document takes
+//! as long as `transform_str()` on the full source, since element positions
+//! may depend on any other part of it. Editors should still debounce renders
+//! of large documents rather than rendering on every keystroke.
+//!
+//! ```js
+//! const doc = new LiveDocument(editor.getValue(), true);
+//! editor.on('change', (cm, change) => {
+//!     // offsets are in UTF-16 code units, as for JS strings
+//!     const from = cm.indexFromPos(change.from);
+//!     const to = from + change.removed.join('\n').length;
+//!     doc.update(from, to, change.text.join('\n'));
+//! });
+//! const svg = doc.render();
+//! ```
+
+use crate::{transform_str, TransformConfig};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct LiveDocument {
+    source: String,
+    config: TransformConfig,
+    /// Result of transforming the current source, if rendered since last changed
+    rendered: Option<Result<String, String>>,
+}
+
+/// Byte offset in `s` of the given offset in UTF-16 code units.
+fn utf16_byte_offset(s: &str, offset: usize) -> Option<usize> {
+    let mut count = 0;
+    for (idx, ch) in s.char_indices() {
+        if count == offset {
+            return Some(idx);
+        }
+        if count > offset {
+            // within a surrogate pair
+            return None;
+        }
+        count += ch.len_utf16();
+    }
+    (count == offset).then_some(s.len())
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl LiveDocument {
+    /// Create a document with the given source.
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(source: String, add_metadata: bool) -> Self {
+        Self {
+            source,
+            config: TransformConfig {
+                add_metadata,
+                ..Default::default()
+            },
+            rendered: None,
+        }
+    }
+
+    /// Current source of the document.
+    pub fn source(&self) -> String {
+        self.source.clone()
+    }
+
+    /// Replace the entire source of the document.
+    pub fn set_source(&mut self, source: String) {
+        if source != self.source {
+            self.source = source;
+            self.rendered = None;
+        }
+    }
+
+    /// Replace the source between offsets `from` and `to` with `text`.
+    ///
+    /// Offsets are in UTF-16 code units, matching JavaScript string indices.
+    pub fn update(&mut self, from: usize, to: usize, text: &str) -> Result<(), String> {
+        let range = utf16_byte_offset(&self.source, from)
+            .zip(utf16_byte_offset(&self.source, to))
+            .filter(|(start, end)| start <= end)
+            .map(|(start, end)| start..end)
+            .ok_or_else(|| format!("Invalid update range {from}..{to}"))?;
+        if self.source[range.clone()] != *text {
+            self.source.replace_range(range, text);
+            self.rendered = None;
+        }
+        Ok(())
+    }
+
+    pub fn set_add_metadata(&mut self, add_metadata: bool) {
+        if add_metadata != self.config.add_metadata {
+            self.config.add_metadata = add_metadata;
+            self.rendered = None;
+        }
+    }
+
+    /// True if the document has changed since it was last rendered.
+    pub fn is_dirty(&self) -> bool {
+        self.rendered.is_none()
+    }
+
+    /// Transform the document, returning the SVG output or an error message.
+    ///
+    /// Any change since the last render requires a full transform; the result
+    /// is cached until the document is next changed.
+    pub fn render(&mut self) -> Result<String, String> {
+        self.rendered
+            .get_or_insert_with(|| {
+                transform_str(self.source.as_str(), &self.config).map_err(|e| e.to_string())
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_offset() {
+        let s = "a\u{e9}\u{1f600}b";
+        assert_eq!(utf16_byte_offset(s, 0), Some(0));
+        assert_eq!(utf16_byte_offset(s, 2), Some(3));
+        // within the surrogate pair of the emoji
+        assert_eq!(utf16_byte_offset(s, 3), None);
+        assert_eq!(utf16_byte_offset(s, 4), Some(7));
+        assert_eq!(utf16_byte_offset(s, 5), Some(8));
+        assert_eq!(utf16_byte_offset(s, 6), None);
+    }
+
+    #[test]
+    fn test_live_document() {
+        let mut doc = LiveDocument::new(r#"<svg><rect wh="10"/></svg>"#.to_string(), false);
+        assert!(doc.is_dirty());
+        let first = doc.render().unwrap();
+        assert!(first.contains(r#"width="10" height="10""#));
+        assert!(!doc.is_dirty());
+
+        // no-op updates don't invalidate the render
+        doc.update(15, 17, "10").unwrap();
+        assert!(!doc.is_dirty());
+
+        doc.update(15, 17, "20").unwrap();
+        assert_eq!(doc.source(), r#"<svg><rect wh="20"/></svg>"#);
+        assert!(doc.is_dirty());
+        assert!(doc.render().unwrap().contains(r#"width="20" height="20""#));
+
+        // insertion, then deletion
+        doc.update(18, 18, r#" text="a""#).unwrap();
+        assert!(doc.render().unwrap().contains(">a</text>"));
+        doc.update(18, 27, "").unwrap();
+        assert_eq!(doc.source(), r#"<svg><rect wh="20"/></svg>"#);
+
+        assert!(doc.update(20, 19, "").is_err());
+        assert!(doc.update(0, 100, "").is_err());
+
+        doc.set_source("<svg><rect wh=\"#x\"/></svg>".to_string());
+        assert!(doc.render().is_err());
+    }
+}
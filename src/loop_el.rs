@@ -4,13 +4,45 @@ use crate::errors::{Result, SvgdxError};
 use crate::events::OutputList;
 use crate::expression::{eval_attr, eval_condition, eval_list};
 use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::reuse::bake_events;
 use crate::transform::{process_events, EventGen};
+use crate::types::{attr_split, strp};
+
+/// Variables holding the column, row and (row-major) cell index of a grid loop
+const GRID_X_VAR: &str = "x";
+const GRID_Y_VAR: &str = "y";
+const GRID_INDEX_VAR: &str = "i";
+
+/// Iteration limit for a loop element; a `limit` attribute overrides the
+/// `loop-limit` config value.
+fn loop_limit(element: &SvgElement, context: &TransformerContext) -> Result<u32> {
+    if let Some(limit) = element.get_attr("limit") {
+        let limit = eval_attr(&limit, context);
+        limit
+            .parse()
+            .map_err(|_| SvgdxError::InvalidData(format!("Invalid loop limit '{limit}'")))
+    } else {
+        Ok(context.config.loop_limit)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 enum LoopType {
     Repeat(String),
     While(String),
     Until(String),
+    /// Columns and rows, e.g. from `grid="5x3"` or `count-x="5" count-y="3"`
+    Grid(String, String),
+}
+
+/// Parse a grid size (e.g. "5x3") into columns and rows.
+fn parse_grid(value: &str) -> Result<(u32, u32)> {
+    let err = || SvgdxError::InvalidData(format!("Invalid loop grid '{value}'"));
+    let (cols, rows) = value.split_once('x').ok_or_else(err)?;
+    Ok((
+        cols.trim().parse().map_err(|_| err())?,
+        rows.trim().parse().map_err(|_| err())?,
+    ))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -44,9 +76,17 @@ impl TryFrom<&SvgElement> for LoopDef {
             loop_type = LoopType::While(while_expr);
         } else if let Some(until_expr) = element.get_attr("until") {
             loop_type = LoopType::Until(until_expr);
+        } else if let Some(grid) = element.get_attr("grid") {
+            // Split once evaluated, as the grid may be an expression
+            loop_type = LoopType::Grid(grid, String::new());
+        } else if element.has_attr("count-x") || element.has_attr("count-y") {
+            loop_type = LoopType::Grid(
+                element.get_attr("count-x").unwrap_or("1".to_string()),
+                element.get_attr("count-y").unwrap_or("1".to_string()),
+            );
         } else {
             return Err(SvgdxError::MissingAttribute(
-                "count | while | until".to_string(),
+                "count | while | until | grid".to_string(),
             ));
         }
         Ok(Self {
@@ -76,30 +116,81 @@ impl EventGen for LoopElement {
             let mut loop_count = 0;
             let mut loop_var_value = 0.;
             let mut loop_step = 1.;
-            if let LoopType::Repeat(count) = &loop_def.loop_type {
-                loop_count = eval_attr(count, context).parse()?;
+            let mut grid_cols = 0;
+            let mut grid_spacing = None;
+            match &loop_def.loop_type {
+                LoopType::Repeat(count) => loop_count = eval_attr(count, context).parse()?,
+                LoopType::Grid(cols, rows) => {
+                    let (cols, rows) = if rows.is_empty() {
+                        parse_grid(&eval_attr(cols, context))?
+                    } else {
+                        (
+                            eval_attr(cols, context).parse()?,
+                            eval_attr(rows, context).parse()?,
+                        )
+                    };
+                    grid_cols = cols;
+                    // checked up-front, as a grid may be (far) larger than the limit
+                    let limit = loop_limit(event_element, context)?;
+                    loop_count = cols
+                        .checked_mul(rows)
+                        .filter(|&count| count <= limit)
+                        .ok_or(SvgdxError::LoopLimitError(cols.saturating_mul(rows), limit))?;
+                    if let Some(spacing) = event_element.get_attr("spacing") {
+                        let spacing = attr_split(&eval_attr(&spacing, context))
+                            .map(|v| strp(&v))
+                            .collect::<Result<Vec<_>>>()?;
+                        grid_spacing = match spacing[..] {
+                            [d] => Some((d, d)),
+                            [dx, dy] => Some((dx, dy)),
+                            _ => {
+                                return Err(SvgdxError::InvalidData(
+                                    "loop spacing should be one or two numbers".to_string(),
+                                ))
+                            }
+                        };
+                    }
+                }
+                _ => {}
             }
+            let limit = loop_limit(event_element, context)?;
             if let Some((loop_var, start, step)) = loop_def.loop_spec {
                 loop_var_name = eval_attr(&loop_var, context);
                 loop_var_value = eval_attr(&start, context).parse()?;
                 loop_step = eval_attr(&step, context).parse()?;
             }
-            loop {
-                if let LoopType::Repeat(_) = &loop_def.loop_type {
+            let known_count = match &loop_def.loop_type {
+                LoopType::Repeat(_) | LoopType::Grid(..) => Some(loop_count),
+                LoopType::While(_) | LoopType::Until(_) => None,
+            };
+            context.with_loop(known_count, |context| loop {
+                if let LoopType::Repeat(_) | LoopType::Grid(..) = &loop_def.loop_type {
                     if iteration >= loop_count {
-                        break;
+                        return Ok(());
                     }
                 } else if let LoopType::While(expr) = &loop_def.loop_type {
                     if !eval_condition(expr, context)? {
-                        break;
+                        return Ok(());
                     }
                 }
 
                 if !loop_var_name.is_empty() {
                     context.set_var(&loop_var_name, &loop_var_value.to_string());
                 }
+                context.set_loop_index(iteration);
+                let mut cell = (0, 0);
+                if let LoopType::Grid(..) = &loop_def.loop_type {
+                    cell = (iteration % grid_cols, iteration / grid_cols);
+                    context.set_var(GRID_X_VAR, &cell.0.to_string());
+                    context.set_var(GRID_Y_VAR, &cell.1.to_string());
+                    context.set_var(GRID_INDEX_VAR, &iteration.to_string());
+                }
 
-                let (ev_list, ev_bbox) = process_events(inner_events.clone(), context)?;
+                let (mut ev_list, mut ev_bbox) = process_events(inner_events.clone(), context)?;
+                if let Some((dx, dy)) = grid_spacing {
+                    let (x, y) = (cell.0 as f32 * dx, cell.1 as f32 * dy);
+                    (ev_list, ev_bbox) = bake_events(&ev_list, ev_bbox, x, y, false, context)?;
+                }
                 gen_events.extend(&ev_list);
                 if let Some(bb) = ev_bbox {
                     bbox.extend(bb);
@@ -107,18 +198,15 @@ impl EventGen for LoopElement {
 
                 if let LoopType::Until(expr) = &loop_def.loop_type {
                     if eval_condition(expr, context)? {
-                        break;
+                        return Ok(());
                     }
                 }
                 iteration += 1;
                 loop_var_value += loop_step;
-                if iteration > context.config.loop_limit {
-                    return Err(SvgdxError::LoopLimitError(
-                        iteration,
-                        context.config.loop_limit,
-                    ));
+                if iteration > limit {
+                    return Err(SvgdxError::LoopLimitError(iteration, limit));
                 }
-            }
+            })?;
         }
         Ok((gen_events, bbox.build()))
     }
@@ -166,25 +254,31 @@ impl EventGen for ForElement {
             event_element.inner_events(context),
         ) {
             let data_list: Vec<_> = eval_list(&for_def.data, context)?;
+            let limit = loop_limit(event_element, context)?;
             let idx_name = for_def.idx_name.clone();
+            let data_len = data_list.len() as u32;
 
             // TODO: should a new context be created for for loops, so
             // loop & idx vars don't leak out / override existing vars?
-            for item in data_list {
-                context.set_var(&for_def.var_name, &item);
-                if let Some(ref idx_name) = idx_name {
-                    context.set_var(idx_name, &idx.to_string());
-                }
-                let (ev_list, ev_bbox) = process_events(inner_events.clone(), context)?;
-                gen_events.extend(&ev_list);
-                if let Some(bb) = ev_bbox {
-                    bbox.extend(bb);
-                }
-                idx += 1;
-                if idx > context.config.loop_limit {
-                    return Err(SvgdxError::LoopLimitError(idx, context.config.loop_limit));
+            context.with_loop(Some(data_len), |context| {
+                for item in data_list {
+                    context.set_var(&for_def.var_name, &item);
+                    if let Some(ref idx_name) = idx_name {
+                        context.set_var(idx_name, &idx.to_string());
+                    }
+                    context.set_loop_index(idx);
+                    let (ev_list, ev_bbox) = process_events(inner_events.clone(), context)?;
+                    gen_events.extend(&ev_list);
+                    if let Some(bb) = ev_bbox {
+                        bbox.extend(bb);
+                    }
+                    idx += 1;
+                    if idx > limit {
+                        return Err(SvgdxError::LoopLimitError(idx, limit));
+                    }
                 }
-            }
+                Ok(())
+            })?;
             Ok((gen_events, bbox.build()))
         } else {
             Err(SvgdxError::InvalidData("Invalid <for> element".to_string()))
@@ -0,0 +1,369 @@
+//! Minimal Language Server Protocol (LSP) implementation for svgdx documents.
+//!
+//! Documents are synchronised in full on each change, and re-transformed to
+//! provide diagnostics. Completion, hover and goto-definition (for `#id`
+//! references) use the functions in `editor`. Messages are JSON-RPC with
+//! `Content-Length` framing, as used over stdio by LSP clients.
+
+use crate::editor::{complete, definition, hover, CompletionKind};
+use crate::errors::{Result, SvgdxError};
+use crate::{transform_str, TransformConfig, VERSION};
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+// JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// State of a language server session.
+#[derive(Debug, Default)]
+pub struct LspServer {
+    config: TransformConfig,
+    documents: HashMap<String, String>,
+    exit: bool,
+}
+
+/// Byte offset for a zero-based LSP `line` and `character`, where characters
+/// are counted in UTF-16 code units.
+fn position_offset(text: &str, line: usize, character: usize) -> Option<usize> {
+    let mut offset = 0;
+    let mut lines = text.split_inclusive('\n');
+    for _ in 0..line {
+        offset += lines.next()?.len();
+    }
+    let text_line = lines.next().unwrap_or_default().trim_end_matches('\n');
+    let mut units = 0;
+    for (idx, c) in text_line.char_indices() {
+        if units >= character {
+            return Some(offset + idx);
+        }
+        units += c.len_utf16();
+    }
+    (units >= character).then_some(offset + text_line.len())
+}
+
+/// LSP position for a 1-based `line` and `col` (in characters).
+fn lsp_position(text: &str, line: usize, col: usize) -> Value {
+    let character: usize = text
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or_default()
+        .chars()
+        .take(col.saturating_sub(1))
+        .map(char::len_utf16)
+        .sum();
+    json!({ "line": line.saturating_sub(1), "character": character })
+}
+
+impl LspServer {
+    pub fn new(config: TransformConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// True once an `exit` notification has been received.
+    pub fn exited(&self) -> bool {
+        self.exit
+    }
+
+    fn diagnostics(&self, uri: &str) -> Value {
+        // closed documents have no diagnostics
+        let text = self.documents.get(uri);
+        let diagnostics: Vec<Value> = match text.map(|t| (t, transform_str(t, &self.config))) {
+            None | Some((_, Ok(_))) => Vec::new(),
+            Some((text, Err(err))) => err
+                .spans(text)
+                .into_iter()
+                .map(|span| {
+                    json!({
+                        "range": {
+                            "start": lsp_position(text, span.line, span.col),
+                            "end": lsp_position(text, span.line, span.col + span.len),
+                        },
+                        "severity": 1,
+                        "code": span.code,
+                        "source": "svgdx",
+                        "message": span.message,
+                    })
+                })
+                .collect(),
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    /// Document text and byte offset for `textDocument` / `position` params.
+    fn doc_position(&self, params: &Value) -> Option<(&str, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let text = self.documents.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        Some((text, position_offset(text, line, character)?))
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let Some((text, offset)) = self.doc_position(params) else {
+            return Value::Null;
+        };
+        let items: Vec<Value> = complete(text, offset)
+            .into_iter()
+            .map(|c| {
+                // LSP CompletionItemKind values
+                let kind = match c.kind {
+                    CompletionKind::ElementId => 18,
+                    CompletionKind::Attribute => 10,
+                    CompletionKind::Class => 12,
+                    CompletionKind::LocSpec => 20,
+                };
+                json!({ "label": c.label, "kind": kind, "detail": c.detail })
+            })
+            .collect();
+        json!(items)
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((text, offset)) = self.doc_position(params) else {
+            return Value::Null;
+        };
+        match hover(text, offset, &self.config) {
+            Ok(Some(h)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!(
+                        "`<{}>` #{}\n\nx: {}, y: {}, width: {}, height: {}",
+                        h.element, h.id, h.x, h.y, h.width, h.height
+                    ),
+                }
+            }),
+            _ => Value::Null,
+        }
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let Some((text, offset)) = self.doc_position(params) else {
+            return Value::Null;
+        };
+        match definition(text, offset, &self.config) {
+            Some((line, col)) => {
+                let pos = lsp_position(text, line, col);
+                json!({
+                    "uri": params["textDocument"]["uri"],
+                    "range": { "start": pos, "end": pos },
+                })
+            }
+            None => Value::Null,
+        }
+    }
+
+    /// Handle a single incoming message, returning any messages to send.
+    pub fn handle(&mut self, msg: &Value) -> Vec<Value> {
+        let method = msg["method"].as_str().unwrap_or_default();
+        let params = &msg["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": { "triggerCharacters": ["#", "@", "\"", " "] },
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "svgdx-lsp", "version": VERSION },
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/completion" => self.completion(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "exit" => {
+                self.exit = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_owned(), text.to_owned());
+                return vec![self.diagnostics(uri)];
+            }
+            "textDocument/didChange" => {
+                // full sync; the last change contains the entire document
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                    self.documents.insert(uri.to_owned(), text.to_owned());
+                }
+                return vec![self.diagnostics(uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![self.diagnostics(uri)];
+            }
+            _ => {
+                // unknown notifications (without an id) are ignored
+                if msg.get("id").is_none() {
+                    return Vec::new();
+                }
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": msg["id"],
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("Unknown method '{method}'"),
+                    },
+                })];
+            }
+        };
+        vec![json!({ "jsonrpc": "2.0", "id": msg["id"], "result": result })]
+    }
+}
+
+/// Read a single `Content-Length` framed message, or `None` at end of input.
+fn read_message(reader: &mut dyn BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let mut body = vec![0; length.expect("set above")];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map_err(SvgdxError::from_err)
+}
+
+fn write_message(writer: &mut dyn Write, msg: &Value) -> Result<()> {
+    let body = msg.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Run a language server session, reading requests from `reader` and writing
+/// responses to `writer` until an `exit` notification or end of input.
+pub fn run(reader: &mut dyn BufRead, writer: &mut dyn Write) -> Result<()> {
+    let mut server = LspServer::new(TransformConfig::default());
+    while let Some(msg) = read_message(reader)? {
+        for response in server.handle(&msg) {
+            write_message(writer, &response)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn open(server: &mut LspServer, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///a.svg", "text": text } },
+        }))
+    }
+
+    fn request(server: &mut LspServer, method: &str, line: u32, character: u32) -> Value {
+        let mut resp = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": "file:///a.svg" },
+                "position": { "line": line, "character": character },
+            },
+        }));
+        assert_eq!(resp.len(), 1);
+        resp.remove(0)["result"].take()
+    }
+
+    #[test]
+    fn test_position_offset() {
+        let text = "ab\n😀c\n";
+        assert_eq!(position_offset(text, 0, 0), Some(0));
+        assert_eq!(position_offset(text, 0, 2), Some(2));
+        assert_eq!(position_offset(text, 1, 2), Some(7));
+        assert_eq!(position_offset(text, 1, 3), Some(8));
+        assert_eq!(position_offset(text, 1, 4), None);
+        assert_eq!(position_offset(text, 2, 0), Some(9));
+        assert_eq!(lsp_position(text, 2, 2)["character"], 2);
+        assert_eq!(lsp_position(text, 2, 3)["character"], 3);
+    }
+
+    #[test]
+    fn test_lsp_diagnostics() {
+        let mut server = LspServer::default();
+        let msgs = open(&mut server, "<svg>\n<rect xy=\"#nope\" wh=\"2\"/>\n</svg>");
+        let diags = &msgs[0]["params"]["diagnostics"];
+        assert_eq!(diags.as_array().unwrap().len(), 1);
+        assert_eq!(diags[0]["range"]["start"]["line"], 1);
+        assert_eq!(diags[0]["source"], "svgdx");
+
+        let msgs = open(&mut server, "<svg><rect wh=\"2\"/></svg>");
+        assert!(msgs[0]["params"]["diagnostics"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_lsp_requests() {
+        let mut server = LspServer::default();
+        open(
+            &mut server,
+            "<svg>\n<rect id=\"abc\" wh=\"5\"/>\n<line start=\"#abc\" end=\"9 9\"/>\n</svg>",
+        );
+        let items = request(&mut server, "textDocument/completion", 2, 15);
+        assert_eq!(items[0]["label"], "abc");
+        assert_eq!(items[0]["kind"], 18);
+
+        let def = request(&mut server, "textDocument/definition", 2, 15);
+        assert_eq!(def["range"]["start"], json!({ "line": 1, "character": 0 }));
+
+        let hover = request(&mut server, "textDocument/hover", 2, 15);
+        assert!(hover["contents"]["value"]
+            .as_str()
+            .unwrap()
+            .contains("width: 5"));
+
+        let resp = server.handle(&json!({ "jsonrpc": "2.0", "id": 2, "method": "unknown" }));
+        assert_eq!(resp[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_lsp_run() {
+        let mut input = Vec::new();
+        for msg in [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        ] {
+            write_message(&mut input, &msg).unwrap();
+        }
+        let mut output = Vec::new();
+        run(&mut Cursor::new(input), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Content-Length: "));
+        assert!(output.contains(r#""definitionProvider":true"#));
+        // nothing processed after exit
+        assert!(!output.contains(r#""id":2"#));
+    }
+}
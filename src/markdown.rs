@@ -0,0 +1,237 @@
+//! Rendering of svgdx blocks embedded in Markdown documents.
+//!
+//! Fenced code blocks (using ```` ``` ```` or `~~~`) whose info string starts
+//! with `svgdx` - or `{.svgdx}` as used by Pandoc - are replaced by the result
+//! of rendering their content, allowing diagrams to be written inline in
+//! documentation. Any further text in the info string is available to the
+//! renderer, e.g. for use as alt text.
+//!
+//! ````markdown
+//! ```svgdx Example diagram
+//! <svg>
+//!   <rect wh="20 10" text="Hello"/>
+//! </svg>
+//! ```
+//! ````
+//!
+//! Other fenced blocks are left unchanged, so svgdx examples inside (e.g.)
+//! ```` ````markdown ```` blocks are not rendered.
+
+use crate::errors::{Result, SvgdxError};
+use crate::TransformConfig;
+
+use quick_xml::events::BytesStart;
+
+/// An svgdx block found in a Markdown document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkdownBlock<'a> {
+    /// Content of the block
+    pub source: String,
+    /// Text following `svgdx` in the info string, if any
+    pub info: &'a str,
+    /// Line number of the first content line (1-based)
+    pub line: usize,
+}
+
+impl MarkdownBlock<'_> {
+    /// Apply settings from a `<!-- svgdx ... -->` comment at the start of the
+    /// block to `config`, returning the remainder of the block's source.
+    ///
+    /// Settings are named as for `<config>`, e.g. `<!-- svgdx font-size="4" -->`.
+    /// Blocks without such a comment are returned unchanged.
+    pub fn apply_config(&self, config: &mut TransformConfig) -> Result<&str> {
+        block_config(&self.source, config)
+    }
+}
+
+/// Apply settings from a `<!-- svgdx ... -->` comment at the start of a block,
+/// returning the remainder of the block.
+fn block_config<'a>(source: &'a str, config: &mut TransformConfig) -> Result<&'a str> {
+    let trimmed = source.trim_start();
+    let Some(comment) = trimmed.strip_prefix("<!--") else {
+        return Ok(source);
+    };
+    let Some((comment, rest)) = comment.split_once("-->") else {
+        return Ok(source);
+    };
+    let Some(attrs) = comment.trim().strip_prefix("svgdx") else {
+        return Ok(source);
+    };
+    if !(attrs.is_empty() || attrs.starts_with(char::is_whitespace)) {
+        return Ok(source);
+    }
+    let start = BytesStart::from_content(format!("svgdx{attrs}"), 5);
+    for attr in start.attributes() {
+        let attr = attr.map_err(SvgdxError::from_err)?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        let value = attr.unescape_value().map_err(SvgdxError::from_err)?;
+        config.set(&key, &value).map_err(|e| e.in_attr(&key))?;
+    }
+    Ok(rest)
+}
+
+/// An opening code fence: indentation, fence character and fence length.
+fn fence_start(line: &str) -> Option<(usize, char, usize, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let rest = &line[indent..];
+    let ch = rest.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = rest.len() - rest.trim_start_matches(ch).len();
+    let info = rest[len..].trim();
+    // backtick fences may not have backticks in their info string
+    if len < 3 || (ch == '`' && info.contains('`')) {
+        return None;
+    }
+    Some((indent, ch, len, info))
+}
+
+/// Whether `line` closes a fence of at least `len` `ch` characters.
+fn is_fence_end(line: &str, ch: char, len: usize) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+    let rest = trimmed.trim_start_matches(ch);
+    trimmed.len() - rest.len() >= len && rest.trim().is_empty()
+}
+
+/// The text following `svgdx` in an info string, if the block is an svgdx block.
+fn svgdx_info(info: &str) -> Option<&str> {
+    let rest = match info.strip_prefix('{') {
+        // Pandoc attributes, e.g. `{.svgdx #fig1}`
+        Some(attrs) => attrs.strip_suffix('}')?.trim().strip_prefix(".svgdx")?,
+        None => info.strip_prefix("svgdx")?,
+    };
+    (rest.is_empty() || rest.starts_with(char::is_whitespace)).then(|| rest.trim())
+}
+
+/// HTML for inclusion in Markdown, without blank lines which would end the
+/// HTML block and cause the remainder to be treated as Markdown.
+pub fn markdown_html(html: &str) -> String {
+    html.lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace each svgdx fenced block in a Markdown document with the result of
+/// `render`, leaving the rest of the document unchanged.
+///
+/// The replacement is followed by a newline if the block's closing fence was.
+pub fn filter_markdown<F>(input: &str, mut render: F) -> Result<String>
+where
+    F: FnMut(&MarkdownBlock) -> Result<String>,
+{
+    let mut output = String::with_capacity(input.len());
+    let mut lines = input.split_inclusive('\n').enumerate();
+    while let Some((idx, line)) = lines.next() {
+        let Some((indent, ch, len, info)) = fence_start(line) else {
+            output.push_str(line);
+            continue;
+        };
+        let svgdx = svgdx_info(info);
+        if svgdx.is_none() {
+            output.push_str(line);
+        }
+        let mut content = String::new();
+        let mut closing = None;
+        for (_, line) in lines.by_ref() {
+            if is_fence_end(line, ch, len) {
+                closing = Some(line);
+                break;
+            }
+            // content is de-indented by (up to) the indentation of the fence
+            let strip = (line.len() - line.trim_start_matches(' ').len()).min(indent);
+            content.push_str(&line[strip..]);
+        }
+        let Some(info) = svgdx else {
+            output.push_str(&content);
+            output.push_str(closing.unwrap_or_default());
+            continue;
+        };
+        let Some(closing) = closing else {
+            return Err(SvgdxError::DocumentError(format!(
+                "unterminated svgdx block starting at line {}",
+                idx + 1
+            )));
+        };
+        let block = MarkdownBlock {
+            source: content,
+            info,
+            line: idx + 2,
+        };
+        output.push_str(&render(&block)?);
+        if closing.ends_with('\n') {
+            output.push('\n');
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_config() {
+        let mut config = TransformConfig::default();
+        let rest = block_config(
+            "<!-- svgdx font-size=\"4\" title='A &amp; B' -->\n<svg/>",
+            &mut config,
+        )
+        .unwrap();
+        assert_eq!(rest, "\n<svg/>");
+        assert_eq!(config.font_size, 4.);
+        assert_eq!(config.title.as_deref(), Some("A & B"));
+
+        // other comments are part of the document
+        let source = "<!-- a comment -->\n<svg/>";
+        assert_eq!(block_config(source, &mut config).unwrap(), source);
+        assert!(block_config("<!-- svgdx unknown=\"1\" -->", &mut config).is_err());
+    }
+
+    fn upper(block: &MarkdownBlock) -> Result<String> {
+        Ok(format!(
+            "[{}|{}]",
+            block.info,
+            block.source.trim().to_uppercase()
+        ))
+    }
+
+    #[test]
+    fn test_filter_markdown() {
+        let input = "# Title\n\n```svgdx Alt text\n<rect/>\n```\n\nText\n";
+        assert_eq!(
+            filter_markdown(input, upper).unwrap(),
+            "# Title\n\n[Alt text|<RECT/>]\n\nText\n"
+        );
+
+        // other blocks, including those containing svgdx fences, are unchanged
+        let input = "````markdown\n```svgdx\n<rect/>\n```\n````\n~~~rust\nx\n~~~\n";
+        assert_eq!(filter_markdown(input, upper).unwrap(), input);
+
+        // Pandoc attributes, tilde fences, and longer closing fences
+        let input = "~~~{.svgdx #fig}\n<a/>\n~~~~~\nend";
+        assert_eq!(filter_markdown(input, upper).unwrap(), "[#fig|<A/>]\nend");
+
+        // indented fences
+        let input = "  ```svgdx\n  <a/>\n    <b/>\n  ```\n";
+        let mut sources = Vec::new();
+        filter_markdown(input, |b| {
+            sources.push((b.source.clone(), b.line));
+            Ok(String::new())
+        })
+        .unwrap();
+        assert_eq!(sources, [("<a/>\n  <b/>\n".to_string(), 2)]);
+
+        // 'svgdxfoo' is not svgdx
+        let input = "```svgdxfoo\n<a/>\n```\n";
+        assert_eq!(filter_markdown(input, upper).unwrap(), input);
+
+        assert!(filter_markdown("```svgdx\n<a/>\n", upper).is_err());
+        // render errors are propagated
+        assert!(filter_markdown("```svgdx\n```\n", |_| Err("bad".into())).is_err());
+    }
+}
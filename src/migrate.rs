@@ -0,0 +1,146 @@
+//! Migration of svgdx source documents from syntax used by earlier releases.
+//!
+//! The following changes are applied to attributes:
+//!
+//! - relative position separator ':' becomes '|' (`#abc:h` => `#abc|h`), as of v0.18.0
+//! - elref scalar separator '.' becomes '~' (`#abc.w` => `#abc~w`), as of v0.18.0
+//! - `text-inset` is renamed to `text-offset`
+//!
+//! Everything else in the document - including whitespace, comments and text
+//! content - is left unchanged.
+
+use crate::constants::{ELREF_ID_PREFIX, ELREF_PREVIOUS};
+use crate::errors::{Result, SvgdxError};
+use crate::position::ScalarSpec;
+use crate::types::extract_elref;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+/// Attributes which have been renamed; `(old, new)`.
+const RENAMED_ATTRS: &[(&str, &str)] = &[("text-inset", "text-offset")];
+
+/// Rewrite any deprecated elref syntax in an attribute value.
+fn migrate_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(pos) = rest.find([ELREF_ID_PREFIX, ELREF_PREVIOUS]) {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        let Ok((_, remain)) = extract_elref(rest) else {
+            // not an elref; skip the prefix character
+            out.push_str(&rest[..1]);
+            rest = &rest[1..];
+            continue;
+        };
+        out.push_str(&rest[..rest.len() - remain.len()]);
+        rest = remain;
+
+        let word_len = |s: &str| s.find(|c: char| !c.is_alphanumeric()).unwrap_or(s.len());
+        if let Some(tail) = rest.strip_prefix('.') {
+            let scalar = &tail[..word_len(tail)];
+            if scalar.parse::<ScalarSpec>().is_ok() {
+                out.push('~');
+                rest = tail;
+            }
+        } else if let Some(tail) = rest.strip_prefix(':') {
+            if matches!(&tail[..word_len(tail)], "h" | "H" | "v" | "V") {
+                out.push('|');
+                rest = tail;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rewrite the attributes of a raw start (or empty) tag, e.g. `<rect xy="#a:h">`.
+fn migrate_tag(tag: &str) -> String {
+    let mut out = String::with_capacity(tag.len());
+    let mut rest = tag;
+    while let Some(pos) = rest.find(['"', '\'']) {
+        let (before, quoted) = rest.split_at(pos);
+        let quote = &quoted[..1];
+        let Some(end) = quoted[1..].find(quote) else {
+            break;
+        };
+        // `before` ends with the attribute name (and `=`)
+        let mut before = before.to_string();
+        for (old, new) in RENAMED_ATTRS {
+            let trimmed = before.trim_end().trim_end_matches('=').trim_end();
+            if trimmed.ends_with(old)
+                && trimmed[..trimmed.len() - old.len()].ends_with(char::is_whitespace)
+            {
+                let start = trimmed.len() - old.len();
+                before.replace_range(start..trimmed.len(), new);
+            }
+        }
+        out.push_str(&before);
+        out.push_str(quote);
+        out.push_str(&migrate_value(&quoted[1..end + 1]));
+        out.push_str(quote);
+        rest = &quoted[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Rewrite svgdx source `input` which may use syntax from earlier releases.
+pub fn migrate_source(input: &str) -> Result<String> {
+    let mut reader = Reader::from_str(input);
+    let mut output = String::with_capacity(input.len());
+    loop {
+        let start = reader.buffer_position() as usize;
+        let ev = reader.read_event().map_err(|e| {
+            let line = input[..start].matches('\n').count() + 1;
+            SvgdxError::ParseError(format!("XML error near line {line}: {e:?}"))
+        })?;
+        let raw = &input[start..reader.buffer_position() as usize];
+        match ev {
+            Event::Eof => break,
+            Event::Start(_) | Event::Empty(_) => output.push_str(&migrate_tag(raw)),
+            _ => output.push_str(raw),
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_value() {
+        assert_eq!(migrate_value("#abc:h"), "#abc|h");
+        assert_eq!(migrate_value("#abc:V 2"), "#abc|V 2");
+        assert_eq!(migrate_value("^:v"), "^|v");
+        assert_eq!(migrate_value("#abc.w"), "#abc~w");
+        assert_eq!(migrate_value("^2.height"), "^2~height");
+        assert_eq!(migrate_value("{{#a.h + #b.x2}}"), "{{#a~h + #b~x2}}");
+        // already-current or unrelated syntax is unchanged
+        assert_eq!(migrate_value("#abc|h"), "#abc|h");
+        assert_eq!(migrate_value("#abc~w"), "#abc~w");
+        assert_eq!(migrate_value("#abc@tl"), "#abc@tl");
+        assert_eq!(migrate_value("#abc:def"), "#abc:def");
+        assert_eq!(migrate_value("#abc.foo"), "#abc.foo");
+        assert_eq!(migrate_value("#93a1a1"), "#93a1a1");
+        assert_eq!(migrate_value("1.5"), "1.5");
+    }
+
+    #[test]
+    fn test_migrate_source() {
+        let input = r##"<svg>
+  <!-- #a:h is kept in comments -->
+  <rect id="a" wh="10"  text-inset = '2'/>
+  <rect xy="#a:h" width="#a.w"/>
+</svg>"##;
+        let expected = r##"<svg>
+  <!-- #a:h is kept in comments -->
+  <rect id="a" wh="10"  text-offset = '2'/>
+  <rect xy="#a|h" width="#a~w"/>
+</svg>"##;
+        assert_eq!(migrate_source(input).unwrap(), expected);
+        // migration is idempotent
+        assert_eq!(migrate_source(expected).unwrap(), expected);
+    }
+}
@@ -0,0 +1,203 @@
+//! Structured outline of an SVG document as JSON.
+//!
+//! The outline gives the hierarchy of containers and identified elements,
+//! along with any titles and text content, e.g.
+//!
+//! ```json
+//! {"element": "svg", "children": [
+//!   {"element": "g", "id": "grp", "title": "Group", "children": [
+//!     {"element": "text", "text": "Hello"}
+//!   ]}
+//! ]}
+//! ```
+//!
+//! Elements without an id, title or text content (and without such descendants)
+//! are omitted, as is content which is not rendered (e.g. within `<defs>`).
+
+use crate::errors::{Result, SvgdxError};
+use crate::geometry::NON_RENDERED;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+#[derive(Debug, Default)]
+struct OutlineNode {
+    element: String,
+    id: Option<String>,
+    title: Option<String>,
+    text: String,
+    children: Vec<OutlineNode>,
+}
+
+impl OutlineNode {
+    fn is_empty(&self) -> bool {
+        self.id.is_none()
+            && self.title.is_none()
+            && self.text.is_empty()
+            && self.children.is_empty()
+    }
+
+    fn write_json(&self, out: &mut String, indent: usize) {
+        let pad = "  ".repeat(indent);
+        out.push_str(&format!("{pad}{{\"element\": {}", json_str(&self.element)));
+        if let Some(id) = &self.id {
+            out.push_str(&format!(", \"id\": {}", json_str(id)));
+        }
+        if let Some(title) = &self.title {
+            out.push_str(&format!(", \"title\": {}", json_str(title)));
+        }
+        if !self.text.is_empty() {
+            out.push_str(&format!(", \"text\": {}", json_str(&self.text)));
+        }
+        if !self.children.is_empty() {
+            out.push_str(", \"children\": [\n");
+            for (idx, child) in self.children.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(",\n");
+                }
+                child.write_json(out, indent + 1);
+            }
+            out.push_str(&format!("\n{pad}]"));
+        }
+        out.push('}');
+    }
+}
+
+/// Quote and escape `s` as a JSON string.
+pub(crate) fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Collapse whitespace (including non-breaking spaces used by svgdx to retain
+/// spacing in text) into single spaces.
+fn normalize_ws(s: &str) -> String {
+    s.split(|c: char| c.is_whitespace() || c == '\u{a0}')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Extract a structured outline of an SVG document, typically the output of
+/// `transform_str()`, as JSON.
+pub fn svg_outline(svg: &str) -> Result<String> {
+    let mut reader = Reader::from_str(svg);
+    let mut stack: Vec<OutlineNode> = Vec::new();
+    let mut root = None;
+    // Depth within the stack at which non-rendered content starts
+    let mut hidden_depth: Option<usize> = None;
+    loop {
+        let ev = reader
+            .read_event()
+            .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?;
+        let mut closed = None;
+        match &ev {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) => {
+                let element = String::from_utf8(e.name().as_ref().to_vec())?;
+                if hidden_depth.is_none() && NON_RENDERED.contains(&element.as_str()) {
+                    hidden_depth = Some(stack.len());
+                }
+                let id = match e.try_get_attribute("id").map_err(SvgdxError::from_err)? {
+                    Some(a) => Some(a.unescape_value().map_err(SvgdxError::from_err)?.into()),
+                    None => None,
+                };
+                let node = OutlineNode {
+                    element,
+                    id,
+                    ..Default::default()
+                };
+                if matches!(ev, Event::Start(_)) {
+                    stack.push(node);
+                } else {
+                    closed = Some(node);
+                }
+            }
+            Event::End(_) => closed = stack.pop(),
+            Event::Text(t) => {
+                let text = t.unescape().map_err(SvgdxError::from_err)?;
+                // text content is attributed to the nearest text or title element
+                if let Some(node) = stack
+                    .iter_mut()
+                    .rev()
+                    .find(|n| n.element == "text" || n.element == "title")
+                {
+                    node.text.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(mut node) = closed {
+            node.text = normalize_ws(&node.text);
+            let hidden = hidden_depth.is_some_and(|d| stack.len() >= d);
+            if hidden_depth == Some(stack.len()) {
+                hidden_depth = None;
+            }
+            if stack.is_empty() {
+                root = Some(node);
+            } else if hidden || node.is_empty() {
+                continue;
+            } else if node.element == "title" {
+                let parent = stack.last_mut().expect("non-empty stack");
+                parent.title.get_or_insert(node.text);
+            } else {
+                stack
+                    .last_mut()
+                    .expect("non-empty stack")
+                    .children
+                    .push(node);
+            }
+        }
+    }
+    let root = root.ok_or_else(|| SvgdxError::from("No root element found"))?;
+    let mut output = String::new();
+    root.write_json(&mut output, 0);
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_str() {
+        assert_eq!(json_str("abc"), r#""abc""#);
+        assert_eq!(json_str("a=\"1\"\n\t\\\u{1}é"), r#""a=\"1\"\n\t\\\u0001é""#);
+    }
+
+    #[test]
+    fn test_svg_outline() {
+        let svg = r##"<svg>
+<title>Diagram</title>
+<defs><rect id="hidden" width="1" height="1"/></defs>
+<g id="grp"><title>A "group"</title>
+  <rect id="a" width="1" height="1"/>
+  <rect width="1" height="1"/>
+  <text x="0" y="0"><tspan>Hello</tspan>&#160;<tspan>world &amp; all</tspan></text>
+</g>
+<g><circle r="1"/></g>
+</svg>"##;
+        let expected = r#"{"element": "svg", "title": "Diagram", "children": [
+  {"element": "g", "id": "grp", "title": "A \"group\"", "children": [
+    {"element": "rect", "id": "a"},
+    {"element": "text", "text": "Hello world & all"}
+  ]}
+]}
+"#;
+        assert_eq!(svg_outline(svg).unwrap(), expected);
+    }
+}
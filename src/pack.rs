@@ -0,0 +1,187 @@
+//! Packing of multiple elements `inside` a single region.
+//!
+//! Elements with both `inside="#region"` and `pack="..."` attributes are
+//! placed one after another within the region rather than each being sized
+//! to fill it. Supported modes are:
+//!
+//! - `flow [gap]` - left-to-right, wrapping onto a new row when the region
+//!   width is exceeded.
+//! - `grid <cols> [gap]` - a fixed number of equal-width columns, with each
+//!   element centered horizontally within its cell.
+
+use crate::context::{ElementMap, TransformerContext};
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::position::{BoundingBox, TrblLength};
+use crate::types::{attr_split, fstr, strp, ElRef};
+
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PackMode {
+    Flow { gap: f32 },
+    Grid { cols: usize, gap: f32 },
+}
+
+impl FromStr for PackMode {
+    type Err = SvgdxError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let parts: Vec<String> = attr_split(value).collect();
+        let gap = |idx: usize| parts.get(idx).map_or(Ok(0.), |g| strp(g));
+        match parts.first().map(|s| s.as_str()) {
+            Some("flow") if parts.len() <= 2 => Ok(Self::Flow { gap: gap(1)? }),
+            Some("grid") if (2..=3).contains(&parts.len()) => {
+                let cols = parts[1]
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&c| c > 0)
+                    .ok_or_else(|| {
+                        SvgdxError::InvalidData(format!("Invalid grid columns in pack '{value}'"))
+                    })?;
+                Ok(Self::Grid { cols, gap: gap(2)? })
+            }
+            _ => Err(SvgdxError::InvalidData(format!(
+                "Invalid pack mode '{value}'"
+            ))),
+        }
+    }
+}
+
+/// Placement state for a single region.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackState {
+    /// Number of elements placed so far
+    count: usize,
+    /// Offset of the next element from the region's left edge (flow mode)
+    x: f32,
+    /// Offset of the current row from the region's top edge
+    y: f32,
+    /// Height of the tallest element in the current row
+    row_height: f32,
+}
+
+impl PackState {
+    /// Determine the top-left position of an element of size `w` x `h`, returning
+    /// the position and the updated state.
+    fn place(&self, mode: PackMode, region: &BoundingBox, w: f32, h: f32) -> ((f32, f32), Self) {
+        let mut next = self.clone();
+        let pos = match mode {
+            PackMode::Flow { gap } => {
+                if next.count > 0 && next.x + w > region.width() {
+                    next.x = 0.;
+                    next.y += next.row_height + gap;
+                    next.row_height = 0.;
+                }
+                let pos = (region.x1 + next.x, region.y1 + next.y);
+                next.x += w + gap;
+                pos
+            }
+            PackMode::Grid { cols, gap } => {
+                let col = next.count % cols;
+                if col == 0 && next.count > 0 {
+                    next.y += next.row_height + gap;
+                    next.row_height = 0.;
+                }
+                let cell_w = (region.width() - gap * (cols - 1) as f32) / cols as f32;
+                let x = region.x1 + col as f32 * (cell_w + gap) + (cell_w - w) / 2.;
+                (x, region.y1 + next.y)
+            }
+        };
+        next.row_height = next.row_height.max(h);
+        next.count += 1;
+        (pos, next)
+    }
+}
+
+/// If `el` is to be packed inside a region, replace its `inside` / `pack`
+/// attributes with an explicit position.
+///
+/// Returns the region key and updated state, which should be committed with
+/// `TransformerContext::set_pack_state()` once the element has been processed.
+pub fn pack_element(
+    el: &mut SvgElement,
+    ctx: &TransformerContext,
+) -> Result<Option<(String, PackState)>> {
+    let (Some(pack), Some(inside)) = (el.get_attr("pack"), el.get_attr("inside")) else {
+        return Ok(None);
+    };
+    let mode: PackMode = pack.parse()?;
+    let elref: ElRef = inside.trim().parse()?;
+    let region_el = ctx
+        .get_element(&elref)
+        .ok_or_else(|| SvgdxError::ReferenceError(elref.clone()))?;
+    let mut region = ctx
+        .get_element_bbox(region_el)?
+        .ok_or_else(|| SvgdxError::MissingBoundingBox(region_el.to_string()))?;
+    if let Some(margin) = el.get_attr("margin") {
+        let margin: TrblLength = margin.parse()?;
+        region.shrink_trbl_length(margin);
+    }
+
+    // Determine the element's own size
+    let mut sized = el.clone();
+    sized.remove_attrs(&["pack", "inside", "margin", "xy", "xy-loc"]);
+    sized.resolve_position(ctx)?;
+    let bbox = ctx
+        .get_element_bbox(&sized)?
+        .ok_or_else(|| SvgdxError::MissingBoundingBox(el.to_string()))?;
+
+    let key = elref.to_string();
+    let state = ctx.get_pack_state(&key).cloned().unwrap_or_default();
+    let ((x, y), next) = state.place(mode, &region, bbox.width(), bbox.height());
+
+    el.remove_attrs(&["pack", "inside", "margin"]);
+    el.set_attr("xy", &format!("{} {}", fstr(x), fstr(y)));
+    el.set_attr("xy-loc", "tl");
+    el.add_class("d-inside");
+    Ok(Some((key, next)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_mode() {
+        assert_eq!(
+            "flow".parse::<PackMode>().unwrap(),
+            PackMode::Flow { gap: 0. }
+        );
+        assert_eq!(
+            "grid 3 2".parse::<PackMode>().unwrap(),
+            PackMode::Grid { cols: 3, gap: 2. }
+        );
+        assert!("grid".parse::<PackMode>().is_err());
+        assert!("grid 0".parse::<PackMode>().is_err());
+        assert!("stack".parse::<PackMode>().is_err());
+    }
+
+    #[test]
+    fn test_pack_flow() {
+        let region = BoundingBox::new(0., 0., 25., 100.);
+        let mode = PackMode::Flow { gap: 1. };
+        let state = PackState::default();
+        let (pos, state) = state.place(mode, &region, 10., 5.);
+        assert_eq!(pos, (0., 0.));
+        let (pos, state) = state.place(mode, &region, 10., 8.);
+        assert_eq!(pos, (11., 0.));
+        // wraps to next row, below tallest element
+        let (pos, _) = state.place(mode, &region, 10., 5.);
+        assert_eq!(pos, (0., 9.));
+    }
+
+    #[test]
+    fn test_pack_grid() {
+        let region = BoundingBox::new(0., 0., 32., 100.);
+        let mode = PackMode::Grid { cols: 3, gap: 1. };
+        let state = PackState::default();
+        let (pos, state) = state.place(mode, &region, 4., 5.);
+        assert_eq!(pos, (3., 0.));
+        let (pos, state) = state.place(mode, &region, 10., 5.);
+        assert_eq!(pos, (11., 0.));
+        let (_, state) = state.place(mode, &region, 10., 5.);
+        let (pos, _) = state.place(mode, &region, 10., 5.);
+        assert_eq!(pos, (0., 6.));
+    }
+}
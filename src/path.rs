@@ -12,6 +12,110 @@ struct PathParser {
     min_y: f32,
     max_x: f32,
     max_y: f32,
+    /// Start of the current subpath, the target of any 'Z' command
+    subpath_start: Option<(f32, f32)>,
+    /// Last control point of a preceding cubic or quadratic curve, for S and T
+    cubic_ctrl: Option<(f32, f32)>,
+    quad_ctrl: Option<(f32, f32)>,
+    /// Polyline approximation of each subpath
+    polylines: Vec<Vec<(f32, f32)>>,
+}
+
+/// Number of line segments used to approximate each curve or arc.
+const CURVE_STEPS: usize = 32;
+
+fn cubic_points(
+    p0: (f32, f32),
+    p1: (f32, f32),
+    p2: (f32, f32),
+    p3: (f32, f32),
+) -> impl Iterator<Item = (f32, f32)> {
+    (1..=CURVE_STEPS).map(move |i| {
+        let t = i as f32 / CURVE_STEPS as f32;
+        let mt = 1. - t;
+        let (a, b, c, d) = (mt * mt * mt, 3. * mt * mt * t, 3. * mt * t * t, t * t * t);
+        (
+            a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+            a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+        )
+    })
+}
+
+fn quad_points(p0: (f32, f32), p1: (f32, f32), p2: (f32, f32)) -> impl Iterator<Item = (f32, f32)> {
+    (1..=CURVE_STEPS).map(move |i| {
+        let t = i as f32 / CURVE_STEPS as f32;
+        let mt = 1. - t;
+        let (a, b, c) = (mt * mt, 2. * mt * t, t * t);
+        (
+            a * p0.0 + b * p1.0 + c * p2.0,
+            a * p0.1 + b * p1.1 + c * p2.1,
+        )
+    })
+}
+
+/// Points along an elliptical arc, using the endpoint to center conversion
+/// given in the SVG specification (appendix B.2.4).
+fn arc_points(
+    p0: (f32, f32),
+    (rx, ry): (f32, f32),
+    x_axis_rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    p1: (f32, f32),
+) -> Vec<(f32, f32)> {
+    if p0 == p1 {
+        // "the arc is omitted entirely"
+        return Vec::new();
+    }
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0. || ry == 0. {
+        // treated as a straight line
+        return vec![p1];
+    }
+    let (sin_phi, cos_phi) = x_axis_rotation.to_radians().sin_cos();
+    let (dx, dy) = ((p0.0 - p1.0) / 2., (p0.1 - p1.1) / 2.);
+    let x1 = cos_phi * dx + sin_phi * dy;
+    let y1 = -sin_phi * dx + cos_phi * dy;
+    // Scale up radii which are too small to span the endpoints
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1. {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coef = (num / den).max(0.).sqrt();
+    if large_arc == sweep {
+        coef = -coef;
+    }
+    let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (p0.0 + p1.0) / 2.;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (p0.1 + p1.1) / 2.;
+
+    let angle =
+        |(ux, uy): (f32, f32), (vx, vy): (f32, f32)| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let u = ((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let v = ((-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    let theta = angle((1., 0.), u);
+    let mut delta = angle(u, v);
+    if !sweep && delta > 0. {
+        delta -= std::f32::consts::TAU;
+    } else if sweep && delta < 0. {
+        delta += std::f32::consts::TAU;
+    }
+    let mut points: Vec<_> = (1..CURVE_STEPS)
+        .map(|i| {
+            let t = theta + delta * i as f32 / CURVE_STEPS as f32;
+            let (x, y) = (rx * t.cos(), ry * t.sin());
+            (
+                cos_phi * x - sin_phi * y + cx,
+                sin_phi * x + cos_phi * y + cy,
+            )
+        })
+        .collect();
+    // Use the exact endpoint rather than accumulating rounding errors
+    points.push(p1);
+    points
 }
 
 impl PathParser {
@@ -26,6 +130,10 @@ impl PathParser {
             min_y: 0.,
             max_x: 0.,
             max_y: 0.,
+            subpath_start: None,
+            cubic_ctrl: None,
+            quad_ctrl: None,
+            polylines: Vec::new(),
         }
     }
 
@@ -132,6 +240,27 @@ impl PathParser {
         }
     }
 
+    /// Start a new subpath at the given position.
+    fn move_to(&mut self, pos: (f32, f32)) {
+        self.update_position(pos);
+        self.subpath_start = Some(pos);
+        self.polylines.push(vec![pos]);
+    }
+
+    /// Extend the current subpath with the given (already absolute) points;
+    /// the last point becomes the current position.
+    fn draw_to(&mut self, points: impl IntoIterator<Item = (f32, f32)>) {
+        let cur = self.position.unwrap_or((0., 0.));
+        if self.subpath_start.is_none() {
+            self.subpath_start = Some(cur);
+            self.polylines.push(vec![cur]);
+        }
+        let polyline = self.polylines.last_mut().expect("subpath started");
+        polyline.extend(points);
+        let end = *polyline.last().expect("non-empty polyline");
+        self.update_position(end);
+    }
+
     fn process_instruction(&mut self) -> Result<()> {
         if self.command.is_none() {
             self.command = Some(self.read_command()?);
@@ -143,90 +272,86 @@ impl PathParser {
             self.command = Some(command);
         }
 
-        match self.command.expect("Command should be already set") {
-            'M' | 'L' | 'T' => {
+        let command = self.command.expect("Command should be already set");
+        let cur = self.position.unwrap_or((0., 0.));
+        let abs = |(x, y): (f32, f32)| {
+            if command.is_ascii_lowercase() {
+                (cur.0 + x, cur.1 + y)
+            } else {
+                (x, y)
+            }
+        };
+        // Control points are only reflected for S/s and T/t following a curve
+        // of the same type; otherwise the current position is used.
+        let reflect =
+            |ctrl: Option<(f32, f32)>| ctrl.map_or(cur, |(x, y)| (2. * cur.0 - x, 2. * cur.1 - y));
+        let (cubic_ctrl, quad_ctrl) = (self.cubic_ctrl.take(), self.quad_ctrl.take());
+        match command.to_ascii_uppercase() {
+            'M' => {
                 // "(x y)+"
-                let xy = self.read_coord()?;
-                self.update_position(xy);
+                let xy = abs(self.read_coord()?);
+                self.move_to(xy);
+                // "If a moveto is followed by multiple pairs of coordinates,
+                // the subsequent pairs are treated as implicit lineto commands."
+                self.command = Some(if command == 'M' { 'L' } else { 'l' });
             }
-            'm' | 'l' | 't' => {
-                let (dx, dy) = self.read_coord()?;
-                let (cpx, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx + dx, cpy + dy));
+            'L' => {
+                let xy = abs(self.read_coord()?);
+                self.draw_to([xy]);
             }
             'H' => {
-                let new_x = self.read_number()?;
-                let (_, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((new_x, cpy));
-            }
-            'h' => {
-                let dx = self.read_number()?;
-                let (cpx, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx + dx, cpy));
+                let (x, _) = abs((self.read_number()?, 0.));
+                self.draw_to([(x, cur.1)]);
             }
             'V' => {
-                let new_y = self.read_number()?;
-                let (cpx, _) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx, new_y));
-            }
-            'v' => {
-                let dy = self.read_number()?;
-                let (cpx, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx, cpy + dy));
+                let (_, y) = abs((0., self.read_number()?));
+                self.draw_to([(cur.0, y)]);
             }
-            'Z' | 'z' => {
-                self.update_position(self.start_pos.ok_or_else(|| {
+            'Z' => {
+                let start = self.subpath_start.ok_or_else(|| {
                     SvgdxError::InvalidData("Cannot 'z' without start position".to_owned())
-                })?);
+                })?;
+                self.draw_to([start]);
+                // Any following drawing commands start a new subpath here
+                self.polylines.push(vec![start]);
             }
             'C' => {
-                let _cp1 = self.read_coord()?; // control point 1
-                let _cp2 = self.read_coord()?; // control point 2
-                let xy = self.read_coord()?;
-                self.update_position(xy);
+                // "(x1 y1 x2 y2 x y)+"
+                let cp1 = abs(self.read_coord()?);
+                let cp2 = abs(self.read_coord()?);
+                let xy = abs(self.read_coord()?);
+                self.draw_to(cubic_points(cur, cp1, cp2, xy));
+                self.cubic_ctrl = Some(cp2);
             }
-            'c' => {
-                let _cp1 = self.read_coord()?; // control point 1
-                let _cp2 = self.read_coord()?; // control point 2
-                let (dx, dy) = self.read_coord()?;
-                let (cpx, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx + dx, cpy + dy));
+            'S' => {
+                // "(x2 y2 x y)+"
+                let cp2 = abs(self.read_coord()?);
+                let xy = abs(self.read_coord()?);
+                self.draw_to(cubic_points(cur, reflect(cubic_ctrl), cp2, xy));
+                self.cubic_ctrl = Some(cp2);
             }
-            'S' | 'Q' => {
-                // S: "(x2 y2 x y)+"
-                // Q: "(x1 y1 x y)+"
-                // Both 'S' and 'Q' have the same format - a single control point followed by the
-                // target point - so deal with both together. They are semantically different though...
-                let _cp = self.read_coord()?; // control point
-                let xy = self.read_coord()?;
-                self.update_position(xy);
+            'Q' => {
+                // "(x1 y1 x y)+"
+                let cp = abs(self.read_coord()?);
+                let xy = abs(self.read_coord()?);
+                self.draw_to(quad_points(cur, cp, xy));
+                self.quad_ctrl = Some(cp);
             }
-            's' | 'q' => {
-                // s: "(x2 y2 x y)+"
-                // q: "(x1 y1 x y)+"
-                let _cp = self.read_coord()?; // control point
-                let (dx, dy) = self.read_coord()?;
-                let (cpx, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx + dx, cpy + dy));
+            'T' => {
+                // "(x y)+"
+                let cp = reflect(quad_ctrl);
+                let xy = abs(self.read_coord()?);
+                self.draw_to(quad_points(cur, cp, xy));
+                self.quad_ctrl = Some(cp);
             }
             'A' => {
                 // "(rx ry x-axis-rotation large-arc-flag sweep-flag x y)+"
-                let _rxy = self.read_coord()?;
-                let _xar = self.read_number()?;
-                let _laf = self.read_number()?;
-                let _sf = self.read_number()?;
-                let xy = self.read_coord()?;
-                self.update_position(xy);
-            }
-            'a' => {
-                // "(rx ry x-axis-rotation large-arc-flag sweep-flag x y)+"
-                let _rxy = self.read_coord()?;
-                let _xar = self.read_number()?;
-                let _laf = self.read_number()?;
-                let _sf = self.read_number()?;
-                let (dx, dy) = self.read_coord()?;
-                let (cpx, cpy) = self.position.unwrap_or((0., 0.));
-                self.update_position((cpx + dx, cpy + dy));
+                let rxy = self.read_coord()?;
+                let xar = self.read_number()?;
+                let laf = self.read_number()?;
+                let sf = self.read_number()?;
+                let xy = abs(self.read_coord()?);
+                self.draw_to(arc_points(cur, rxy, xar, laf != 0., sf != 0., xy));
             }
             _ => Err(SvgdxError::InvalidData(
                 "Unknown path data instruction".to_string(),
@@ -242,6 +367,61 @@ impl PathParser {
         }
         Ok(())
     }
+
+    /// Evaluate the path data, skipping any malformed segments.
+    ///
+    /// On error, data is skipped up to the next command letter and processing
+    /// continues. Returns the path data with malformed segments removed, together
+    /// with a warning for each skipped segment.
+    fn evaluate_recover(&mut self) -> (String, Vec<String>) {
+        let mut kept = String::new();
+        let mut warnings = Vec::new();
+        self.skip_whitespace();
+        let mut seg_start = self.index;
+        while !self.at_end() {
+            let ins_start = self.index;
+            let saved = (
+                self.position,
+                self.start_pos,
+                self.command,
+                (self.min_x, self.min_y, self.max_x, self.max_y),
+            );
+            if let Err(err) = self.process_instruction() {
+                // Restore state as at start of this instruction; any partially
+                // processed segment is discarded.
+                let bounds;
+                (self.position, self.start_pos, self.command, bounds) = saved;
+                (self.min_x, self.min_y, self.max_x, self.max_y) = bounds;
+                kept.extend(&self.data[seg_start..ins_start]);
+                warnings.push(format!(
+                    "skipped malformed path data at offset {ins_start}: {err}"
+                ));
+                // Skip past the failing position to the next command letter
+                self.index = ins_start + 1;
+                while !self.at_end() && !"MmLlHhVvZzCcSsQqTtAa".contains(self.data[self.index]) {
+                    self.index += 1;
+                }
+                self.command = None;
+                seg_start = self.index;
+            }
+        }
+        kept.extend(&self.data[seg_start..self.index]);
+        (kept.trim().to_string(), warnings)
+    }
+}
+
+/// Remove malformed segments from path data.
+///
+/// Returns the recovered path data and a warning for each skipped segment;
+/// if there are no warnings the path data is returned unchanged.
+pub fn recover_path_data(path_data: &str) -> (String, Vec<String>) {
+    let mut pp = PathParser::new(path_data);
+    let (kept, warnings) = pp.evaluate_recover();
+    if warnings.is_empty() {
+        (path_data.to_string(), warnings)
+    } else {
+        (kept, warnings)
+    }
 }
 
 pub fn path_bbox(element: &SvgElement) -> Result<Option<BoundingBox>> {
@@ -254,6 +434,129 @@ pub fn path_bbox(element: &SvgElement) -> Result<Option<BoundingBox>> {
     }
 }
 
+/// Polyline approximation of the outline of a path or line-like element,
+/// as a list of subpaths.
+pub(crate) fn element_polylines(element: &SvgElement) -> Result<Vec<Vec<(f32, f32)>>> {
+    if element.name == "path" {
+        if let Some(path_data) = element.get_attr("d") {
+            let mut pp = PathParser::new(&path_data);
+            pp.evaluate()?;
+            return Ok(pp.polylines);
+        }
+    } else if let Some(vertices) = element.vertices() {
+        return Ok(vec![vertices]);
+    }
+    Err(SvgdxError::InvalidData(format!(
+        "Element has no path data: {element}"
+    )))
+}
+
+/// Fractions along the segment from `a` to `b` at which it crosses the
+/// outline of the given circle, ellipse, polygon or path, in ascending order.
+///
+/// Circles and ellipses are intersected exactly; paths use the same polyline
+/// approximation as `path_length()`.
+pub fn outline_crossings(element: &SvgElement, a: (f32, f32), b: (f32, f32)) -> Result<Vec<f32>> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let mut crossings = if let "circle" | "ellipse" = element.name.as_str() {
+        // centre and radii from the bbox, so default `cx`/`cy` are honoured
+        let bb = element.bbox()?.ok_or_else(|| {
+            SvgdxError::MissingBoundingBox(format!("Cannot determine outline of {element}"))
+        })?;
+        let (cx, cy) = bb.center();
+        let (rx, ry) = (bb.width() / 2., bb.height() / 2.);
+        // solve |((a - c) + t * d) / r| = 1 for t
+        let (px, py) = ((a.0 - cx) / rx, (a.1 - cy) / ry);
+        let (qx, qy) = (dx / rx, dy / ry);
+        let (qa, qb, qc) = (
+            qx * qx + qy * qy,
+            2. * (px * qx + py * qy),
+            px * px + py * py - 1.,
+        );
+        let disc = qb * qb - 4. * qa * qc;
+        if qa == 0. || disc < 0. {
+            Vec::new()
+        } else {
+            let root = disc.sqrt();
+            vec![(-qb - root) / (2. * qa), (-qb + root) / (2. * qa)]
+        }
+    } else {
+        segments(&element_polylines(element)?)
+            .filter_map(|(p, q)| {
+                // solve a + t * d = p + u * (q - p)
+                let (ex, ey) = (q.0 - p.0, q.1 - p.1);
+                let denom = dx * ey - dy * ex;
+                if denom == 0. {
+                    return None;
+                }
+                let (fx, fy) = (p.0 - a.0, p.1 - a.1);
+                let t = (fx * ey - fy * ex) / denom;
+                let u = (fx * dy - fy * dx) / denom;
+                (0. ..=1.).contains(&u).then_some(t)
+            })
+            .collect()
+    };
+    crossings.retain(|t| (0. ..=1.).contains(t));
+    crossings.sort_by(f32::total_cmp);
+    Ok(crossings)
+}
+
+fn segments(polylines: &[Vec<(f32, f32)>]) -> impl Iterator<Item = ((f32, f32), (f32, f32))> + '_ {
+    polylines
+        .iter()
+        .flat_map(|p| p.iter().copied().zip(p.iter().copied().skip(1)))
+}
+
+fn seg_len((a, b): ((f32, f32), (f32, f32))) -> f32 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Total length of a path or line-like element (line, polyline, polygon).
+///
+/// Curves and arcs are approximated by line segments, so the result may
+/// differ very slightly from the exact length.
+pub fn path_length(element: &SvgElement) -> Result<f32> {
+    Ok(segments(&element_polylines(element)?).map(seg_len).sum())
+}
+
+/// Point at fraction `t` (clamped to 0..1) of the total length along a path
+/// or line-like element.
+///
+/// Any gaps between subpaths (i.e. due to 'moveto' commands) do not count
+/// towards the length.
+pub fn path_point_at(element: &SvgElement, t: f32) -> Result<(f32, f32)> {
+    path_point_dir_at(element, t).map(|(point, _)| point)
+}
+
+/// Point at fraction `t` along a path or line-like element as for
+/// `path_point_at()`, together with the unit direction of the path at
+/// that point (`(0, 0)` if the path has no length).
+pub fn path_point_dir_at(element: &SvgElement, t: f32) -> Result<((f32, f32), (f32, f32))> {
+    let polylines = element_polylines(element)?;
+    let total: f32 = segments(&polylines).map(seg_len).sum();
+    let mut remain = total * t.clamp(0., 1.);
+    let mut last = None;
+    for (a, b) in segments(&polylines) {
+        let len = seg_len((a, b));
+        if len > 0. {
+            let dir = ((b.0 - a.0) / len, (b.1 - a.1) / len);
+            if remain <= len {
+                let t = remain / len;
+                return Ok(((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t), dir));
+            }
+            last = Some((b, dir));
+        }
+        remain -= len;
+    }
+    last.or_else(|| {
+        polylines
+            .first()
+            .and_then(|p| p.first().copied())
+            .map(|p| (p, (0., 0.)))
+    })
+    .ok_or_else(|| SvgdxError::InvalidData(format!("Element has no path data: {element}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -407,4 +710,83 @@ mod tests {
         pp.evaluate().unwrap();
         assert_eq!(pp.get_bbox(), Some(BoundingBox::new(10., 30., 50., 30.)));
     }
+
+    #[test]
+    fn test_pp_recover() {
+        let mut pp = PathParser::new("M10 20 L 30 x 40 L 50 60");
+        assert!(pp.evaluate().is_err());
+
+        let mut pp = PathParser::new("M10 20 L 30 x 40 L 50 60");
+        let (kept, warnings) = pp.evaluate_recover();
+        assert_eq!(kept, "M10 20 L 50 60");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(pp.get_bbox(), Some(BoundingBox::new(10., 20., 50., 60.)));
+
+        // Implicit repeated commands before the error are retained
+        let (kept, warnings) = recover_path_data("M0 0 L 10 10 20 # L 5 5");
+        assert_eq!(kept, "M0 0 L 10 10 L 5 5");
+        assert_eq!(warnings.len(), 1);
+
+        // Invalid command letters are skipped
+        let (kept, warnings) = recover_path_data("M0 0 X 1 2 L 3 4 B");
+        assert_eq!(kept, "M0 0 L 3 4");
+        assert_eq!(warnings.len(), 2);
+
+        // Valid data is returned unchanged
+        let (kept, warnings) = recover_path_data(" M 1 2 h 3 ");
+        assert_eq!(kept, " M 1 2 h 3 ");
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_pp_polylines() {
+        // Subpaths are split on moveto, and 'z' returns to the subpath start
+        let mut pp = PathParser::new("M0 0 h10 v10 z m20 0 l5 0");
+        pp.evaluate().unwrap();
+        assert_eq!(
+            pp.polylines,
+            vec![
+                vec![(0., 0.), (10., 0.), (10., 10.), (0., 0.)],
+                vec![(0., 0.)],
+                vec![(20., 0.), (25., 0.)],
+            ]
+        );
+
+        // 'S' reflects the previous cubic control point
+        let mut pp = PathParser::new("M0 0 C0 10 10 10 10 0 S20 -10 20 0");
+        pp.evaluate().unwrap();
+        let points = &pp.polylines[0];
+        assert_eq!(points.len(), 2 * CURVE_STEPS + 1);
+        assert_eq!(points[CURVE_STEPS / 2], (5., 7.5));
+        assert_eq!(points[CURVE_STEPS * 3 / 2], (15., -7.5));
+        assert_eq!(points.last(), Some(&(20., 0.)));
+    }
+
+    #[test]
+    fn test_outline_crossings() {
+        let circle = SvgElement::new(
+            "circle",
+            &[
+                ("cx".to_string(), "0".to_string()),
+                ("cy".to_string(), "0".to_string()),
+                ("r".to_string(), "5".to_string()),
+            ],
+        );
+        assert_eq!(
+            outline_crossings(&circle, (-10., 0.), (10., 0.)).unwrap(),
+            [0.25, 0.75]
+        );
+        assert!(outline_crossings(&circle, (-10., 6.), (10., 6.))
+            .unwrap()
+            .is_empty());
+
+        let path = SvgElement::new(
+            "path",
+            &[("d".to_string(), "M 0 0 h 10 v 10 z".to_string())],
+        );
+        assert_eq!(
+            outline_crossings(&path, (5., -5.), (5., 15.)).unwrap(),
+            [0.25, 0.5]
+        );
+    }
 }
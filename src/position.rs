@@ -316,6 +316,8 @@ impl From<&SvgElement> for Position {
 pub enum Length {
     Absolute(f32),
     Ratio(f32),
+    /// A ratio plus an absolute offset, e.g. `(25% + 2)`
+    Mixed(f32, f32),
 }
 
 impl Default for Length {
@@ -348,6 +350,7 @@ impl Length {
         match self {
             Self::Absolute(abs) => *abs,
             Self::Ratio(ratio) => base * ratio,
+            Self::Mixed(ratio, abs) => base * ratio + abs,
         }
     }
 
@@ -357,6 +360,7 @@ impl Length {
         match self {
             Self::Absolute(abs) => value + abs,
             Self::Ratio(ratio) => value * ratio,
+            Self::Mixed(ratio, abs) => value * ratio + abs,
         }
     }
 
@@ -377,10 +381,56 @@ impl Length {
                 }
             }
             Self::Ratio(ratio) => start + (end - start) * ratio,
+            Self::Mixed(ratio, abs) => {
+                let mult = if end < start { -1. } else { 1. };
+                start + (end - start) * ratio + abs * mult
+            }
         }
     }
 }
 
+/// Parse a parenthesised sum of ratio and absolute terms, e.g. `(25% + 2)`
+/// or `(100% - 3 - 10%)`.
+fn parse_mixed_length(value: &str) -> Result<Length> {
+    let err = || SvgdxError::ParseError(format!("Invalid length expression '{value}'"));
+    let inner = value
+        .strip_prefix('(')
+        .and_then(|v| v.strip_suffix(')'))
+        .ok_or_else(err)?;
+    let (mut ratio, mut abs) = (0., 0.);
+    let mut has_ratio = false;
+    let mut rest = inner.trim();
+    let mut sign = 1.;
+    if let Some(r) = rest.strip_prefix('-') {
+        sign = -1.;
+        rest = r.trim_start();
+    }
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = rest[..end].trim();
+        if let Some(pc) = term.strip_suffix('%') {
+            ratio += sign * strp(pc).map_err(|_| err())? * 0.01;
+            has_ratio = true;
+        } else {
+            abs += sign * strp(term).map_err(|_| err())?;
+        }
+        if end == rest.len() {
+            break;
+        }
+        sign = if rest[end..].starts_with('-') {
+            -1.
+        } else {
+            1.
+        };
+        rest = &rest[end + 1..];
+    }
+    Ok(match (has_ratio, abs) {
+        (false, _) => Length::Absolute(abs),
+        (true, 0.) => Length::Ratio(ratio),
+        (true, _) => Length::Mixed(ratio, abs),
+    })
+}
+
 pub fn strp_length(s: &str) -> Result<Length> {
     s.parse::<Length>()
 }
@@ -392,7 +442,9 @@ impl FromStr for Length {
     /// Note this deliberately does not clamp to 0..1
     fn from_str(value: &str) -> Result<Self> {
         let value = value.trim();
-        if let Some(pc) = value.strip_suffix('%') {
+        if value.starts_with('(') {
+            parse_mixed_length(value)
+        } else if let Some(pc) = value.strip_suffix('%') {
             Ok(Length::Ratio(strp(pc)? * 0.01))
         } else {
             Ok(Length::Absolute(strp(value)?))
@@ -452,6 +504,15 @@ pub enum LocSpec {
     RightEdge(Length),
     BottomEdge(Length),
     LeftEdge(Length),
+    /// Midpoint of a line-like element; the center of other elements
+    Mid,
+    /// 1-based interior vertex of a line-like element; for other elements
+    /// the corners are numbered clockwise from top-left
+    Corner(usize),
+    /// First point of a line-like element; the top-left of other elements
+    Start,
+    /// Last point of a line-like element; the bottom-right of other elements
+    End,
 }
 
 impl LocSpec {
@@ -484,6 +545,28 @@ impl LocSpec {
     }
 }
 
+impl LocSpec {
+    /// Parse a locspec which may instead be the name of one of the given
+    /// `ports`, a space-separated list of `name:locspec` entries, e.g.
+    /// `in:l:25% out:r:75%`.
+    pub fn with_ports(value: &str, ports: Option<&str>) -> Result<Self> {
+        if let Ok(loc) = value.parse() {
+            return Ok(loc);
+        }
+        for port in ports.unwrap_or_default().split_whitespace() {
+            let (name, loc) = port
+                .split_once(':')
+                .ok_or_else(|| SvgdxError::InvalidData(format!("Invalid port '{port}'")))?;
+            if name == value {
+                return loc.parse();
+            }
+        }
+        Err(SvgdxError::InvalidData(format!(
+            "Invalid LocSpec format {value}"
+        )))
+    }
+}
+
 impl FromStr for LocSpec {
     type Err = SvgdxError;
 
@@ -498,8 +581,17 @@ impl FromStr for LocSpec {
             "bl" => Ok(Self::BottomLeft),
             "l" => Ok(Self::Left),
             "c" => Ok(Self::Center),
+            "m" => Ok(Self::Mid),
+            "start" => Ok(Self::Start),
+            "end" => Ok(Self::End),
             s => {
-                if let Some((edge, len)) = s.split_once(EDGESPEC_SEP) {
+                if let Some(n) = s
+                    .strip_prefix('c')
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|&n| n > 0)
+                {
+                    Ok(Self::Corner(n))
+                } else if let Some((edge, len)) = s.split_once(EDGESPEC_SEP) {
                     let len = len.parse::<Length>()?;
                     match edge {
                         "t" => Ok(Self::TopEdge(len)),
@@ -645,6 +737,10 @@ impl BoundingBox {
             RightEdge(len) => (self.x2, len.calc_offset(self.y1, self.y2)),
             BottomEdge(len) => (len.calc_offset(self.x1, self.x2), self.y2),
             LeftEdge(len) => (self.x1, len.calc_offset(self.y1, self.y2)),
+            Mid => c,
+            Corner(n) => [tl, tr, br, bl][(n - 1) % 4],
+            Start => tl,
+            End => br,
         }
     }
 
@@ -845,8 +941,28 @@ impl FromStr for TrblLength {
     }
 }
 
-/// Parse a elref + optional locspec, e.g. `#id@tl:10%` or `#id`
-pub fn parse_el_loc(s: &str) -> Result<(ElRef, Option<LocSpec>)> {
+/// Split a string at the first whitespace which is not within parentheses,
+/// so locspecs such as `@t:(25% + 2)` are kept intact.
+///
+/// Returns the first part and the (left-trimmed) remainder.
+pub fn split_locspec(s: &str) -> (&str, &str) {
+    let mut depth = 0;
+    for (idx, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth <= 0 => {
+                return (&s[..idx], s[idx..].trim_start());
+            }
+            _ => (),
+        }
+    }
+    (s, "")
+}
+
+/// Split a elref + optional locspec, e.g. `#id@tl:10%` or `#id@in`, leaving
+/// the locspec uninterpreted as it may be the name of a port of the element.
+pub fn split_el_loc(s: &str) -> Result<(ElRef, Option<&str>)> {
     let (elref, remain) = extract_elref(s)?;
     if remain.is_empty() {
         return Ok((elref, None));
@@ -854,16 +970,9 @@ pub fn parse_el_loc(s: &str) -> Result<(ElRef, Option<LocSpec>)> {
     let remain = remain
         .strip_prefix(LOCSPEC_SEP)
         .ok_or(SvgdxError::ParseError(format!("Invalid locspec: {s}")))?;
-    let mut chars = remain.chars();
-    let mut loc = String::new();
-    loop {
-        match chars.next() {
-            Some(c) if c.is_whitespace() => {
-                return Err(SvgdxError::ParseError(format!("Invalid locspec: {s}")))
-            }
-            Some(c) => loc.push(c),
-            None => return Ok((elref, Some(loc.parse()?))),
-        }
+    match split_locspec(remain) {
+        (loc, "") if !loc.is_empty() => Ok((elref, Some(loc))),
+        _ => Err(SvgdxError::ParseError(format!("Invalid locspec: {s}"))),
     }
 }
 
@@ -893,28 +1002,25 @@ mod test {
     use super::*;
 
     #[test]
-    fn test_parse_loc() {
+    fn test_split_loc() {
         assert_eq!(
-            parse_el_loc("#a@b").unwrap(),
-            (ElRef::Id("a".to_string()), Some(LocSpec::Bottom))
+            split_el_loc("#a@b").unwrap(),
+            (ElRef::Id("a".to_string()), Some("b"))
         );
         assert_eq!(
-            parse_el_loc("#id@tl").unwrap(),
-            (ElRef::Id("id".to_string()), Some(LocSpec::TopLeft))
+            split_el_loc("#id@t:25%").unwrap(),
+            (ElRef::Id("id".to_string()), Some("t:25%"))
         );
         assert_eq!(
-            parse_el_loc("#id@t:25%").unwrap(),
-            (
-                ElRef::Id("id".to_string()),
-                Some(LocSpec::TopEdge(Length::Ratio(0.25)))
-            )
+            split_el_loc("#id@in").unwrap(),
+            (ElRef::Id("id".to_string()), Some("in"))
         );
         assert_eq!(
-            parse_el_loc("#id").unwrap(),
+            split_el_loc("#id").unwrap(),
             (ElRef::Id("id".to_string()), None)
         );
-        assert!(parse_el_loc("#id@").is_err());
-        assert!(parse_el_loc("#id@ l").is_err());
+        assert!(split_el_loc("#id@").is_err());
+        assert!(split_el_loc("#id@ l").is_err());
     }
 
     #[test]
@@ -1060,6 +1166,18 @@ mod test {
         assert_eq!(ratio_len.absolute(), None);
         assert_eq!(ratio_len.ratio(), Some(0.75));
         assert_eq!(ratio_len.adjust(3.125), 0.75 * 3.125);
+
+        assert_eq!(strp_length("(25% + 2)").unwrap(), Length::Mixed(0.25, 2.));
+        assert_eq!(
+            strp_length("(-50% - 1 + 3)").unwrap(),
+            Length::Mixed(-0.5, 2.)
+        );
+        assert_eq!(strp_length("(50%)").unwrap(), Length::Ratio(0.5));
+        assert_eq!(strp_length("( 2 - 5 )").unwrap(), Length::Absolute(-3.));
+        assert!(strp_length("(25% +)").is_err());
+        assert!(strp_length("(25% * 2)").is_err());
+        assert_eq!(strp_length("(25% + 2)").unwrap().calc_offset(10., 50.), 22.);
+        assert_eq!(strp_length("(25% + 2)").unwrap().calc_offset(50., 10.), 38.);
     }
 
     #[test]
@@ -1125,6 +1243,29 @@ mod test {
             "l:75%".parse::<LocSpec>().expect("test"),
             LocSpec::LeftEdge(Length::Ratio(0.75))
         );
+        assert_eq!("m".parse::<LocSpec>().expect("test"), LocSpec::Mid);
+        assert_eq!("c2".parse::<LocSpec>().expect("test"), LocSpec::Corner(2));
+        assert_eq!("end".parse::<LocSpec>().expect("test"), LocSpec::End);
+        assert!("c0".parse::<LocSpec>().is_err());
+        assert!("cx".parse::<LocSpec>().is_err());
+    }
+
+    #[test]
+    fn test_locspec_ports() {
+        let ports = Some("in:l:25% out:r:75% t:b");
+        assert_eq!(
+            LocSpec::with_ports("in", ports).unwrap(),
+            LocSpec::LeftEdge(Length::Ratio(0.25))
+        );
+        assert_eq!(
+            LocSpec::with_ports("out", ports).unwrap(),
+            LocSpec::RightEdge(Length::Ratio(0.75))
+        );
+        // standard locspecs take precedence
+        assert_eq!(LocSpec::with_ports("t", ports).unwrap(), LocSpec::Top);
+        assert!(LocSpec::with_ports("other", ports).is_err());
+        assert!(LocSpec::with_ports("in", None).is_err());
+        assert!(LocSpec::with_ports("in", Some("in")).is_err());
     }
 
     #[test]
@@ -0,0 +1,26 @@
+//! Rendering of SVG output to PNG images.
+
+use crate::errors::{Result, SvgdxError};
+
+use resvg::{tiny_skia, usvg};
+
+/// Render the given SVG document to PNG image data.
+///
+/// The image size is derived from the SVG `width` and `height` at 96 DPI,
+/// so e.g. `--scale` applies as for SVG output. Text is rendered using
+/// system fonts.
+pub fn svg_png(svg: &str) -> Result<Vec<u8>> {
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options).map_err(SvgdxError::from_err)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(|| {
+        SvgdxError::InvalidData(format!(
+            "Cannot render {}x{} image",
+            size.width(),
+            size.height()
+        ))
+    })?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(SvgdxError::from_err)
+}
@@ -1,11 +1,11 @@
-use crate::context::TransformerContext;
+use crate::context::{ElementMap, TransformerContext};
 use crate::element::SvgElement;
 use crate::errors::{Result, SvgdxError};
 use crate::events::{InputEvent, InputList, OutputEvent, OutputList};
 use crate::expression::eval_attr;
 use crate::position::BoundingBox;
 use crate::transform::{process_events, EventGen};
-use crate::types::ElRef;
+use crate::types::{fnv1a, fstr, strp, ElRef};
 
 use itertools::Itertools;
 
@@ -21,7 +21,22 @@ impl EventGen for ReuseElement {
 
         reuse_element.eval_attributes(context);
 
+        // the reuse element is on the stack (providing its attributes as
+        // variables) while the instance is processed, even if that fails.
         context.push_element(&reuse_element);
+        let res = Self::instance_events(&reuse_element, context);
+        context.pop_element();
+        res
+    }
+}
+
+impl ReuseElement {
+    /// Events (and bbox) of the instance of the element referenced by the
+    /// (evaluated) `reuse_element`.
+    fn instance_events(
+        reuse_element: &SvgElement,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
         let elref = reuse_element
             .get_attr("href")
             .ok_or_else(|| SvgdxError::MissingAttribute("href".to_owned()))?;
@@ -29,13 +44,13 @@ impl EventGen for ReuseElement {
         // Take a copy of the referenced element as starting point for our new instance
         let mut instance_element = context
             .get_original_element(&elref)
-            .ok_or_else(|| SvgdxError::ReferenceError(elref))?
+            .ok_or_else(|| SvgdxError::ReferenceError(elref.clone()))?
             .clone();
 
         // Override 'default' attr values in the target
         for (attr, value) in reuse_element.get_attrs() {
             match attr.as_str() {
-                "href" | "id" | "x" | "y" | "transform" => continue,
+                "href" | "id" | "x" | "y" | "transform" | "override" | "bake" => continue,
                 _ => {
                     if instance_element.has_attr(&attr) {
                         instance_element.set_attr(&attr, &value);
@@ -53,12 +68,13 @@ impl EventGen for ReuseElement {
         let ref_id = instance_element.pop_attr("id");
         if let Some(inst_id) = reuse_element.get_attr("id") {
             instance_element.set_attr("id", &inst_id);
-            context.update_element(&reuse_element);
+            context.update_element(reuse_element);
         }
         // the instanced element should have the same indent as the original
         // `reuse` element, as well as inherit `style` and `class` values.
         instance_element.set_indent(reuse_element.indent);
         instance_element.set_src_line(reuse_element.src_line);
+        instance_element.set_src_col(reuse_element.src_col);
         if let Some(inst_style) = reuse_element.get_attr("style") {
             instance_element.set_attr("style", &inst_style);
         }
@@ -70,13 +86,22 @@ impl EventGen for ReuseElement {
         // Emulate (a bit) the `<use>` element - in particular `transform` is passed through
         // and any x/y attrs become a new (final) entry in the `transform`.
         // TODO: ensure transform() is considered by bbox() / positioning.
+        // With `bake="true"`, x/y are instead applied directly to the coordinates of
+        // the generated elements once they have been processed.
+        let bake = reuse_element.get_attr("bake").as_deref() == Some("true");
+        let mut bake_offset = None;
         {
             let reuse_x = reuse_element.get_attr("x");
             let reuse_y = reuse_element.get_attr("y");
             let xy_xfrm = if reuse_x.is_some() || reuse_y.is_some() {
                 let reuse_x = eval_attr(&reuse_x.unwrap_or("0".to_string()), context);
                 let reuse_y = eval_attr(&reuse_y.unwrap_or("0".to_string()), context);
-                Some(format!("translate({reuse_x}, {reuse_y})"))
+                if bake {
+                    bake_offset = Some((strp(&reuse_x)?, strp(&reuse_y)?));
+                    None
+                } else {
+                    Some(format!("translate({reuse_x}, {reuse_y})"))
+                }
             } else {
                 None
             };
@@ -95,6 +120,22 @@ impl EventGen for ReuseElement {
             }
         }
 
+        if context.config.add_metadata {
+            let provenance = provenance_attrs(&elref, context)?;
+            for (attr, value) in provenance {
+                instance_element.set_attr(attr, &value);
+            }
+        }
+
+        let overrides = if let Some(ovr) = reuse_element.get_attr("override") {
+            parse_overrides(&ovr)?
+        } else {
+            Vec::new()
+        };
+        apply_overrides(&mut instance_element, &overrides);
+        // overrides for elements within the instance are applied as they are processed
+        context.set_element_overrides(overrides);
+
         // reuse of a symbol element wraps the resulting content in a new <g> element
         if instance_element.name == "symbol" {
             instance_element = SvgElement::new("g", &[]).with_attrs_from(&instance_element);
@@ -121,7 +162,232 @@ impl EventGen for ReuseElement {
         } else {
             instance_element.generate_events(context)
         };
-        context.pop_element();
+        if let (Some((dx, dy)), Ok((events, bbox))) = (bake_offset, &res) {
+            return bake_events(events, *bbox, dx, dy, true, context);
+        }
         res
     }
 }
+
+/// Metadata identifying the source of a reused element.
+///
+/// The hash is derived from the source of the referenced element (including any
+/// content), so changes to a component definition can be detected in the output.
+fn provenance_attrs(
+    elref: &ElRef,
+    context: &TransformerContext,
+) -> Result<Vec<(&'static str, String)>> {
+    let target = context
+        .get_original_element(elref)
+        .ok_or_else(|| SvgdxError::ReferenceError(elref.clone()))?;
+    let mut source = Vec::new();
+    let events = target.all_events(context);
+    if events.is_empty() {
+        source.extend(target.to_string().as_bytes());
+    } else {
+        OutputList::from(events).write_to(&mut source)?;
+    }
+    Ok(vec![
+        ("data-reuse-href", elref.to_string()),
+        ("data-reuse-src-line", target.src_line.to_string()),
+        ("data-reuse-hash", format!("{:016x}", fnv1a(&source))),
+        ("data-svgdx-version", crate::VERSION.to_string()),
+    ])
+}
+
+/// Apply a translation to the given element's coordinates.
+///
+/// The instance element itself has the translation applied prior to any `transform`
+/// it has (matching the non-baked `reuse` behaviour), whereas descendants with their
+/// own `transform` have the translation applied after it.
+/// Paths are translated via a `transform` rather than rewriting path data.
+fn bake_element(el: &SvgElement, dx: f32, dy: f32, is_instance: bool) -> Result<SvgElement> {
+    let xfrm = el.get_attr("transform");
+    let new_el = if bakes_as_transform(el, is_instance) {
+        let mut new_el = el.clone();
+        let translate = format!("translate({}, {})", fstr(dx), fstr(dy));
+        let xfrm = match xfrm {
+            Some(xfrm) if is_instance => format!("{xfrm} {translate}"),
+            Some(xfrm) => format!("{translate} {xfrm}"),
+            None => translate,
+        };
+        new_el.set_attr("transform", &xfrm);
+        new_el
+    } else {
+        // position attributes may be omitted where they default to zero
+        let mut el = el.clone();
+        let pos_attrs: &[&str] = match el.name.as_str() {
+            "rect" | "use" | "image" | "text" | "svg" | "foreignObject" => &["x", "y"],
+            "circle" | "ellipse" => &["cx", "cy"],
+            "line" => &["x1", "y1", "x2", "y2"],
+            _ => &[],
+        };
+        for attr in pos_attrs {
+            el.set_default_attr(attr, "0");
+        }
+        let mut new_el = el.translated(dx, dy)?;
+        new_el.content_bbox = new_el.content_bbox.map(|bb| bb.translated(dx, dy));
+        new_el
+    };
+    Ok(new_el)
+}
+
+/// Whether `bake_element` applies its translation as a `transform` rather
+/// than by rewriting coordinates.
+fn bakes_as_transform(el: &SvgElement, is_instance: bool) -> bool {
+    el.name == "path" || (el.has_attr("transform") && !is_instance)
+}
+
+/// Rewrite coordinates of generated elements to include a translation (e.g. from
+/// `reuse`), updating any referenceable elements so they are available at their
+/// final position.
+///
+/// If `instance` is set, the first element is treated as a `reuse` instance.
+pub(crate) fn bake_events(
+    events: &OutputList,
+    bbox: Option<BoundingBox>,
+    dx: f32,
+    dy: f32,
+    instance: bool,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    let mut new_events = OutputList::new();
+    let mut is_instance = instance;
+    // nesting depth within an element translated via `transform`; content
+    // of such elements is already moved and must not be translated again.
+    let mut xfrm_depth = 0;
+    for ev in events.iter() {
+        let new_ev = match ev {
+            OutputEvent::Start(el) | OutputEvent::Empty(el) => {
+                let in_xfrm = xfrm_depth > 0;
+                if let Some(id) = el.get_attr("id") {
+                    if let Some(ctx_el) = context.get_element(&ElRef::Id(id)) {
+                        let ctx_el = bake_element(&ctx_el.clone(), dx, dy, is_instance)?;
+                        context.update_element(&ctx_el);
+                    }
+                }
+                let baked = if in_xfrm {
+                    el.clone()
+                } else {
+                    bake_element(el, dx, dy, is_instance)?
+                };
+                if let OutputEvent::Start(_) = ev {
+                    if in_xfrm || bakes_as_transform(el, is_instance) {
+                        xfrm_depth += 1;
+                    }
+                }
+                is_instance = false;
+                if matches!(ev, OutputEvent::Start(_)) {
+                    OutputEvent::Start(baked)
+                } else {
+                    OutputEvent::Empty(baked)
+                }
+            }
+            OutputEvent::End(_) => {
+                if xfrm_depth > 0 {
+                    xfrm_depth -= 1;
+                }
+                ev.clone()
+            }
+            other => other.clone(),
+        };
+        new_events.push(new_ev);
+    }
+    if let Some(prev) = context.get_element(&ElRef::Prev) {
+        let prev = bake_element(&prev.clone(), dx, dy, instance)?;
+        context.update_prev_element(&prev);
+    }
+    Ok((new_events, bbox.map(|bb| bb.translated(dx, dy))))
+}
+
+/// A targeted attribute override for elements within a reused element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    selector: String,
+    attr: String,
+    value: String,
+}
+
+impl Override {
+    /// Selectors are of the form `name`, `name.class`, `.class` or `#id`.
+    fn matches(&self, el: &SvgElement) -> bool {
+        el.matches_selector(&self.selector)
+    }
+}
+
+/// Parse an `override` attribute value, e.g.
+/// `rect.bg:fill=red; text.title:text='New'`
+fn parse_overrides(value: &str) -> Result<Vec<Override>> {
+    let mut overrides = Vec::new();
+    for entry in value.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (selector, assign) = entry
+            .split_once(':')
+            .ok_or_else(|| SvgdxError::ParseError(format!("Invalid override '{entry}'")))?;
+        let (attr, value) = assign
+            .split_once('=')
+            .ok_or_else(|| SvgdxError::ParseError(format!("Invalid override '{entry}'")))?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('\'')
+            .and_then(|v| v.strip_suffix('\''))
+            .unwrap_or(value);
+        overrides.push(Override {
+            selector: selector.trim().to_owned(),
+            attr: attr.trim().to_owned(),
+            value: value.to_owned(),
+        });
+    }
+    Ok(overrides)
+}
+
+/// Apply any matching overrides to the given element.
+pub fn apply_overrides(el: &mut SvgElement, overrides: &[Override]) {
+    // determine all matches first so earlier overrides (e.g. of `class`)
+    // don't affect which later overrides apply.
+    let matched: Vec<_> = overrides.iter().filter(|o| o.matches(el)).collect();
+    for ovr in matched {
+        el.set_attr(&ovr.attr, &ovr.value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides() {
+        let ovr = parse_overrides("rect.bg:fill=red; text.title:text='New value';").unwrap();
+        assert_eq!(ovr.len(), 2);
+        assert_eq!(ovr[0].selector, "rect.bg");
+        assert_eq!(ovr[0].attr, "fill");
+        assert_eq!(ovr[0].value, "red");
+        assert_eq!(ovr[1].selector, "text.title");
+        assert_eq!(ovr[1].attr, "text");
+        assert_eq!(ovr[1].value, "New value");
+
+        assert!(parse_overrides("rect.bg fill=red").is_err());
+        assert!(parse_overrides("rect.bg:fill").is_err());
+    }
+
+    #[test]
+    fn test_override_matches() {
+        let mut el = SvgElement::new("rect", &[("id".into(), "a".into())]);
+        el.add_class("bg");
+        for (selector, expected) in [
+            ("rect", true),
+            ("rect.bg", true),
+            (".bg", true),
+            ("#a", true),
+            ("circle", false),
+            ("rect.fg", false),
+            ("#b", false),
+        ] {
+            let ovr = Override {
+                selector: selector.to_owned(),
+                attr: "fill".to_owned(),
+                value: "red".to_owned(),
+            };
+            assert_eq!(ovr.matches(&el), expected, "{selector}");
+        }
+    }
+}
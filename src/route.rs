@@ -0,0 +1,263 @@
+//! Orthogonal routing of connectors around obstacles.
+//!
+//! Routes are found on a sparse grid formed from the connector's endpoints
+//! and the edges of each obstacle's bounding box (expanded by a margin), with
+//! segments running only along grid lines outside of any obstacle. The route
+//! with the fewest corners is chosen, with ties broken by total length.
+
+use crate::position::BoundingBox;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Index used for 'no direction yet', i.e. a start point without a direction.
+const NO_DIR: usize = 4;
+/// Unit vectors for each direction index
+const DIRS: [(f32, f32); 4] = [(0., -1.), (1., 0.), (0., 1.), (-1., 0.)];
+
+/// An endpoint of a route, with an optional direction leaving (for the start)
+/// or entering (for the end, as the direction *out* of the element) the point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RouteEnd {
+    pub point: (f32, f32),
+    pub dir: Option<(f32, f32)>,
+}
+
+fn dir_index(dir: (f32, f32)) -> Option<usize> {
+    DIRS.iter().position(|&d| d == dir)
+}
+
+/// Whether `p` is strictly inside `bb`.
+fn inside(bb: &BoundingBox, (x, y): (f32, f32)) -> bool {
+    bb.x1 < x && x < bb.x2 && bb.y1 < y && y < bb.y2
+}
+
+/// Whether the axis-aligned segment from `a` to `b` passes through the
+/// interior of `bb`.
+fn crosses(bb: &BoundingBox, a: (f32, f32), b: (f32, f32)) -> bool {
+    let (min_x, max_x) = (a.0.min(b.0), a.0.max(b.0));
+    let (min_y, max_y) = (a.1.min(b.1), a.1.max(b.1));
+    let overlap = |lo: f32, hi: f32, bb_lo: f32, bb_hi: f32| {
+        if lo == hi {
+            bb_lo < lo && lo < bb_hi
+        } else {
+            lo < bb_hi && bb_lo < hi
+        }
+    };
+    overlap(min_x, max_x, bb.x1, bb.x2) && overlap(min_y, max_y, bb.y1, bb.y2)
+}
+
+fn sorted_unique(mut values: Vec<f32>) -> Vec<f32> {
+    values.sort_by(f32::total_cmp);
+    values.dedup();
+    values
+}
+
+/// Remove repeated points and intermediate points of straight runs, retaining
+/// any points where the path reverses direction.
+pub fn simplify(points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    let mut result: Vec<(f32, f32)> = Vec::new();
+    for p in points {
+        if result.last() == Some(&p) {
+            continue;
+        }
+        if let [.., a, b] = result.as_slice() {
+            let collinear = (a.0 == b.0 && b.0 == p.0) || (a.1 == b.1 && b.1 == p.1);
+            let onward = (b.0 - a.0) * (p.0 - b.0) + (b.1 - a.1) * (p.1 - b.1) > 0.;
+            if collinear && onward {
+                result.pop();
+            }
+        }
+        result.push(p);
+    }
+    result
+}
+
+/// Find an orthogonal route from `start` to `end` avoiding `obstacles`, which
+/// are kept at least `margin` away from the route. Returns the points of the
+/// route, or `None` if no route exists.
+pub fn route(
+    start: RouteEnd,
+    end: RouteEnd,
+    obstacles: &[BoundingBox],
+    margin: f32,
+) -> Option<Vec<(f32, f32)>> {
+    let obstacles: Vec<BoundingBox> = obstacles
+        .iter()
+        .map(|bb| *bb.clone().expand(margin, margin))
+        .collect();
+    let stub = |end: &RouteEnd| match end.dir {
+        Some((dx, dy)) => (end.point.0 + dx * margin, end.point.1 + dy * margin),
+        None => end.point,
+    };
+    let (s1, e1) = (stub(&start), stub(&end));
+
+    let xs = sorted_unique(
+        [s1.0, e1.0]
+            .into_iter()
+            .chain(obstacles.iter().flat_map(|bb| [bb.x1, bb.x2]))
+            .collect(),
+    );
+    let ys = sorted_unique(
+        [s1.1, e1.1]
+            .into_iter()
+            .chain(obstacles.iter().flat_map(|bb| [bb.y1, bb.y2]))
+            .collect(),
+    );
+    let (nx, ny) = (xs.len(), ys.len());
+    let point = |node: usize| (xs[node % nx], ys[node / nx]);
+    let node_of = |p: (f32, f32)| {
+        let i = xs.iter().position(|&x| x == p.0)?;
+        let j = ys.iter().position(|&y| y == p.1)?;
+        Some(j * nx + i)
+    };
+    let (start_node, end_node) = (node_of(s1)?, node_of(e1)?);
+    let open = |node: usize| {
+        node == start_node
+            || node == end_node
+            || !obstacles.iter().any(|bb| inside(bb, point(node)))
+    };
+
+    // The route from each endpoint to its stub must itself be clear
+    if [(start.point, s1), (e1, end.point)]
+        .iter()
+        .any(|&(a, b)| obstacles.iter().any(|bb| crosses(bb, a, b)))
+    {
+        return None;
+    }
+
+    // Dijkstra over (node, direction) states, minimising (corners, length).
+    // Lengths are compared in thousandths of a unit to give a total order.
+    let state = |node: usize, dir: usize| node * 5 + dir;
+    let mut best: Vec<Option<(u32, u64)>> = vec![None; nx * ny * 5];
+    let mut prev: Vec<Option<usize>> = vec![None; nx * ny * 5];
+    let mut heap = BinaryHeap::new();
+    let start_dir = start.dir.and_then(dir_index).unwrap_or(NO_DIR);
+    let first = state(start_node, start_dir);
+    best[first] = Some((0, 0));
+    heap.push(Reverse((0, 0, first)));
+    // the final segment enters the end element, opposite to its direction
+    let end_dir = end.dir.and_then(|(dx, dy)| dir_index((-dx, -dy)));
+
+    let mut found = None;
+    while let Some(Reverse((corners, length, st))) = heap.pop() {
+        if best[st] != Some((corners, length)) {
+            continue;
+        }
+        let (node, dir) = (st / 5, st % 5);
+        if node == end_node {
+            let extra = u32::from(end_dir.is_some_and(|d| dir != NO_DIR && d != dir));
+            let cost = (corners + extra, length);
+            if found.is_none_or(|(c, _)| cost < c) {
+                found = Some((cost, st));
+            }
+            continue;
+        }
+        let (i, j) = (node % nx, node / nx);
+        for (new_dir, &(dx, dy)) in DIRS.iter().enumerate() {
+            // never reverse direction
+            if dir != NO_DIR && (new_dir + 2) % 4 == dir {
+                continue;
+            }
+            let (Some(ni), Some(nj)) = (
+                i.checked_add_signed(dx as isize).filter(|&v| v < nx),
+                j.checked_add_signed(dy as isize).filter(|&v| v < ny),
+            ) else {
+                continue;
+            };
+            let next = nj * nx + ni;
+            let (a, b) = (point(node), point(next));
+            if !open(next) || obstacles.iter().any(|bb| crosses(bb, a, b)) {
+                continue;
+            }
+            let step = ((b.0 - a.0).abs() + (b.1 - a.1).abs()) * 1000.;
+            let cost = (
+                corners + u32::from(dir != NO_DIR && dir != new_dir),
+                length + step.round() as u64,
+            );
+            let next_st = state(next, new_dir);
+            if best[next_st].is_none_or(|c| cost < c) {
+                best[next_st] = Some(cost);
+                prev[next_st] = Some(st);
+                heap.push(Reverse((cost.0, cost.1, next_st)));
+            }
+        }
+    }
+
+    let (_, mut st) = found?;
+    let mut points = vec![end.point];
+    loop {
+        points.push(point(st / 5));
+        match prev[st] {
+            Some(p) => st = p,
+            None => break,
+        }
+    }
+    points.push(start.point);
+    points.reverse();
+    Some(simplify(points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simplify() {
+        assert_eq!(
+            simplify(vec![(0., 0.), (0., 0.), (5., 0.), (10., 0.), (10., 5.)]),
+            [(0., 0.), (10., 0.), (10., 5.)]
+        );
+        assert_eq!(
+            simplify(vec![(0., 0.), (0., 20.), (0., 5.)]),
+            [(0., 0.), (0., 20.), (0., 5.)]
+        );
+    }
+
+    #[test]
+    fn test_route_clear() {
+        let start = RouteEnd {
+            point: (0., 0.),
+            dir: Some((1., 0.)),
+        };
+        let end = RouteEnd {
+            point: (20., 0.),
+            dir: Some((-1., 0.)),
+        };
+        assert_eq!(route(start, end, &[], 2.), Some(vec![(0., 0.), (20., 0.)]));
+    }
+
+    #[test]
+    fn test_route_obstacle() {
+        let start = RouteEnd {
+            point: (0., 0.),
+            dir: Some((1., 0.)),
+        };
+        let end = RouteEnd {
+            point: (30., 0.),
+            dir: Some((-1., 0.)),
+        };
+        let obstacle = BoundingBox::new(10., -5., 20., 10.);
+        // passes above the obstacle, which is nearer the route's line
+        assert_eq!(
+            route(start, end, &[obstacle], 2.),
+            Some(vec![
+                (0., 0.),
+                (8., 0.),
+                (8., -7.),
+                (28., -7.),
+                (28., 0.),
+                (30., 0.)
+            ])
+        );
+
+        // fully enclosed end point
+        let walls = [
+            BoundingBox::new(25., -10., 35., -5.),
+            BoundingBox::new(25., 5., 35., 10.),
+            BoundingBox::new(20., -10., 25., 10.),
+            BoundingBox::new(35., -10., 40., 10.),
+        ];
+        assert_eq!(route(start, end, &walls, 1.), None);
+    }
+}
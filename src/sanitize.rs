@@ -0,0 +1,192 @@
+//! Removal of potentially unsafe content from untrusted documents.
+//!
+//! When `TransformConfig::sanitize` is set, both the input and output event
+//! streams are filtered to remove scripts, `<foreignObject>` content, event
+//! handler attributes and references to external resources.
+
+use crate::element::SvgElement;
+use crate::events::OutputEvent;
+
+use quick_xml::events::Event;
+
+/// Elements which are removed (along with any content) when sanitizing.
+const UNSAFE_ELEMENTS: &[&str] = &[
+    "script",
+    "foreignObject",
+    "iframe",
+    "object",
+    "embed",
+    "handler",
+    "listener",
+];
+
+/// Attributes which may refer to another resource.
+const REF_ATTRS: &[&str] = &["href", "xlink:href", "src"];
+
+/// Returns true if `value` is a reference to something other than a local
+/// fragment or embedded image data.
+fn is_external_ref(value: &str) -> bool {
+    let value = value.trim_start_matches(['"', '\'']).trim();
+    !(value.starts_with('#') || value.to_lowercase().starts_with("data:image/"))
+}
+
+/// Returns true if the given CSS text (from a `style` attribute or element, or an
+/// attribute value such as `fill="url(#grad)"`) imports or refers to external content.
+fn unsafe_css(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    if lower.contains("@import") || lower.contains("expression(") {
+        return true;
+    }
+    lower
+        .split("url(")
+        .skip(1)
+        .any(|rest| is_external_ref(rest.trim_start()))
+}
+
+fn unsafe_attr(name: &str, value: &str) -> bool {
+    let lower_name = name.to_lowercase();
+    let compact_value: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    lower_name.starts_with("on")
+        || compact_value.contains("javascript:")
+        || compact_value.contains("vbscript:")
+        || (REF_ATTRS.contains(&lower_name.as_str()) && is_external_ref(value))
+        || unsafe_css(value)
+}
+
+fn unsafe_element(el: &SvgElement) -> bool {
+    if UNSAFE_ELEMENTS.contains(&el.name.as_str()) {
+        return true;
+    }
+    // Animation of `href` or event attributes can be used to inject script
+    if matches!(
+        el.name.as_str(),
+        "animate" | "animateMotion" | "animateTransform" | "set"
+    ) {
+        if let Some(target) = el.get_attr("attributeName") {
+            let target = target.to_lowercase();
+            return target.ends_with("href") || target.starts_with("on");
+        }
+    }
+    false
+}
+
+/// Remove any unsafe attributes from `el`, returning true if any were removed.
+fn strip_attrs(el: &mut SvgElement) -> bool {
+    let remove: Vec<String> = el
+        .get_attrs()
+        .into_iter()
+        .filter(|(k, v)| unsafe_attr(k, v))
+        .map(|(k, _)| k)
+        .collect();
+    for attr in &remove {
+        el.pop_attr(attr);
+    }
+    !remove.is_empty()
+}
+
+/// Result of checking a single event.
+#[derive(Debug, PartialEq)]
+pub enum SanitizeAction {
+    Keep,
+    /// The event has been modified, e.g. attributes removed
+    Modified,
+    Drop,
+}
+
+/// Stateful filter for a stream of events.
+#[derive(Debug, Default)]
+pub struct Sanitizer {
+    /// Names of currently open elements
+    stack: Vec<String>,
+    /// Depth within an unsafe element which is being removed
+    skip_depth: usize,
+}
+
+impl Sanitizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check the given event, which may be modified in-place.
+    pub fn check(&mut self, ev: &mut OutputEvent) -> SanitizeAction {
+        match ev {
+            OutputEvent::Start(el) => {
+                if self.skip_depth > 0 || unsafe_element(el) {
+                    self.skip_depth += 1;
+                    return SanitizeAction::Drop;
+                }
+                self.stack.push(el.name.clone());
+                if strip_attrs(el) {
+                    SanitizeAction::Modified
+                } else {
+                    SanitizeAction::Keep
+                }
+            }
+            OutputEvent::Empty(el) => {
+                if self.skip_depth > 0 || unsafe_element(el) {
+                    SanitizeAction::Drop
+                } else if strip_attrs(el) {
+                    SanitizeAction::Modified
+                } else {
+                    SanitizeAction::Keep
+                }
+            }
+            OutputEvent::End(_) => {
+                if self.skip_depth > 0 {
+                    self.skip_depth -= 1;
+                    return SanitizeAction::Drop;
+                }
+                self.stack.pop();
+                SanitizeAction::Keep
+            }
+            OutputEvent::Text(t) | OutputEvent::CData(t) => {
+                if self.skip_depth > 0
+                    || (self.stack.last().map(|s| s.as_str()) == Some("style") && unsafe_css(t))
+                {
+                    SanitizeAction::Drop
+                } else {
+                    SanitizeAction::Keep
+                }
+            }
+            OutputEvent::Comment(_) => {
+                if self.skip_depth > 0 {
+                    SanitizeAction::Drop
+                } else {
+                    SanitizeAction::Keep
+                }
+            }
+            OutputEvent::Other(e) => {
+                // Processing instructions (e.g. xml-stylesheet) and DOCTYPE declarations
+                // (which may define entities) are removed.
+                if self.skip_depth > 0 || matches!(e, Event::PI(_) | Event::DocType(_)) {
+                    SanitizeAction::Drop
+                } else {
+                    SanitizeAction::Keep
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsafe_attr() {
+        assert!(unsafe_attr("onclick", "alert(1)"));
+        assert!(unsafe_attr("href", "https://example.com/"));
+        assert!(unsafe_attr("xlink:href", "javascript:alert(1)"));
+        assert!(unsafe_attr("fill", "url(https://example.com/x.svg#g)"));
+        assert!(unsafe_attr("style", "background: url('http://a/b.png')"));
+        assert!(!unsafe_attr("href", "#abc"));
+        assert!(!unsafe_attr("href", "data:image/png;base64,AAAA"));
+        assert!(!unsafe_attr("fill", "url(#grad)"));
+        assert!(!unsafe_attr("marker-end", "url( '#arrow' )"));
+        assert!(!unsafe_attr("width", "10"));
+    }
+}
@@ -0,0 +1,263 @@
+//! Schematic parts, pins and nets.
+//!
+//! A `<part>` is drawn as a `<rect>`, with `<pin>` children declaring pins on
+//! its sides. Pins are numbered from 1 in the order given, and are spaced
+//! evenly along their side of the part; each is drawn as a short stub with an
+//! optional name label inside the part.
+//!
+//! ```xml
+//! <part id="u1" xy="0" wh="20 30" text="U1">
+//!   <pin name="in" side="l"/>
+//!   <pin name="out" side="r"/>
+//! </part>
+//! <net pins="u1.out u2.in"/>
+//! ```
+//!
+//! The end of each pin stub is a `<point>` with id `<part>-<number>`, and
+//! `<part>-<name>` for named pins. A `<net>` connects the pins listed in its
+//! `pins` attribute (as `part.number` or `part.name`) in order, with
+//! orthogonal traces leaving each pin in the direction of its stub.
+
+use crate::context::{ElementMap, TransformerContext};
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{tagify_events, OutputList, Tag};
+use crate::expression::eval_attr;
+use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::transform::{process_elements, EventGen};
+use crate::types::{attr_split, fstr, ElRef};
+
+/// Length of pin stubs
+const PIN_LENGTH: f32 = 3.;
+/// Gap between a pin and its name label
+const PIN_LABEL_GAP: f32 = 1.;
+/// Sides on which pins may be placed
+const PIN_SIDES: &[&str] = &["l", "r", "t", "b"];
+
+/// Generate events for a `<part>` or `<net>` element.
+pub(crate) fn schematic_events(
+    element: &SvgElement,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    if element.name == "part" {
+        PartElement(element.clone()).generate_events(context)
+    } else {
+        NetElement(element.clone()).generate_events(context)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PartElement(pub SvgElement);
+
+impl PartElement {
+    /// The `(name, side)` of each `<pin>` child, in order.
+    fn pins(&self, context: &TransformerContext) -> Result<Vec<(Option<String>, String)>> {
+        let mut pins = Vec::new();
+        let content = self.0.inner_events(context).unwrap_or_default();
+        for el in tagify_events(content)?.iter().filter_map(Tag::get_element) {
+            if el.name != "pin" {
+                return Err(SvgdxError::InvalidData(format!(
+                    "part: unexpected <{}> element; only <pin> is allowed",
+                    el.name
+                )));
+            }
+            let side = el.get_attr("side").unwrap_or("l".to_string());
+            if !PIN_SIDES.contains(&side.as_str()) {
+                return Err(SvgdxError::InvalidData(format!(
+                    "pin: side must be one of l, r, t, b, not '{side}'"
+                )));
+            }
+            pins.push((el.get_attr("name"), side));
+        }
+        Ok(pins)
+    }
+
+    /// Stubs, labels and points for pins on a part with the given bounding box.
+    fn pin_elements(
+        &self,
+        part_id: &str,
+        part_bb: &BoundingBox,
+        pins: &[(Option<String>, String)],
+    ) -> Vec<SvgElement> {
+        let mut elements = Vec::new();
+        for (idx, (name, side)) in pins.iter().enumerate() {
+            let same_side: Vec<_> = pins.iter().filter(|(_, s)| s == side).collect();
+            let pos = pins[..idx].iter().filter(|(_, s)| s == side).count();
+            let t = (pos + 1) as f32 / (same_side.len() + 1) as f32;
+            let ((x, y), (dx, dy), label_loc) = match side.as_str() {
+                "l" => (
+                    (part_bb.x1, part_bb.y1 + part_bb.height() * t),
+                    (-1., 0.),
+                    "r",
+                ),
+                "r" => (
+                    (part_bb.x2, part_bb.y1 + part_bb.height() * t),
+                    (1., 0.),
+                    "l",
+                ),
+                "t" => (
+                    (part_bb.x1 + part_bb.width() * t, part_bb.y1),
+                    (0., -1.),
+                    "b",
+                ),
+                _ => (
+                    (part_bb.x1 + part_bb.width() * t, part_bb.y2),
+                    (0., 1.),
+                    "t",
+                ),
+            };
+            let (ox, oy) = (x + dx * PIN_LENGTH, y + dy * PIN_LENGTH);
+            let mut stub = SvgElement::new("line", &[]);
+            stub.set_attr("x1", &fstr(x));
+            stub.set_attr("y1", &fstr(y));
+            stub.set_attr("x2", &fstr(ox));
+            stub.set_attr("y2", &fstr(oy));
+            elements.push(stub);
+
+            let pin_ids = [Some((idx + 1).to_string()), name.clone()];
+            for pin_id in pin_ids.iter().flatten() {
+                let mut point = SvgElement::new("point", &[]);
+                point.set_attr("id", &format!("{part_id}-{pin_id}"));
+                point.set_attr("xy", &format!("{} {}", fstr(ox), fstr(oy)));
+                point.set_attr("side", side);
+                elements.push(point);
+            }
+
+            if let Some(name) = name {
+                let mut label = SvgElement::new("text", &[]);
+                let (lx, ly) = (x - dx * PIN_LABEL_GAP, y - dy * PIN_LABEL_GAP);
+                label.set_attr("xy", &format!("{} {}", fstr(lx), fstr(ly)));
+                label.set_attr("text", name);
+                label.set_attr("text-loc", label_loc);
+                label.add_class("d-text-smaller");
+                elements.push(label);
+            }
+        }
+        elements
+    }
+}
+
+impl EventGen for PartElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let part_id = self
+            .0
+            .get_attr("id")
+            .ok_or_else(|| SvgdxError::MissingAttribute("id".to_string()))?;
+        let pins = self.pins(context)?;
+
+        let mut body = self.0.clone();
+        body.name = "rect".to_string();
+        body.event_range = None;
+        let (mut events, part_bb) = process_elements(vec![body], context)?;
+        let part_bb = part_bb.ok_or_else(|| SvgdxError::MissingBoundingBox(self.0.to_string()))?;
+
+        let (pin_events, pin_bb) =
+            process_elements(self.pin_elements(&part_id, &part_bb, &pins), context)?;
+        events.extend(&pin_events);
+
+        let mut bbox = BoundingBoxBuilder::new();
+        bbox.extend(part_bb);
+        if let Some(bb) = pin_bb {
+            bbox.extend(bb);
+        }
+        Ok((events, bbox.build()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NetElement(pub SvgElement);
+
+impl NetElement {
+    /// Element reference for a pin given as `part.pin`, including the side
+    /// of the part the pin is on.
+    fn pin_ref(&self, pin: &str, context: &TransformerContext) -> Result<String> {
+        let (part, pin_id) = pin.split_once('.').ok_or_else(|| {
+            SvgdxError::InvalidData(format!("net: pin '{pin}' must be of the form part.pin"))
+        })?;
+        let id = format!("{part}-{pin_id}");
+        let point = context
+            .get_element(&ElRef::Id(id.clone()))
+            .ok_or_else(|| SvgdxError::ReferenceError(ElRef::Id(id.clone())))?;
+        let side = point.get_attr("side").unwrap_or("l".to_string());
+        Ok(format!("#{id}@{side}"))
+    }
+}
+
+impl EventGen for NetElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let pins: Vec<String> = attr_split(&eval_attr(
+            &self.0.get_attr("pins").unwrap_or_default(),
+            context,
+        ))
+        .collect();
+        if pins.len() < 2 {
+            return Err(SvgdxError::InvalidData(
+                "net: at least two pins are required".to_string(),
+            ));
+        }
+        let pin_refs = pins
+            .iter()
+            .map(|p| self.pin_ref(p, context))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut template = self.0.clone();
+        template.name = "polyline".to_string();
+        template.pop_attr("pins");
+        let traces = pin_refs
+            .windows(2)
+            .map(|pair| {
+                let mut trace = template.clone();
+                trace.set_attr("start", &pair[0]);
+                trace.set_attr("end", &pair[1]);
+                trace
+            })
+            .collect();
+        process_elements(traces, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_elements() {
+        let part = PartElement(SvgElement::new("part", &[]));
+        let pins = [
+            (Some("a".to_string()), "l".to_string()),
+            (None, "l".to_string()),
+            (Some("q".to_string()), "b".to_string()),
+        ];
+        let els = part.pin_elements("u1", &BoundingBox::new(0., 0., 20., 30.), &pins);
+        let points: Vec<_> = els.iter().filter(|el| el.name == "point").collect();
+        let summary: Vec<_> = points
+            .iter()
+            .map(|el| {
+                (
+                    el.get_attr("id").unwrap(),
+                    el.get_attr("xy").unwrap(),
+                    el.get_attr("side").unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("u1-1".to_string(), "-3 10".to_string(), "l".to_string()),
+                ("u1-a".to_string(), "-3 10".to_string(), "l".to_string()),
+                ("u1-2".to_string(), "-3 20".to_string(), "l".to_string()),
+                ("u1-3".to_string(), "10 33".to_string(), "b".to_string()),
+                ("u1-q".to_string(), "10 33".to_string(), "b".to_string()),
+            ]
+        );
+        let labels: Vec<_> = els.iter().filter(|el| el.name == "text").collect();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[1].get_attr("text-loc").unwrap(), "t");
+    }
+}
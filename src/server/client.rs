@@ -0,0 +1,208 @@
+//! TypeScript client for the svgdx-server HTTP API.
+//!
+//! The client is generated from the OpenAPI description (see `openapi.rs`),
+//! so it has a typed method for each operation, with parameter and result
+//! types following the Rust request and response types. It is served at
+//! `/api/client.ts`, e.g. for editor plugins to vendor into their source.
+
+use serde_json::Value;
+
+use std::fmt::Write;
+
+const PREAMBLE: &str = r#"
+/** Error response from svgdx-server. */
+export class SvgdxApiError extends Error {
+  status: number;
+
+  constructor(status: number, message: string) {
+    super(message);
+    this.status = status;
+  }
+}
+
+type Query = Record<string, string | number | boolean | null | undefined>;
+
+export class SvgdxClient {
+  private baseUrl: string;
+
+  /** `baseUrl` is the server root, e.g. `http://127.0.0.1:3003`. */
+  constructor(baseUrl: string = "") {
+    this.baseUrl = baseUrl;
+  }
+
+  private async request(method: string, path: string, query: Query, body?: string): Promise<Response> {
+    const params = new URLSearchParams();
+    for (const [key, value] of Object.entries(query)) {
+      if (value !== undefined && value !== null) {
+        params.set(key, String(value));
+      }
+    }
+    const search = params.toString();
+    const url = this.baseUrl + path + (search ? `?${search}` : "");
+    const response = await fetch(url, { method, body });
+    if (!response.ok) {
+      throw new SvgdxApiError(response.status, await response.text());
+    }
+    return response;
+  }
+"#;
+
+/// TypeScript type corresponding to a JSON schema.
+fn ts_type(schema: &Value) -> String {
+    if let Some(any_of) = schema["anyOf"].as_array() {
+        return any_of.iter().map(ts_type).collect::<Vec<_>>().join(" | ");
+    }
+    match schema["type"].as_str() {
+        Some("boolean") => "boolean".to_string(),
+        Some("integer" | "number") => "number".to_string(),
+        Some("string") => "string".to_string(),
+        Some("null") => "null".to_string(),
+        Some("array") => match ts_type(&schema["items"]) {
+            item if item.contains(" | ") => format!("({item})[]"),
+            item => format!("{item}[]"),
+        },
+        Some("object") => match schema["properties"].as_object() {
+            Some(props) if !props.is_empty() => {
+                let required = schema["required"].as_array().map_or(&[][..], Vec::as_slice);
+                let fields: Vec<_> = props
+                    .iter()
+                    .map(|(name, prop)| {
+                        let optional = !required.contains(&name.as_str().into());
+                        let mark = if optional { "?" } else { "" };
+                        format!("{name}{mark}: {}", ts_type(prop))
+                    })
+                    .collect();
+                format!("{{ {} }}", fields.join("; "))
+            }
+            _ => "Record<string, unknown>".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+/// Client method for a single operation.
+fn method(path: &str, http_method: &str, path_params: &[Value], op: &Value) -> String {
+    let params = path_params
+        .iter()
+        .chain(op["parameters"].as_array().into_iter().flatten());
+    let mut args = Vec::new();
+    let mut query = Vec::new();
+    let mut query_required = false;
+    for param in params {
+        let name = param["name"].as_str().unwrap_or_default();
+        if param["in"] == "path" {
+            args.push(format!("{name}: {}", ts_type(&param["schema"])));
+        } else if param["required"] == true {
+            query_required = true;
+            query.push(format!("{name}: {}", ts_type(&param["schema"])));
+        } else {
+            query.push(format!("{name}?: {}", ts_type(&param["schema"])));
+        }
+    }
+    let body = op.get("requestBody").is_some();
+    if body {
+        args.push("body: string".to_string());
+    }
+    if !query.is_empty() {
+        let default = if query_required { "" } else { " = {}" };
+        args.push(format!("query: {{ {} }}{default}", query.join("; ")));
+    }
+
+    let url = path.replace('{', "${encodeURIComponent(");
+    let url = url.replace('}', ")}");
+    let call = format!(
+        "this.request(\"{}\", `{url}`, {}{})",
+        http_method.to_uppercase(),
+        if query.is_empty() { "{}" } else { "query" },
+        if body { ", body" } else { "" },
+    );
+
+    let success = op["responses"]
+        .as_object()
+        .and_then(|r| r.iter().find(|(status, _)| status.starts_with('2')))
+        .map(|(_, response)| response["content"].as_object());
+    let (result, ret) = match success.flatten().and_then(|c| c.iter().next()) {
+        Some((mime, content)) if mime == "application/json" => (
+            ts_type(&content["schema"]),
+            format!("return (await {call}).json();"),
+        ),
+        Some(_) => (
+            "string".to_string(),
+            format!("return (await {call}).text();"),
+        ),
+        None => ("void".to_string(), format!("await {call};")),
+    };
+
+    let mut out = String::new();
+    if let Some(summary) = op["summary"].as_str() {
+        writeln!(out, "  /** {summary} */").expect("write to String");
+    }
+    let name = op["operationId"].as_str().unwrap_or_default();
+    writeln!(
+        out,
+        "  async {name}({}): Promise<{result}> {{\n    {ret}\n  }}",
+        args.join(", ")
+    )
+    .expect("write to String");
+    out
+}
+
+/// TypeScript source of a client for the API with the given OpenAPI description.
+pub(super) fn typescript(doc: &Value) -> String {
+    let mut out = format!(
+        "// Client for {} {}, generated from its OpenAPI description.\n",
+        doc["info"]["title"].as_str().unwrap_or_default(),
+        doc["info"]["version"].as_str().unwrap_or_default(),
+    );
+    out.push_str(PREAMBLE);
+    for (path, item) in doc["paths"].as_object().into_iter().flatten() {
+        let path_params = item["parameters"].as_array().map_or(&[][..], Vec::as_slice);
+        for http_method in ["get", "post", "put", "delete"] {
+            if let Some(op) = item.get(http_method) {
+                out.push('\n');
+                out.push_str(&method(path, http_method, path_params, op));
+            }
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::openapi;
+
+    #[test]
+    fn test_ts_type() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "a": { "type": "integer" },
+                "b": {
+                    "type": "array",
+                    "items": { "anyOf": [{ "type": "string" }, { "type": "null" }] },
+                },
+            },
+            "required": ["a"],
+        });
+        assert_eq!(ts_type(&schema), "{ a: number; b?: (string | null)[] }");
+    }
+
+    #[test]
+    fn test_client() {
+        let client = typescript(&openapi::document());
+        assert!(client.starts_with("// Client for svgdx-server "));
+        assert!(client.contains(
+            "  async transform(body: string, query: { add_metadata?: boolean; sanitize?: boolean } = {}): Promise<string> {\n    return (await this.request(\"POST\", `/api/transform`, query, body)).text();\n  }"
+        ));
+        assert!(client.contains(
+            "  async complete(body: string, query: { col: number; line: number }): Promise<{ detail: string; kind: string; label: string }[]> {"
+        ));
+        assert!(client.contains(
+            "  async deleteDocument(id: string, name: string): Promise<void> {\n    await this.request(\"DELETE\", `/api/session/${encodeURIComponent(id)}/doc/${encodeURIComponent(name)}`, {});\n  }"
+        ));
+        assert!(client.contains("  async metadata(body: string, query: "));
+        assert!(client.ends_with("}\n"));
+    }
+}
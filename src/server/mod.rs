@@ -0,0 +1,782 @@
+use axum::{
+    body::Body,
+    extract::{FromRef, Path, Query, State},
+    http::{Response, StatusCode},
+    response::IntoResponse,
+    routing::{get, post, put},
+    Json, Router,
+};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_derive::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{BuildHasher, RandomState};
+use std::path::{Component, PathBuf};
+use std::sync::{Arc, Mutex};
+
+mod client;
+mod openapi;
+
+use openapi::api_type;
+
+use crate::element::SvgElement;
+use crate::errors::SvgdxError;
+use crate::geometry::rendered_elements;
+use crate::transform_str;
+use crate::{complete, cursor_offset, hover, CompletionKind, TransformConfig};
+
+// Content-Security-Policy - allow inline CSS used for the generated SVG images,
+// but otherwise restrict to same-origin resources.
+// Includes 'wasm-unsafe-eval' for consistency even though is doing server-side
+// transforms rather than in-browser.
+// img-src requires `blob:` scheme for clipboard copy.
+const CSP: &str = "default-src 'self'; script-src 'self' 'wasm-unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src 'self' blob:; frame-ancestors 'none'";
+
+// Not all fields make sense for the editor, but add_metadata
+// is needed to allow hover-over line highlighting.
+api_type! {
+    #[derive(Debug, Default, Deserialize)]
+    struct RequestConfig {
+        /// Add source metadata (e.g. `data-src-line`) to output
+        #[serde(default)]
+        add_metadata: bool,
+        /// Remove scripts, event handlers and external references
+        #[serde(default)]
+        sanitize: bool,
+    }
+}
+
+impl From<RequestConfig> for TransformConfig {
+    fn from(config: RequestConfig) -> Self {
+        TransformConfig {
+            add_metadata: config.add_metadata,
+            sanitize: config.sanitize,
+            ..Default::default()
+        }
+    }
+}
+
+async fn transform(config: Query<RequestConfig>, input: String) -> impl IntoResponse {
+    let Query(config) = config;
+    svg_response(input, &config.into())
+}
+
+fn svg_response(input: String, config: &TransformConfig) -> Response<Body> {
+    transform_str(input, config)
+        .and_then(|output| {
+            if output.is_empty() {
+                // Can't build a valid image/svg+xml response from empty string.
+                Err(SvgdxError::from("Empty response"))
+            } else {
+                Ok(output)
+            }
+        })
+        .map(|output| {
+            Response::builder()
+                .header("Content-Type", "image/svg+xml")
+                .header("Content-Security-Policy", CSP)
+                .body(Body::from(output))
+                .unwrap()
+        })
+        .map_err(|e| {
+            // TODO: make the error more informative, e.g. by returning a JSON object
+            // including line number(s) of failed elements.
+            Response::builder()
+                .status(400)
+                .header("Content-Type", "text/plain")
+                .body(Body::from(format!("Error: {}", e)))
+                .unwrap()
+        })
+        .unwrap_or_else(|e| e)
+}
+
+api_type! {
+    /// Cursor position within a document, 1-based.
+    #[derive(Debug, Deserialize)]
+    struct CursorPosition {
+        /// Line number, starting at 1
+        line: usize,
+        /// Column number (in characters), starting at 1
+        col: usize,
+    }
+}
+
+api_type! {
+    #[derive(Debug, Serialize)]
+    struct CompletionItem {
+        /// One of `id`, `attribute`, `class` or `locspec`
+        kind: &'static str,
+        /// Text to insert
+        label: String,
+        /// Description of the candidate
+        detail: String,
+    }
+}
+
+api_type! {
+    /// Element with an id, and its bounding box.
+    #[derive(Debug, Serialize)]
+    struct ElementItem {
+        /// Element id
+        id: String,
+        /// Element name
+        element: String,
+        /// Bounding box in user units
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    }
+}
+
+api_type! {
+    /// Metadata of a rendered document.
+    #[derive(Debug, Default, Serialize)]
+    struct DocumentMetadata {
+        /// Width of the rendered image, e.g. `40mm`
+        width: Option<String>,
+        /// Height of the rendered image, e.g. `30mm`
+        height: Option<String>,
+        /// viewBox of the rendered image, in user units
+        view_box: Option<String>,
+        /// Document title, from its `<title>` element
+        title: Option<String>,
+        /// Rendered elements with an id, in document order
+        elements: Vec<ElementItem>,
+    }
+}
+
+impl DocumentMetadata {
+    /// Metadata of the given SVG output of a transform.
+    fn from_svg(svg: &str) -> crate::errors::Result<Self> {
+        let mut metadata = Self::default();
+        let mut reader = Reader::from_str(svg);
+        let mut depth = 0;
+        let mut title: Option<String> = None;
+        let mut in_title = false;
+        loop {
+            match reader
+                .read_event()
+                .map_err(|e| SvgdxError::ParseError(format!("XML error: {e:?}")))?
+            {
+                Event::Start(e) | Event::Empty(e) if depth == 0 => {
+                    let root = SvgElement::try_from(&e)?;
+                    metadata.width = root.get_attr("width");
+                    metadata.height = root.get_attr("height");
+                    metadata.view_box = root.get_attr("viewBox");
+                    depth += 1;
+                }
+                Event::Start(e) => {
+                    depth += 1;
+                    in_title = depth == 2 && title.is_none() && e.name().as_ref() == b"title";
+                    if in_title {
+                        title = Some(String::new());
+                    }
+                }
+                Event::Text(t) if in_title => {
+                    let text = t.unescape().map_err(SvgdxError::from_err)?;
+                    title.get_or_insert_with(String::new).push_str(&text);
+                }
+                Event::End(_) => {
+                    depth -= 1;
+                    in_title = false;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        metadata.title = title;
+        let mut seen = HashSet::new();
+        metadata.elements = rendered_elements(svg)?
+            .into_iter()
+            .filter_map(|(el, bbox)| Some((el.get_attr("id")?, el.name, bbox?)))
+            .filter(|(id, _, _)| seen.insert(id.clone()))
+            .map(|(id, element, bb)| ElementItem {
+                id,
+                element,
+                x: bb.x1,
+                y: bb.y1,
+                width: bb.width(),
+                height: bb.height(),
+            })
+            .collect();
+        Ok(metadata)
+    }
+}
+
+async fn metadata(config: Query<RequestConfig>, input: String) -> impl IntoResponse {
+    let Query(config) = config;
+    transform_str(input, &config.into())
+        .and_then(|svg| DocumentMetadata::from_svg(&svg))
+        .map(Json)
+        .map_err(|e| bad_request(&e.to_string()))
+}
+
+fn bad_request(msg: &str) -> Response<Body> {
+    error_response(StatusCode::BAD_REQUEST, msg)
+}
+
+fn error_response(status: StatusCode, msg: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "text/plain")
+        .body(Body::from(format!("Error: {}", msg)))
+        .unwrap()
+}
+
+// Limits on in-memory session state, since sessions are only removed on request.
+const MAX_SESSIONS: usize = 64;
+const MAX_SESSION_DOCUMENTS: usize = 64;
+
+/// Named documents uploaded to a session.
+type Documents = HashMap<String, String>;
+
+/// Multi-document workspace sessions.
+///
+/// Each session holds a set of named documents, any of which may be rendered
+/// with `<import src="name"/>` elements resolved against the other documents
+/// in the same session, e.g. a shared library of `<specs>` used by several
+/// diagrams.
+#[derive(Clone, Default)]
+struct Sessions(Arc<Mutex<HashMap<String, Arc<Documents>>>>);
+
+#[derive(Debug, PartialEq)]
+enum SessionError {
+    NotFound(String),
+    Limit(String),
+}
+
+impl From<SessionError> for Response<Body> {
+    fn from(err: SessionError) -> Self {
+        match err {
+            SessionError::NotFound(msg) => error_response(StatusCode::NOT_FOUND, &msg),
+            SessionError::Limit(msg) => error_response(StatusCode::TOO_MANY_REQUESTS, &msg),
+        }
+    }
+}
+
+impl Sessions {
+    /// Create a new empty session, returning its id.
+    fn create(&self) -> Result<String, SessionError> {
+        let mut sessions = self.0.lock().unwrap();
+        if sessions.len() >= MAX_SESSIONS {
+            return Err(SessionError::Limit("too many sessions".to_string()));
+        }
+        // RandomState is randomly seeded, so ids are not predictable from
+        // previous ones.
+        let id = loop {
+            let (a, b) = (RandomState::new(), RandomState::new());
+            let id = format!("{:016x}{:016x}", a.hash_one(0), b.hash_one(1));
+            if !sessions.contains_key(&id) {
+                break id;
+            }
+        };
+        sessions.insert(id.clone(), Arc::default());
+        Ok(id)
+    }
+
+    fn remove(&self, id: &str) -> Result<(), SessionError> {
+        self.0
+            .lock()
+            .unwrap()
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| SessionError::NotFound(format!("no session '{id}'")))
+    }
+
+    /// Snapshot of the documents in a session.
+    fn documents(&self, id: &str) -> Result<Arc<Documents>, SessionError> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| SessionError::NotFound(format!("no session '{id}'")))
+    }
+
+    /// Add or replace a named document in a session.
+    fn put_document(&self, id: &str, name: &str, source: String) -> Result<(), SessionError> {
+        let mut sessions = self.0.lock().unwrap();
+        let docs = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(format!("no session '{id}'")))?;
+        if !docs.contains_key(name) && docs.len() >= MAX_SESSION_DOCUMENTS {
+            return Err(SessionError::Limit("too many documents".to_string()));
+        }
+        Arc::make_mut(docs).insert(name.to_string(), source);
+        Ok(())
+    }
+
+    fn remove_document(&self, id: &str, name: &str) -> Result<(), SessionError> {
+        let mut sessions = self.0.lock().unwrap();
+        let docs = sessions
+            .get_mut(id)
+            .ok_or_else(|| SessionError::NotFound(format!("no session '{id}'")))?;
+        Arc::make_mut(docs)
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| SessionError::NotFound(format!("no document '{name}'")))
+    }
+}
+
+api_type! {
+    #[derive(Debug, Serialize)]
+    struct SessionInfo {
+        /// Session id
+        id: String,
+        /// Names of the documents in the session, sorted
+        documents: Vec<String>,
+    }
+}
+
+impl SessionInfo {
+    fn new(id: String, docs: &Documents) -> Self {
+        let mut documents: Vec<_> = docs.keys().cloned().collect();
+        documents.sort();
+        Self { id, documents }
+    }
+}
+
+async fn create_session(State(sessions): State<Sessions>) -> impl IntoResponse {
+    let id = sessions.create().map_err(Response::from)?;
+    Ok::<_, Response<Body>>((
+        StatusCode::CREATED,
+        Json(SessionInfo::new(id, &Documents::new())),
+    ))
+}
+
+async fn session_info(
+    State(sessions): State<Sessions>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let docs = sessions.documents(&id).map_err(Response::from)?;
+    Ok::<_, Response<Body>>(Json(SessionInfo::new(id, &docs)))
+}
+
+async fn delete_session(
+    State(sessions): State<Sessions>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    sessions.remove(&id).map_err(Response::from)?;
+    Ok::<_, Response<Body>>(StatusCode::NO_CONTENT)
+}
+
+async fn put_document(
+    State(sessions): State<Sessions>,
+    Path((id, name)): Path<(String, String)>,
+    input: String,
+) -> impl IntoResponse {
+    sessions
+        .put_document(&id, &name, input)
+        .map_err(Response::from)?;
+    Ok::<_, Response<Body>>(StatusCode::NO_CONTENT)
+}
+
+async fn delete_document(
+    State(sessions): State<Sessions>,
+    Path((id, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    sessions
+        .remove_document(&id, &name)
+        .map_err(Response::from)?;
+    Ok::<_, Response<Body>>(StatusCode::NO_CONTENT)
+}
+
+async fn render_document(
+    State(sessions): State<Sessions>,
+    Path((id, name)): Path<(String, String)>,
+    config: Query<RequestConfig>,
+) -> Response<Body> {
+    let docs = match sessions.documents(&id) {
+        Ok(docs) => docs,
+        Err(e) => return e.into(),
+    };
+    let Some(input) = docs.get(&name).cloned() else {
+        return SessionError::NotFound(format!("no document '{name}'")).into();
+    };
+    let Query(config) = config;
+    let config = TransformConfig {
+        documents: Some(docs),
+        source_file: Some(name),
+        ..config.into()
+    };
+    svg_response(input, &config)
+}
+
+async fn completions(pos: Query<CursorPosition>, input: String) -> impl IntoResponse {
+    let Some(offset) = cursor_offset(&input, pos.line, pos.col) else {
+        return Err(bad_request("Invalid cursor position"));
+    };
+    let items: Vec<_> = complete(&input, offset)
+        .into_iter()
+        .map(|c| CompletionItem {
+            kind: match c.kind {
+                CompletionKind::ElementId => "id",
+                CompletionKind::Attribute => "attribute",
+                CompletionKind::Class => "class",
+                CompletionKind::LocSpec => "locspec",
+            },
+            label: c.label,
+            detail: c.detail,
+        })
+        .collect();
+    Ok(Json(items))
+}
+
+async fn hover_info(pos: Query<CursorPosition>, input: String) -> impl IntoResponse {
+    let Some(offset) = cursor_offset(&input, pos.line, pos.col) else {
+        return Err(bad_request("Invalid cursor position"));
+    };
+    match hover(&input, offset, &TransformConfig::default()) {
+        Ok(info) => Ok(Json(info.map(|h| ElementItem {
+            id: h.id,
+            element: h.element,
+            x: h.x,
+            y: h.y,
+            width: h.width,
+            height: h.height,
+        }))),
+        Err(e) => Err(bad_request(&e.to_string())),
+    }
+}
+
+macro_rules! include_or_read {
+    ($path:expr, $mime:expr) => {{
+        // If configured as a release build, use include_bytes! to embed the file.
+        #[cfg(not(debug_assertions))]
+        let content =
+            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/editor/", $path)).as_ref();
+        // During development it's useful to have it re-read each request.
+        #[cfg(debug_assertions)]
+        let content = tokio::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/editor/", $path))
+            .await
+            .unwrap();
+
+        Response::builder()
+            .header("Content-Type", $mime)
+            .header("Content-Security-Policy", CSP)
+            .body(Body::from(content))
+            .unwrap()
+    }};
+}
+
+macro_rules! include_js {
+    ($path:expr) => {{
+        include_or_read!($path, "application/javascript")
+    }};
+}
+
+macro_rules! include_css {
+    ($path:expr) => {{
+        include_or_read!($path, "text/css")
+    }};
+}
+
+macro_rules! include_html {
+    ($path:expr) => {{
+        include_or_read!($path, "text/html")
+    }};
+}
+
+macro_rules! include_ico {
+    ($path:expr) => {{
+        include_or_read!($path, "image/x-icon")
+    }};
+}
+
+async fn openapi_json() -> impl IntoResponse {
+    Json(openapi::document())
+}
+
+async fn client_ts() -> impl IntoResponse {
+    Response::builder()
+        .header("Content-Type", "text/typescript")
+        .body(Body::from(client::typescript(&openapi::document())))
+        .unwrap()
+}
+
+/// Directory of files overriding the built-in editor assets, e.g. to brand
+/// the editor for an internal deployment.
+///
+/// Files are looked up by their path relative to the server root, e.g.
+/// `index.html` or `static/svgdx-editor.css`, and may include files not
+/// otherwise provided, such as images referenced from an overridden page.
+/// An `example.svg` file is used as the editor's default document.
+#[derive(Clone, Default)]
+struct Assets(Option<PathBuf>);
+
+impl Assets {
+    /// Location of an override file, if the request path is valid and the
+    /// file exists.
+    fn path(&self, path: &str) -> Option<PathBuf> {
+        let dir = self.0.as_ref()?;
+        // Only plain relative paths, so requests can't escape the directory
+        let rel = std::path::Path::new(path);
+        if path.is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            return None;
+        }
+        let full = dir.join(rel);
+        full.is_file().then_some(full)
+    }
+
+    /// Response with the content of an override file, if there is one.
+    async fn response(&self, path: &str) -> Option<Response<Body>> {
+        let full = self.path(path)?;
+        let content = tokio::fs::read(&full).await.ok()?;
+        Some(
+            Response::builder()
+                .header("Content-Type", mime_type(path))
+                .header("Content-Security-Policy", CSP)
+                .body(Body::from(content))
+                .unwrap(),
+        )
+    }
+}
+
+fn mime_type(path: &str) -> &'static str {
+    let ext = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("html" | "htm") => "text/html",
+        Some("js" | "mjs") => "application/javascript",
+        Some("css") => "text/css",
+        Some("ico") => "image/x-icon",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("json") => "application/json",
+        Some("txt" | "md") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+fn not_found() -> Response<Body> {
+    Response::builder()
+        .status(404)
+        .header("Content-Type", "text/plain")
+        .body(Body::from("File not found"))
+        .unwrap()
+}
+
+/// Shared state of the server.
+#[derive(Clone, Default)]
+struct AppState {
+    sessions: Sessions,
+    assets: Assets,
+}
+
+impl FromRef<AppState> for Sessions {
+    fn from_ref(state: &AppState) -> Self {
+        state.sessions.clone()
+    }
+}
+
+impl FromRef<AppState> for Assets {
+    fn from_ref(state: &AppState) -> Self {
+        state.assets.clone()
+    }
+}
+
+async fn index(State(assets): State<Assets>) -> impl IntoResponse {
+    if let Some(response) = assets.response("index.html").await {
+        return response;
+    }
+    include_html!("index.html")
+}
+
+async fn favicon(State(assets): State<Assets>) -> impl IntoResponse {
+    if let Some(response) = assets.response("favicon.ico").await {
+        return response;
+    }
+    include_ico!("favicon.ico")
+}
+
+// There is no built-in example document; the editor uses its own default
+// unless one is provided as an asset override.
+async fn example(State(assets): State<Assets>) -> impl IntoResponse {
+    assets
+        .response("example.svg")
+        .await
+        .unwrap_or_else(not_found)
+}
+
+// Note svgdx-server injects a different bootstrap script (-server.js) vs the bootstrap
+// picked up by a static file server (such as `python3 -m http.server`). This is to ensure
+// transform requests come to the server rather than being handled by the browser WASM code.
+async fn bootstrap() -> impl IntoResponse {
+    include_js!("svgdx-bootstrap-server.js")
+}
+
+async fn static_file(State(assets): State<Assets>, Path(path): Path<String>) -> impl IntoResponse {
+    if let Some(response) = assets.response(&format!("static/{path}")).await {
+        return response;
+    }
+    match path.as_str() {
+        "svgdx-editor.js" => {
+            include_js!("static/svgdx-editor.js")
+        }
+        "svgdx-editor.css" => {
+            include_css!("static/svgdx-editor.css")
+        }
+        "vendor/cm5/codemirror.min.css" => {
+            include_css!("static/vendor/cm5/codemirror.min.css")
+        }
+        "vendor/cm5/codemirror.min.js" => include_js!("static/vendor/cm5/codemirror.min.js"),
+        "vendor/cm5/mode/xml/xml.min.js" => include_js!("static/vendor/cm5/mode/xml/xml.min.js"),
+        "vendor/cm5/addon/fold/xml-fold.min.js" => {
+            include_js!("static/vendor/cm5/addon/fold/xml-fold.min.js")
+        }
+        "vendor/cm5/addon/fold/foldcode.js" => {
+            include_js!("static/vendor/cm5/addon/fold/foldcode.js")
+        }
+        "vendor/cm5/addon/fold/foldgutter.js" => {
+            include_js!("static/vendor/cm5/addon/fold/foldgutter.js")
+        }
+        "vendor/cm5/addon/fold/foldgutter.min.css" => {
+            include_css!("static/vendor/cm5/addon/fold/foldgutter.min.css")
+        }
+        "vendor/cm5/addon/display/autorefresh.min.js" => {
+            include_js!("static/vendor/cm5/addon/display/autorefresh.min.js")
+        }
+        _ => not_found(),
+    }
+}
+
+/// Run the server, listening on `listen_addr` (default `127.0.0.1:3003`).
+///
+/// If `asset_dir` is given, files in it override the built-in editor assets.
+pub async fn start_server(
+    listen_addr: Option<&str>,
+    asset_dir: Option<PathBuf>,
+    ready: Option<Sender<()>>,
+) {
+    let addr = listen_addr.unwrap_or("127.0.0.1:3003");
+    let state = AppState {
+        assets: Assets(asset_dir),
+        ..Default::default()
+    };
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/favicon.ico", get(favicon))
+        .route("/example.svg", get(example))
+        .route("/static/{*path}", get(static_file))
+        .route("/svgdx-bootstrap.js", get(bootstrap))
+        .route("/api/transform", post(transform))
+        .route("/api/complete", post(completions))
+        .route("/api/hover", post(hover_info))
+        .route("/api/metadata", post(metadata))
+        .route("/api/session", post(create_session))
+        .route(
+            "/api/session/{id}",
+            get(session_info).delete(delete_session),
+        )
+        .route(
+            "/api/session/{id}/doc/{name}",
+            put(put_document).delete(delete_document),
+        )
+        .route("/api/session/{id}/render/{name}", get(render_document))
+        .route("/api/openapi.json", get(openapi_json))
+        .route("/api/client.ts", get(client_ts))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    println!("Listening on: http://{}", addr);
+    if let Some(ready) = ready {
+        ready.send(()).await.unwrap();
+    }
+    axum::serve(listener, app).await.unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions() {
+        let sessions = Sessions::default();
+        let id = sessions.create().unwrap();
+        assert_ne!(id, sessions.create().unwrap());
+        assert!(matches!(
+            sessions.put_document("x", "a.svg", String::new()),
+            Err(SessionError::NotFound(_))
+        ));
+
+        sessions
+            .put_document(&id, "lib.svg", "<svg/>".to_string())
+            .unwrap();
+        let before = sessions.documents(&id).unwrap();
+        sessions
+            .put_document(&id, "main.svg", "<svg/>".to_string())
+            .unwrap();
+        // Snapshots are unaffected by later updates
+        assert_eq!(before.len(), 1);
+        assert_eq!(sessions.documents(&id).unwrap().len(), 2);
+
+        sessions.remove_document(&id, "lib.svg").unwrap();
+        assert!(sessions.remove_document(&id, "lib.svg").is_err());
+        sessions.remove(&id).unwrap();
+        assert!(sessions.documents(&id).is_err());
+    }
+
+    #[test]
+    fn test_asset_path() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("editor");
+        let assets = Assets(Some(dir));
+        assert!(assets.path("static/svgdx-editor.js").is_some());
+        assert!(assets.path("static/logo.png").is_none());
+        assert!(assets.path("static").is_none());
+        assert!(assets.path("static/../static/svgdx-editor.js").is_none());
+        assert!(assets.path("/etc/passwd").is_none());
+        assert!(assets.path("").is_none());
+        assert!(Assets(None).path("static/svgdx-editor.js").is_none());
+
+        assert_eq!(mime_type("static/logo.PNG"), "image/png");
+        assert_eq!(mime_type("index.html"), "text/html");
+        assert_eq!(mime_type("README"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_session_render() {
+        let sessions = Sessions::default();
+        let id = sessions.create().unwrap();
+        sessions
+            .put_document(
+                &id,
+                "lib.svg",
+                r#"<svg><specs><rect id="box" wh="20 10"/></specs></svg>"#.to_string(),
+            )
+            .unwrap();
+        let main = r##"<svg><import src="lib.svg"/><reuse id="b" href="#box"/></svg>"##;
+        let docs = sessions.documents(&id).unwrap();
+        let config = TransformConfig {
+            documents: Some(docs),
+            ..Default::default()
+        };
+        let output = transform_str(main.to_string(), &config).unwrap();
+        assert!(output.contains(r#"width="20" height="10""#));
+    }
+
+    #[test]
+    fn test_document_metadata() {
+        let input = r#"<svg><title>Overview</title><rect id="a" wh="10"/><rect wh="1"/>
+<g id="g"><circle id="c" cxy="20 5" r="2"/></g></svg>"#;
+        let svg = transform_str(input.to_string(), &TransformConfig::default()).unwrap();
+        let metadata = DocumentMetadata::from_svg(&svg).unwrap();
+        assert_eq!(metadata.width.as_deref(), Some("32mm"));
+        assert_eq!(metadata.view_box.as_deref(), Some("-5 -5 32 20"));
+        assert_eq!(metadata.title.as_deref(), Some("Overview"));
+        let ids: Vec<_> = metadata.elements.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["a", "g", "c"]);
+        assert_eq!(metadata.elements[1].element, "g");
+        assert_eq!(metadata.elements[1].x, 18.);
+        assert_eq!(metadata.elements[1].width, 4.);
+
+        let metadata = DocumentMetadata::from_svg("<svg/>").unwrap();
+        assert_eq!(metadata.title, None);
+        assert!(metadata.elements.is_empty());
+    }
+}
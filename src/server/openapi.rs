@@ -0,0 +1,398 @@
+//! OpenAPI description of the svgdx-server HTTP API.
+//!
+//! Request and response types are declared with the `api_type!` macro, which
+//! derives a JSON schema from the struct definition (including field doc
+//! comments), so the description served at `/api/openapi.json` stays in step
+//! with the types the handlers actually use.
+
+use serde_json::{json, Map, Value};
+
+use super::{
+    CompletionItem, CursorPosition, DocumentMetadata, ElementItem, RequestConfig, SessionInfo,
+};
+
+/// JSON schema for a type used in a request or response.
+pub(super) trait ApiSchema {
+    /// Whether a field of this type must be present.
+    const REQUIRED: bool = true;
+
+    fn schema() -> Value;
+}
+
+impl ApiSchema for bool {
+    fn schema() -> Value {
+        json!({ "type": "boolean" })
+    }
+}
+
+impl ApiSchema for usize {
+    fn schema() -> Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl ApiSchema for f32 {
+    fn schema() -> Value {
+        json!({ "type": "number" })
+    }
+}
+
+impl ApiSchema for String {
+    fn schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl ApiSchema for &str {
+    fn schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Vec<T> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Option<T> {
+    const REQUIRED: bool = false;
+
+    fn schema() -> Value {
+        json!({ "anyOf": [T::schema(), { "type": "null" }] })
+    }
+}
+
+/// Declare a struct used in the API, implementing `ApiSchema` for it.
+///
+/// Field doc comments become schema descriptions. Fields with a `serde`
+/// attribute (only `#[serde(default)]` is used) or an `Option` type are
+/// not required.
+macro_rules! api_type {
+    (
+        $(#[doc = $doc:literal])*
+        #[derive($($derive:ident),*)]
+        struct $name:ident {
+            $(
+                $(#[doc = $fdoc:literal])*
+                $(#[serde($serde:meta)])?
+                $field:ident: $ty:ty,
+            )*
+        }
+    ) => {
+        $(#[doc = $doc])*
+        #[derive($($derive),*)]
+        struct $name {
+            $(
+                $(#[doc = $fdoc])*
+                $(#[serde($serde)])?
+                $field: $ty,
+            )*
+        }
+
+        impl $crate::server::openapi::ApiSchema for $name {
+            fn schema() -> serde_json::Value {
+                let mut properties = serde_json::Map::new();
+                let mut required = Vec::new();
+                $(
+                    let mut field = <$ty as $crate::server::openapi::ApiSchema>::schema();
+                    let doc: &[&str] = &[$($fdoc.trim()),*];
+                    if !doc.is_empty() {
+                        field["description"] = doc.join(" ").into();
+                    }
+                    properties.insert(stringify!($field).to_string(), field);
+                    if api_type!(@required $ty $(, $serde)?) {
+                        required.push(stringify!($field));
+                    }
+                )*
+                serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                })
+            }
+        }
+    };
+    (@required $ty:ty) => {
+        <$ty as $crate::server::openapi::ApiSchema>::REQUIRED
+    };
+    (@required $ty:ty, $serde:meta) => {
+        false
+    };
+}
+
+pub(super) use api_type;
+
+/// Query parameters corresponding to the fields of an object schema.
+fn query_params<T: ApiSchema>() -> Vec<Value> {
+    let schema = T::schema();
+    let required = &schema["required"];
+    schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, prop)| {
+            let mut prop = prop.clone();
+            let description = prop.as_object_mut().and_then(|p| p.remove("description"));
+            let mut param = json!({
+                "name": name,
+                "in": "query",
+                "required": required.as_array().is_some_and(|r| r.contains(&name.as_str().into())),
+                "schema": prop,
+            });
+            if let Some(description) = description {
+                param["description"] = description;
+            }
+            param
+        })
+        .collect()
+}
+
+fn path_param(name: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "path",
+        "required": true,
+        "description": description,
+        "schema": { "type": "string" },
+    })
+}
+
+fn content(mime: &str, schema: Value) -> Value {
+    json!({ mime: { "schema": schema } })
+}
+
+fn svgdx_body() -> Value {
+    json!({
+        "description": "svgdx document",
+        "required": true,
+        "content": content("application/xml", json!({ "type": "string" })),
+    })
+}
+
+fn svg_response() -> Value {
+    json!({
+        "description": "Rendered SVG",
+        "content": content("image/svg+xml", json!({ "type": "string" })),
+    })
+}
+
+fn json_response<T: ApiSchema>(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": content("application/json", T::schema()),
+    })
+}
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": content("text/plain", json!({ "type": "string" })),
+    })
+}
+
+fn empty_response(description: &str) -> Value {
+    json!({ "description": description })
+}
+
+/// OpenAPI 3.1 description of the server API.
+pub(super) fn document() -> Value {
+    let cursor = query_params::<CursorPosition>();
+    let session_id = path_param("id", "Session id");
+    let doc_name = path_param("name", "Document name, as used in `<import src=\"...\">`");
+    let not_found = error_response("Unknown session or document");
+
+    let mut paths = Map::new();
+    paths.insert(
+        "/api/transform".to_string(),
+        json!({ "post": {
+            "operationId": "transform",
+            "summary": "Transform an svgdx document to SVG",
+            "parameters": query_params::<RequestConfig>(),
+            "requestBody": svgdx_body(),
+            "responses": {
+                "200": svg_response(),
+                "400": error_response("Invalid document"),
+            },
+        }}),
+    );
+    paths.insert(
+        "/api/complete".to_string(),
+        json!({ "post": {
+            "operationId": "complete",
+            "summary": "Completion candidates at a cursor position",
+            "parameters": cursor,
+            "requestBody": svgdx_body(),
+            "responses": {
+                "200": json_response::<Vec<CompletionItem>>("Completion candidates"),
+                "400": error_response("Invalid cursor position"),
+            },
+        }}),
+    );
+    paths.insert(
+        "/api/hover".to_string(),
+        json!({ "post": {
+            "operationId": "hover",
+            "summary": "Resolved geometry of the element referenced at a cursor position",
+            "parameters": cursor,
+            "requestBody": svgdx_body(),
+            "responses": {
+                "200": json_response::<Option<ElementItem>>("Referenced element, if any"),
+                "400": error_response("Invalid cursor position or document"),
+            },
+        }}),
+    );
+    paths.insert(
+        "/api/metadata".to_string(),
+        json!({ "post": {
+            "operationId": "metadata",
+            "summary": "Size, title and element geometry of a transformed document",
+            "parameters": query_params::<RequestConfig>(),
+            "requestBody": svgdx_body(),
+            "responses": {
+                "200": json_response::<DocumentMetadata>("Document metadata"),
+                "400": error_response("Invalid document"),
+            },
+        }}),
+    );
+    paths.insert(
+        "/api/session".to_string(),
+        json!({ "post": {
+            "operationId": "createSession",
+            "summary": "Create a workspace session",
+            "responses": {
+                "201": json_response::<SessionInfo>("New session"),
+                "429": error_response("Too many sessions"),
+            },
+        }}),
+    );
+    paths.insert(
+        "/api/session/{id}".to_string(),
+        json!({
+            "parameters": [session_id],
+            "get": {
+                "operationId": "getSession",
+                "summary": "List the documents in a session",
+                "responses": {
+                    "200": json_response::<SessionInfo>("Session"),
+                    "404": not_found,
+                },
+            },
+            "delete": {
+                "operationId": "deleteSession",
+                "summary": "Remove a session",
+                "responses": {
+                    "204": empty_response("Session removed"),
+                    "404": not_found,
+                },
+            },
+        }),
+    );
+    paths.insert(
+        "/api/session/{id}/doc/{name}".to_string(),
+        json!({
+            "parameters": [session_id, doc_name],
+            "put": {
+                "operationId": "putDocument",
+                "summary": "Add or replace a document in a session",
+                "requestBody": svgdx_body(),
+                "responses": {
+                    "204": empty_response("Document stored"),
+                    "404": not_found,
+                    "429": error_response("Too many documents"),
+                },
+            },
+            "delete": {
+                "operationId": "deleteDocument",
+                "summary": "Remove a document from a session",
+                "responses": {
+                    "204": empty_response("Document removed"),
+                    "404": not_found,
+                },
+            },
+        }),
+    );
+    paths.insert(
+        "/api/session/{id}/render/{name}".to_string(),
+        json!({
+            "parameters": [session_id, doc_name],
+            "get": {
+                "operationId": "renderDocument",
+                "summary": "Render a session document, resolving imports from the session",
+                "parameters": query_params::<RequestConfig>(),
+                "responses": {
+                    "200": svg_response(),
+                    "400": error_response("Invalid document"),
+                    "404": not_found,
+                },
+            },
+        }),
+    );
+    paths.insert(
+        "/api/openapi.json".to_string(),
+        json!({ "get": {
+            "operationId": "openapi",
+            "summary": "This description of the API",
+            "responses": {
+                "200": {
+                    "description": "OpenAPI description",
+                    "content": content("application/json", json!({ "type": "object" })),
+                },
+            },
+        }}),
+    );
+
+    paths.insert(
+        "/api/client.ts".to_string(),
+        json!({ "get": {
+            "operationId": "client",
+            "summary": "TypeScript client for this API, generated from this description",
+            "responses": {
+                "200": {
+                    "description": "TypeScript source",
+                    "content": content("text/typescript", json!({ "type": "string" })),
+                },
+            },
+        }}),
+    );
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "svgdx-server",
+            "version": crate::VERSION,
+        },
+        "paths": paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_schema() {
+        let schema = CursorPosition::schema();
+        assert_eq!(schema["properties"]["line"]["type"], "integer");
+        assert_eq!(schema["required"], json!(["line", "col"]));
+        assert!(schema["properties"]["col"]["description"].is_string());
+
+        // serde(default) fields are optional
+        let schema = RequestConfig::schema();
+        assert_eq!(schema["required"], json!([]));
+        assert_eq!(schema["properties"]["sanitize"]["type"], "boolean");
+    }
+
+    #[test]
+    fn test_document() {
+        let doc = document();
+        assert_eq!(doc["openapi"], "3.1.0");
+        let params = &doc["paths"]["/api/complete"]["post"]["parameters"];
+        assert_eq!(params.as_array().unwrap().len(), 2);
+        assert_eq!(params[0]["in"], "query");
+        assert_eq!(params[0]["required"], true);
+        let items = &doc["paths"]["/api/complete"]["post"]["responses"]["200"]["content"]
+            ["application/json"]["schema"]["items"];
+        assert_eq!(items["properties"]["label"]["type"], "string");
+    }
+}
@@ -0,0 +1,190 @@
+//! State machine diagrams from a list of transitions.
+//!
+//! The content of a `<statemachine>` element is a list of transitions, one
+//! per line, of the form `from -> to` or `from -> to : label`, e.g.
+//!
+//! ```xml
+//! <statemachine>
+//! [*] -> idle
+//! idle -> running : start
+//! running -> idle : stop
+//! running -> [*] : fail
+//! </statemachine>
+//! ```
+//!
+//! Each distinct state becomes a node of a `<graph>` (with a `layered` layout
+//! unless another `layout` is given), and each transition a labelled connector.
+//! The pseudo-state `[*]` is a start marker when used as the source of a
+//! transition, and an end marker when used as the target.
+
+use crate::context::TransformerContext;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{InputList, OutputList};
+use crate::graph::graph_events;
+use crate::position::BoundingBox;
+use crate::transform::EventGen;
+
+use std::str::FromStr;
+
+/// Pseudo-state for initial and final transitions.
+const TERMINAL_STATE: &str = "[*]";
+
+/// A single transition parsed from a `<statemachine>` line.
+#[derive(Clone, Debug, PartialEq)]
+struct Transition {
+    from: String,
+    to: String,
+    label: Option<String>,
+}
+
+impl FromStr for Transition {
+    type Err = SvgdxError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid =
+            || SvgdxError::ParseError(format!("statemachine: invalid transition '{value}'"));
+        let (from, rest) = value.split_once("->").ok_or_else(invalid)?;
+        let (to, label) = match rest.split_once(':') {
+            Some((to, label)) => (to, Some(label.trim().to_string())),
+            None => (rest, None),
+        };
+        let state = |s: &str| {
+            let s = s.trim();
+            if s.is_empty() || s.contains(char::is_whitespace) || s.contains('"') {
+                Err(invalid())
+            } else {
+                Ok(s.to_string())
+            }
+        };
+        Ok(Self {
+            from: state(from)?,
+            to: state(to)?,
+            label: label.filter(|l| !l.is_empty()),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StateMachineElement(pub SvgElement);
+
+impl StateMachineElement {
+    /// Build `<graph>` content of nodes and edges for the given transitions.
+    fn graph_content(&self, transitions: &[Transition]) -> Result<InputList> {
+        let prefix = self.0.get_attr("id").unwrap_or("sm".to_string());
+        let (start_id, end_id) = (format!("{prefix}-start"), format!("{prefix}-end"));
+
+        let mut states: Vec<&str> = Vec::new();
+        let (mut has_start, mut has_end) = (false, false);
+        for t in transitions {
+            for (state, terminal) in [(&t.from, &mut has_start), (&t.to, &mut has_end)] {
+                if state == TERMINAL_STATE {
+                    *terminal = true;
+                } else if !states.contains(&state.as_str()) {
+                    states.push(state);
+                }
+            }
+        }
+
+        let mut content = Vec::new();
+        if has_start {
+            content.push(format!(
+                r#"<circle id="{start_id}" r="2" class="d-fill-black"/>"#
+            ));
+        }
+        for state in states {
+            content.push(format!(r#"<node id="{state}" text="{state}" rx="2"/>"#));
+        }
+        if has_end {
+            content.push(format!(r#"<circle id="{end_id}" r="3"/>"#));
+            content.push(format!(
+                r##"<circle cxy="#{end_id}@c" r="1.5" class="d-fill-black"/>"##
+            ));
+        }
+        for t in transitions {
+            let (from, to) = match (t.from.as_str(), t.to.as_str()) {
+                (TERMINAL_STATE, TERMINAL_STATE) => {
+                    return Err(SvgdxError::InvalidData(
+                        "statemachine: transition between [*] states".to_string(),
+                    ))
+                }
+                (TERMINAL_STATE, to) => (start_id.as_str(), to),
+                (from, TERMINAL_STATE) => (from, end_id.as_str()),
+                (from, to) => (from, to),
+            };
+            let label = t
+                .label
+                .as_ref()
+                .map(|l| format!(r#" text="{}""#, l.replace('"', "&quot;")))
+                .unwrap_or_default();
+            if from == to {
+                // self-transitions loop around the right-hand side of the state
+                content.push(format!(
+                    r#"<edge from="{from}@r:30%" to="{to}@r:70%" connector="polyline" class="d-arrow"{label}/>"#
+                ));
+            } else {
+                content.push(format!(
+                    r#"<edge from="{from}" to="{to}" class="d-arrow"{label}/>"#
+                ));
+            }
+        }
+        InputList::from_str(&format!("\n{}\n", content.join("\n")))
+    }
+}
+
+impl EventGen for StateMachineElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        // Note text is kept escaped, as transition labels become attribute values
+        let mut text = String::new();
+        for ev in self.0.inner_events(context).unwrap_or_default().iter() {
+            if let Some(t) = ev.text_string().or_else(|| ev.cdata_string()) {
+                text.push_str(&t);
+            }
+        }
+        let transitions = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(Transition::from_str)
+            .collect::<Result<Vec<_>>>()?;
+
+        let content = self.graph_content(&transitions)?;
+        let mut graph = self.0.clone();
+        graph.name = "graph".to_string();
+        if !graph.has_attr("layout") {
+            graph.set_attr("layout", "layered");
+        }
+        graph_events(&graph, Some(content), context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transition() {
+        assert_eq!(
+            Transition::from_str("idle -> running : start now").unwrap(),
+            Transition {
+                from: "idle".to_string(),
+                to: "running".to_string(),
+                label: Some("start now".to_string()),
+            }
+        );
+        assert_eq!(
+            Transition::from_str("[*]->idle").unwrap(),
+            Transition {
+                from: "[*]".to_string(),
+                to: "idle".to_string(),
+                label: None,
+            }
+        );
+        assert!(Transition::from_str("idle running").is_err());
+        assert!(Transition::from_str("idle -> ").is_err());
+        assert!(Transition::from_str("a b -> c").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! Summary statistics of a transformation, e.g. for `svgdx --stats`.
+
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// Counts and timings collected during a single transformation.
+#[derive(Debug, Clone, Default)]
+pub struct TransformStats {
+    /// Number of elements processed, including those generated by `reuse` / loops
+    pub elements: u32,
+    /// Number of connectors routed
+    pub connectors: u32,
+    /// Number of auto-style rules and defs generated
+    pub styles: u32,
+    /// Number of additional passes needed to resolve forward references
+    pub deferred_passes: u32,
+    /// Highest build `step` of any element, or zero if no steps are used
+    pub steps: u32,
+    /// Time taken by each phase of the transformation, in order
+    pub phases: Vec<(&'static str, Duration)>,
+    /// Non-fatal issues, e.g. malformed path data skipped with `path-recover`
+    pub warnings: Vec<String>,
+}
+
+impl Display for TransformStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "elements processed: {}", self.elements)?;
+        writeln!(f, "connectors routed:  {}", self.connectors)?;
+        writeln!(f, "styles generated:   {}", self.styles)?;
+        writeln!(f, "deferred passes:    {}", self.deferred_passes)?;
+        for (phase, duration) in &self.phases {
+            writeln!(
+                f,
+                "{:<19} {:.3}ms",
+                format!("{phase} time:"),
+                duration.as_secs_f64() * 1000.
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TransformStats {
+    /// Phase timings in Chrome trace-event JSON format, as used by
+    /// `chrome://tracing` and <https://ui.perfetto.dev>.
+    pub fn chrome_trace(&self) -> String {
+        let mut ts = 0;
+        let events: Vec<String> = self
+            .phases
+            .iter()
+            .map(|(phase, duration)| {
+                let dur = duration.as_micros();
+                let ev = format!(
+                    r#"{{"name":"{phase}","cat":"svgdx","ph":"X","ts":{ts},"dur":{dur},"pid":1,"tid":1}}"#
+                );
+                ts += dur;
+                ev
+            })
+            .collect();
+        format!("[{}]\n", events.join(",\n"))
+    }
+}
+
+/// Measures elapsed time for a phase.
+///
+/// `std::time::Instant` is not available on wasm32, where durations are always zero.
+/// With the `profiling` feature enabled, a `tracing` span is also entered for the
+/// duration of the phase.
+pub struct PhaseTimer {
+    name: &'static str,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+    #[cfg(feature = "profiling")]
+    _span: tracing::span::EnteredSpan,
+}
+
+impl PhaseTimer {
+    pub fn start(name: &'static str) -> Self {
+        Self {
+            name,
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+            #[cfg(feature = "profiling")]
+            _span: tracing::info_span!("phase", name).entered(),
+        }
+    }
+
+    /// Complete the phase, returning its name and duration.
+    pub fn finish(self) -> (&'static str, Duration) {
+        #[cfg(not(target_arch = "wasm32"))]
+        let elapsed = self.start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        let elapsed = Duration::ZERO;
+        (self.name, elapsed)
+    }
+}
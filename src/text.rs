@@ -1,6 +1,10 @@
+use crate::context::TransformerContext;
 use crate::element::SvgElement;
+use crate::expression::eval_attr;
+use crate::path::path_point_dir_at;
 use crate::position::LocSpec;
-use crate::types::{attr_split_cycle, fstr, strp};
+use crate::themes::TEXT_SIZE_SCALES;
+use crate::types::{attr_split, attr_split_cycle, fstr, strp};
 
 use crate::errors::{Result, SvgdxError};
 
@@ -36,6 +40,83 @@ fn text_string(text_value: &str) -> String {
     result
 }
 
+/// Approximate width of a character, in 'em'.
+const CHAR_WIDTH: f32 = 0.6;
+/// Padding between text and the edge of an auto-sized element
+const AUTO_SIZE_PADDING: f32 = 1.;
+
+/// Replace `size="auto [padding]"` with a `width` and `height` fitting the
+/// element's `text`, using an approximate width for each character.
+///
+/// The document's base font size is scaled by any `d-text-*` size class, or
+/// replaced by a numeric `font-size` attribute.
+pub fn resolve_auto_size(element: &mut SvgElement, context: &TransformerContext) -> Result<()> {
+    let Some(size) = element.get_attr("size") else {
+        return Ok(());
+    };
+    let mut parts = attr_split(&size);
+    if parts.next().as_deref() != Some("auto") {
+        return Ok(());
+    }
+    let padding = parts
+        .next()
+        .map(|p| strp(&p))
+        .transpose()
+        .map_err(|e| e.in_attr("size"))?
+        .unwrap_or(AUTO_SIZE_PADDING);
+    if !matches!(element.name.as_str(), "rect" | "box") {
+        return Err(SvgdxError::InvalidData(format!(
+            "size='auto' is not supported for <{}>",
+            element.name
+        ))
+        .in_attr("size"));
+    }
+    let text = element
+        .get_attr("text")
+        .map(|t| eval_attr(&t, context))
+        .ok_or_else(|| SvgdxError::MissingAttribute("text".to_string()).in_attr("size"))?;
+    let font_size = context.config.font_size;
+    element.pop_attr("size");
+
+    let font_size = match element.get_attr("font-size") {
+        Some(fs) => strp(&fs).map_err(|e| e.in_attr("font-size"))?,
+        None => TEXT_SIZE_SCALES
+            .iter()
+            .find(|(class, _)| element.has_class(class))
+            .map_or(font_size, |(_, scale)| font_size * scale),
+    };
+    let line_spacing = element
+        .get_attr("text-lsp")
+        .map(|lsp| strp(&lsp))
+        .transpose()?
+        .unwrap_or(1.05);
+    let text = text_string(&text);
+    let lines: Vec<_> = text.lines().collect();
+    let line_count = lines.len().max(1) as f32;
+    let max_chars = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as f32;
+    let (mut w, mut h) = (
+        max_chars * CHAR_WIDTH * font_size,
+        (1. + (line_count - 1.) * line_spacing) * font_size,
+    );
+    if element.has_class("d-text-vertical") {
+        (w, h) = (h, w);
+    }
+    element.set_attr("width", &fstr(w + padding * 2.));
+    element.set_attr("height", &fstr(h + padding * 2.));
+    Ok(())
+}
+
+/// Fraction of the length along a line-like element (e.g. a connector)
+/// at which text is placed for the `start`, `mid` and `end` text locations.
+fn text_loc_fraction(text_loc: &str) -> Option<f32> {
+    match text_loc {
+        "start" => Some(0.25),
+        "mid" => Some(0.5),
+        "end" => Some(0.75),
+        _ => None,
+    }
+}
+
 fn get_text_position(element: &mut SvgElement) -> Result<(f32, f32, bool, LocSpec, Vec<String>)> {
     let mut t_dx = 0.;
     let mut t_dy = 0.;
@@ -62,6 +143,20 @@ fn get_text_position(element: &mut SvgElement) -> Result<(f32, f32, bool, LocSpe
 
     let mut text_classes = vec!["d-text".to_owned()];
     let text_loc_str = element.pop_attr("text-loc").unwrap_or("c".into());
+    if let Some(frac) = text_loc_fraction(&text_loc_str) {
+        // Text is centred on the line, or offset to its left in the direction
+        // of travel, with a knockout so it remains readable over the line.
+        let text_offset = strp(&element.pop_attr("text-offset").unwrap_or("0".to_string()))?;
+        let ((x, y), (dir_x, dir_y)) = path_point_dir_at(element, frac)?;
+        text_classes.push("d-text-knockout".to_owned());
+        return Ok((
+            x + dir_y * text_offset + t_dx,
+            y - dir_x * text_offset + t_dy,
+            true,
+            LocSpec::Center,
+            text_classes,
+        ));
+    }
     let text_anchor = text_loc_str.parse::<LocSpec>()?;
 
     // Default dx/dy to push it in slightly from the edge (or out for lines);
@@ -236,6 +331,7 @@ pub fn process_text_attr(element: &SvgElement) -> Result<(SvgElement, Vec<SvgEle
         }
     }
     text_elem.src_line = orig_elem.src_line;
+    text_elem.src_col = orig_elem.src_col;
     text_elem.classes = text_classes.into();
 
     // Add this prior to copying over presentation attrs which take precedence
@@ -290,6 +386,7 @@ pub fn process_text_attr(element: &SvgElement) -> Result<(SvgElement, Vec<SvgEle
             tspan_elem.set_attr("style", style);
         }
         tspan_elem.src_line = orig_elem.src_line;
+        tspan_elem.src_col = orig_elem.src_col;
         if vertical {
             tspan_elem.set_attr("y", &y_str);
             lines = lines.into_iter().rev().collect();
@@ -6,7 +6,13 @@
 use crate::context::TransformerContext;
 use crate::errors::{Result, SvgdxError};
 use crate::types::fstr;
-use std::{collections::HashSet, str::FromStr};
+use crate::TransformConfig;
+use std::{
+    collections::HashSet,
+    fmt::{self, Debug, Display},
+    str::FromStr,
+    sync::Arc,
+};
 
 use crate::colours::{COLOUR_LIST, DARK_COLOURS};
 
@@ -41,6 +47,31 @@ impl FromStr for ThemeType {
     }
 }
 
+impl Display for ThemeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Default => "default",
+            Self::Bold => "bold",
+            Self::Fine => "fine",
+            Self::Glass => "glass",
+            Self::Light => "light",
+            Self::Dark => "dark",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Font size of each text size class, relative to the base font size.
+pub(crate) const TEXT_SIZE_SCALES: [(&str, f32); 7] = [
+    ("d-text-smallest", 0.333333),
+    ("d-text-smaller", 0.5),
+    ("d-text-small", 0.666666),
+    ("d-text-medium", 1.), // Default, but include explicitly for completeness
+    ("d-text-large", 1.5),
+    ("d-text-larger", 2.),
+    ("d-text-largest", 3.),
+];
+
 fn append_common_styles(tb: &mut ThemeBuilder, fill: &str, stroke: &str, stroke_width: f32) {
     // Default styles suitable for box-and-line diagrams
     let font_family = &tb.font_family;
@@ -84,16 +115,8 @@ fn append_text_styles(tb: &mut ThemeBuilder) {
         }
     }
 
-    let text_sizes = vec![
-        ("d-text-smallest", tb.font_size * 0.333333),
-        ("d-text-smaller", tb.font_size * 0.5),
-        ("d-text-small", tb.font_size * 0.666666),
-        ("d-text-medium", tb.font_size), // Default, but include explicitly for completeness
-        ("d-text-large", tb.font_size * 1.5),
-        ("d-text-larger", tb.font_size * 2.),
-        ("d-text-largest", tb.font_size * 3.),
-    ];
-    for (class, size) in text_sizes {
+    for (class, scale) in TEXT_SIZE_SCALES {
+        let size = tb.font_size * scale;
         if tb.has_class(class) {
             tb.add_style(&format!(
                 "text.{0}, text.{0} * {{ font-size: {1}px; }}",
@@ -102,6 +125,13 @@ fn append_text_styles(tb: &mut ThemeBuilder) {
             ));
         }
     }
+    if tb.has_class("d-text-knockout") {
+        // wide outline in the background colour hides lines beneath the text
+        tb.add_style(&format!(
+            "text.d-text-knockout, text.d-text-knockout * {{ stroke-width: {}; }}",
+            fstr(tb.font_size * 0.5)
+        ));
+    }
     let text_ol_widths = vec![
         ("d-text-ol", 0.5), // Must be first, so other classes can override
         ("d-text-ol-thinner", 0.125),
@@ -201,21 +231,26 @@ fn append_colour_styles(tb: &mut ThemeBuilder) {
     }
 }
 
-fn append_arrow_styles(tb: &mut ThemeBuilder) {
+fn append_arrow_styles(tb: &mut ThemeBuilder, stroke: &str) {
+    let both = "marker-start: url(#d-arrow); marker-end: url(#d-arrow);";
     let mut has_arrow = false;
-    if tb.has_class("d-arrow") {
-        tb.add_style("line.d-arrow, polyline.d-arrow, path.d-arrow { marker-end: url(#d-arrow); }");
-        has_arrow = true;
-    }
-    if tb.has_class("d-biarrow") {
-        tb.add_style(
-                "line.d-biarrow, polyline.d-biarrow, path.d-biarrow { marker-start: url(#d-arrow); marker-end: url(#d-arrow); }",
-            );
-        has_arrow = true;
+    for (class, markers) in [
+        ("d-arrow", "marker-end: url(#d-arrow);"),
+        ("d-arrow-start", "marker-start: url(#d-arrow);"),
+        ("d-arrow-both", both),
+        ("d-biarrow", both),
+    ] {
+        if tb.has_class(class) {
+            tb.add_style(&format!(
+                "line.{class}, polyline.{class}, path.{class} {{ {markers} }}"
+            ));
+            has_arrow = true;
+        }
     }
     if has_arrow {
-        // override the default 'fill:none' for markers.
-        tb.add_style("marker path { fill: inherit; }");
+        // override the default 'fill:none' for markers; the theme's stroke
+        // colour is used where `context-stroke` isn't supported.
+        tb.add_style(&format!("marker path {{ fill: {stroke}; }}"));
         // Note use of context-stroke for fill, and setting stroke:none to prevent
         // the marker size extending beyond the path boundary.
         // NOTE: the arrow marker butts up against the end of the line so doesn't have
@@ -354,6 +389,23 @@ fn append_pattern_styles(tb: &mut ThemeBuilder, t_stroke: &str) {
     }
 }
 
+/// Styles to hide `d-step-N` elements within an element whose `data-step`
+/// attribute is less than N, so a single document can reveal build steps.
+fn append_step_styles(tb: &mut ThemeBuilder) {
+    let mut steps: Vec<u32> = tb
+        .classes
+        .iter()
+        .filter_map(|c| c.strip_prefix("d-step-")?.parse().ok())
+        .collect();
+    steps.sort();
+    for step in steps {
+        let selectors: Vec<_> = (0..step)
+            .map(|s| format!("[data-step=\"{s}\"] .d-step-{step}"))
+            .collect();
+        tb.add_style(&format!("{} {{ display: none; }}", selectors.join(", ")));
+    }
+}
+
 fn d_softshadow(tb: &mut ThemeBuilder, _: &str) {
     tb.add_style(".d-softshadow { filter: url(#d-softshadow); }");
     tb.add_defs(
@@ -376,88 +428,124 @@ fn d_hardshadow(tb: &mut ThemeBuilder, _: &str) {
     );
 }
 
-trait Theme: Clone {
-    fn build(&self, tb: &mut ThemeBuilder) {
-        let mut outer_svg = String::from("svg");
-        if let Some(id) = &tb.local_style_id {
-            outer_svg = format!("svg#{}", id);
-        }
-        // Any background style needs to be prior to potential CSS nesting from local_id
-        // - it isn't a descendant of the local_id element, but that element itself.
-        if tb.background != "default" {
-            tb.add_style(&format!(
-                "{} {{ background: {}; }}",
-                outer_svg, tb.background
-            ));
-        } else {
-            tb.add_style(&format!(
-                "{} {{ background: {}; }}",
-                outer_svg,
-                self.default_background()
-            ));
-        }
-        if let Some(id) = &tb.local_style_id {
-            // Start a nested CSS block for styles to ensure they don't leak
-            // to surrounding document.
-            tb.add_style(&format!("#{} {{", id));
-        }
-        self.append_early_styles(tb);
-        // Must be before any colour styles which need to override this
-        if tb.has_class("d-surround") {
-            tb.add_style(".d-surround { fill: none; }");
-        }
-
-        append_common_styles(
-            tb,
-            &self.default_fill(),
-            &self.default_stroke(),
-            self.default_stroke_width(),
-        );
-        // Colour styles must appear before text styles, at least so
-        // d-text-ol-[colour] (which sets a default stroke-width) can be
-        // overridden by the text style `d-text-ol-[thickness]`.
-        append_colour_styles(tb);
-
-        append_stroke_width_styles(tb, self.default_stroke_width());
-        if tb.elements.contains("text") {
-            append_text_styles(tb);
-        }
-
-        append_arrow_styles(tb);
-        append_dash_styles(tb);
-        append_pattern_styles(tb, &self.default_stroke());
-
-        type Tfn = dyn Fn(&mut ThemeBuilder, &str);
-        for (class, build_fn) in [
-            ("d-softshadow", &d_softshadow as &Tfn),
-            ("d-hardshadow", &d_hardshadow as &Tfn),
-        ] {
-            if tb.has_class(class) {
-                build_fn(tb, &self.default_stroke());
-            }
-        }
-        self.append_late_styles(tb);
-        // Close the nested CSS block if we opened one.
-        if tb.local_style_id.is_some() {
-            tb.add_style("}");
-        }
-    }
+/// A theme determines the default appearance of elements in a document.
+///
+/// Library users may implement this to provide their own theme, which can
+/// be used by setting `TransformConfig::custom_theme`. All methods have
+/// defaults matching the built-in `default` theme.
+pub trait Theme: Debug + Send + Sync {
+    /// Fill colour for closed shapes, and outline colour for text
     fn default_fill(&self) -> String {
         String::from("white")
     }
+    /// Stroke colour for shapes and lines, and fill colour for text
     fn default_stroke(&self) -> String {
         String::from("black")
     }
+    /// Background for the document, unless overridden by config
     fn default_background(&self) -> String {
         String::from("none")
     }
+    /// Base stroke width; `d-thin` etc. are scaled relative to this
     fn default_stroke_width(&self) -> f32 {
         0.5
     }
+    /// Font family, overriding `TransformConfig::font_family` if provided
+    fn font_family(&self) -> Option<String> {
+        None
+    }
+    /// Font size, overriding `TransformConfig::font_size` if provided
+    fn font_size(&self) -> Option<f32> {
+        None
+    }
+    /// Add styles prior to the common styles, e.g. to set text weight
     fn append_early_styles(&self, _tb: &mut ThemeBuilder) {}
+    /// Add styles after all others, e.g. to override class-based styles
     fn append_late_styles(&self, _tb: &mut ThemeBuilder) {}
 }
 
+fn build_theme(theme: &dyn Theme, tb: &mut ThemeBuilder) {
+    if let Some(font_family) = theme.font_family() {
+        tb.font_family = font_family;
+    }
+    if let Some(font_size) = theme.font_size() {
+        tb.font_size = font_size;
+    }
+    let mut outer_svg = String::from("svg");
+    if let Some(id) = &tb.local_style_id {
+        outer_svg = format!("svg#{}", id);
+    }
+    // Any background style needs to be prior to potential CSS nesting from local_id
+    // - it isn't a descendant of the local_id element, but that element itself.
+    if tb.background != "default" {
+        tb.add_style(&format!(
+            "{} {{ background: {}; }}",
+            outer_svg, tb.background
+        ));
+    } else {
+        tb.add_style(&format!(
+            "{} {{ background: {}; }}",
+            outer_svg,
+            theme.default_background()
+        ));
+    }
+    if let Some(id) = &tb.local_style_id {
+        // Start a nested CSS block for styles to ensure they don't leak
+        // to surrounding document.
+        tb.add_style(&format!("#{} {{", id));
+    }
+    theme.append_early_styles(tb);
+    // Must be before any colour styles which need to override this
+    if tb.has_class("d-surround") {
+        tb.add_style(".d-surround { fill: none; }");
+    }
+
+    append_common_styles(
+        tb,
+        &theme.default_fill(),
+        &theme.default_stroke(),
+        theme.default_stroke_width(),
+    );
+    // Colour styles must appear before text styles, at least so
+    // d-text-ol-[colour] (which sets a default stroke-width) can be
+    // overridden by the text style `d-text-ol-[thickness]`.
+    append_colour_styles(tb);
+
+    append_stroke_width_styles(tb, theme.default_stroke_width());
+    if tb.elements.contains("text") {
+        append_text_styles(tb);
+    }
+
+    append_arrow_styles(tb, &theme.default_stroke());
+    append_dash_styles(tb);
+    append_pattern_styles(tb, &theme.default_stroke());
+
+    append_step_styles(tb);
+
+    if tb.has_class("d-faded") {
+        tb.add_style(".d-faded { opacity: 0.35; }");
+    }
+
+    type Tfn = dyn Fn(&mut ThemeBuilder, &str);
+    for (class, build_fn) in [
+        ("d-softshadow", &d_softshadow as &Tfn),
+        ("d-hardshadow", &d_hardshadow as &Tfn),
+    ] {
+        if tb.has_class(class) {
+            build_fn(tb, &theme.default_stroke());
+        }
+    }
+    theme.append_late_styles(tb);
+    // Close the nested CSS block if we opened one.
+    if tb.local_style_id.is_some() {
+        tb.add_style("}");
+    }
+}
+
+/// Builds the auto-generated styles and defs for a document.
+///
+/// This is passed to `Theme::append_early_styles()` and
+/// `Theme::append_late_styles()` so custom themes may add their own entries.
 pub struct ThemeBuilder {
     local_style_id: Option<String>,
     styles: Vec<String>,
@@ -467,48 +555,67 @@ pub struct ThemeBuilder {
     font_size: f32,
     font_family: String,
     theme: ThemeType,
+    custom_theme: Option<Arc<dyn Theme>>,
     classes: HashSet<String>,
     elements: HashSet<String>,
 }
 
 impl ThemeBuilder {
-    pub fn new(
+    pub(crate) fn new(
         context: &TransformerContext,
         elements: &HashSet<String>,
         classes: &HashSet<String>,
+    ) -> Self {
+        let mut tb = Self::from_config(&context.config, elements, classes);
+        tb.local_style_id.clone_from(&context.local_style_id);
+        tb
+    }
+    fn from_config(
+        config: &TransformConfig,
+        elements: &HashSet<String>,
+        classes: &HashSet<String>,
     ) -> Self {
         Self {
-            local_style_id: context.local_style_id.clone(),
+            local_style_id: None,
             styles: Vec::new(),
             defs: Vec::new(),
-            background: context.config.background.clone(),
-            font_size: context.config.font_size,
-            font_family: context.config.font_family.clone(),
-            theme: context.config.theme.clone(),
+            background: config.background.clone(),
+            font_size: config.font_size,
+            font_family: config.font_family.clone(),
+            theme: config.theme.clone(),
+            custom_theme: config.custom_theme.clone(),
             classes: classes.to_owned(),
             elements: elements.to_owned(),
         }
     }
     pub fn build(&mut self) {
+        if let Some(theme) = self.custom_theme.clone() {
+            build_theme(theme.as_ref(), self);
+            return;
+        }
         match self.theme {
-            ThemeType::Default => DefaultTheme {}.build(self),
-            ThemeType::Bold => BoldTheme {}.build(self),
-            ThemeType::Fine => FineTheme {}.build(self),
-            ThemeType::Glass => GlassTheme {}.build(self),
-            ThemeType::Light => LightTheme {}.build(self),
-            ThemeType::Dark => DarkTheme {}.build(self),
+            ThemeType::Default => build_theme(&DefaultTheme, self),
+            ThemeType::Bold => build_theme(&BoldTheme, self),
+            ThemeType::Fine => build_theme(&FineTheme, self),
+            ThemeType::Glass => build_theme(&GlassTheme, self),
+            ThemeType::Light => build_theme(&LightTheme, self),
+            ThemeType::Dark => build_theme(&DarkTheme, self),
         }
     }
-    fn has_class(&self, s: &str) -> bool {
+    /// Is the given class used anywhere in the document?
+    pub fn has_class(&self, s: &str) -> bool {
         self.classes.iter().any(|x| x == s)
     }
-    fn has_element(&self, s: &str) -> bool {
+    /// Is the given element type used anywhere in the document?
+    pub fn has_element(&self, s: &str) -> bool {
         self.elements.iter().any(|x| x == s)
     }
-    fn add_defs(&mut self, s: &str) {
+    /// Add an entry (e.g. a `<marker>` or `<pattern>`) to the generated `<defs>`
+    pub fn add_defs(&mut self, s: &str) {
         self.defs.push(s.to_owned());
     }
-    fn add_style(&mut self, s: &str) {
+    /// Add a CSS rule to the generated `<style>`
+    pub fn add_style(&mut self, s: &str) {
         self.styles.push(s.to_owned());
     }
     pub fn get_defs(&self) -> Vec<String> {
@@ -588,3 +695,289 @@ impl Theme for DarkTheme {
         String::from("#073642")
     }
 }
+
+/// Description of an auto-style class and the styles it generates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoStyleClass {
+    /// Class name, e.g. `d-red`
+    pub name: String,
+    /// Short human-readable description
+    pub description: String,
+    /// CSS rules added when the class is used
+    pub css: Vec<String>,
+    /// Entries (e.g. markers or patterns) added to `<defs>` when the class is used
+    pub defs: Vec<String>,
+}
+
+fn auto_style_descriptions() -> Vec<(String, String)> {
+    let mut classes: Vec<(String, String)> = [
+        (
+            "d-surround",
+            "No fill, e.g. for shapes surrounding other elements",
+        ),
+        (
+            "d-text",
+            "Center text horizontally and vertically on its anchor",
+        ),
+        ("d-text-top", "Text below its anchor point"),
+        ("d-text-bottom", "Text above its anchor point"),
+        ("d-text-left", "Text to the right of its anchor point"),
+        ("d-text-right", "Text to the left of its anchor point"),
+        (
+            "d-text-top-vertical",
+            "Vertical text below its anchor point",
+        ),
+        (
+            "d-text-bottom-vertical",
+            "Vertical text above its anchor point",
+        ),
+        (
+            "d-text-left-vertical",
+            "Vertical text to the right of its anchor point",
+        ),
+        (
+            "d-text-right-vertical",
+            "Vertical text to the left of its anchor point",
+        ),
+        ("d-text-bold", "Bold text"),
+        ("d-text-normal", "Normal weight text"),
+        ("d-text-light", "Light weight text"),
+        ("d-text-italic", "Italic text"),
+        ("d-text-monospace", "Monospace font text"),
+        (
+            "d-text-knockout",
+            "Text with a background outline, readable over lines",
+        ),
+        ("d-text-smallest", "Text at 1/3 of the default size"),
+        ("d-text-smaller", "Text at 1/2 of the default size"),
+        ("d-text-small", "Text at 2/3 of the default size"),
+        ("d-text-medium", "Text at the default size"),
+        ("d-text-large", "Text at 1.5x the default size"),
+        ("d-text-larger", "Text at 2x the default size"),
+        ("d-text-largest", "Text at 3x the default size"),
+        ("d-text-ol", "Outline text"),
+        ("d-text-ol-thinner", "Outline text with a thinner stroke"),
+        ("d-text-ol-thin", "Outline text with a thin stroke"),
+        ("d-text-ol-medium", "Outline text with the default stroke"),
+        ("d-text-ol-thick", "Outline text with a thick stroke"),
+        ("d-text-ol-thicker", "Outline text with a thicker stroke"),
+        ("d-thinner", "Stroke at 1/4 of the default width"),
+        ("d-thin", "Stroke at 1/2 of the default width"),
+        ("d-thick", "Stroke at 2x the default width"),
+        ("d-thicker", "Stroke at 4x the default width"),
+        ("d-arrow", "Arrowhead at the end of a line"),
+        ("d-arrow-start", "Arrowhead at the start of a line"),
+        ("d-arrow-both", "Arrowheads at both ends of a line"),
+        ("d-biarrow", "Arrowheads at both ends of a line"),
+        ("d-flow-slower", "Animated dashes, slower"),
+        ("d-flow-slow", "Animated dashes, slow"),
+        ("d-flow", "Animated dashes moving along a line"),
+        ("d-flow-fast", "Animated dashes, fast"),
+        ("d-flow-faster", "Animated dashes, faster"),
+        ("d-flow-rev", "Reverse the direction of d-flow animation"),
+        ("d-dash", "Dashed stroke"),
+        ("d-dot", "Dotted stroke"),
+        ("d-dot-dash", "Alternating dots and dashes"),
+        ("d-grid", "Grid pattern fill; append -N for spacing N"),
+        (
+            "d-grid-h",
+            "Horizontal line pattern fill; append -N for spacing N",
+        ),
+        (
+            "d-grid-v",
+            "Vertical line pattern fill; append -N for spacing N",
+        ),
+        ("d-hatch", "Hatched pattern fill; append -N for spacing N"),
+        (
+            "d-crosshatch",
+            "Cross-hatched pattern fill; append -N for spacing N",
+        ),
+        (
+            "d-stipple",
+            "Stippled pattern fill; append -N for spacing N",
+        ),
+        ("d-softshadow", "Soft drop shadow"),
+        ("d-hardshadow", "Hard drop shadow"),
+        ("d-faded", "Reduced opacity, e.g. for background context"),
+        (
+            "d-step-1",
+            "Build step; d-step-N is hidden within elements with data-step below N",
+        ),
+    ]
+    .iter()
+    .map(|(c, d)| (c.to_string(), d.to_string()))
+    .collect();
+    for (prefix, desc) in [
+        ("d-", "Stroke colour (and text colour)"),
+        ("d-fill-", "Fill colour, with contrasting text"),
+        ("d-text-", "Text colour"),
+        ("d-text-ol-", "Text outline colour"),
+    ] {
+        for colour in COLOUR_LIST {
+            classes.push((format!("{prefix}{colour}"), format!("{desc}: {colour}")));
+        }
+    }
+    classes
+}
+
+/// Return all available auto-style classes with the styles they generate
+/// given the theme and font settings in `config`.
+///
+/// This is intended for editor front-ends, e.g. to provide autocomplete.
+pub fn auto_style_classes(config: &TransformConfig) -> Vec<AutoStyleClass> {
+    let elements: HashSet<String> = ["text".to_string()].into();
+    let mut base = ThemeBuilder::from_config(config, &elements, &HashSet::new());
+    base.build();
+
+    auto_style_descriptions()
+        .into_iter()
+        .map(|(name, description)| {
+            let mut tb = ThemeBuilder::from_config(config, &elements, &[name.clone()].into());
+            tb.build();
+            AutoStyleClass {
+                css: tb
+                    .styles
+                    .into_iter()
+                    .filter(|s| !base.styles.contains(s))
+                    .collect(),
+                defs: tb.defs,
+                name,
+                description,
+            }
+        })
+        .collect()
+}
+
+/// A `Theme` defined by values rather than a new type.
+///
+/// ```
+/// use std::sync::Arc;
+/// use svgdx::{CustomTheme, TransformConfig};
+///
+/// let theme = CustomTheme::new()
+///     .stroke("navy")
+///     .stroke_width(0.25)
+///     .css("rect { rx: 1px; }");
+/// let cfg = TransformConfig {
+///     custom_theme: Some(Arc::new(theme)),
+///     ..Default::default()
+/// };
+/// let output = svgdx::transform_str("<svg><rect wh=\"10\"/></svg>", &cfg).unwrap();
+/// assert!(output.contains("stroke-width: 0.25; fill: white; stroke: navy;"));
+/// assert!(output.contains("rect { rx: 1px; }"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CustomTheme {
+    fill: Option<String>,
+    stroke: Option<String>,
+    background: Option<String>,
+    stroke_width: Option<f32>,
+    font_family: Option<String>,
+    font_size: Option<f32>,
+    css: Vec<String>,
+}
+
+impl CustomTheme {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fill(mut self, fill: &str) -> Self {
+        self.fill = Some(fill.to_owned());
+        self
+    }
+
+    pub fn stroke(mut self, stroke: &str) -> Self {
+        self.stroke = Some(stroke.to_owned());
+        self
+    }
+
+    pub fn background(mut self, background: &str) -> Self {
+        self.background = Some(background.to_owned());
+        self
+    }
+
+    pub fn stroke_width(mut self, stroke_width: f32) -> Self {
+        self.stroke_width = Some(stroke_width);
+        self
+    }
+
+    pub fn font_family(mut self, font_family: &str) -> Self {
+        self.font_family = Some(font_family.to_owned());
+        self
+    }
+
+    pub fn font_size(mut self, font_size: f32) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    /// Add a CSS rule, appended after all other styles
+    pub fn css(mut self, rule: &str) -> Self {
+        self.css.push(rule.to_owned());
+        self
+    }
+}
+
+impl Theme for CustomTheme {
+    fn default_fill(&self) -> String {
+        self.fill
+            .clone()
+            .unwrap_or_else(|| DefaultTheme.default_fill())
+    }
+    fn default_stroke(&self) -> String {
+        self.stroke
+            .clone()
+            .unwrap_or_else(|| DefaultTheme.default_stroke())
+    }
+    fn default_background(&self) -> String {
+        self.background
+            .clone()
+            .unwrap_or_else(|| DefaultTheme.default_background())
+    }
+    fn default_stroke_width(&self) -> f32 {
+        self.stroke_width
+            .unwrap_or_else(|| DefaultTheme.default_stroke_width())
+    }
+    fn font_family(&self) -> Option<String> {
+        self.font_family.clone()
+    }
+    fn font_size(&self) -> Option<f32> {
+        self.font_size
+    }
+    fn append_late_styles(&self, tb: &mut ThemeBuilder) {
+        for rule in &self.css {
+            tb.add_style(rule);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_style_classes() {
+        let classes = auto_style_classes(&TransformConfig::default());
+        // every class should generate some output
+        for class in &classes {
+            assert!(
+                !class.css.is_empty() || !class.defs.is_empty(),
+                "{}",
+                class.name
+            );
+        }
+        let thick = classes.iter().find(|c| c.name == "d-thick").unwrap();
+        assert_eq!(thick.css, vec![".d-thick { stroke-width: 1; }"]);
+        let arrow = classes.iter().find(|c| c.name == "d-arrow").unwrap();
+        assert_eq!(arrow.defs.len(), 1);
+
+        let config = TransformConfig {
+            theme: ThemeType::Bold,
+            ..Default::default()
+        };
+        let classes = auto_style_classes(&config);
+        let thick = classes.iter().find(|c| c.name == "d-thick").unwrap();
+        assert_eq!(thick.css, vec![".d-thick { stroke-width: 2; }"]);
+    }
+}
@@ -1,13 +1,31 @@
+use crate::align::align_events;
+use crate::areas::areas_events;
+use crate::bus::BusElement;
+use crate::connect_el::ConnectElement;
+use crate::connector::{connector_jumps, parallel_offset};
 use crate::context::{ElementMap, TransformerContext};
 use crate::element::SvgElement;
 use crate::errors::{Result, SvgdxError};
-use crate::events::{tagify_events, InputList, OutputEvent, OutputList, Tag};
+use crate::events::{tagify_events, InputEvent, InputList, OutputEvent, OutputList, Tag};
 use crate::expression::{eval_attr, eval_condition};
+use crate::graph::GraphElement;
+use crate::import::expand_imports;
+use crate::key::key_events;
+use crate::layout::layout_events;
 use crate::loop_el::{ForElement, LoopElement};
+use crate::pack::pack_element;
+use crate::path::recover_path_data;
 use crate::position::{BoundingBox, BoundingBoxBuilder, LocSpec};
 use crate::reuse::ReuseElement;
+use crate::schematic::schematic_events;
+use crate::statemachine::StateMachineElement;
+use crate::stats::PhaseTimer;
+use crate::text::resolve_auto_size;
 use crate::themes::ThemeBuilder;
+use crate::transform_attr::TransformAttr;
+use crate::tree::tree_events;
 use crate::types::{fstr, split_unit, AttrMap, OrderIndex};
+use crate::wave::WaveElement;
 use crate::TransformConfig;
 
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -41,6 +59,16 @@ impl EventGen for SvgElement {
             "if" => IfElement(self.clone()).generate_events(context),
             "defaults" => DefaultsElement(self.clone()).generate_events(context),
             "for" => ForElement(self.clone()).generate_events(context),
+            "connect" => ConnectElement(self.clone()).generate_events(context),
+            "graph" => GraphElement(self.clone()).generate_events(context),
+            "bus" => BusElement(self.clone()).generate_events(context),
+            "wave" => WaveElement(self.clone()).generate_events(context),
+            "part" | "net" => schematic_events(self, context),
+            "key" | "keyboard" => key_events(self, context),
+            "statemachine" => StateMachineElement(self.clone()).generate_events(context),
+            "align" | "distribute" => align_events(self, context),
+            "row" | "col" => layout_events(self, self.inner_events(context), context),
+            "tree" => tree_events(self, self.inner_events(context), context),
             "g" | "symbol" => GroupElement(self.clone()).generate_events(context),
             _ => {
                 if let Some((start, end)) = self.event_range {
@@ -115,18 +143,25 @@ impl EventGen for Container {
                     return Ok((self.0.all_events(context).into(), None));
                 }
                 new_el.eval_attributes(context);
+                new_el.resolve_fade()?;
+                resolve_step(&mut new_el, context)?;
                 if context.config.add_metadata {
                     new_el
                         .attrs
                         .insert("data-src-line", self.0.src_line.to_string());
                 }
                 let mut events = OutputList::new();
-                events.push(OutputEvent::Start(new_el));
+                events.push(OutputEvent::Start(new_el.clone()));
                 let (evlist, mut bbox) = if inner_text.is_some() {
                     // inner_text implies no processable events; use as-is
                     (inner_events.into(), None)
                 } else {
-                    process_events(inner_events, context)?
+                    // provides parent size for percentage-valued attributes
+                    // of child elements.
+                    context.push_parent(&new_el);
+                    let res = process_events(inner_events, context);
+                    context.pop_parent();
+                    res?
                 };
                 events.extend(&evlist);
                 events.push(OutputEvent::End(self.0.name.clone()));
@@ -143,6 +178,28 @@ impl EventGen for Container {
     }
 }
 
+/// Convert any `step` attribute on the element into a `d-step-N` class,
+/// hiding the element if it is after the configured build step.
+fn resolve_step(el: &mut SvgElement, context: &mut TransformerContext) -> Result<()> {
+    let Some(step) = el.pop_attr("step") else {
+        return Ok(());
+    };
+    let step: u32 = eval_attr(&step, context)
+        .parse()
+        .ok()
+        .filter(|&s| s > 0)
+        .ok_or_else(|| {
+            SvgdxError::InvalidData(format!("step must be a positive integer: '{step}'"))
+                .in_attr("step")
+        })?;
+    context.stats.steps = context.stats.steps.max(step);
+    el.add_class(&format!("d-step-{step}"));
+    if context.config.step.is_some_and(|s| step > s) {
+        el.set_attr("display", "none");
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 struct OtherElement(SvgElement);
 
@@ -153,14 +210,57 @@ impl EventGen for OtherElement {
     ) -> Result<(OutputList, Option<BoundingBox>)> {
         let mut output = OutputList::new();
         let mut e = self.0.clone();
+        let packed = pack_element(&mut e, context)?;
+        resolve_auto_size(&mut e, context)?;
+        e.resolve_auto_aspect(context)?;
         e.resolve_position(context)?; // transmute assumes some of this (e.g. dxy -> dx/dy) has been done
+        if let Some((dx, dy)) = e
+            .get_attr("id")
+            .filter(|_| !e.is_connector())
+            .and_then(|id| context.get_adjustment(&id))
+        {
+            e = e.translated(dx, dy)?;
+        }
+        resolve_step(&mut e, context)?;
+        // recovered path data is only used for geometry; output is unchanged
+        let mut original_d = None;
+        if e.name == "path" && context.config.path_recover {
+            if let Some(d) = e.get_attr("d") {
+                let (recovered, warnings) = recover_path_data(&d);
+                if !warnings.is_empty() {
+                    e.set_attr("d", &recovered);
+                    original_d = Some(d);
+                    let line = self.0.src_line;
+                    context
+                        .stats
+                        .warnings
+                        .extend(warnings.iter().map(|w| format!("line {line}: {w}")));
+                }
+            }
+        }
+        let is_connector = e.is_connector();
+        let conn_key = if is_connector {
+            parallel_offset(&mut e, context)
+        } else {
+            None
+        };
         e.transmute(context)?;
+        if is_connector {
+            context.stats.connectors += 1;
+            connector_jumps(&mut e, context)?;
+        }
+        if let Some(key) = conn_key {
+            context.add_connector(key);
+        }
         e.resolve_position(context)?;
         context.update_element(&e);
         let mut bb = context.get_element_bbox(&e)?;
         if bb.is_some() {
             context.set_prev_element(&e);
         }
+        if let Some(d) = original_d {
+            e.set_attr("d", &d);
+        }
         let events = e.element_events(context)?;
         for svg_ev in events {
             let is_empty = matches!(svg_ev, OutputEvent::Empty(_));
@@ -168,7 +268,8 @@ impl EventGen for OtherElement {
                 let mut new_el = SvgElement::new(&e.name, &[]);
                 // Collect pass-through attributes
                 for (k, v) in e.attrs {
-                    if k != "class" && k != "data-src-line" && k != "_" && k != "__" {
+                    // `ports` are only used to resolve references to this element
+                    if !matches!(k.as_str(), "class" | "data-src-line" | "_" | "__" | "ports") {
                         new_el.set_attr(&k, &v);
                     }
                 }
@@ -198,6 +299,9 @@ impl EventGen for OtherElement {
             // (They can generate text though, so not rejected earlier.
             bb = None;
         }
+        if let Some((region, state)) = packed {
+            context.set_pack_state(&region, state);
+        }
         Ok((output, bb))
     }
 }
@@ -210,51 +314,111 @@ impl EventGen for GroupElement {
         &self,
         context: &mut TransformerContext,
     ) -> Result<(OutputList, Option<BoundingBox>)> {
-        // since we synthesize the opening element event here, we need to
-        // do any required transformations on the <g> itself here.
-        let mut new_el = self.0.clone();
-        new_el.eval_attributes(context);
+        if self.0.has_attr("template-areas") {
+            return areas_events(&self.0, self.0.inner_events(context), context);
+        }
+        group_events(&self.0, self.0.inner_events(context), context)
+    }
+}
 
-        // push variables onto the stack
-        context.push_element(&self.0);
+/// Translate a group moved by `<align>` or `<distribute>`.
+fn adjust_group(el: &mut SvgElement, context: &TransformerContext) {
+    if let Some((dx, dy)) = el
+        .get_attr("id")
+        .and_then(|id| context.get_adjustment(&eval_attr(&id, context)))
+    {
+        let translate = format!("translate({}, {})", fstr(dx), fstr(dy));
+        let xfrm = match el.get_attr("transform") {
+            Some(xfrm) => format!("{translate} {xfrm}"),
+            None => translate,
+        };
+        el.set_attr("transform", &xfrm);
+    }
+}
 
-        let mut content_bb = None;
-        let mut events = OutputList::new();
-        if self.0.is_empty_element() {
-            events.push(OutputEvent::Empty(new_el));
-        } else {
-            let el_name = new_el.name.clone();
-            events.push(OutputEvent::Start(new_el));
+/// Process a group-like element with the given content, which may differ from
+/// the element's own content in the source document (e.g. for `<graph>`).
+pub(crate) fn group_events(
+    element: &SvgElement,
+    content: Option<InputList>,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    // since we synthesize the opening element event here, we need to
+    // do any required transformations on the <g> itself here.
+    let mut new_el = element.clone();
+    adjust_group(&mut new_el, context);
+    new_el.eval_attributes(context);
+    new_el.resolve_fade()?;
+    resolve_step(&mut new_el, context)?;
+    new_el.pop_attr("ports");
+
+    // content of translated groups is positioned relative to the group
+    let translation = match new_el.get_attr("transform") {
+        Some(xfrm) if new_el.name == "g" => xfrm.parse::<TransformAttr>()?.translation(),
+        _ => None,
+    };
+    if let Some((dx, dy)) = translation {
+        context.push_group_offset(dx, dy);
+    }
 
-            if let Some(inner_events) = self.0.inner_events(context) {
-                let (ev_list, bb) = process_events(inner_events, context)?;
-                content_bb = bb;
-                events.extend(&ev_list);
+    // push variables onto the stack; the group also provides parent size
+    // for percentage-valued attributes of child elements.
+    context.push_element(element);
+    context.push_parent(&new_el);
+
+    let mut content_bb = None;
+    let mut events = OutputList::new();
+    if element.is_empty_element() {
+        events.push(OutputEvent::Empty(new_el));
+    } else {
+        let el_name = new_el.name.clone();
+        events.push(OutputEvent::Start(new_el));
+
+        if let Some(inner_events) = content {
+            let processed = process_events(inner_events, context);
+            if processed.is_err() {
+                context.pop_parent();
+                if translation.is_some() {
+                    // don't leave subsequent elements offset
+                    context.pop_group_offset();
+                }
             }
-
-            events.push(OutputEvent::End(el_name));
+            let (ev_list, bb) = processed?;
+            content_bb = bb;
+            events.extend(&ev_list);
         }
 
-        // pop variables off the stack
-        context.pop_element();
+        events.push(OutputEvent::End(el_name));
+    }
 
-        // Messy! should probably have a id->bbox map in context
-        let mut new_el = self.0.clone();
-        new_el.content_bbox = content_bb;
-        context.update_element(&new_el);
-        context.set_prev_element(&new_el);
+    // pop variables off the stack
+    context.pop_parent();
+    context.pop_element();
+    if translation.is_some() {
+        context.pop_group_offset();
+    }
 
-        let result_bb = if self.0.name == "symbol" {
-            // symbols have a size which needs storing in context for evaluating
-            // bbox of 'use' elements referencing them, but they don't contribute
-            // to the parent bbox.
-            None
-        } else {
-            // this handles any `transform` attr. Assumes .content_bbox is set.
-            new_el.bbox()?
-        };
-        Ok((events, result_bb))
+    // Messy! should probably have a id->bbox map in context
+    let mut new_el = element.clone();
+    adjust_group(&mut new_el, context);
+    new_el.content_bbox = content_bb;
+    if let Some(xfrm) = new_el.get_attr("transform") {
+        // bbox() requires the evaluated transform, e.g. `translate({{$x}})`
+        new_el.set_attr("transform", &eval_attr(&xfrm, context));
     }
+    context.update_element(&new_el);
+    context.set_prev_element(&new_el);
+
+    let result_bb = if element.name == "symbol" {
+        // symbols have a size which needs storing in context for evaluating
+        // bbox of 'use' elements referencing them, but they don't contribute
+        // to the parent bbox.
+        None
+    } else {
+        // this handles any `transform` attr. Assumes .content_bbox is set.
+        new_el.bbox()?
+    };
+    Ok((events, result_bb))
 }
 
 #[derive(Debug, Clone)]
@@ -267,27 +431,7 @@ impl EventGen for ConfigElement {
     ) -> Result<(OutputList, Option<BoundingBox>)> {
         let mut new_config = context.config.clone();
         for (key, value) in &self.0.attrs {
-            match key.as_str() {
-                "scale" => new_config.scale = value.parse()?,
-                "debug" => new_config.debug = value.parse()?,
-                "add-auto-styles" => new_config.add_auto_styles = value.parse()?,
-                "use-local-styles" => new_config.use_local_styles = value.parse()?,
-                "border" => new_config.border = value.parse()?,
-                "background" => new_config.background.clone_from(value),
-                "loop-limit" => new_config.loop_limit = value.parse()?,
-                "var-limit" => new_config.var_limit = value.parse()?,
-                "depth-limit" => new_config.depth_limit = value.parse()?,
-                "font-size" => new_config.font_size = value.parse()?,
-                "font-family" => new_config.font_family.clone_from(value),
-                "seed" => new_config.seed = value.parse()?,
-                "theme" => new_config.theme = value.parse()?,
-                "svg-style" => new_config.svg_style = Some(value.clone()),
-                _ => {
-                    return Err(SvgdxError::InvalidData(format!(
-                        "Unknown config setting {key}"
-                    )))
-                }
-            }
+            new_config.set(key, value)?;
         }
         context.set_config(new_config);
         Ok((OutputList::new(), None))
@@ -408,6 +552,8 @@ impl EventGen for Tag {
         let mut bbox = None;
         match self {
             Tag::Compound(el, tail) => {
+                let mut el = el.clone();
+                context.apply_overrides(&mut el);
                 let (ev, bb) = el.generate_events(context)?;
                 (events, bbox) = (ev, bb);
                 if let (Some(tail), false) = (tail, events.is_empty()) {
@@ -417,6 +563,7 @@ impl EventGen for Tag {
             Tag::Leaf(el, tail) => {
                 let mut el = el.clone();
                 context.apply_defaults(&mut el);
+                context.apply_overrides(&mut el);
                 let (ev, bb) = el.generate_events(context)?;
                 (events, bbox) = (ev, bb);
                 if let (Some(tail), false) = (tail, events.is_empty()) {
@@ -449,24 +596,40 @@ fn process_tags(
     let mut element_errors: HashMap<OrderIndex, (SvgElement, SvgdxError)> = HashMap::new();
     let remain = &mut Vec::new();
 
+    let mut first_pass = true;
     while !tags.is_empty() && remain.len() != tags.len() {
+        let mut resolve_timer = None;
+        if !first_pass {
+            context.stats.deferred_passes += 1;
+            resolve_timer = context.start_resolve();
+        }
+        first_pass = false;
         for (idx, t) in &mut tags.iter_mut() {
             let idx = idx.clone();
             let el = if let Some(el) = t.get_element() {
                 // update early so reuse targets are available even if the element
                 // is not ready (e.g. within a specs block)
                 context.update_element(&el);
+                context.seed_element_rng(&el);
                 Some(el.clone())
             } else {
                 None
             };
             let gen_result = t.generate_events(context);
+            if let Err(err @ SvgdxError::ElementLimitError(..)) = gen_result {
+                // Not specific to this element; abort processing entirely.
+                return Err(err);
+            }
             if !context.in_specs {
                 // if we *are* in a specs block, we don't care if there were errors;
                 // a specs entry may have insufficient context until reuse time.
                 // We do still call generate_events for side-effects including registering
                 // elements for reuse.
                 if let Ok((events, maybe_bbox)) = gen_result {
+                    if let Some(el) = &el {
+                        context.inc_element_count()?;
+                        context.add_processed(el);
+                    }
                     if let Some(bbox) = maybe_bbox {
                         bbb.extend(bbox); // TODO: should this pattern take an Option?
                     }
@@ -487,6 +650,7 @@ fn process_tags(
                 }
             }
         }
+        context.finish_resolve(resolve_timer);
         if tags.len() == remain.len() {
             return Err(SvgdxError::MultiError(element_errors));
         }
@@ -497,6 +661,23 @@ fn process_tags(
     Ok(bbb.clone().build())
 }
 
+/// Attributes which position a generated group (e.g. a `<keyboard>` or `<wave>`),
+/// and are moved to its frame element.
+pub(crate) const FRAME_POSITION_ATTRS: &[&str] = &["x", "y", "xy", "dx", "dy", "dxy"];
+
+/// Process the given elements as a sequence of empty elements.
+pub(crate) fn process_elements(
+    elements: Vec<SvgElement>,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    let mut events = InputList::new();
+    for el in elements {
+        events.push(InputEvent::from(OutputEvent::Text("\n".to_string())));
+        events.push(InputEvent::from(OutputEvent::Empty(el)));
+    }
+    process_events(events, context)
+}
+
 pub fn process_events(
     input: InputList,
     context: &mut TransformerContext,
@@ -538,10 +719,185 @@ impl Transformer {
     }
 
     pub fn transform(&mut self, reader: &mut dyn BufRead, writer: &mut dyn Write) -> Result<()> {
-        let input = InputList::from_reader(reader)?;
+        let timer = PhaseTimer::start("parse");
+        let mut input = InputList::from_reader(reader)?;
+        input = expand_imports(
+            input,
+            self.context.config.import_dir.as_deref(),
+            self.context.config.documents.as_deref(),
+        )?;
+        if self.context.config.sanitize {
+            input = input.sanitized();
+        }
         self.context.set_events(input.events.clone());
-        let output = process_events(input, &mut self.context)?;
-        self.postprocess(output, writer)
+        self.context.stats.phases.push(timer.finish());
+
+        // Deferred passes to resolve forward references are timed separately
+        // as the 'resolve' phase, so are excluded from the 'collect' time.
+        let timer = PhaseTimer::start("collect");
+        let config = self.context.config.clone();
+        let mut output = process_events(input.clone(), &mut self.context)?;
+        let mut resolve_time = self.context.take_resolve_time();
+        let adjustments = self.context.take_adjustments();
+        if !adjustments.is_empty() {
+            // Elements moved by `<align>` or `<distribute>` may already have been
+            // rendered and used to position others, so process the document again
+            // with the adjustments applied as each element is positioned.
+            let mut context = TransformerContext::from_config(&config);
+            context.local_style_id = self.context.local_style_id.clone();
+            context.stats.phases = mem::take(&mut self.context.stats.phases);
+            context.set_events(input.events.clone());
+            context.apply_adjustments(adjustments);
+            self.context = context;
+            let resolve_timer = self.context.start_resolve();
+            output = process_events(input, &mut self.context)?;
+            self.context.finish_resolve(resolve_timer);
+            resolve_time += self.context.take_resolve_time();
+        }
+        let (name, elapsed) = timer.finish();
+        let phases = &mut self.context.stats.phases;
+        phases.push((name, elapsed.saturating_sub(resolve_time)));
+        phases.push(("resolve", resolve_time));
+
+        // Output is buffered so the size limit can be checked before anything is written.
+        // Generation of auto-styles is timed as the 'style' phase within postprocess().
+        let timer = PhaseTimer::start("write");
+        let mut buf = Vec::new();
+        self.postprocess(output, &mut buf)?;
+        if buf.len() > self.context.config.output_limit as usize {
+            return Err(SvgdxError::OutputLimitError(
+                buf.len(),
+                self.context.config.output_limit,
+            ));
+        }
+        writer.write_all(&buf)?;
+        let (name, elapsed) = timer.finish();
+        let phases = &mut self.context.stats.phases;
+        let style_time = phases
+            .iter()
+            .filter(|(phase, _)| *phase == "style")
+            .map(|(_, duration)| *duration)
+            .sum();
+        phases.push((name, elapsed.saturating_sub(style_time)));
+
+        self.context.stats.elements = self.context.element_count();
+        Ok(())
+    }
+
+    /// Extent of the output canvas given the bbox of the content: expanded
+    /// by the border width and rounded.
+    fn canvas_extent(&self, bbox: Option<BoundingBox>) -> Option<BoundingBox> {
+        let mut extent = bbox;
+        if let Some(bb) = &mut extent {
+            let border = self.context.config.border as f32;
+            bb.expand(border, border);
+            bb.round();
+        }
+        extent
+    }
+
+    /// Events for a watermark (text or image) centred on the canvas, if configured.
+    fn watermark_events(&self, bbox: Option<BoundingBox>) -> Option<OutputList> {
+        let config = &self.context.config;
+        if config.watermark.is_none() && config.watermark_image.is_none() {
+            return None;
+        }
+        let canvas = self.canvas_extent(bbox)?;
+        let (cx, cy) = canvas.locspec(LocSpec::Center);
+        let mut group = SvgElement::new(
+            "g",
+            &[
+                ("opacity".to_string(), fstr(config.watermark_opacity)),
+                ("pointer-events".to_string(), "none".to_string()),
+            ],
+        );
+        group.add_class("d-watermark");
+        if config.watermark_angle != 0. {
+            group.set_attr(
+                "transform",
+                &format!(
+                    "rotate({} {} {})",
+                    fstr(config.watermark_angle),
+                    fstr(cx),
+                    fstr(cy)
+                ),
+            );
+        }
+        let mut events = vec![OutputEvent::Start(group)];
+        if let Some(href) = &config.watermark_image {
+            // Image is scaled to fit the canvas, retaining its aspect ratio.
+            let attrs = [
+                ("href", href.clone()),
+                ("x", fstr(canvas.x1)),
+                ("y", fstr(canvas.y1)),
+                ("width", fstr(canvas.width())),
+                ("height", fstr(canvas.height())),
+                ("preserveAspectRatio", "xMidYMid meet".to_owned()),
+            ];
+            let attrs: Vec<_> = attrs.map(|(k, v)| (k.to_string(), v)).to_vec();
+            events.push(OutputEvent::Empty(SvgElement::new("image", &attrs)));
+        }
+        if let Some(text) = &config.watermark {
+            // Approximate text as 0.6em per character, spanning 80% of the canvas
+            // width, but no more than half its height.
+            let chars = text.chars().count().max(1) as f32;
+            let font_size = (canvas.width() * 0.8 / (chars * 0.6)).min(canvas.height() * 0.5);
+            let attrs = [
+                ("x", fstr(cx)),
+                ("y", fstr(cy)),
+                (
+                    "style",
+                    format!(
+                        "font-size: {}px; text-anchor: middle; dominant-baseline: central; fill: grey; stroke: none;",
+                        fstr(font_size)
+                    ),
+                ),
+            ];
+            let attrs: Vec<_> = attrs.map(|(k, v)| (k.to_string(), v)).to_vec();
+            events.extend([
+                OutputEvent::Start(SvgElement::new("text", &attrs)),
+                OutputEvent::Text(text.clone()),
+                OutputEvent::End("text".to_owned()),
+            ]);
+        }
+        events.push(OutputEvent::End("g".to_owned()));
+        let mut events = OutputList::from(events);
+        if config.sanitize {
+            events = events.sanitized();
+        }
+        Some(events)
+    }
+
+    /// Wrap the content of the root element in a group with any configured
+    /// `content_transform`, returning the new events and transformed bbox.
+    fn apply_content_transform(
+        &self,
+        events: OutputList,
+        bbox: Option<BoundingBox>,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let Some(xfrm) = &self.context.config.content_transform else {
+            return Ok((events, bbox));
+        };
+        // Allow e.g. `rotate 90` as shorthand for `rotate(90)`
+        let xfrm = match xfrm.trim().split_once(char::is_whitespace) {
+            Some((name, args)) if !xfrm.contains('(') => format!("{name}({})", args.trim()),
+            _ => xfrm.trim().to_owned(),
+        };
+        let transform: TransformAttr = xfrm.parse()?;
+        let mut events: Vec<_> = events.into_iter().collect();
+        let pos = events
+            .iter()
+            .rposition(|ev| matches!(ev, OutputEvent::End(name) if name == "svg"))
+            .unwrap_or(events.len());
+        events.insert(pos, OutputEvent::End("g".to_owned()));
+        events.insert(
+            0,
+            OutputEvent::Start(SvgElement::new("g", &[("transform".to_string(), xfrm)])),
+        );
+        Ok((
+            OutputList::from(events),
+            bbox.map(|bb| transform.apply(&bb)),
+        ))
     }
 
     fn write_root_svg(
@@ -570,18 +926,16 @@ impl Transformer {
         if let Some(svg_style) = &self.context.config.svg_style {
             new_svg_attrs.insert("style", svg_style.as_str());
         }
+        if let (true, Some(file)) = (
+            self.context.config.add_metadata,
+            &self.context.config.source_file,
+        ) {
+            new_svg_attrs.insert("data-src-file", file.as_str());
+        }
         // If width or height are provided, leave width/height/viewBox alone.
         let orig_width = orig_svg_attrs.get("width");
         let orig_height = orig_svg_attrs.get("height");
-        // Expand by given border width
-        let mut extent = bbox;
-        if let Some(bb) = &mut extent {
-            bb.expand(
-                self.context.config.border as f32,
-                self.context.config.border as f32,
-            );
-            bb.round();
-
+        if let Some(bb) = self.canvas_extent(bbox) {
             let aspect_ratio = bb.width() / bb.height();
             let view_width = fstr(bb.width());
             let view_height = fstr(bb.height());
@@ -614,17 +968,112 @@ impl Transformer {
             }
         }
 
-        OutputList::from(
+        let mut root_svg = OutputList::from(
             [OutputEvent::Start(SvgElement::new(
                 "svg",
                 &new_svg_attrs.to_vec(),
             ))]
             .as_slice(),
-        )
-        .write_to(writer)
+        );
+        if self.context.config.sanitize {
+            // e.g. `svg_style` may have been set from a `<config>` element
+            root_svg = root_svg.sanitized();
+        }
+        root_svg.write_to(writer)
+    }
+
+    /// Write `<title>` and `<desc>` elements for any configured title and
+    /// description, an attribution comment for any configured author, license
+    /// or source URL, and - if `add_metadata` is set - Dublin Core RDF metadata
+    /// derived from these or from top-level `<title>` / `<desc>` elements.
+    fn write_doc_metadata(&self, events: &OutputList, writer: &mut dyn Write) -> Result<()> {
+        let config = &self.context.config;
+        let escape = |s: &str| quick_xml::escape::escape(s).into_owned();
+        let mut title = config.title.as_deref().map(escape);
+        let mut desc = config.desc.as_deref().map(escape);
+        for (tag, value) in [("title", &title), ("desc", &desc)] {
+            if let Some(value) = value {
+                write!(writer, "\n  <{tag}>{value}</{tag}>")?;
+            }
+        }
+        let attribution = [
+            ("Author", &config.author, "creator"),
+            ("License", &config.license, "rights"),
+            ("Source", &config.source_url, "source"),
+        ];
+        let comment: Vec<_> = attribution
+            .iter()
+            .filter_map(|(label, value, _)| value.as_ref().map(|v| format!("{label}: {v}")))
+            .collect();
+        if !comment.is_empty() {
+            // '--' is not permitted within XML comments
+            let comment = comment.join(", ").replace("--", "- -");
+            write!(writer, "\n  <!-- {comment} -->")?;
+        }
+        if !config.add_metadata {
+            return Ok(());
+        }
+
+        // Otherwise use (escaped) text content of top-level title / desc elements
+        let mut depth = 0;
+        let mut current: Option<(&str, String)> = None;
+        for ev in events.iter() {
+            match ev {
+                OutputEvent::Start(el) => {
+                    if depth == 0 && (el.name == "title" || el.name == "desc") {
+                        current = Some((el.name.as_str(), String::new()));
+                    }
+                    depth += 1;
+                }
+                OutputEvent::Text(t) => {
+                    if let Some((_, text)) = &mut current {
+                        text.push_str(t);
+                    }
+                }
+                OutputEvent::End(_) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        match current.take() {
+                            Some(("title", text)) => title = title.or(Some(text)),
+                            Some((_, text)) => desc = desc.or(Some(text)),
+                            None => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if title.is_none() && desc.is_none() && comment.is_empty() {
+            return Ok(());
+        }
+        write!(
+            writer,
+            concat!(
+                "\n  <metadata>",
+                "\n    <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"",
+                " xmlns:dc=\"http://purl.org/dc/elements/1.1/\">",
+                "\n      <rdf:Description>",
+                "\n        <dc:format>image/svg+xml</dc:format>"
+            )
+        )?;
+        for (tag, value) in [("title", title), ("description", desc)] {
+            if let Some(value) = value {
+                write!(writer, "\n        <dc:{tag}>{}</dc:{tag}>", value.trim())?;
+            }
+        }
+        for (_, value, tag) in attribution {
+            if let Some(value) = value {
+                write!(writer, "\n        <dc:{tag}>{}</dc:{tag}>", escape(value))?;
+            }
+        }
+        write!(
+            writer,
+            "\n      </rdf:Description>\n    </rdf:RDF>\n  </metadata>"
+        )?;
+        Ok(())
     }
 
-    fn write_auto_styles(&self, events: &mut OutputList, writer: &mut dyn Write) -> Result<()> {
+    fn write_auto_styles(&mut self, events: &mut OutputList, writer: &mut dyn Write) -> Result<()> {
         // Collect the set of elements and classes so relevant styles can be
         // automatically added.
         let mut element_set = HashSet::new();
@@ -644,6 +1093,7 @@ impl Transformer {
         tb.build();
         let auto_defs = tb.get_defs();
         let auto_styles = tb.get_styles();
+        self.context.stats.styles = (auto_defs.len() + auto_styles.len()) as u32;
 
         let indent_line = |n| format!("\n{}", " ".repeat(n));
         if !auto_defs.is_empty() {
@@ -666,7 +1116,13 @@ impl Transformer {
             ]);
             OutputList::from(defs_events).write_to(writer)?;
         }
-        if !auto_styles.is_empty() {
+        if self.context.config.css_href.is_some() {
+            // Styles are written to a separate stylesheet, referenced by an
+            // `xml-stylesheet` processing instruction prior to the root element.
+            let mut css = indent_all(auto_styles, 0).join("\n");
+            css.push('\n');
+            self.context.extracted_css = Some(css);
+        } else if !auto_styles.is_empty() {
             let mut style_events = vec![
                 OutputEvent::Text(indent_line(indent)),
                 OutputEvent::Start(SvgElement::new("style", &[])),
@@ -693,11 +1149,14 @@ impl Transformer {
     }
 
     fn postprocess(
-        &self,
+        &mut self,
         output: (OutputList, Option<BoundingBox>),
         writer: &mut dyn Write,
     ) -> Result<()> {
         let (mut events, bbox) = output;
+        if self.context.config.sanitize {
+            events = events.sanitized();
+        }
 
         if self.context.real_svg {
             // We don't do any post-processing on 'real' SVG documents
@@ -705,11 +1164,23 @@ impl Transformer {
         }
 
         let mut has_svg_element = false;
+        let mut watermark = None;
         if let (pre_svg, Some(first_svg), remain) = events.partition("svg") {
+            let (remain, bbox) = self.apply_content_transform(remain, bbox)?;
             pre_svg.write_to(writer)?;
+            if let Some(href) = &self.context.config.css_href {
+                if self.context.config.add_auto_styles {
+                    let href = quick_xml::escape::escape(href.as_str());
+                    writeln!(
+                        writer,
+                        r#"<?xml-stylesheet type="text/css" href="{href}"?>"#
+                    )?;
+                }
+            }
             self.write_root_svg(first_svg, bbox, writer)?;
             events = remain;
             has_svg_element = true;
+            watermark = self.watermark_events(bbox);
         }
 
         if self.context.config.debug {
@@ -728,11 +1199,35 @@ impl Transformer {
             .write_to(writer)?;
         }
 
+        if has_svg_element {
+            self.write_doc_metadata(&events, writer)?;
+        }
+
         // Default behaviour: include auto defs/styles iff we have an SVG element,
         // i.e. this is a full SVG document rather than a fragment.
+        let timer = PhaseTimer::start("style");
         if has_svg_element && self.context.config.add_auto_styles {
             self.write_auto_styles(&mut events, writer)?;
         }
+        self.context.stats.phases.push(timer.finish());
+
+        if let Some(watermark) = watermark {
+            if self.context.config.watermark_above {
+                // Insert prior to the closing root `</svg>`
+                let mut events: Vec<_> = events.into_iter().collect();
+                let pos = events
+                    .iter()
+                    .rposition(|ev| matches!(ev, OutputEvent::End(name) if name == "svg"))
+                    .unwrap_or(events.len());
+                let tail = events.split_off(pos);
+                OutputList::from(events).write_to(writer)?;
+                watermark.write_to(writer)?;
+                writer.write_all(b"\n")?;
+                return OutputList::from(tail).write_to(writer);
+            }
+            writer.write_all(b"\n")?;
+            watermark.write_to(writer)?;
+        }
 
         events.write_to(writer)
     }
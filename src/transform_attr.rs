@@ -147,23 +147,91 @@ impl FromStr for TransformAttr {
     }
 }
 
-impl TransformAttr {
-    pub fn apply(&self, bbox: &BoundingBox) -> BoundingBox {
-        let mut result = *bbox;
-
-        for transform in self.transforms.iter().rev() {
-            match *transform {
-                TransformType::Translate(tx, ty) => {
-                    result = result.xfrm_translate(tx, ty);
-                }
-                TransformType::Scale(sx, sy) => {
-                    result = result.xfrm_scale(sx, sy);
+impl TransformType {
+    /// Affine matrix `[a, b, c, d, e, f]` for this transform, as used by the
+    /// SVG `matrix()` transform.
+    fn matrix(&self) -> [f32; 6] {
+        match *self {
+            TransformType::Translate(tx, ty) => [1., 0., 0., 1., tx, ty],
+            TransformType::Scale(sx, sy) => [sx, 0., 0., sy, 0., 0.],
+            TransformType::Rotate(angle, cx, cy) => {
+                let (mut sin, mut cos) = angle.to_radians().sin_cos();
+                if angle % 90. == 0. {
+                    // avoid rounding errors for quarter turns
+                    (sin, cos) = (sin.round(), cos.round());
                 }
-                _ => (),
+                // rotate about (cx, cy): translate(cx, cy) rotate(a) translate(-cx, -cy)
+                [
+                    cos,
+                    sin,
+                    -sin,
+                    cos,
+                    cx - cos * cx + sin * cy,
+                    cy - sin * cx - cos * cy,
+                ]
             }
+            TransformType::SkewX(angle) => [1., 0., angle.to_radians().tan(), 1., 0., 0.],
+            TransformType::SkewY(angle) => [1., angle.to_radians().tan(), 0., 1., 0., 0.],
+            TransformType::Matrix(a, b, c, d, e, f) => [a, b, c, d, e, f],
         }
+    }
+}
+
+/// Combine two affine matrices, returning the equivalent of applying `n` then `m`.
+fn matrix_mul(m: &[f32; 6], n: &[f32; 6]) -> [f32; 6] {
+    [
+        m[0] * n[0] + m[2] * n[1],
+        m[1] * n[0] + m[3] * n[1],
+        m[0] * n[2] + m[2] * n[3],
+        m[1] * n[2] + m[3] * n[3],
+        m[0] * n[4] + m[2] * n[5] + m[4],
+        m[1] * n[4] + m[3] * n[5] + m[5],
+    ]
+}
 
-        result
+impl TransformAttr {
+    /// The combined affine matrix of all transforms in this attribute.
+    fn matrix(&self) -> [f32; 6] {
+        self.transforms
+            .iter()
+            .fold([1., 0., 0., 1., 0., 0.], |acc, t| {
+                matrix_mul(&acc, &t.matrix())
+            })
+    }
+
+    /// The translation given by this transform, if it is a pure translation.
+    pub fn translation(&self) -> Option<(f32, f32)> {
+        let m = self.matrix();
+        (m[..4] == [1., 0., 0., 1.]).then_some((m[4], m[5]))
+    }
+
+    /// Bounding box of the given bbox after transformation.
+    ///
+    /// Where the transform includes rotation or skew, the result is the bounding
+    /// box of the transformed corners.
+    pub fn apply(&self, bbox: &BoundingBox) -> BoundingBox {
+        let m = self.matrix();
+        if m[1] == 0. && m[2] == 0. && m[0] > 0. && m[3] > 0. {
+            // common case of translate / scale; avoid rounding errors from
+            // min/max of corner points.
+            return bbox.xfrm_scale(m[0], m[3]).xfrm_translate(m[4], m[5]);
+        }
+        let corners = [
+            (bbox.x1, bbox.y1),
+            (bbox.x2, bbox.y1),
+            (bbox.x1, bbox.y2),
+            (bbox.x2, bbox.y2),
+        ]
+        .map(|(x, y)| (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5]));
+        let (mut x1, mut y1) = corners[0];
+        let (mut x2, mut y2) = corners[0];
+        for (x, y) in &corners[1..] {
+            x1 = x1.min(*x);
+            y1 = y1.min(*y);
+            x2 = x2.max(*x);
+            y2 = y2.max(*y);
+        }
+        BoundingBox::new(x1, y1, x2, y2)
     }
 }
 
@@ -206,4 +274,34 @@ mod test {
         let bbox = BoundingBox::new(0., 0., 10., 10.);
         assert_eq!(t.apply(&bbox), BoundingBox::new(20., 40., 40., 60.));
     }
+
+    fn assert_bbox_near(a: BoundingBox, b: BoundingBox) {
+        for (p, q) in [(a.x1, b.x1), (a.y1, b.y1), (a.x2, b.x2), (a.y2, b.y2)] {
+            assert!((p - q).abs() < 1e-4, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_transform_apply_rotate_skew() {
+        let bbox = BoundingBox::new(0., 0., 10., 10.);
+
+        let t: TransformAttr = "rotate(90)".parse().unwrap();
+        assert_bbox_near(t.apply(&bbox), BoundingBox::new(-10., 0., 0., 10.));
+
+        let t: TransformAttr = "rotate(180, 5, 5)".parse().unwrap();
+        assert_bbox_near(t.apply(&bbox), bbox);
+
+        let t: TransformAttr = "scale(-1, 1)".parse().unwrap();
+        assert_bbox_near(t.apply(&bbox), BoundingBox::new(-10., 0., 0., 10.));
+
+        let t: TransformAttr = "skewX(45)".parse().unwrap();
+        assert_bbox_near(t.apply(&bbox), BoundingBox::new(0., 0., 20., 10.));
+
+        let t: TransformAttr = "matrix(1 0 0 1 3 4)".parse().unwrap();
+        assert_bbox_near(t.apply(&bbox), BoundingBox::new(3., 4., 13., 14.));
+
+        // composition applies right-to-left
+        let t: TransformAttr = "translate(20) rotate(90)".parse().unwrap();
+        assert_bbox_near(t.apply(&bbox), BoundingBox::new(10., 0., 20., 10.));
+    }
 }
@@ -0,0 +1,464 @@
+//! Tree layout of hierarchies such as org charts and call graphs.
+//!
+//! A `<tree>` element is a group whose nodes are nested within each other:
+//!
+//! ```xml
+//! <tree direction="TB" gap="10 20">
+//!   <node text="CEO">
+//!     <node text="CTO">
+//!       <node text="Dev"/>
+//!     </node>
+//!     <node text="CFO"/>
+//!   </node>
+//! </tree>
+//! ```
+//!
+//! Nested nodes (`<node>` or any graphics element other than a connector) are
+//! flattened into a sequence of siblings, with a connector generated from each
+//! parent to each of its children. Alternatively, nodes may be given as direct
+//! children with parent-child edges from connectors, `<edge>` or `<connect>`
+//! elements, as for `<graph>`. Each node is then positioned using the
+//! Reingold-Tilford algorithm, with subtrees packed as closely as `gap`
+//! allows and each parent centred over its children.
+
+use crate::context::TransformerContext;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{tagify_events, InputEvent, InputList, OutputEvent, OutputList, Tag};
+use crate::expression::eval_attr;
+use crate::graph::{expand_shorthand, Direction, GraphModel};
+use crate::layout::element_size;
+use crate::position::BoundingBox;
+use crate::transform::group_events;
+use crate::types::{attr_split, fstr, strp};
+
+/// Attributes of `<tree>` which are not present on the generated `<g>`.
+const TREE_ATTRS: &[&str] = &["direction", "gap", "connector", "edge-class"];
+
+/// Default (across, along) gap between nodes
+const DEFAULT_GAP: (f32, f32) = (10., 20.);
+
+/// Extent of a subtree either side of its root's centre, at each depth.
+type Contour = Vec<(f32, f32)>;
+
+/// Whether `el` is a node of a tree, rather than other content.
+fn is_tree_node(el: &SvgElement) -> bool {
+    el.name == "node" || (el.is_graphics_element() && !el.is_connector())
+}
+
+/// Conversion of nested nodes to a flat list of nodes and parent-child edges.
+struct Flattener {
+    /// Prefix for the ids of nodes without one
+    prefix: String,
+    count: usize,
+    /// Whitespace separating nodes in the output
+    sep: String,
+    nodes: Vec<InputEvent>,
+    edges: Vec<(String, String)>,
+}
+
+impl Flattener {
+    /// Add the node `el`, starting with the event `source` and containing the
+    /// events `content`, followed by its descendants.
+    fn add(
+        &mut self,
+        el: &SvgElement,
+        source: &InputEvent,
+        content: &[InputEvent],
+        parent: Option<&str>,
+    ) -> Result<()> {
+        let mut node = el.clone();
+        let id = match node.get_attr("id") {
+            Some(id) => id,
+            None => {
+                self.count += 1;
+                let id = format!("{}-{}", self.prefix, self.count);
+                node.set_attr("id", &id);
+                id
+            }
+        };
+        if let Some(parent) = parent {
+            self.edges.push((parent.to_string(), id.clone()));
+        }
+        if !self.nodes.is_empty() {
+            self.nodes
+                .push(InputEvent::from(OutputEvent::Text(self.sep.clone())));
+        }
+        let index = self.nodes.len();
+        self.nodes
+            .push(InputEvent::from(OutputEvent::Empty(node.clone())).at_position_of(source));
+
+        let mut text = Vec::new();
+        for tag in tagify_events(InputList::from(content))? {
+            let (child, tail) = match tag {
+                Tag::Compound(child, tail) | Tag::Leaf(child, tail) => (Some(child), tail),
+                Tag::Comment(_, tail) => (None, tail),
+                Tag::Text(t) | Tag::CData(t) => (None, Some(t)),
+            };
+            text.extend(tail.filter(|t| !t.trim().is_empty()));
+            let Some(child) = child else {
+                continue;
+            };
+            let Some((start, end)) = child.event_range.filter(|_| is_tree_node(&child)) else {
+                return Err(SvgdxError::DocumentError(format!(
+                    "tree: '{}' may only contain text and other nodes, not '{}'",
+                    el.name, child.name
+                )));
+            };
+            let from = content.iter().position(|ev| ev.index == start);
+            let to = content.iter().position(|ev| ev.index == end);
+            let (Some(from), Some(to)) = (from, to) else {
+                continue;
+            };
+            let inner = if from < to {
+                &content[from + 1..to]
+            } else {
+                &[]
+            };
+            self.add(&child, &content[from], inner, Some(&id))?;
+        }
+
+        // text content is the node's label, as for other elements
+        if !text.is_empty() && !node.has_attr("text") {
+            let text = text.iter().map(|t| t.trim()).collect::<Vec<_>>().join(" ");
+            let text = quick_xml::escape::unescape(&text).map_err(SvgdxError::from_err)?;
+            node.set_attr("text", &text);
+            self.nodes[index] =
+                InputEvent::from(OutputEvent::Empty(node)).at_position_of(&self.nodes[index]);
+        }
+        Ok(())
+    }
+}
+
+/// Replace nested nodes in `content` with a flat list of nodes, followed by
+/// `<edge>` elements from each parent to its children.
+fn flatten_nodes(
+    content: InputList,
+    prefix: &str,
+    edge: &SvgElement,
+    locs: (&str, &str),
+) -> Result<InputList> {
+    let mut flattener = Flattener {
+        prefix: prefix.to_string(),
+        count: 0,
+        sep: "\n".to_string(),
+        nodes: Vec::new(),
+        edges: Vec::new(),
+    };
+    let mut events = Vec::new();
+    let mut skip_to = None;
+    let tags = tagify_events(content.clone())?;
+    for (pos, ev) in content.events.iter().enumerate() {
+        if let Some(end) = skip_to {
+            if ev.index == end {
+                skip_to = None;
+            }
+            continue;
+        }
+        let nested = tags
+            .iter()
+            .filter_map(Tag::get_element)
+            .find(|el| matches!(el.event_range, Some((s, e)) if s == ev.index && s != e));
+        match nested {
+            Some(el) if is_tree_node(&el) => {
+                if let Some(sep) = events.last().and_then(InputEvent::text_string) {
+                    if let Some((_, indent)) = sep.rsplit_once('\n') {
+                        flattener.sep = format!("\n{indent}");
+                    }
+                }
+                let (_, end) = el.event_range.unwrap_or_default();
+                let len = content.events[pos..]
+                    .iter()
+                    .position(|ev| ev.index == end)
+                    .unwrap_or_default();
+                flattener.add(&el, ev, &content.events[pos + 1..pos + len], None)?;
+                events.append(&mut flattener.nodes);
+                skip_to = Some(end);
+            }
+            _ => events.push(ev.clone()),
+        }
+    }
+    // edges are placed before any trailing whitespace
+    let trailing = events.pop_if(|ev| ev.text_string().is_some_and(|t| t.trim().is_empty()));
+    for (parent, child) in flattener.edges {
+        let mut edge = edge.clone();
+        edge.set_attr("from", &format!("#{parent}@{}", locs.0));
+        edge.set_attr("to", &format!("#{child}@{}", locs.1));
+        events.push(InputEvent::from(OutputEvent::Text(flattener.sep.clone())));
+        events.push(InputEvent::from(OutputEvent::Empty(edge)));
+    }
+    events.extend(trailing);
+    Ok(InputList::reindexed(events))
+}
+
+/// Parent-child structure of a forest of trees.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct TreeModel {
+    /// Children of each node, in document order
+    children: Vec<Vec<usize>>,
+    roots: Vec<usize>,
+}
+
+impl TreeModel {
+    /// Forest of `n` nodes from graph edges. Nodes keep their first parent,
+    /// and edges which would form a cycle are ignored.
+    fn from_edges(n: usize, edges: &[(usize, usize)]) -> Self {
+        let mut parent: Vec<Option<usize>> = vec![None; n];
+        for &(a, b) in edges {
+            let mut ancestor = Some(a);
+            while let Some(node) = ancestor.filter(|&node| node != b) {
+                ancestor = parent[node];
+            }
+            if parent[b].is_none() && ancestor.is_none() {
+                parent[b] = Some(a);
+            }
+        }
+        let mut children = vec![Vec::new(); n];
+        let mut roots = Vec::new();
+        for (node, parent) in parent.iter().enumerate() {
+            match parent {
+                Some(p) => children[*p].push(node),
+                None => roots.push(node),
+            }
+        }
+        Self { children, roots }
+    }
+
+    /// Lay out the subtree at `node`, setting the offset of each descendant
+    /// from its parent in `offsets`, and returning the subtree's contour.
+    fn subtree(&self, node: usize, sizes: &[f32], gap: f32, offsets: &mut [f32]) -> Contour {
+        let half = sizes[node] / 2.;
+        let mut contour = vec![(-half, half)];
+        let children = &self.children[node];
+        if let (Some(_), (centres, below)) =
+            (children.first(), self.pack(children, sizes, gap, offsets))
+        {
+            let mid = (centres[0] + centres[centres.len() - 1]) / 2.;
+            for (&child, centre) in children.iter().zip(centres) {
+                offsets[child] = centre - mid;
+            }
+            contour.extend(below.iter().map(|(l, r)| (l - mid, r - mid)));
+        }
+        contour
+    }
+
+    /// Place subtrees side by side as closely as their contours allow,
+    /// returning the centre of each relative to the first, and the contour
+    /// of the combined subtrees.
+    fn pack(
+        &self,
+        nodes: &[usize],
+        sizes: &[f32],
+        gap: f32,
+        offsets: &mut [f32],
+    ) -> (Vec<f32>, Contour) {
+        let mut centres = Vec::new();
+        let mut contour: Contour = Vec::new();
+        for &node in nodes {
+            let sub = self.subtree(node, sizes, gap, offsets);
+            let shift = if centres.is_empty() {
+                0.
+            } else {
+                contour
+                    .iter()
+                    .zip(&sub)
+                    .map(|(a, b)| a.1 + gap - b.0)
+                    .fold(f32::NEG_INFINITY, f32::max)
+            };
+            for (depth, (l, r)) in sub.into_iter().enumerate() {
+                let (l, r) = (l + shift, r + shift);
+                match contour.get_mut(depth) {
+                    Some(level) => *level = (level.0.min(l), level.1.max(r)),
+                    None => contour.push((l, r)),
+                }
+            }
+            centres.push(shift);
+        }
+        (centres, contour)
+    }
+
+    /// Centre of each node as `(across, along)`, given node sizes and the gap
+    /// between nodes in the same form. The layout starts at zero on each axis.
+    fn layout(&self, sizes: &[(f32, f32)], gap: (f32, f32)) -> Vec<(f32, f32)> {
+        let n = sizes.len();
+        let across: Vec<_> = sizes.iter().map(|s| s.0).collect();
+        let mut offsets = vec![0.; n];
+        let (centres, _) = self.pack(&self.roots, &across, gap.0, &mut offsets);
+
+        let mut pos = vec![(0., 0.); n];
+        let mut depth = vec![0; n];
+        let mut stack = Vec::new();
+        for (&root, centre) in self.roots.iter().zip(centres) {
+            pos[root].0 = centre;
+            stack.push(root);
+        }
+        while let Some(node) = stack.pop() {
+            for &child in &self.children[node] {
+                pos[child].0 = pos[node].0 + offsets[child];
+                depth[child] = depth[node] + 1;
+                stack.push(child);
+            }
+        }
+
+        // each level is as deep as its deepest node
+        let levels = depth.iter().max().map_or(0, |d| d + 1);
+        let mut level_size = vec![0.; levels];
+        for (node, &d) in depth.iter().enumerate() {
+            level_size[d] = f32::max(level_size[d], sizes[node].1);
+        }
+        let mut level_centre = Vec::with_capacity(levels);
+        let mut start = 0.;
+        for size in level_size {
+            level_centre.push(start + size / 2.);
+            start += size + gap.1;
+        }
+
+        let min_across = (0..n)
+            .map(|node| pos[node].0 - across[node] / 2.)
+            .fold(f32::INFINITY, f32::min);
+        (0..n)
+            .map(|node| (pos[node].0 - min_across, level_centre[depth[node]]))
+            .collect()
+    }
+}
+
+/// Lay out the given tree content according to the attributes of `element`,
+/// generating a group containing the result.
+pub(crate) fn tree_events(
+    element: &SvgElement,
+    content: Option<InputList>,
+    context: &mut TransformerContext,
+) -> Result<(OutputList, Option<BoundingBox>)> {
+    let direction: Direction = element
+        .get_attr("direction")
+        .map(|v| eval_attr(&v, context).parse())
+        .transpose()
+        .map_err(|e: SvgdxError| e.in_attr("direction"))?
+        .unwrap_or_default();
+    let gap = element
+        .get_attr("gap")
+        .map(|v| -> Result<_> {
+            let value = eval_attr(&v, context);
+            let mut parts = attr_split(&value).map(|p| strp(&p));
+            let across = parts.next().transpose()?.unwrap_or(DEFAULT_GAP.0);
+            let along = parts.next().transpose()?.unwrap_or(across);
+            Ok((across, along))
+        })
+        .transpose()
+        .map_err(|e| e.in_attr("gap"))?
+        .unwrap_or(DEFAULT_GAP);
+    let connector = element
+        .get_attr("connector")
+        .unwrap_or("polyline".to_string());
+
+    let content = if let Some(content) = content {
+        let mut edge = SvgElement::new("edge", &[]);
+        edge.set_attr("connector", &connector);
+        if let Some(class) = element.get_attr("edge-class") {
+            edge.set_attr("class", &class);
+        }
+        let locs = match direction {
+            Direction::TB => ("b", "t"),
+            Direction::BT => ("t", "b"),
+            Direction::LR => ("r", "l"),
+            Direction::RL => ("l", "r"),
+        };
+        let prefix = element.get_attr("id").unwrap_or("tree".to_string());
+        let content = expand_shorthand(flatten_nodes(content, &prefix, &edge, locs)?)?;
+        let model = GraphModel::from_events(&content, context)?;
+
+        let elements: Vec<_> = tagify_events(content.clone())?
+            .iter()
+            .filter_map(Tag::get_element)
+            .collect();
+        let mut sizes = Vec::with_capacity(model.nodes.len());
+        for node in &model.nodes {
+            let el = elements
+                .iter()
+                .find(|el| matches!(el.event_range, Some((start, _)) if start == node.index));
+            let (w, h) = match el {
+                Some(el) => element_size(el, context)?,
+                None => (0., 0.),
+            };
+            sizes.push(match direction {
+                Direction::TB | Direction::BT => (w, h),
+                Direction::LR | Direction::RL => (h, w),
+            });
+        }
+
+        let tree = TreeModel::from_edges(model.nodes.len(), &model.edges);
+        let positions = tree.layout(&sizes, gap);
+        let extent = positions
+            .iter()
+            .zip(&sizes)
+            .map(|(p, s)| p.1 + s.1 / 2.)
+            .fold(0., f32::max);
+        let mut events = content.events;
+        for (node, &(across, along)) in model.nodes.iter().zip(&positions) {
+            let (x, y) = match direction {
+                Direction::TB => (across, along),
+                Direction::BT => (across, extent - along),
+                Direction::LR => (along, across),
+                Direction::RL => (extent - along, across),
+            };
+            if let Some(ev) = events.iter_mut().find(|ev| ev.index == node.index) {
+                *ev = ev.with_attr("cxy", &format!("{} {}", fstr(x), fstr(y)));
+            }
+        }
+        Some(InputList { events })
+    } else {
+        None
+    };
+
+    let mut group = element.clone();
+    group.name = "g".to_string();
+    for attr in TREE_ATTRS {
+        group.pop_attr(attr);
+    }
+    group_events(&group, content, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_model() {
+        // 0 -> 1, 0 -> 2, 2 -> 3; 3 -> 0 would form a cycle, and 3 already has a parent
+        let tree = TreeModel::from_edges(5, &[(0, 1), (0, 2), (2, 3), (3, 0), (1, 3)]);
+        assert_eq!(tree.roots, [0, 4]);
+        assert_eq!(tree.children, [vec![1, 2], vec![], vec![3], vec![], vec![]]);
+    }
+
+    #[test]
+    fn test_tree_layout() {
+        //      0
+        //    1   2
+        //   3 4   5
+        let tree = TreeModel::from_edges(6, &[(0, 1), (0, 2), (1, 3), (1, 4), (2, 5)]);
+        let sizes = [(10., 4.); 6];
+        let pos = tree.layout(&sizes, (2., 6.));
+        assert_eq!(pos[3], (5., 22.));
+        assert_eq!(pos[4], (17., 22.));
+        assert_eq!(pos[1], (11., 12.));
+        // 2 is placed so its child clears the children of 1
+        assert_eq!(pos[2], (29., 12.));
+        assert_eq!(pos[5], (29., 22.));
+        assert_eq!(pos[0], (20., 2.));
+
+        // subtrees are packed by their contours, so 2 need only clear 1
+        // rather than the wider children of 1
+        let tree = TreeModel::from_edges(5, &[(0, 1), (0, 2), (1, 3), (1, 4)]);
+        let sizes = [(10., 4.), (10., 4.), (10., 4.), (30., 4.), (10., 4.)];
+        let pos = tree.layout(&sizes, (2., 6.));
+        assert_eq!(pos[3], (15., 22.));
+        assert_eq!(pos[4], (37., 22.));
+        assert_eq!(pos[1], (26., 12.));
+        assert_eq!(pos[2], (38., 12.));
+
+        // a forest is laid out side by side
+        let tree = TreeModel::from_edges(2, &[]);
+        let pos = tree.layout(&[(10., 4.), (6., 8.)], (2., 6.));
+        assert_eq!(pos, [(5., 4.), (15., 4.)]);
+    }
+}
@@ -66,6 +66,17 @@ pub fn attr_split_cycle(input: &str) -> impl Iterator<Item = String> + '_ {
     x.into_iter().cycle()
 }
 
+/// FNV-1a hash of the given bytes.
+///
+/// Used rather than `DefaultHasher` which is not guaranteed to be stable
+/// across Rust releases.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    const FNV_PRIME: u64 = 0x100_0000_01b3;
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OrderIndex(Vec<usize>);
 
@@ -87,6 +98,10 @@ impl OrderIndex {
 
         Self(new_idx)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().copied()
+    }
 }
 
 /// `AttrMap` - an order preserving map for storing element attributes.
@@ -294,7 +309,7 @@ impl ClassList {
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &String> + '_ {
-        self.classes.iter().map(|item| (&item.1))
+        self.classes.iter().map(|item| &item.1)
     }
 
     /// Replace a class entry with a new class (or multiple space-separated)
@@ -373,7 +388,10 @@ impl<'s> IntoIterator for &'s ClassList {
 #[derive(Clone, Debug, PartialEq)]
 pub enum ElRef {
     Id(String),
+    /// The previous element, `^`
     Prev,
+    /// The nth previous element, e.g. `^2` (n >= 2)
+    PrevN(usize),
 }
 
 impl FromStr for ElRef {
@@ -393,6 +411,7 @@ impl Display for ElRef {
         match self {
             ElRef::Id(id) => write!(f, "{ELREF_ID_PREFIX}{}", id),
             ElRef::Prev => write!(f, "{ELREF_PREVIOUS}"),
+            ElRef::PrevN(n) => write!(f, "{ELREF_PREVIOUS}{n}"),
         }
     }
 }
@@ -412,7 +431,14 @@ pub fn extract_elref(s: &str) -> Result<(ElRef, &str)> {
             }
         }
     } else if let Some(s) = s.strip_prefix(ELREF_PREVIOUS) {
-        return Ok((ElRef::Prev, s));
+        // optional count, e.g. `^2` for the element before the previous one
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (count, remain) = s.split_at(split);
+        return match count.parse::<usize>() {
+            Err(_) | Ok(1) => Ok((ElRef::Prev, remain)),
+            Ok(0) => Err(SvgdxError::ParseError(format!("Invalid elref format {s}"))),
+            Ok(n) => Ok((ElRef::PrevN(n), remain)),
+        };
     }
 
     Err(SvgdxError::ParseError(format!("Invalid elref format {s}")))
@@ -605,6 +631,10 @@ mod test {
         );
         assert_eq!(extract_elref("^@bl").unwrap(), (ElRef::Prev, "@bl"));
         assert_eq!(extract_elref("^").unwrap(), (ElRef::Prev, ""));
+        assert_eq!(extract_elref("^1~x").unwrap(), (ElRef::Prev, "~x"));
+        assert_eq!(extract_elref("^3@tl").unwrap(), (ElRef::PrevN(3), "@tl"));
+        assert_eq!(ElRef::PrevN(3).to_string(), "^3");
+        assert!(extract_elref("^0").is_err());
         assert!(extract_elref("id").is_err());
     }
 }
@@ -0,0 +1,548 @@
+//! Validation of generated SVG against a simplified SVG 1.1 / 2.0 content model.
+//!
+//! This is not a full schema validator; it checks that elements and attributes
+//! are known SVG elements / attributes, and that elements only contain children
+//! permitted by the content model. Elements in other namespaces (e.g. `inkscape:`)
+//! and the content of `<foreignObject>` and `<metadata>` elements are ignored.
+
+use crate::errors::{Result, SvgdxError};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fmt::{self, Display};
+
+/// A single problem found when validating a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    /// Source line (1-based) in the validated document
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+const SHAPE: &[&str] = &[
+    "circle", "ellipse", "line", "path", "polygon", "polyline", "rect",
+];
+const STRUCTURAL: &[&str] = &["defs", "g", "svg", "symbol", "use"];
+const DESCRIPTIVE: &[&str] = &["desc", "metadata", "title"];
+const ANIMATION: &[&str] = &["animate", "animateMotion", "animateTransform", "set"];
+const GRADIENT: &[&str] = &["linearGradient", "radialGradient"];
+const OTHER_CONTENT: &[&str] = &[
+    "a",
+    "clipPath",
+    "filter",
+    "foreignObject",
+    "image",
+    "marker",
+    "mask",
+    "pattern",
+    "script",
+    "style",
+    "switch",
+    "text",
+    "view",
+];
+const TEXT_CONTENT: &[&str] = &["a", "textPath", "tspan"];
+const FILTER_PRIMITIVE: &[&str] = &[
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDropShadow",
+    "feFlood",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMorphology",
+    "feOffset",
+    "feSpecularLighting",
+    "feTile",
+    "feTurbulence",
+];
+const LIGHT_SOURCE: &[&str] = &["feDistantLight", "fePointLight", "feSpotLight"];
+const TRANSFER_FUNCTION: &[&str] = &["feFuncA", "feFuncB", "feFuncG", "feFuncR"];
+
+/// Attributes which may appear on any SVG element.
+const GLOBAL_ATTRS: &[&str] = &[
+    "id",
+    "class",
+    "style",
+    "lang",
+    "tabindex",
+    "autofocus",
+    "role",
+    "xmlns",
+    "requiredExtensions",
+    "requiredFeatures",
+    "systemLanguage",
+    // presentation attributes
+    "alignment-baseline",
+    "baseline-shift",
+    "clip",
+    "clip-path",
+    "clip-rule",
+    "color",
+    "color-interpolation",
+    "color-interpolation-filters",
+    "color-profile",
+    "color-rendering",
+    "cursor",
+    "direction",
+    "display",
+    "dominant-baseline",
+    "enable-background",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "filter",
+    "flood-color",
+    "flood-opacity",
+    "font",
+    "font-family",
+    "font-size",
+    "font-size-adjust",
+    "font-stretch",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "glyph-orientation-horizontal",
+    "glyph-orientation-vertical",
+    "image-rendering",
+    "kerning",
+    "letter-spacing",
+    "lighting-color",
+    "marker",
+    "marker-end",
+    "marker-mid",
+    "marker-start",
+    "mask",
+    "mask-type",
+    "opacity",
+    "overflow",
+    "paint-order",
+    "pointer-events",
+    "shape-rendering",
+    "stop-color",
+    "stop-opacity",
+    "stroke",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-opacity",
+    "stroke-width",
+    "text-anchor",
+    "text-decoration",
+    "text-overflow",
+    "text-rendering",
+    "transform",
+    "transform-box",
+    "transform-origin",
+    "unicode-bidi",
+    "vector-effect",
+    "visibility",
+    "white-space",
+    "word-spacing",
+    "writing-mode",
+];
+
+/// Union of attributes across the various animation elements.
+const ANIMATION_ATTRS: &[&str] = &[
+    "href",
+    "attributeName",
+    "attributeType",
+    "begin",
+    "dur",
+    "end",
+    "min",
+    "max",
+    "restart",
+    "repeatCount",
+    "repeatDur",
+    "calcMode",
+    "values",
+    "keyTimes",
+    "keySplines",
+    "from",
+    "to",
+    "by",
+    "additive",
+    "accumulate",
+    "type",
+    "path",
+    "keyPoints",
+    "rotate",
+    "origin",
+];
+
+const FILTER_PRIMITIVE_ATTRS: &[&str] = &[
+    "x",
+    "y",
+    "width",
+    "height",
+    "result",
+    "in",
+    "in2",
+    "mode",
+    "type",
+    "values",
+    "operator",
+    "k1",
+    "k2",
+    "k3",
+    "k4",
+    "order",
+    "kernelMatrix",
+    "divisor",
+    "bias",
+    "targetX",
+    "targetY",
+    "edgeMode",
+    "kernelUnitLength",
+    "preserveAlpha",
+    "surfaceScale",
+    "diffuseConstant",
+    "specularConstant",
+    "specularExponent",
+    "scale",
+    "xChannelSelector",
+    "yChannelSelector",
+    "dx",
+    "dy",
+    "stdDeviation",
+    "href",
+    "preserveAspectRatio",
+    "crossorigin",
+    "radius",
+    "baseFrequency",
+    "numOctaves",
+    "seed",
+    "stitchTiles",
+];
+
+/// Element-specific attributes, or `None` if the element is not known.
+pub(crate) fn element_attrs(name: &str) -> Option<&'static [&'static str]> {
+    let attrs: &[&str] = match name {
+        "svg" => &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "viewBox",
+            "preserveAspectRatio",
+            "version",
+            "baseProfile",
+            "zoomAndPan",
+        ],
+        "g" | "defs" | "switch" | "desc" | "title" | "metadata" => &[],
+        "rect" => &["x", "y", "width", "height", "rx", "ry", "pathLength"],
+        "circle" => &["cx", "cy", "r", "pathLength"],
+        "ellipse" => &["cx", "cy", "rx", "ry", "pathLength"],
+        "line" => &["x1", "y1", "x2", "y2", "pathLength"],
+        "polyline" | "polygon" => &["points", "pathLength"],
+        "path" => &["d", "pathLength"],
+        "text" | "tspan" => &["x", "y", "dx", "dy", "rotate", "textLength", "lengthAdjust"],
+        "textPath" => &[
+            "href",
+            "startOffset",
+            "method",
+            "spacing",
+            "side",
+            "path",
+            "textLength",
+            "lengthAdjust",
+        ],
+        "use" => &["href", "x", "y", "width", "height"],
+        "image" => &[
+            "href",
+            "x",
+            "y",
+            "width",
+            "height",
+            "preserveAspectRatio",
+            "crossorigin",
+            "decoding",
+        ],
+        "symbol" => &[
+            "viewBox",
+            "preserveAspectRatio",
+            "x",
+            "y",
+            "width",
+            "height",
+            "refX",
+            "refY",
+        ],
+        "marker" => &[
+            "viewBox",
+            "preserveAspectRatio",
+            "refX",
+            "refY",
+            "markerUnits",
+            "markerWidth",
+            "markerHeight",
+            "orient",
+        ],
+        "linearGradient" => &[
+            "x1",
+            "y1",
+            "x2",
+            "y2",
+            "gradientUnits",
+            "gradientTransform",
+            "spreadMethod",
+            "href",
+        ],
+        "radialGradient" => &[
+            "cx",
+            "cy",
+            "r",
+            "fx",
+            "fy",
+            "fr",
+            "gradientUnits",
+            "gradientTransform",
+            "spreadMethod",
+            "href",
+        ],
+        "stop" => &["offset"],
+        "pattern" => &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "patternUnits",
+            "patternContentUnits",
+            "patternTransform",
+            "viewBox",
+            "preserveAspectRatio",
+            "href",
+        ],
+        "clipPath" => &["clipPathUnits"],
+        "mask" => &["x", "y", "width", "height", "maskUnits", "maskContentUnits"],
+        "filter" => &[
+            "x",
+            "y",
+            "width",
+            "height",
+            "filterUnits",
+            "primitiveUnits",
+            "href",
+        ],
+        "a" => &[
+            "href",
+            "target",
+            "download",
+            "hreflang",
+            "ping",
+            "referrerpolicy",
+            "rel",
+            "type",
+        ],
+        "style" => &["type", "media", "title"],
+        "script" => &["type", "href", "crossorigin"],
+        "foreignObject" => &["x", "y", "width", "height"],
+        "view" => &["viewBox", "preserveAspectRatio", "zoomAndPan", "viewTarget"],
+        "mpath" => &["href"],
+        "animate" | "animateMotion" | "animateTransform" | "set" => ANIMATION_ATTRS,
+        "feMergeNode" => &["in"],
+        "feDistantLight" => &["azimuth", "elevation"],
+        "fePointLight" => &["x", "y", "z"],
+        "feSpotLight" => &[
+            "x",
+            "y",
+            "z",
+            "pointsAtX",
+            "pointsAtY",
+            "pointsAtZ",
+            "specularExponent",
+            "limitingConeAngle",
+        ],
+        "feFuncA" | "feFuncB" | "feFuncG" | "feFuncR" => &[
+            "type",
+            "tableValues",
+            "slope",
+            "intercept",
+            "amplitude",
+            "exponent",
+            "offset",
+        ],
+        _ if FILTER_PRIMITIVE.contains(&name) => FILTER_PRIMITIVE_ATTRS,
+        _ => return None,
+    };
+    Some(attrs)
+}
+
+/// Whether `child` is a permitted child element of `parent`.
+///
+/// `parent` is assumed to be a known element.
+fn child_permitted(parent: &str, child: &str) -> bool {
+    let any_of = |groups: &[&[&str]]| groups.iter().any(|g| g.contains(&child));
+    match parent {
+        "svg" | "g" | "defs" | "symbol" | "marker" | "mask" | "pattern" | "a" | "switch" => {
+            any_of(&[
+                SHAPE,
+                STRUCTURAL,
+                DESCRIPTIVE,
+                ANIMATION,
+                GRADIENT,
+                OTHER_CONTENT,
+            ])
+        }
+        "clipPath" => any_of(&[SHAPE, DESCRIPTIVE, ANIMATION, &["text", "use"]]),
+        "text" | "tspan" => any_of(&[TEXT_CONTENT, DESCRIPTIVE, ANIMATION]),
+        "textPath" => any_of(&[&["a", "tspan"], DESCRIPTIVE, ANIMATION]),
+        "linearGradient" | "radialGradient" => {
+            any_of(&[&["stop", "animate", "animateTransform", "set"], DESCRIPTIVE])
+        }
+        "filter" => any_of(&[FILTER_PRIMITIVE, DESCRIPTIVE, &["animate", "set"]]),
+        "feMerge" => child == "feMergeNode",
+        "feComponentTransfer" => any_of(&[TRANSFER_FUNCTION, DESCRIPTIVE]),
+        "feDiffuseLighting" | "feSpecularLighting" => any_of(&[LIGHT_SOURCE, DESCRIPTIVE]),
+        "animateMotion" => any_of(&[&["mpath"], DESCRIPTIVE]),
+        "desc" | "title" | "style" | "script" => false,
+        _ => any_of(&[DESCRIPTIVE, ANIMATION]),
+    }
+}
+
+fn attr_permitted(el_attrs: &[&str], attr: &str) -> bool {
+    el_attrs.contains(&attr)
+        || GLOBAL_ATTRS.contains(&attr)
+        || attr.starts_with("data-")
+        || attr.starts_with("aria-")
+        // event attributes, e.g. onclick
+        || (attr.starts_with("on") && attr.len() > 2 && attr[2..].chars().all(|c| c.is_ascii_lowercase()))
+        // namespaced attributes, e.g. xml:space, xlink:href, xmlns:xlink
+        || attr.contains(':')
+}
+
+fn check_element(
+    e: &BytesStart,
+    line: usize,
+    parent: Option<&str>,
+    issues: &mut Vec<ValidationIssue>,
+) -> String {
+    let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+    if name.contains(':') {
+        // Element in another namespace; not our concern.
+        return name;
+    }
+    let mut issue = |message: String| issues.push(ValidationIssue { line, message });
+    let Some(el_attrs) = element_attrs(&name) else {
+        issue(format!("unknown element <{name}>"));
+        return name;
+    };
+    if let Some(parent) = parent {
+        if element_attrs(parent).is_some() && !child_permitted(parent, &name) {
+            issue(format!("<{name}> is not permitted within <{parent}>"));
+        }
+    } else if name != "svg" {
+        issue(format!("root element should be <svg>, not <{name}>"));
+    }
+    for attr in e.attributes().flatten() {
+        let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+        if !attr_permitted(el_attrs, &key) {
+            issue(format!("unknown attribute '{key}' on <{name}>"));
+        }
+    }
+    name
+}
+
+/// Check the given SVG document against a built-in model of SVG elements and
+/// attributes, returning a list of any issues found.
+///
+/// An error is returned only if the document cannot be parsed.
+pub fn validate_svg(svg: &str) -> Result<Vec<ValidationIssue>> {
+    let mut reader = Reader::from_str(svg);
+    let mut issues = Vec::new();
+    // Stack of open element names
+    let mut stack: Vec<String> = Vec::new();
+    // Depth within the stack at which validation is suspended, e.g. within
+    // foreignObject, which may contain arbitrary (e.g. HTML) content.
+    let mut opaque_depth: Option<usize> = None;
+    let mut line = 1;
+    let mut pos = 0;
+    loop {
+        let ev_start = reader.buffer_position() as usize;
+        line += svg.as_bytes()[pos..ev_start]
+            .iter()
+            .filter(|&c| *c == b'\n')
+            .count();
+        pos = ev_start;
+        let ev = reader
+            .read_event()
+            .map_err(|e| SvgdxError::ParseError(format!("XML error near line {line}: {e:?}")))?;
+        match &ev {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) => {
+                let name = if opaque_depth.is_some() {
+                    String::from_utf8_lossy(e.name().as_ref()).to_string()
+                } else {
+                    check_element(e, line, stack.last().map(|s| s.as_str()), &mut issues)
+                };
+                if matches!(ev, Event::Start(_)) {
+                    if opaque_depth.is_none() && (name == "foreignObject" || name == "metadata") {
+                        opaque_depth = Some(stack.len());
+                    }
+                    stack.push(name);
+                }
+            }
+            Event::End(_) => {
+                stack.pop();
+                if opaque_depth == Some(stack.len()) {
+                    opaque_depth = None;
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_valid() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">
+<defs><linearGradient id="lg"><stop offset="0" stop-color="red"/></linearGradient></defs>
+<rect width="5" height="5" data-src-line="2" onclick="f()"><title>A</title></rect>
+<text x="1" y="2">Hello <tspan dy="1">world</tspan></text>
+<foreignObject width="5" height="5"><div xmlns="http://www.w3.org/1999/xhtml" foo="1"/></foreignObject>
+</svg>"#;
+        assert_eq!(validate_svg(svg).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_validate_issues() {
+        let svg = r#"<svg>
+<rect wh="5"><circle r="1"/></rect>
+<stop offset="1"/>
+<blob/>
+</svg>"#;
+        let issues: Vec<String> = validate_svg(svg)
+            .unwrap()
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                "line 2: unknown attribute 'wh' on <rect>",
+                "line 2: <circle> is not permitted within <rect>",
+                "line 3: <stop> is not permitted within <svg>",
+                "line 4: unknown element <blob>",
+            ]
+        );
+    }
+}
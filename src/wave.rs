@@ -0,0 +1,323 @@
+//! Digital timing waveforms.
+//!
+//! A `<wave>` element renders a single signal of a timing diagram from a
+//! pattern string, with one character per time period:
+//!
+//! - `p` / `n` - a clock period, starting with a rising / falling edge
+//! - `h` / `1` - high level
+//! - `l` / `0` - low level
+//! - `z` - high impedance (mid level)
+//! - `x` - unknown value (hatched)
+//! - `=` - data value, labelled with the next entry from `data`
+//! - `.` - extends the previous period
+//!
+//! ```xml
+//! <wave signal="clk" pattern="p...." xy="0"/>
+//! <wave signal="data" pattern="x=.=x" data="A B" xy="^|v 4"/>
+//! ```
+//!
+//! The `xy` position of the wave is the start of the time axis; the signal
+//! name is placed to the left of this, so waves with the same `x` and
+//! `period` share a time axis.
+
+use crate::context::TransformerContext;
+use crate::element::SvgElement;
+use crate::errors::{Result, SvgdxError};
+use crate::events::{OutputEvent, OutputList};
+use crate::expression::eval_attr;
+use crate::position::{BoundingBox, BoundingBoxBuilder};
+use crate::transform::{process_elements, EventGen, FRAME_POSITION_ATTRS};
+use crate::types::{attr_split, fstr, strp};
+
+/// Attributes of `<wave>` which are not present on the generated group.
+const WAVE_ATTRS: &[&str] = &["signal", "pattern", "data", "period", "height"];
+
+const DEFAULT_PERIOD: f32 = 10.;
+const DEFAULT_HEIGHT: f32 = 5.;
+/// Gap between the signal name and the start of the wave
+const LABEL_GAP: f32 = 2.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Level {
+    High,
+    Low,
+    Mid,
+}
+
+/// A run of one or more periods of a waveform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Segment {
+    /// Clock periods, starting with a rising edge if `rising`
+    Clock {
+        rising: bool,
+    },
+    Level(Level),
+    Unknown,
+    Data,
+}
+
+/// Parse a wave pattern into segments with their start period and length.
+fn parse_pattern(pattern: &str) -> Result<Vec<(Segment, usize, usize)>> {
+    let mut segments: Vec<(Segment, usize, usize)> = Vec::new();
+    for (idx, ch) in pattern.chars().enumerate() {
+        let seg = match ch {
+            'p' => Segment::Clock { rising: true },
+            'n' => Segment::Clock { rising: false },
+            'h' | '1' => Segment::Level(Level::High),
+            'l' | '0' => Segment::Level(Level::Low),
+            'z' => Segment::Level(Level::Mid),
+            'x' => Segment::Unknown,
+            '=' => Segment::Data,
+            '.' => match segments.last_mut() {
+                Some((_, _, len)) => {
+                    *len += 1;
+                    continue;
+                }
+                None => {
+                    return Err(SvgdxError::InvalidData(
+                        "wave: pattern cannot start with '.'".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(SvgdxError::InvalidData(format!(
+                    "wave: invalid pattern character '{ch}'"
+                )))
+            }
+        };
+        segments.push((seg, idx, 1));
+    }
+    Ok(segments)
+}
+
+/// Shapes for a waveform with its time axis starting at `(x, y)`.
+fn wave_elements(
+    pattern: &str,
+    data: &[String],
+    (x, y): (f32, f32),
+    period: f32,
+    height: f32,
+) -> Result<Vec<SvgElement>> {
+    let level_y = |level| match level {
+        Level::High => y,
+        Level::Low => y + height,
+        Level::Mid => y + height / 2.,
+    };
+    let point = |px: f32, py: f32| format!("{} {}", fstr(px), fstr(py));
+
+    let mut elements = Vec::new();
+    let mut points: Vec<String> = Vec::new();
+    let flush = |points: &mut Vec<String>, elements: &mut Vec<SvgElement>| {
+        // adjacent segments may share a point
+        points.dedup();
+        if points.len() > 1 {
+            let mut line = SvgElement::new("polyline", &[]);
+            line.set_attr("points", &points.join(", "));
+            elements.push(line);
+        }
+        points.clear();
+    };
+    let mut data = data.iter();
+    for (seg, start, len) in parse_pattern(pattern)? {
+        let (x0, x1) = (x + start as f32 * period, x + (start + len) as f32 * period);
+        match seg {
+            Segment::Level(level) => {
+                points.push(point(x0, level_y(level)));
+                points.push(point(x1, level_y(level)));
+            }
+            Segment::Clock { rising } => {
+                let (first, second) = if rising {
+                    (Level::High, Level::Low)
+                } else {
+                    (Level::Low, Level::High)
+                };
+                for i in 0..len {
+                    let px = x0 + i as f32 * period;
+                    points.push(point(px, level_y(second)));
+                    points.push(point(px, level_y(first)));
+                    points.push(point(px + period / 2., level_y(first)));
+                    points.push(point(px + period / 2., level_y(second)));
+                    points.push(point(px + period, level_y(second)));
+                }
+            }
+            Segment::Unknown => {
+                flush(&mut points, &mut elements);
+                let mut rect = SvgElement::new("rect", &[]);
+                rect.set_attr("x", &fstr(x0));
+                rect.set_attr("y", &fstr(y));
+                rect.set_attr("width", &fstr(x1 - x0));
+                rect.set_attr("height", &fstr(height));
+                rect.add_class("d-hatch");
+                elements.push(rect);
+            }
+            Segment::Data => {
+                flush(&mut points, &mut elements);
+                // slanted ends indicate transitions between values
+                let slant = (height / 4.).min((x1 - x0) / 4.);
+                let mid = y + height / 2.;
+                let mut poly = SvgElement::new("polygon", &[]);
+                poly.set_attr(
+                    "points",
+                    &[
+                        point(x0, mid),
+                        point(x0 + slant, y),
+                        point(x1 - slant, y),
+                        point(x1, mid),
+                        point(x1 - slant, y + height),
+                        point(x0 + slant, y + height),
+                    ]
+                    .join(", "),
+                );
+                if let Some(text) = data.next() {
+                    poly.set_attr("text", text);
+                }
+                elements.push(poly);
+            }
+        }
+    }
+    flush(&mut points, &mut elements);
+    Ok(elements)
+}
+
+#[derive(Debug, Clone)]
+pub struct WaveElement(pub SvgElement);
+
+impl WaveElement {
+    fn number_attr(&self, attr: &str, default: f32, context: &TransformerContext) -> Result<f32> {
+        self.0
+            .get_attr(attr)
+            .map(|v| strp(&eval_attr(&v, context)))
+            .transpose()
+            .map(|v| v.unwrap_or(default))
+    }
+}
+
+impl EventGen for WaveElement {
+    fn generate_events(
+        &self,
+        context: &mut TransformerContext,
+    ) -> Result<(OutputList, Option<BoundingBox>)> {
+        let pattern = eval_attr(
+            &self
+                .0
+                .get_attr("pattern")
+                .ok_or_else(|| SvgdxError::MissingAttribute("pattern".to_string()))?,
+            context,
+        );
+        let data: Vec<String> = self
+            .0
+            .get_attr("data")
+            .map(|d| attr_split(&eval_attr(&d, context)).collect())
+            .unwrap_or_default();
+        let period = self.number_attr("period", DEFAULT_PERIOD, context)?;
+        let height = self.number_attr("height", DEFAULT_HEIGHT, context)?;
+        let width = pattern.chars().count() as f32 * period;
+
+        // Position the wave as if it were a rect covering the waveform
+        let mut frame = SvgElement::new("rect", &[]);
+        for attr in FRAME_POSITION_ATTRS {
+            if let Some(value) = self.0.get_attr(attr) {
+                frame.set_attr(attr, &value);
+            }
+        }
+        frame.set_attr("width", &fstr(width));
+        frame.set_attr("height", &fstr(height));
+        frame.resolve_position(context)?;
+        let x = frame.get_attr("x").map(|x| strp(&x)).transpose()?;
+        let y = frame.get_attr("y").map(|y| strp(&y)).transpose()?;
+        let origin = (x.unwrap_or_default(), y.unwrap_or_default());
+
+        let mut shapes = Vec::new();
+        if let Some(signal) = self.0.get_attr("signal") {
+            let mut label = SvgElement::new("text", &[]);
+            label.set_attr(
+                "xy",
+                &format!(
+                    "{} {}",
+                    fstr(origin.0 - LABEL_GAP),
+                    fstr(origin.1 + height / 2.)
+                ),
+            );
+            label.set_attr("text", &signal);
+            label.set_attr("text-loc", "l");
+            shapes.push(label);
+        }
+        shapes.extend(wave_elements(&pattern, &data, origin, period, height)?);
+        let (content, content_bb) = process_elements(shapes, context)?;
+
+        let mut group = self.0.clone();
+        group.name = "g".to_string();
+        for attr in WAVE_ATTRS.iter().chain(FRAME_POSITION_ATTRS) {
+            group.pop_attr(attr);
+        }
+        let mut events = OutputList::new();
+        events.push(OutputEvent::Start(group.clone()));
+        events.extend(&content);
+        events.push(OutputEvent::Text("\n".to_string()));
+        events.push(OutputEvent::End("g".to_string()));
+
+        // References to the wave (e.g. `^`) use the waveform itself, excluding
+        // the signal name, so waves positioned relative to each other align.
+        group.content_bbox = Some(BoundingBox::new(
+            origin.0,
+            origin.1,
+            origin.0 + width,
+            origin.1 + height,
+        ));
+        context.update_element(&group);
+        context.set_prev_element(&group);
+
+        let mut bbox = BoundingBoxBuilder::new();
+        if let Some(bb) = group.content_bbox {
+            bbox.extend(bb);
+        }
+        if let Some(bb) = content_bb {
+            bbox.extend(bb);
+        }
+        Ok((events, bbox.build()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(
+            parse_pattern("p..h=.x").unwrap(),
+            [
+                (Segment::Clock { rising: true }, 0, 3),
+                (Segment::Level(Level::High), 3, 1),
+                (Segment::Data, 4, 2),
+                (Segment::Unknown, 6, 1),
+            ]
+        );
+        assert!(parse_pattern(".h").is_err());
+        assert!(parse_pattern("hq").is_err());
+    }
+
+    #[test]
+    fn test_wave_elements() {
+        let els = wave_elements("lh.", &[], (0., 0.), 10., 5.).unwrap();
+        assert_eq!(els.len(), 1);
+        assert_eq!(els[0].get_attr("points").unwrap(), "0 5, 10 5, 10 0, 30 0");
+
+        let els = wave_elements("n", &[], (0., 0.), 10., 5.).unwrap();
+        assert_eq!(
+            els[0].get_attr("points").unwrap(),
+            "0 0, 0 5, 5 5, 5 0, 10 0"
+        );
+
+        let data = ["A".to_string()];
+        let els = wave_elements("h=x", &data, (0., 0.), 10., 4.).unwrap();
+        assert_eq!(els.len(), 3);
+        assert_eq!(els[1].name, "polygon");
+        assert_eq!(
+            els[1].get_attr("points").unwrap(),
+            "10 2, 11 0, 19 0, 20 2, 19 4, 11 4"
+        );
+        assert_eq!(els[1].get_attr("text").unwrap(), "A");
+        assert_eq!(els[2].name, "rect");
+    }
+}
@@ -0,0 +1,113 @@
+//! Python bindings for svgdx.
+//!
+//! Exposes `transform(input, **config)`, returning the rendered SVG, and
+//! raising `SvgdxError` with the source position of each problem on failure.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use svgdx::TransformConfig;
+
+create_exception!(
+    svgdx,
+    SvgdxError,
+    PyException,
+    "Error transforming an svgdx document; `spans` gives the location of each problem."
+);
+
+/// Location and details of a problem in the source document.
+#[pyclass(module = "svgdx", frozen, get_all)]
+struct ErrorSpan {
+    /// Line number (1-based)
+    line: usize,
+    /// Column number (1-based, in characters)
+    col: usize,
+    /// Length of the relevant source text, in characters
+    len: usize,
+    /// Error code, e.g. "E0101"
+    code: Option<String>,
+    message: String,
+}
+
+#[pymethods]
+impl ErrorSpan {
+    fn __repr__(&self) -> String {
+        format!(
+            "ErrorSpan(line={}, col={}, len={}, code={:?}, message={:?})",
+            self.line, self.col, self.len, self.code, self.message
+        )
+    }
+}
+
+/// Build a `TransformConfig` from Python keyword arguments.
+///
+/// Keys are `<config>` setting names with `_` in place of `-`, plus settings
+/// only available to the host (e.g. `import_dir`).
+fn config_from_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<TransformConfig> {
+    let mut config = TransformConfig::default();
+    let Some(kwargs) = kwargs else {
+        return Ok(config);
+    };
+    for (key, value) in kwargs {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "add_metadata" => config.add_metadata = value.extract()?,
+            "sanitize" => config.sanitize = value.extract()?,
+            "allow_env" => config.allow_env = value.extract()?,
+            "import_dir" => config.import_dir = value.extract()?,
+            "source_file" => config.source_file = value.extract()?,
+            "element_limit" => config.element_limit = value.extract()?,
+            "output_limit" => config.output_limit = value.extract()?,
+            _ => {
+                // Python's `str(True)` is "True", which Rust's bool parsing rejects
+                let value = match value.extract::<bool>() {
+                    Ok(b) => b.to_string(),
+                    Err(_) => value.str()?.to_string(),
+                };
+                config
+                    .set(&key.replace('_', "-"), &value)
+                    .map_err(|e| PyValueError::new_err(format!("{key}: {e}")))?;
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Transform an svgdx document to SVG.
+///
+/// Keyword arguments set transform options, e.g. `font_size=4`.
+#[pyfunction]
+#[pyo3(signature = (input, **config))]
+fn transform(py: Python<'_>, input: &str, config: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let config = config_from_kwargs(config)?;
+    svgdx::transform_str(input, &config).map_err(|e| {
+        let err = SvgdxError::new_err(e.to_string());
+        let spans: Vec<ErrorSpan> = e
+            .spans(input)
+            .into_iter()
+            .map(|s| ErrorSpan {
+                line: s.line,
+                col: s.col,
+                len: s.len,
+                code: s.code.map(str::to_string),
+                message: s.message,
+            })
+            .collect();
+        match err.value(py).setattr("spans", spans) {
+            Ok(()) => err,
+            Err(setattr_err) => setattr_err,
+        }
+    })
+}
+
+#[pymodule]
+#[pyo3(name = "svgdx")]
+fn svgdx_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", svgdx::VERSION)?;
+    m.add("SvgdxError", m.py().get_type::<SvgdxError>())?;
+    m.add_class::<ErrorSpan>()?;
+    m.add_function(wrap_pyfunction!(transform, m)?)?;
+    Ok(())
+}
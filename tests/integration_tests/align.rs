@@ -0,0 +1,71 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_align() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="20 5" wh="10 20"/>
+<circle id="c" cxy="50 3" r="4"/>
+<line start="#a" end="#b"/>
+<rect id="d" xy="#c|h 2" wh="5"/>
+<align refs="#a #b #c" mode="cy"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_not_contains!(output, "<align");
+    // aligned with the first referenced element, which is unchanged
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="0" y="0" width="10" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="b" x="20" y="-5" width="10" height="20"/>"#
+    );
+    assert_contains!(output, r#"<circle id="c" cx="50" cy="5" r="4"/>"#);
+    // connectors and relatively positioned elements follow moved elements
+    assert_contains!(output, r#"<line x1="10" y1="5" x2="20" y2="5"/>"#);
+    assert_contains!(
+        output,
+        r#"<rect id="d" x="56" y="2.5" width="5" height="5"/>"#
+    );
+}
+
+#[test]
+fn test_distribute() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="12 0" wh="4"/>
+<rect id="c" xy="40 0" wh="10"/>
+<distribute refs="#a #b #c"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_not_contains!(output, "<distribute");
+    // evenly spaced between the first and last
+    assert_contains!(output, r#"<rect id="b" x="23" y="0""#);
+    assert_contains!(output, r#"<rect id="c" x="40" y="0""#);
+
+    // groups may be moved, and refs may be to later elements
+    let input = r##"
+<distribute refs="#g #b" dir="v" gap="2"/>
+<g id="g"><rect id="r" wh="10"/></g>
+<rect id="b" xy="20 0" wh="10"/>
+<rect id="e" xy="#r|v 1" wh="3"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="g">"#);
+    assert_contains!(output, r#"<rect id="b" x="20" y="12""#);
+    assert_contains!(output, r#"<rect id="e" x="3.5" y="11""#);
+}
+
+#[test]
+fn test_align_bad() {
+    let input = r#"<rect wh="2"/><align refs="^" mode="cx"/>"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"<rect id="a" wh="2"/><align refs="#a" mode="middle"/>"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"<rect id="a" wh="2"/><distribute refs="#a" dir="x"/>"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"<align refs="#missing" mode="cx"/>"##;
+    assert!(transform_str_default(input).is_err());
+}
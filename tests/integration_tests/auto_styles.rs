@@ -121,6 +121,23 @@ fn test_style_arrow() {
     let expected_defs = r#"<marker id="d-arrow" "#;
     assert_contains!(output, expected_defs);
 
+    let input = r#"<svg><line xy1="0" xy2="10" class="d-arrow-start" /></svg>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#".d-arrow-start { marker-start: url(#d-arrow); }"#);
+    assert_contains!(output, "marker path { fill: black; }");
+
+    // a single marker def is shared by all arrow classes
+    let input = r#"<svg>
+<line xy1="0" xy2="10" class="d-arrow"/>
+<line xy1="0" xy2="10" class="d-arrow-both"/>
+</svg>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#".d-arrow-both { marker-start: url(#d-arrow); marker-end: url(#d-arrow); }"#
+    );
+    assert_eq!(output.matches("<marker ").count(), 1);
+
     let input = r#"<svg><line xy1="0" xy2="10" class="d-biarrow" /></svg>"#;
     let expected_style =
         r#".d-biarrow { marker-start: url(#d-arrow); marker-end: url(#d-arrow); }"#;
@@ -198,3 +215,78 @@ fn test_style_grid() {
     assert_not_contains!(output, expected1);
     assert_not_contains!(output, expected2);
 }
+
+#[test]
+fn test_style_custom_theme() {
+    use std::sync::Arc;
+    use svgdx::{transform_str, CustomTheme, Theme, ThemeBuilder, TransformConfig};
+
+    let theme = CustomTheme::new()
+        .stroke("navy")
+        .background("ivory")
+        .font_family("serif")
+        .css("rect { rx: 1px; }");
+    let cfg = TransformConfig {
+        custom_theme: Some(Arc::new(theme)),
+        ..Default::default()
+    };
+    let output = transform_str(r#"<svg><rect wh="10" text="a"/></svg>"#, &cfg).unwrap();
+    assert_contains!(output, "svg { background: ivory; }");
+    assert_contains!(output, "stroke-width: 0.5; fill: white; stroke: navy; }");
+    assert_contains!(output, "font-family: serif;");
+    assert_contains!(output, "rect { rx: 1px; }");
+
+    #[derive(Debug)]
+    struct Stripes;
+    impl Theme for Stripes {
+        fn default_stroke_width(&self) -> f32 {
+            2.
+        }
+        fn append_early_styles(&self, tb: &mut ThemeBuilder) {
+            if tb.has_class("stripy") {
+                tb.add_style(".stripy { stroke-dasharray: 1; }");
+            }
+        }
+    }
+    let cfg = TransformConfig {
+        custom_theme: Some(Arc::new(Stripes)),
+        ..Default::default()
+    };
+    let output =
+        transform_str(r#"<svg><rect wh="10" class="stripy d-thin"/></svg>"#, &cfg).unwrap();
+    assert_contains!(output, ".stripy { stroke-dasharray: 1; }");
+    assert_contains!(output, ".d-thin { stroke-width: 1; }");
+}
+
+#[test]
+fn test_style_extract_css() {
+    use svgdx::{transform_str_extract_css, TransformConfig};
+
+    let cfg = TransformConfig {
+        css_href: Some("shared.css".to_owned()),
+        ..Default::default()
+    };
+    let input =
+        r#"<svg><rect wh="10" class="d-red"/><line xy1="0" xy2="5" class="d-arrow"/></svg>"#;
+    let (output, css) = transform_str_extract_css(input, &cfg).unwrap();
+    assert!(output.starts_with(r#"<?xml-stylesheet type="text/css" href="shared.css"?>"#));
+    assert_not_contains!(output, "<style>");
+    assert_not_contains!(output, ".d-red");
+    // defs (e.g. markers) are still included in the SVG
+    assert_contains!(output, "<marker");
+    assert_contains!(css, ".d-red { stroke: red; }");
+
+    // css_href is required
+    assert!(transform_str_extract_css(input, &TransformConfig::default()).is_err());
+}
+
+#[test]
+fn test_style_faded() {
+    let input = r#"<svg><g class="d-faded"><rect wh="10"/></g></svg>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ".d-faded { opacity: 0.35; }");
+
+    let input = r#"<svg><rect wh="10"/></svg>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_not_contains!(output, ".d-faded");
+}
@@ -65,3 +65,41 @@ fn test_box_text() {
     let output = transform_str_default(input).unwrap();
     assert_contains!(output, expected);
 }
+
+#[test]
+fn test_box_region_label() {
+    let input = r##"
+<box id="region" xy="0" wh="40 20" text="Region" text-loc="tl"/>
+<rect id="a" inside="#region" margin="4"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<text x="1" y="1" class="d-text d-text-top d-text-left">Region</text>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="4" y="4" width="32" height="12" class="d-inside"/>"#
+    );
+    assert_not_contains!(output, "<box");
+    assert_not_contains!(output, r#"width="40""#);
+}
+
+#[test]
+fn test_box_region_inside_text() {
+    let input = r##"
+<box id="region" xy="0" wh="40 20"/>
+<text inside="#region" text="centre"/>
+<text inside="#region" margin="2" text="corner" text-loc="br"/>
+<point id="p" inside="#region"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<text x="20" y="10" class="d-text d-inside">centre</text>"#
+    );
+    assert_contains!(
+        output,
+        r#"<text x="37" y="17" class="d-text d-text-bottom d-text-right d-inside">corner</text>"#
+    );
+}
@@ -78,3 +78,486 @@ fn test_cmdline_same_file() {
         .failure()
         .code(1);
 }
+
+#[test]
+fn test_cmdline_validate() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, r#"<svg><rect xy="0" wh="1"/></svg>"#).expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["--validate", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, r#"<svg><rect xy="0" wh="1" foo="2"/></svg>"#).expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--validate", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .get_output()
+        .clone();
+    // Output is still generated
+    assert_contains!(String::from_utf8(output.stdout).unwrap(), "<rect");
+    assert_contains!(
+        String::from_utf8(output.stderr).unwrap(),
+        "unknown attribute 'foo' on <rect>"
+    );
+}
+
+#[test]
+fn test_cmdline_explain() {
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd.args(["--explain", "E0101"]).assert().success();
+    let stdout = String::from_utf8(output.get_output().stdout.clone()).unwrap();
+    assert_contains!(stdout, "A referenced element could not be found.");
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["--explain", "E9999"]).assert().failure().code(1);
+}
+
+#[test]
+fn test_cmdline_fmt() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, r#"<svg><rect wh="1" y="2" x="1"/></svg>"#).expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["fmt", "--check", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .code(1);
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["fmt", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let formatted = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(formatted, "<svg>\n  <rect xy=\"1 2\" wh=\"1\"/>\n</svg>\n");
+
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, "{formatted}").expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["fmt", "--check", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_cmdline_migrate() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(
+        tmpfile,
+        r##"<svg><rect id="a" wh="1"/><rect xy="#a:h" width="#a.w"/></svg>"##
+    )
+    .expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args(["migrate", "--check", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .failure()
+        .code(1);
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["migrate", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        r##"<svg><rect id="a" wh="1"/><rect xy="#a|h" width="#a~w"/></svg>"##
+    );
+}
+
+#[test]
+fn test_cmdline_geometry() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(
+        tmpfile,
+        r##"<svg><rect id="a" wh="10" style="fill: red"/><rect xy="20 0" wh="10"/><line start="#a" end="^"/></svg>"##
+    )
+    .expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--geometry", tmpfile.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "rect #a 0 0 10 10\nline 10 5 10 0 : 10,5 20,5\n"
+    );
+}
+
+#[test]
+fn test_cmdline_config_outputs() {
+    let dir = tempfile::tempdir().expect("could not create tmpdir");
+    let input = dir.path().join("input.xml");
+    std::fs::write(&input, r#"<svg><rect xy="0" wh="1"/></svg>"#).unwrap();
+    std::fs::write(
+        dir.path().join("svgdx.toml"),
+        r#"
+[[output]]
+file = "out2.svg"
+theme = "dark"
+
+[[output]]
+file = "out3.svg"
+scale = 3
+"#,
+    )
+    .unwrap();
+    let out1 = dir.path().join("out1.svg");
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args([
+        input.to_str().unwrap(),
+        "--scale",
+        "2",
+        "-o",
+        out1.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+
+    let out1 = std::fs::read_to_string(out1).unwrap();
+    let out2 = std::fs::read_to_string(dir.path().join("out2.svg")).unwrap();
+    let out3 = std::fs::read_to_string(dir.path().join("out3.svg")).unwrap();
+    assert_contains!(out1, r#"width="22mm""#);
+    // outputs inherit command line settings unless overridden
+    assert_contains!(out2, r#"width="22mm""#);
+    assert_ne!(out1, out2);
+    assert_contains!(out3, r#"width="33mm""#);
+
+    // with configured outputs, nothing is written to stdout by default
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.arg(input.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_cmdline_config_file() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, r#"<svg><rect xy="0" wh="1"/></svg>"#).expect("tmpfile write failed");
+    let dir = tempfile::tempdir().expect("could not create tmpdir");
+    let config_file = dir.path().join("outputs.toml");
+
+    // output paths are relative to the config file
+    std::fs::write(&config_file, "[[output]]\nfile = \"out.svg\"\nborder = 0\n").unwrap();
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args([
+        tmpfile.path().to_str().unwrap(),
+        "--config-file",
+        config_file.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+    let out = std::fs::read_to_string(dir.path().join("out.svg")).unwrap();
+    assert_contains!(out, r#"viewBox="0 0 1 1""#);
+
+    for bad in [
+        "theme = \"dark\"\n",
+        "[[output]]\nborder = 0\n",
+        "[[output]]\nfile = \"out.svg\"\nnot-a-setting = 1\n",
+        "[[output]]\nfile = \"out.svg\"\nformat = \"gif\"\n",
+    ] {
+        std::fs::write(&config_file, bad).unwrap();
+        let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+        cmd.args([
+            tmpfile.path().to_str().unwrap(),
+            "--config-file",
+            config_file.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+    }
+}
+#[test]
+fn test_cmdline_html_fragment() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, r#"<svg><title>Boxes</title><rect wh="1"/></svg>"#)
+        .expect("tmpfile write failed");
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args([
+            "--format",
+            "html-fragment",
+            "--extract-css",
+            "/dev/null",
+            tmpfile.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert!(output.starts_with("<figure class=\"svgdx-figure\">\n<svg "));
+    assert!(output.ends_with("</svg>\n<figcaption>Boxes</figcaption>\n</figure>\n"));
+}
+
+#[test]
+fn test_cmdline_steps() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(
+        tmpfile,
+        r#"<svg><rect wh="1"/><rect xy="2 0" wh="1" step="2"/><circle r="1" step="3"/></svg>"#
+    )
+    .expect("tmpfile write failed");
+    let outdir = tempfile::tempdir().expect("could not create tmpdir");
+    let out = outdir.path().join("slides.svg");
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args([
+        tmpfile.path().to_str().unwrap(),
+        "--steps",
+        "-o",
+        out.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+
+    let step = |n| std::fs::read_to_string(outdir.path().join(format!("slides-{n}.svg")));
+    let (step1, step2, step3) = (step(1).unwrap(), step(2).unwrap(), step(3).unwrap());
+    assert!(step(4).is_err());
+    assert_eq!(step1.matches(r#"display="none""#).count(), 2);
+    assert_eq!(step2.matches(r#"display="none""#).count(), 1);
+    assert_eq!(step3.matches(r#"display="none""#).count(), 0);
+    // all steps have the same canvas
+    assert_contains!(step1, r#"viewBox="-6 -6 14 12""#);
+    assert_contains!(step3, r#"viewBox="-6 -6 14 12""#);
+
+    // an output file is required
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args([tmpfile.path().to_str().unwrap(), "--steps"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_cmdline_diff() {
+    let mut old = NamedTempFile::new().expect("could not create tmpfile");
+    write!(
+        old,
+        r#"<svg><rect id="a" wh="10"/><rect id="b" xy="^|h 5" wh="10"/></svg>"#
+    )
+    .expect("tmpfile write failed");
+    let mut new = NamedTempFile::new().expect("could not create tmpfile");
+    write!(
+        new,
+        r#"<svg><rect id="a" wh="10"/><rect id="c" xy="^|v 5" wh="10"/></svg>"#
+    )
+    .expect("tmpfile write failed");
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let assert = cmd
+        .args([
+            "diff",
+            old.path().to_str().unwrap(),
+            new.path().to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+    let output = assert.get_output();
+    let stdout = String::from_utf8(output.stdout.clone()).expect("non-UTF8");
+    let stderr = String::from_utf8(output.stderr.clone()).expect("non-UTF8");
+    assert_eq!(stderr, "+ #c\n- #b\n");
+    assert_contains!(stdout, r#"class="diff-added""#);
+    assert_contains!(stdout, r#"class="diff-removed""#);
+    assert_contains!(stdout, r#"<rect id="a""#);
+}
+
+#[test]
+fn test_cmdline_compose() {
+    let mut a = NamedTempFile::new().expect("could not create tmpfile");
+    write!(a, r#"<svg><rect id="a" wh="10"/></svg>"#).expect("tmpfile write failed");
+    let mut b = NamedTempFile::new().expect("could not create tmpfile");
+    write!(b, r#"<svg><rect id="b" wh="20 10"/></svg>"#).expect("tmpfile write failed");
+    let (a, b) = (a.path().to_str().unwrap(), b.path().to_str().unwrap());
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["compose", a, b, "--gap", "5"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).expect("non-UTF8");
+    // each document has a 5 unit border
+    assert_contains!(output, r#"width="55mm" height="20mm" viewBox="0 0 55 20""#);
+    assert_contains!(
+        output,
+        r#" x="0" y="0" width="20" height="20" viewBox="-5 -5 20 20">"#
+    );
+    assert_contains!(
+        output,
+        r#" x="25" y="0" width="30" height="20" viewBox="-5 -5 30 20">"#
+    );
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["compose", a, b, "--layout", "v"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).expect("non-UTF8");
+    assert_contains!(output, r#"viewBox="0 0 30 50""#);
+    assert_contains!(output, r#" x="5" y="0" width="20" height="20""#);
+
+    // at least one document is required
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.arg("compose").assert().failure();
+}
+
+#[test]
+fn test_cmdline_compose_scoped() {
+    let mut a = NamedTempFile::new().expect("could not create tmpfile");
+    write!(
+        a,
+        r#"<svg><rect id="a" wh="10"/><line start="^@r" end="20 5" class="d-arrow"/></svg>"#
+    )
+    .expect("tmpfile write failed");
+    let mut b = NamedTempFile::new().expect("could not create tmpfile");
+    write!(b, r#"<svg><config theme="dark"/><rect id="a" wh="10" class="d-red"/><line start="^@r" end="20 5" class="d-arrow"/></svg>"#)
+        .expect("tmpfile write failed");
+    let (a, b) = (a.path().to_str().unwrap(), b.path().to_str().unwrap());
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["compose", a, b])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).expect("non-UTF8");
+    // ids are unique across documents
+    assert_contains!(output, r#"<rect id="p1-a""#);
+    assert_contains!(output, r#"<rect id="p2-a""#);
+    assert_contains!(output, r#"<marker id="p1-d-arrow""#);
+    assert_contains!(output, r#"<marker id="p2-d-arrow""#);
+    assert_contains!(output, r#"marker-end: url(#p2-d-arrow)"#);
+    // styles are scoped to each document
+    assert_contains!(output, r#"<svg id="p1-svgdx-"#);
+    assert_contains!(output, r#"<svg id="p2-svgdx-"#);
+    assert_eq!(output.matches("\n      #p1-svgdx-").count(), 1);
+    assert_eq!(output.matches("\n      #p2-svgdx-").count(), 1);
+}
+
+#[test]
+fn test_cmdline_stdin_filename() {
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--stdin-filename", "diagrams/doc.svg"])
+        .write_stdin("<svg>\n<rect xy=\"#nope\" wh=\"5\"/>\n</svg>\n")
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).expect("non-UTF8");
+    assert_contains!(stderr, "  --> diagrams/doc.svg:2:7\n");
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--stdin-filename", "doc.svg", "--add-metadata"])
+        .write_stdin(r#"<svg><rect wh="5"/></svg>"#)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).expect("non-UTF8");
+    assert_contains!(output, r#"data-src-file="doc.svg""#);
+}
+
+#[test]
+fn test_cmdline_path_recover() {
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .arg("--path-recover")
+        .write_stdin("<svg>\n<path d=\"M 0 0 L 10 oops 20 L 20 10\"/>\n</svg>\n")
+        .assert()
+        .success()
+        .get_output()
+        .clone();
+    let stderr = String::from_utf8(output.stderr).expect("non-UTF8");
+    assert_contains!(stderr, "warning: line 2: skipped malformed path data");
+    let stdout = String::from_utf8(output.stdout).expect("non-UTF8");
+    assert_contains!(stdout, r#"d="M 0 0 L 10 oops 20 L 20 10""#);
+}
+
+#[test]
+fn test_cmdline_filter_markdown() {
+    let input =
+        "# Doc\n\n```svgdx A box\n<svg>\n<rect wh=\"5\"/>\n\n</svg>\n```\n\n```xml\n<rect/>\n```\n";
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--filter", "markdown"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).expect("non-UTF8");
+    assert!(output.starts_with("# Doc\n\n<svg"));
+    assert_contains!(output, r#"<rect width="5" height="5"/>"#);
+    // no blank lines within the inline SVG
+    assert_contains!(output, "</svg>\n\n```xml\n<rect/>\n```\n");
+    assert!(!output.contains("```svgdx"));
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--filter", "markdown", "--format", "datauri-base64"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let output = String::from_utf8(output).expect("non-UTF8");
+    assert_contains!(output, "# Doc\n\n![A box](data:image/svg+xml;base64,");
+
+    // errors are reported relative to the block
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    let output = cmd
+        .args(["--filter", "markdown", "--stdin-filename", "doc.md"])
+        .write_stdin("Text\n\n```svgdx\n<svg>\n<rect xy=\"#nope\" wh=\"5\"/>\n</svg>\n```\n")
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).expect("non-UTF8");
+    assert_contains!(stderr, "doc.md (svgdx block at line 4):2:7");
+    assert_contains!(stderr, "svgdx block at line 4:");
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn test_cmdline_png_output() {
+    let mut tmpfile = NamedTempFile::new().expect("could not create tmpfile");
+    write!(tmpfile, r#"<svg><rect xy="0" wh="10"/></svg>"#).expect("tmpfile write failed");
+    let dir = tempfile::tempdir().expect("could not create tmpdir");
+    let config_file = dir.path().join("svgdx.toml");
+    std::fs::write(&config_file, "[[output]]\nfile = \"out.png\"\n").unwrap();
+
+    let mut cmd = Command::cargo_bin(crate_name!()).unwrap();
+    cmd.args([
+        tmpfile.path().to_str().unwrap(),
+        "--config-file",
+        config_file.to_str().unwrap(),
+    ])
+    .assert()
+    .success();
+    let png = std::fs::read(dir.path().join("out.png")).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+}
@@ -1,4 +1,4 @@
-use assertables::assert_contains;
+use assertables::{assert_contains, assert_not_contains};
 use svgdx::transform_str_default;
 
 #[test]
@@ -49,6 +49,20 @@ fn test_config_scale() {
     assert_contains!(output, expected);
 }
 
+#[test]
+fn test_config_scale_units() {
+    let input = r#"
+<svg>
+<config border="0" scale="2"/>
+<rect xy="0" wh="{{mm(30)}} {{in(1)}}"/>
+<rect xy="0" wh="{{pt(36)}}"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect x="0" y="0" width="15" height="12.7"/>"#);
+    assert_contains!(output, r#"<rect x="0" y="0" width="6.35" height="6.35"/>"#);
+}
+
 #[test]
 fn test_config_background() {
     let input = r#"
@@ -88,3 +102,208 @@ fn test_config_auto_style() {
     let output = transform_str_default(input).unwrap();
     assert_contains!(output, expected);
 }
+
+#[test]
+fn test_config_path_recover() {
+    let input = r#"
+<svg>
+<path d="M 0 0 L 10 oops 20 L 20 10"/>
+</svg>
+"#;
+    assert!(transform_str_default(input).is_err());
+
+    let input = r#"
+<svg>
+<config path-recover="true"/>
+<path d="M 0 0 L 10 oops 20 L 20 10"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    // path data is output unchanged; only the bbox uses the recovered data
+    assert_contains!(output, r#"d="M 0 0 L 10 oops 20 L 20 10""#);
+    assert_not_contains!(output, "data-warning");
+    assert_contains!(output, r#"viewBox="-5 -5 30 20""#);
+}
+
+#[test]
+fn test_config_title_desc() {
+    use assertables::assert_not_contains;
+    use svgdx::{transform_str, TransformConfig};
+
+    let input = r#"
+<svg>
+<config title="Boxes &amp; lines" desc="A diagram"/>
+<rect xy="0" wh="5"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, "<title>Boxes &amp; lines</title>");
+    assert_contains!(output, "<desc>A diagram</desc>");
+    assert_not_contains!(output, "<metadata>");
+
+    let cfg = TransformConfig {
+        add_metadata: true,
+        ..Default::default()
+    };
+    let output = transform_str(input, &cfg).unwrap();
+    assert_contains!(output, r#"xmlns:dc="http://purl.org/dc/elements/1.1/""#);
+    assert_contains!(output, "<dc:title>Boxes &amp; lines</dc:title>");
+    assert_contains!(output, "<dc:description>A diagram</dc:description>");
+
+    // Metadata may also be derived from top-level title / desc elements
+    let input = r#"
+<svg>
+<title>Top-level title</title>
+<g><title>Group title</title></g>
+</svg>
+"#;
+    let output = transform_str(input, &cfg).unwrap();
+    assert_contains!(output, "<dc:title>Top-level title</dc:title>");
+    assert_not_contains!(output, "<dc:description>");
+}
+
+#[test]
+fn test_config_attribution() {
+    use svgdx::{transform_str, TransformConfig};
+
+    let input = r#"
+<svg>
+<config author="A. N. Other" license="CC-BY-4.0" source-url="https://example.com/src"/>
+<rect xy="0" wh="5"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        "<!-- Author: A. N. Other, License: CC-BY-4.0, Source: https://example.com/src -->"
+    );
+
+    let cfg = TransformConfig {
+        add_metadata: true,
+        ..Default::default()
+    };
+    let output = transform_str(input, &cfg).unwrap();
+    assert_contains!(output, "<dc:creator>A. N. Other</dc:creator>");
+    assert_contains!(output, "<dc:rights>CC-BY-4.0</dc:rights>");
+    assert_contains!(output, "<dc:source>https://example.com/src</dc:source>");
+
+    // Comments must not contain '--'
+    let input = r#"<svg><config author="A--B"/></svg>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, "<!-- Author: A- -B -->");
+}
+
+#[test]
+fn test_config_watermark() {
+    let input = r#"
+<svg>
+<config watermark="DRAFT"/>
+<rect xy="0" wh="40 20"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    // behind content, centred on the canvas
+    let wm_idx = output.find(r#"class="d-watermark""#).unwrap();
+    assert!(wm_idx < output.find("<rect").unwrap());
+    assert_contains!(
+        output,
+        r#"<g opacity="0.15" pointer-events="none" transform="rotate(-30 20 10)""#
+    );
+    assert_contains!(output, r#"<text x="20" y="10" style="font-size: 13.333px;"#);
+    assert_contains!(output, ">DRAFT</text></g>");
+
+    // text is escaped exactly once
+    let cfg = svgdx::TransformConfig {
+        watermark: Some("DRAFT <&>".to_string()),
+        ..Default::default()
+    };
+    let output = svgdx::transform_str(r#"<svg><rect wh="10"/></svg>"#, &cfg).unwrap();
+    assert_contains!(output, ">DRAFT &lt;&amp;&gt;</text></g>");
+
+    let input = r#"
+<svg>
+<config watermark-image="logo.png" watermark-opacity="0.5" watermark-angle="0" watermark-above="true"/>
+<rect xy="0" wh="40 20"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert!(output.find("d-watermark").unwrap() > output.find("<rect").unwrap());
+    assert_contains!(
+        output,
+        r#"<g opacity="0.5" pointer-events="none" class="d-watermark">"#
+    );
+    assert_contains!(
+        output,
+        r#"<image href="logo.png" x="-5" y="-5" width="50" height="30" preserveAspectRatio="xMidYMid meet"/>"#
+    );
+}
+
+#[test]
+fn test_config_content_transform() {
+    use svgdx::{transform_str, TransformConfig};
+
+    let input = r#"
+<svg>
+<config content-transform="rotate 90"/>
+<rect xy="0" wh="40 20"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"viewBox="-25 -5 30 50""#);
+    assert_contains!(output, r#"<g transform="rotate(90)">"#);
+    assert_contains!(output, "\n</g></svg>");
+
+    let cfg = TransformConfig {
+        content_transform: Some("translate(10, 5)".to_string()),
+        ..Default::default()
+    };
+    let output = transform_str("<svg><rect wh='10'/></svg>", &cfg).unwrap();
+    assert_contains!(output, r#"viewBox="5 0 20 20""#);
+    assert_contains!(output, r#"<g transform="translate(10, 5)"><rect"#);
+}
+
+#[test]
+fn test_config_set() {
+    use svgdx::TransformConfig;
+
+    let mut cfg = TransformConfig::default();
+    cfg.set("font-size", "4.5").unwrap();
+    cfg.set("connector-jumps", "true").unwrap();
+    cfg.set("title", "Example").unwrap();
+    assert_eq!(cfg.font_size, 4.5);
+    assert!(cfg.connector_jumps);
+    assert_eq!(cfg.title.as_deref(), Some("Example"));
+
+    assert!(cfg.set("font-size", "big").is_err());
+    assert!(cfg.set("no-such-setting", "1").is_err());
+    // limits protecting the host can't be set from documents
+    assert!(cfg.set("element-limit", "10").is_err());
+}
+
+#[test]
+fn test_config_allow_env() {
+    use svgdx::{transform_str, TransformConfig};
+
+    std::env::set_var("SVGDX_TEST_VERSION", "1.2.3");
+    let input = r#"
+<svg>
+<rect wh="10" text="v{{_(env('SVGDX_TEST_VERSION'))}}"/>
+<rect wh="10" text="{{_(env('SVGDX_TEST_UNSET', 'dev'))}}"/>
+</svg>
+"#;
+    // disabled by default, and can't be enabled from documents
+    let output = transform_str_default(input).unwrap();
+    assert_not_contains!(output, "1.2.3");
+    let mut cfg = TransformConfig::default();
+    assert!(cfg.set("allow-env", "true").is_err());
+
+    cfg.allow_env = true;
+    let output = transform_str(input, &cfg).unwrap();
+    assert_contains!(output, ">v1.2.3</text>");
+    assert_contains!(output, ">dev</text>");
+
+    // never available in sanitized mode
+    cfg.sanitize = true;
+    let output = transform_str(input, &cfg).unwrap();
+    assert_not_contains!(output, "1.2.3");
+}
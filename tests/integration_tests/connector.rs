@@ -1,4 +1,4 @@
-use assertables::assert_contains;
+use assertables::{assert_contains, assert_not_contains};
 use svgdx::transform_str_default;
 
 const RECT_SVG: &str = r#"
@@ -272,3 +272,643 @@ fn test_connector_previous() {
     let output = transform_str_default(input).unwrap();
     assert_contains!(output, expected);
 }
+
+#[test]
+fn test_connector_edgespec_expr() {
+    let input = r##"
+<rect id="a" wh="30 10"/>
+<rect id="b" xy="0 30" wh="30 10"/>
+<line start="#a@b:{{100/3}}%" end="#b@t:{{200/3}}%"/>
+<line start="#a@b:(25% + 2)" end="#b@t:(100% - 5)"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<line x1="10" y1="10" x2="20" y2="30"/>"#);
+    assert_contains!(output, r#"<line x1="9.5" y1="10" x2="25" y2="30"/>"#);
+
+    let input = r##"
+<rect id="a" wh="30 10"/>
+<rect xy="#a@r:(50% - 2) 1" wh="2"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect x="31" y="4" width="2" height="2"/>"#);
+}
+
+#[test]
+fn test_connector_mid_corner_ref() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="40 30" wh="10"/>
+<polyline id="c" start="#a@r" end="#b@t"/>
+<circle cxy="#c@m" r="1"/>
+<line start="#c@c1" end="#b@l"/>
+<line id="d" start="#a@b" end="#b@l"/>
+<text xy="#d@m">label</text>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<polyline id="c" points="10 5, 45 5, 45 30"/>"#);
+    assert_contains!(output, r#"<circle cx="40" cy="5" r="1"/>"#);
+    assert_contains!(output, r#"<line x1="45" y1="5" x2="40" y2="35"/>"#);
+    assert_contains!(
+        output,
+        r#"<text x="22.5" y="22.5" class="d-text">label</text>"#
+    );
+
+    // straight lines have no corners
+    let input = r##"
+<rect id="a" wh="10"/>
+<line id="c" start="#a@r" end="#a@b"/>
+<line start="#c@c1" end="#a@t"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+
+    // missing corners are errors rather than falling back to the bbox
+    let input = r##"
+<line id="l" xy1="0 20" xy2="10 20"/>
+<circle cxy="#l@c1" r="1"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"
+<polyline id="p" points="0 0, 10 0, 10 10, 20 10"/>
+<circle cxy="#p@c3" r="1"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"
+<polyline id="p" points="0 0, 10 0, 10 10, 20 10"/>
+<rect xy="#p@c2" wh="1"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect x="10" y="10" width="1" height="1"/>"#);
+}
+
+#[test]
+fn test_connect_table() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="^|h 10" wh="10"/>
+<rect id="c" xy="^|v 10" wh="10"/>
+<connect class="d-arrow">
+from, to, label, class
+a, b, uses,
+b, #c@b, , d-dash
+</connect>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<line x1="10" y1="5" x2="20" y2="5" class="d-arrow"/>
+<text x="15" y="5" class="d-text d-text-knockout d-arrow">uses</text>
+<line x1="25" y1="10" x2="25" y2="30" class="d-arrow d-dash"/>"#
+    );
+
+    // custom column names, and connectors before the referenced elements
+    let input = r##"
+<connect from-col="src" to-col="dst" connector="polyline">
+dst, src
+b, a
+</connect>
+<rect id="a" wh="10"/>
+<rect id="b" xy="^|v 10" wh="10"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<polyline points="5 10, 5 15, 5 15, 5 20"/>"#);
+
+    // missing columns or values are errors
+    let input = r##"<rect id="a" wh="1"/><connect>start, end
+a, a</connect>"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"<rect id="a" wh="1"/><connect>from, to
+a, </connect>"##;
+    assert!(transform_str_default(input).is_err());
+
+    // errors in generated connectors refer to the table
+    let input = r##"<svg>
+  <rect id="a" wh="10"/>
+  <connect>
+  from, to
+  a, nope
+  </connect>
+</svg>"##;
+    let err = transform_str_default(input).unwrap_err();
+    assert_contains!(err.render_with_source(input), "--> line 3, column 3");
+}
+
+#[test]
+fn test_connect_class_map() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="^|h 10" wh="10"/>
+<rect id="c" xy="^|v 10" wh="10"/>
+<connect class-from="status" class-map="failed: d-red; optional: d-dash d-thin">
+from, to, status, class
+a, b, failed,
+b, c, optional, d-arrow
+a, c, ok,
+</connect>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<line x1="10" y1="5" x2="20" y2="5" class="d-red"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<line x1="25" y1="10" x2="25" y2="20" class="d-arrow d-dash d-thin"/>"#
+    );
+    assert_contains!(output, r#"<line x1="10" y1="10" x2="20" y2="20"/>"#);
+    assert_not_contains!(output, "class-from");
+    assert_not_contains!(output, "class-map");
+
+    // unknown class-from column or malformed mapping are errors
+    let input = r##"<rect id="a" wh="1"/><rect id="b" wh="1"/>
+<connect class-from="status" class-map="x: y">from, to
+a, b</connect>"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"<rect id="a" wh="1"/><rect id="b" wh="1"/>
+<connect class-from="to" class-map="b">from, to
+a, b</connect>"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_bus() {
+    let input = r##"
+<rect id="a" xy="0" wh="20 10"/>
+<rect id="b" xy="^|h 10" wh="20 10"/>
+<rect id="c" xy="0 50" wh="20 10"/>
+<bus id="data" xy1="0 30" xy2="60 30" taps="#a #b #c" signals="8" class="d-red"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<line id="data" x1="0" y1="30" x2="60" y2="30" class="d-red d-thicker"/>"#
+    );
+    // stubs meet the bus at evenly spaced points, from the side facing the bus
+    assert_contains!(output, r#"<polyline points="10 10, 10 20, 10 20, 10 30"/>"#);
+    assert_contains!(output, r#"<polyline points="40 10, 40 20, 30 20, 30 30"/>"#);
+    assert_contains!(output, r#"<polyline points="10 50, 10 40, 50 40, 50 30"/>"#);
+    // signal count annotation
+    assert_contains!(output, r#"<line x1="2.5" y1="31.5" x2="5.5" y2="28.5"/>"#);
+    assert_contains!(output, ">8</text>");
+    assert_not_contains!(output, "taps=");
+    assert_not_contains!(output, "signals=");
+}
+
+#[test]
+fn test_bus_vertical() {
+    let input = r##"
+<bus xy1="30 0" xy2="30 20" taps="#a #b"/>
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="40 0" wh="10"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<line x1="30" y1="0" x2="30" y2="20" class="d-thicker"/>"#
+    );
+    // tapped elements may be defined after the bus
+    assert_contains!(output, r#"<polyline points="10 5, 20 5, 20 5, 30 5"/>"#);
+    assert_contains!(output, r#"<polyline points="40 5, 35 5, 35 15, 30 15"/>"#);
+}
+
+#[test]
+fn test_connector_label() {
+    let input = r##"<svg>
+<rect id="a" wh="10"/>
+<rect id="b" xy="^|h 20" wh="10"/>
+<polyline start="#a@b" end="#b@b" text="mid"/>
+<line start="#a" end="#b" text="start" text-loc="start"/>
+<line start="#a" end="#b" text="end" text-loc="end" text-offset="2" offset="0"/>
+<line start="#b" end="#a" text="rev" text-offset="2" offset="0"/>
+</svg>"##;
+    let output = transform_str_default(input).unwrap();
+    // label is at the midpoint along the connector, not its bbox centre
+    assert_contains!(
+        output,
+        r#"<text x="20" y="13" class="d-text d-text-knockout">mid</text>"#
+    );
+    assert_contains!(
+        output,
+        r#"<text x="15" y="5" class="d-text d-text-knockout">start</text>"#
+    );
+    // offsets are to the left of the direction of travel
+    assert_contains!(
+        output,
+        r#"<text x="25" y="3" class="d-text d-text-knockout">end</text>"#
+    );
+    assert_contains!(
+        output,
+        r#"<text x="20" y="7" class="d-text d-text-knockout">rev</text>"#
+    );
+    assert_contains!(output, "text.d-text-knockout, text.d-text-knockout *");
+
+    // other lines keep their existing text placement
+    let output = transform_str_default(r#"<line xy1="0" xy2="10 0" text="x"/>"#).unwrap();
+    assert_contains!(output, r#"<text x="5" y="0" class="d-text">x</text>"#);
+    // start / mid / end apply only to line-like elements
+    assert!(transform_str_default(r#"<rect wh="10" text="x" text-loc="mid"/>"#).is_err());
+}
+
+#[test]
+fn test_connector_route() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="block" xy="20 -5" wh="10 20"/>
+<rect id="b" xy="40 0" wh="10"/>
+<polyline id="c1" start="#a" end="#b" edge-type="route"/>
+<polyline id="c2" start="#a" end="#b" edge-type="route" corner-offset="1" offset="0"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline id="c1" points="10 5, 17 5, 17 18, 37 18, 37 5, 40 5"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline id="c2" points="10 5, 19 5, 19 16, 39 16, 39 5, 40 5"/>"#
+    );
+
+    // unobstructed routes are straight where possible
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="40 0" wh="10"/>
+<polyline id="c1" start="#a" end="#b" edge-type="route"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<line id="c1" x1="10" y1="5" x2="40" y2="5"/>"#);
+
+    // falls back to an elbow connector if the end is enclosed
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="40 0" wh="10"/>
+<rect id="w1" xy="35 -5" wh="2 20"/>
+<rect id="w2" xy="53 -5" wh="2 20"/>
+<rect id="w3" xy="35 -5" wh="20 2"/>
+<rect id="w4" xy="35 13" wh="20 2"/>
+<polyline id="c1" start="#a" end="#b" edge-type="route"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline id="c1" points="10 5, 25 5, 25 5, 40 5"/>"#
+    );
+}
+
+#[test]
+fn test_connector_via() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="hub" xy="30 30" wh="10"/>
+<rect id="b" xy="60 0" wh="10"/>
+<polyline id="c1" start="#a" end="#b" via="#hub@b"/>
+<line id="c2" start="#a" end="#b" via="20 40 #hub" offset="0"/>
+<polyline id="c3" start="#a@r" end="#b@l" via="35 -10 45 20"/>
+<polyline id="c4" start="#a" end="#b" via="#hub@b" edge-type="route" offset="0"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline id="c1" points="5 10, 5 40, 65 40, 65 10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline id="c2" points="10 10, 20 40, 35 35, 60 10"/>"#
+    );
+    // each waypoint is a corner
+    assert_contains!(
+        output,
+        r#"<polyline id="c3" points="10 5, 35 5, 35 -10, 45 -10, 45 20, 45 5, 60 5"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline id="c4" points="5 10, 5 40, 65 40, 65 10"/>"#
+    );
+
+    let input = r##"<rect id="a" wh="5"/><line start="#a" end="10 0" via="1 2 3"/>"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_connector_parallel() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="40 20" wh="10"/>
+<polyline id="p1" start="#a" end="#b"/>
+<polyline id="p2" start="#a" end="#b"/>
+<polyline id="p3" start="#b" end="#a"/>
+<polyline id="p4" start="#a" end="#b" offset="-1"/>
+<line id="l1" start="#a@r" end="#b@l"/>
+<line id="l2" start="#a@b" end="#b@l"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline id="p1" points="10 5, 25 5, 25 25, 40 25"/>"#
+    );
+    // offset to the left of the direction of travel
+    assert_contains!(
+        output,
+        r#"<polyline id="p2" points="10 3, 27 3, 27 23, 40 23"/>"#
+    );
+    // reversed connectors are fanned out on the other side
+    assert_contains!(
+        output,
+        r#"<polyline id="p3" points="40 27, 23 27, 23 7, 10 7"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline id="p4" points="10 6, 24 6, 24 26, 40 26"/>"#
+    );
+    // connectors of different types or locations are not offset
+    assert_contains!(output, r#"<line id="l1" x1="10" y1="5" x2="40" y2="25"/>"#);
+    assert_contains!(output, r#"<line id="l2" x1="5" y1="10" x2="40" y2="25"/>"#);
+}
+
+#[test]
+fn test_connector_chain() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="40 30" wh="10"/>
+<line id="c1" start="#a@r" end="20 5"/>
+<polyline id="c2" start="^@end" end="#b@t" class="d-dash"/>
+<line id="c3" start="#a@b" end="#c2@start"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<line id="c1" x1="10" y1="5" x2="20" y2="5"/>"#);
+    // continues in the direction of the previous connector
+    assert_contains!(
+        output,
+        r#"<polyline id="c2" points="20 5, 45 5, 45 30" class="d-dash"/>"#
+    );
+    assert_contains!(output, r#"<line id="c3" x1="5" y1="10" x2="20" y2="5"/>"#);
+}
+
+#[test]
+fn test_connector_curve() {
+    let input = r##"
+<rect id="a" xy="0" wh="10"/>
+<rect id="b" xy="30 20" wh="10"/>
+<line id="c1" start="#a" end="#b" edge-type="curve"/>
+<polyline id="c2" start="#a@b" end="#b@l" edge-type="curve" corner-offset="5"/>
+<line id="c3" start="#a@r" end="40 0" edge-type="curve" corner-offset="4"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<path id="c1" d="M 10 5 C 24.142 5, 15.858 25, 30 25"/>"#
+    );
+    assert_contains!(output, r#"<path id="c2" d="M 5 10 C 5 15, 25 25, 30 25"/>"#);
+    // no direction at a point end
+    assert_contains!(output, r#"<path id="c3" d="M 10 5 C 14 5, 40 0, 40 0"/>"#);
+}
+
+#[test]
+fn test_connector_outline() {
+    let input = r##"
+<circle id="a" cxy="0" r="5"/>
+<circle id="b" cxy="30 30" r="5"/>
+<ellipse id="e" cxy="40 0" rxy="8 4"/>
+<polygon id="p" points="0 40, 10 50, 0 60"/>
+<line id="c1" start="#a" end="#b"/>
+<line id="c2" start="#e" end="#b"/>
+<line id="c3" start="#p" end="#b"/>
+<line id="c4" start="#a@tl" end="0 -20"/>
+<polyline id="c5" start="#a" end="#b"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    // along the line between centres
+    assert_contains!(
+        output,
+        r#"<line id="c1" x1="3.536" y1="3.536" x2="26.464" y2="26.464"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<line id="c2" x1="38.685" y1="3.946" x2="31.581" y2="25.257"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<line id="c3" x1="7.778" y1="47.778" x2="26.096" y2="33.123"/>"#
+    );
+    // explicit locations are moved towards the centre
+    assert_contains!(
+        output,
+        r#"<line id="c4" x1="-3.536" y1="-3.536" x2="0" y2="-20"/>"#
+    );
+    assert_contains!(output, r#"<polyline id="c5" points="5 0, 30 0, 30 25"/>"#);
+}
+
+#[test]
+fn test_connector_outline_default_centre() {
+    // `cx` / `cy` default to zero
+    let input = r##"
+<circle id="a" r="5"/>
+<rect id="b" xy="25 -5" wh="10"/>
+<line start="#a" end="#b"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<line x1="5" y1="0" x2="25" y2="0"/>"#);
+}
+
+#[test]
+fn test_connector_jumps() {
+    let input = r##"
+<config connector-jumps="true"/>
+<rect id="a" xy="0 10" wh="10"/>
+<rect id="b" xy="50 10" wh="10"/>
+<rect id="c" xy="25 -20" wh="10"/>
+<rect id="d" xy="25 40" wh="10"/>
+<line id="c1" start="#a" end="#b"/>
+<line id="c2" start="#c" end="#d" class="d-arrow"/>
+<polyline id="c3" start="#c@r" end="#b@t"/>
+<g transform="translate(0, 10)">
+<line id="c4" start="0 0" end="60 0"/>
+</g>
+"##;
+    let output = transform_str_default(input).unwrap();
+    // earlier connectors are unchanged
+    assert_contains!(output, r#"<line id="c1" x1="10" y1="15" x2="50" y2="15"/>"#);
+    assert_contains!(
+        output,
+        r#"<path id="c2" d="M 30 -10 L 30 13.5 A 1.5 1.5 0 0 0 30 16.5 L 30 40" class="d-arrow"/>"#
+    );
+    // no crossings
+    assert_contains!(
+        output,
+        r#"<polyline id="c3" points="35 -15, 55 -15, 55 10"/>"#
+    );
+    // crossings are found allowing for group translation
+    assert_contains!(
+        output,
+        r#"<path id="c4" d="M 0 0 L 28.5 0 A 1.5 1.5 0 0 1 31.5 0 L 60 0"/>"#
+    );
+
+    // disabled by default
+    let output = transform_str_default(input.replace("true", "false")).unwrap();
+    assert_contains!(output, r#"<line id="c2" x1="30" y1="-10" x2="30" y2="40""#);
+}
+
+#[test]
+fn test_connect_tree() {
+    let input = r##"
+<rect id="p" xy="30 0" wh="20 10"/>
+<rect id="a" xy="0 30" wh="16 10"/>
+<rect id="b" xy="32 30" wh="16 10"/>
+<rect id="c" xy="64 40" wh="16 10"/>
+<connect start="#p" end="#a #b #c" class="d-arrow"/>
+<connect start="#p@r" end="#c" corner-offset="4"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    // common trunk and bus, half way to the nearest child
+    assert_contains!(
+        output,
+        r#"<polyline points="40 10, 40 20, 8 20, 8 30" class="d-arrow"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline points="40 10, 40 20, 40 20, 40 30" class="d-arrow"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline points="40 10, 40 20, 72 20, 72 40" class="d-arrow"/>"#
+    );
+    assert_contains!(output, r#"<polyline points="50 5, 54 5, 54 45, 64 45"/>"#);
+
+    let input = r##"
+<rect id="p" wh="10"/>
+<connect start="#p@tl" end="#p"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_connector_ports() {
+    let input = r##"
+<specs>
+<rect id="chip" wh="20 10" ports="in:l:25% out:r:75%"/>
+</specs>
+<reuse id="cpu" href="#chip" x="0" y="0"/>
+<reuse id="mem" href="#chip" x="40" y="0"/>
+<rect id="io" xy="0 30" wh="20 10" ports="bus:t:80%"/>
+<g id="grp" ports="p:b"><rect xy="60 30" wh="5"/></g>
+<polyline id="c1" start="#cpu@out" end="#mem@in"/>
+<line id="c2" start="#io@bus" end="#cpu@b"/>
+<line id="c3" start="#grp@p" end="#io@r"/>
+<circle id="c" cxy="#mem@out" r="1"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline id="c1" points="20 7.5, 30 7.5, 30 2.5, 40 2.5"/>"#
+    );
+    assert_contains!(output, r#"<line id="c2" x1="16" y1="30" x2="10" y2="10"/>"#);
+    assert_contains!(
+        output,
+        r#"<line id="c3" x1="62.5" y1="35" x2="20" y2="35"/>"#
+    );
+    assert_contains!(output, r#"<circle id="c" cx="60" cy="7.5" r="1"/>"#);
+    // ports are not included in the output
+    assert_not_contains!(output, "ports=");
+
+    let input = r##"<rect id="a" wh="10" ports="in:l"/><line start="#a@out" end="20 20"/>"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_connector_margin() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="30 0" wh="10"/>
+<rect id="c" xy="30 30" wh="10"/>
+<line id="l1" start="#a" end="#b" margin-start="2" margin-end="3"/>
+<polyline id="p1" start="#a@b" end="#c@l" margin-start="5" margin-end="1"/>
+<polyline id="p2" start="#a@b" end="#c@l" margin-start="30" offset="0"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<line id="l1" x1="12" y1="5" x2="27" y2="5"/>"#);
+    assert_contains!(output, r#"<polyline id="p1" points="5 15, 5 35, 29 35"/>"#);
+    // margin consumes the first segment entirely
+    assert_contains!(output, r#"<polyline id="p2" points="10 35, 30 35"/>"#);
+
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="30 0" wh="10"/>
+<line start="#a" end="#b" margin-start="10" margin-end="10"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="30 0" wh="10"/>
+<line start="#a" end="#b" margin-end="-1"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_connector_dir() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="20 20" wh="10"/>
+<line id="l1" start="#a" end="#b@l" dir="forward"/>
+<polyline id="p1" start="#a@r" end="#b@t" dir="back" class="d-arrow-start d-red"/>
+<polyline id="p2" start="#b@t" end="#a@r" class="d-arrow" offset="0"/>
+<line id="l2" start="#a@b" end="#b@l" dir="both" margin-start="1" offset="0"/>
+<line id="l3" start="#a@b" end="#b@l" dir="none" class="d-arrow" offset="0"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<line id="l1" x1="10" y1="10" x2="20" y2="25" class="d-arrow"/>"#
+    );
+    // reversed connector matches an explicitly reversed one, and keeps other classes
+    assert_contains!(
+        output,
+        r#"<polyline id="p1" points="25 20, 25 5, 10 5" class="d-red d-arrow"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline id="p2" points="25 20, 25 5, 10 5" class="d-arrow"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<line id="l2" x1="5.707" y1="10.707" x2="20" y2="25" class="d-arrow-both"/>"#
+    );
+    assert_contains!(output, r#"<line id="l3" x1="5" y1="10" x2="20" y2="25"/>"#);
+
+    let input = r##"<rect id="a" wh="10"/><line start="#a" end="20 20" dir="up"/>"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_connector_corner_radius() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<rect id="b" xy="20 20" wh="10"/>
+<polyline id="p1" start="#a@r" end="#b@t" corner-radius="4"/>
+<polyline id="p2" start="#a@r" end="#b@t" corner-radius="50%" offset="0"/>
+<polyline id="p3" start="#a@r" end="#b@l" corner-radius="1 10"/>
+<polyline id="p4" start="#a@r" end="#b@l" corner-radius="0 1" offset="0"/>
+<line id="l1" start="#a" end="#b" corner-radius="2"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<path id="p1" d="M 10 5 L 21 5 A 4 4 0 0 1 25 9 L 25 20"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<path id="p2" d="M 10 5 L 17.5 5 A 7.5 7.5 0 0 1 25 12.5 L 25 20"/>"#
+    );
+    // the second radius is limited by the length of the final segment
+    assert_contains!(
+        output,
+        r#"<path id="p3" d="M 10 5 L 14 5 A 1 1 0 0 1 15 6 L 15 22.5 A 2.5 2.5 0 0 0 17.5 25 L 20 25"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<path id="p4" d="M 10 5 L 15 5 L 15 24 A 1 1 0 0 0 16 25 L 20 25"/>"#
+    );
+    // straight connectors have no corners to round
+    assert_contains!(output, r#"<line id="l1""#);
+
+    let input = r##"<rect id="a" wh="10"/><polyline start="#a" end="20 20" corner-radius="x"/>"##;
+    assert!(transform_str_default(input).is_err());
+}
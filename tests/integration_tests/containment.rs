@@ -293,3 +293,43 @@ fn test_inside_surround_invalid() {
     let output = transform_str_default(input);
     assert!(output.is_err());
 }
+
+#[test]
+fn test_inside_pack() {
+    let input = r##"
+<box id="area" wh="30 40"/>
+<rect inside="#area" pack="flow 2" wh="10"/>
+<rect inside="#area" pack="flow 2" wh="10 5"/>
+<circle inside="#area" pack="flow 2" r="5"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect x="0" y="0" width="10" height="10" class="d-inside"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect x="12" y="0" width="10" height="5" class="d-inside"/>"#
+    );
+    assert_contains!(output, r#"<circle cx="5" cy="17" r="5" class="d-inside"/>"#);
+
+    let input = r##"
+<rect id="area" wh="32 20"/>
+<loop count="3">
+<rect inside="#area" pack="grid 2 2" margin="1" wh="5"/>
+</loop>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect x="5.5" y="1" width="5" height="5" class="d-inside"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect x="21.5" y="1" width="5" height="5" class="d-inside"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect x="5.5" y="8" width="5" height="5" class="d-inside"/>"#
+    );
+}
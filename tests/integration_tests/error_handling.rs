@@ -74,3 +74,37 @@ fn test_error_bad_attr_value() {
     </svg>"##;
     assert!(transform_str_default(input).is_err());
 }
+
+#[test]
+fn test_error_render_snippet() {
+    let input = r##"<svg>
+  <rect id="a" wh="10"/>
+  <rect xy="#b|h" wh="5"/>
+  <circle cx="#a@r"
+    cy="#nope@t" r="2"/>
+</svg>"##;
+
+    let err = transform_str_default(input).unwrap_err();
+    let rendered = err.render_with_source(input);
+    let expected = r##"error[E0101]: Reference error: #b (attribute 'xy')
+  --> line 3, column 9
+  |
+3 |   <rect xy="#b|h" wh="5"/>
+  |         ^^
+error[E0101]: Reference error: #nope (attribute 'cy')
+  --> line 5, column 5
+  |
+5 |     cy="#nope@t" r="2"/>
+  |     ^^
+"##;
+    assert_eq!(rendered, expected);
+}
+
+#[test]
+fn test_error_codes() {
+    let input = r##"<svg><config loop-limit="5"/><loop count="10"><rect wh="1"/></loop></svg>"##;
+    let err = transform_str_default(input).unwrap_err();
+    assert_eq!(err.code(), None); // MultiError has no code itself
+    let rendered = err.render_with_source(input);
+    assert!(rendered.starts_with("error[E0204]: Loop count"));
+}
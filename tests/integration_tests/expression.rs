@@ -1,3 +1,4 @@
+use assertables::assert_contains;
 use svgdx::transform_str_default;
 
 #[test]
@@ -46,3 +47,166 @@ fn test_scalarspec() {
         expected.trim()
     );
 }
+
+#[test]
+fn test_random_element_streams() {
+    let input = r#"
+<svg>
+<config seed="42"/>
+<rect id="a" wh="{{random()}}"/>
+<rect id="b" wh="{{random()}}"/>
+</svg>
+"#;
+    let with_extra = r#"
+<svg>
+<config seed="42"/>
+<rect id="a" wh="{{random()}} {{random()}}"/>
+<rect id="b" wh="{{random()}}"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    let output_extra = transform_str_default(with_extra).unwrap();
+    let b_line = |out: &str| {
+        out.lines()
+            .find(|l| l.contains(r#"id="b""#))
+            .unwrap()
+            .to_owned()
+    };
+    // additional random() calls in 'a' don't affect values in 'b'
+    assert_eq!(b_line(&output), b_line(&output_extra));
+    // but different elements get different values
+    assert_ne!(
+        b_line(&output).replace(r#"id="b""#, ""),
+        output
+            .lines()
+            .find(|l| l.contains(r#"id="a""#))
+            .unwrap()
+            .replace(r#"id="a""#, "")
+    );
+
+    // inserting elements, whitespace or comments upstream doesn't affect
+    // values for elements with an id
+    let with_inserted = r#"
+<svg>
+<config seed="42"/>
+<!-- a comment -->
+<rect id="a" wh="{{random()}}"/>
+<rect id="new" wh="{{random()}}"/>
+
+<rect id="b" wh="{{random()}}"/>
+</svg>
+"#;
+    let output_inserted = transform_str_default(with_inserted).unwrap();
+    assert_eq!(b_line(&output), b_line(&output_inserted));
+
+    // nor does reformatting affect elements without an id
+    let input = r#"<svg><loop count="2"><rect wh="{{random()}}"/></loop></svg>"#;
+    let reformatted = r#"<svg>
+  <loop count="2">
+    <!-- random sizes -->
+    <rect wh="{{random()}}"/>
+  </loop>
+</svg>"#;
+    let rects = |out: &str| -> Vec<String> {
+        out.split("<rect ")
+            .skip(1)
+            .map(|r| r.split("/>").next().unwrap().to_owned())
+            .collect()
+    };
+    let output = transform_str_default(input).unwrap();
+    assert_eq!(
+        rects(&output),
+        rects(&transform_str_default(reformatted).unwrap())
+    );
+
+    // deferred processing passes don't change values
+    let deferred =
+        r##"<svg><rect wh="{{random()}}" xy="#later|h"/><rect id="later" wh="1"/></svg>"##;
+    let direct = r#"<svg><rect wh="{{random()}}" xy="0"/><rect id="later" wh="1"/></svg>"#;
+    let width = |out: &str| rects(out)[0].split("width=").nth(1).unwrap().to_owned();
+    assert_eq!(
+        width(&transform_str_default(deferred).unwrap()),
+        width(&transform_str_default(direct).unwrap())
+    );
+
+    // repeated elements get a new stream each time
+    let input = r#"
+<svg>
+<loop count="2"><rect wh="{{random()}}"/></loop>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    let rects: Vec<_> = output.split("<rect ").skip(1).collect();
+    assert_eq!(rects.len(), 2);
+    assert_ne!(rects[0], rects[1]);
+}
+
+#[test]
+fn test_expr_count_of() {
+    let input = r#"
+<rect class="d-node" wh="5"/>
+<circle class="d-node" r="2"/>
+<rect wh="5"/>
+<text id="z" text="{{count_of('.d-node')}} {{count_of('rect')}} {{count_of('rect.d-node, #z')}}"/>
+<rect class="d-node" wh="5"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert!(output.contains(">2 2 1<"), "{output}");
+}
+
+#[test]
+fn test_expr_prev_elements() {
+    let input = r#"
+<rect xy="0" wh="10"/>
+<rect xy="20 0" wh="10"/>
+<rect id="z" xy="{{^2~x2 + 5}} {{prev('y2')}}" wh="{{elref('^2', 'w')}} {{prev('h', 2)}}"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert!(
+        output.contains(r#"<rect id="z" x="15" y="10" width="10" height="10"/>"#),
+        "{output}"
+    );
+
+    let input = r#"
+<rect xy="0" wh="10"/>
+<rect xy="20 0" wh="10"/>
+<rect xy="^2|v" wh="5"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert!(
+        output.contains(r#"<rect x="2.5" y="10" width="5" height="5"/>"#),
+        "{output}"
+    );
+}
+
+#[test]
+fn test_expr_path_length() {
+    let input = r#"
+<path id="p" d="M 0 0 h 30 v 40"/>
+<polyline id="pl" points="0 0 3 4 3 14"/>
+<path id="arc" d="M 0 0 A 10 10 0 0 1 20 0"/>
+<rect id="a" wh="{{pathlen(#p)}} {{pathlen('#pl')}}"/>
+<rect id="b" wh="{{floor(pathlen(#arc) * 10)}}"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect id="a" width="70" height="15"/>"#);
+    // half circle of radius 10: 10 * pi
+    assert_contains!(output, r#"<rect id="b" width="314" height="314"/>"#);
+}
+
+#[test]
+fn test_expr_point_at() {
+    let input = r#"
+<path id="p" d="M 0 0 h 30 v 40"/>
+<path id="q" d="M 0 0 Q 10 10 20 0 T 40 0"/>
+<loop count="3" loop-var="i">
+<circle id="c$i" cxy="{{pointat(#p, $i / 2)}}" r="1"/>
+</loop>
+<circle id="d" cxy="{{pointat(#q, 0.5)}}" r="1"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<circle id="c0" cx="0" cy="0" r="1"/>"#);
+    assert_contains!(output, r#"<circle id="c1" cx="30" cy="5" r="1"/>"#);
+    assert_contains!(output, r#"<circle id="c2" cx="30" cy="40" r="1"/>"#);
+    assert_contains!(output, r#"<circle id="d" cx="20" cy="0" r="1"/>"#);
+}
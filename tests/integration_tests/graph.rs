@@ -0,0 +1,215 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+const GRAPH_SVG: &str = r##"
+<graph id="g1" layout="force" spacing="30">
+  <rect id="a" wh="10"/>
+  <rect id="b" wh="10"/>
+  <rect id="c" wh="10"/>
+  <rect id="fixed" xy="100" wh="10"/>
+  <line start="#a" end="#b"/>
+  <connect>
+  from, to
+  b, c
+  c, fixed
+  </connect>
+</graph>
+"##;
+
+#[test]
+fn test_graph_force() {
+    let output = transform_str_default(GRAPH_SVG).unwrap();
+    assert_contains!(output, r#"<g id="g1">"#);
+    assert_not_contains!(output, "layout=");
+    assert_not_contains!(output, "spacing=");
+    // explicitly positioned elements are not moved
+    assert_contains!(output, r#"<rect id="fixed" x="100" y="100""#);
+    // connectors from both lines and connect tables are generated
+    assert_eq!(output.matches("<line ").count(), 3);
+
+    // each auto-positioned node is given a distinct position
+    let positions: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .map(|id| {
+            let start = output.find(&format!(r#"<rect id="{id}" x="#)).unwrap();
+            output[start..].split('/').next().unwrap().replace(id, "")
+        })
+        .collect();
+    assert_ne!(positions[0], positions[1]);
+    assert_ne!(positions[1], positions[2]);
+    assert_ne!(positions[0], positions[2]);
+}
+
+#[test]
+fn test_graph_deterministic() {
+    let output = transform_str_default(GRAPH_SVG).unwrap();
+    assert_eq!(output, transform_str_default(GRAPH_SVG).unwrap());
+
+    let seeded = format!(r#"<config seed="42"/>{GRAPH_SVG}"#);
+    let output2 = transform_str_default(&seeded).unwrap();
+    assert_eq!(output2, transform_str_default(&seeded).unwrap());
+    assert_ne!(output, output2);
+}
+
+#[test]
+fn test_graph_bad_layout() {
+    assert!(transform_str_default(r#"<graph><rect id="a" wh="5"/></graph>"#).is_err());
+    assert!(
+        transform_str_default(r#"<graph layout="unknown"><rect id="a" wh="5"/></graph>"#).is_err()
+    );
+}
+
+#[test]
+fn test_graph_force_limit() {
+    // excessive layout work is rejected rather than running indefinitely
+    let input = r#"
+<graph layout="force" iterations="100000000"><rect id="a" wh="5"/><rect id="b" wh="5"/></graph>
+"#;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_graph_layered() {
+    let input = r##"
+<graph layout="layered" direction="LR" spacing="30">
+  <node id="a"/>
+  <node id="b" wh="10"/>
+  <node id="c"/>
+  <edge from="a" to="b" class="d-arrow"/>
+  <edge from="#a" to="c" connector="polyline"/>
+</graph>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g>"#);
+    assert_not_contains!(output, "<node");
+    assert_not_contains!(output, "<edge");
+    assert_not_contains!(output, "direction=");
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="-10" y="10" width="20" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="b" x="25" y="-5" width="10" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="c" x="20" y="25" width="20" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<line x1="10" y1="10" x2="25" y2="5" class="d-arrow"/>"#
+    );
+    assert_contains!(output, "<polyline points=");
+}
+
+#[test]
+fn test_graph_bad_direction() {
+    let input = r#"<graph layout="layered" direction="up"><node id="a"/></graph>"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r#"<graph layout="layered"><edge from="a"/></graph>"#;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_graph_circular() {
+    let input = r##"
+<graph layout="circular" spacing="10">
+  <node id="a" wh="4"/>
+  <node id="b" wh="4"/>
+  <node id="c" wh="4"/>
+  <node id="d" wh="4"/>
+  <edge from="a" to="c"/>
+</graph>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="5" y="-2" width="4" height="4"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="b" x="12" y="5" width="4" height="4"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="c" x="5" y="12" width="4" height="4"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="d" x="-2" y="5" width="4" height="4"/>"#
+    );
+    assert_contains!(output, r#"<line x1="7" y1="2" x2="7" y2="12"/>"#);
+}
+
+#[test]
+fn test_graph_circular_bundle() {
+    let input = r##"
+<graph layout="circular" spacing="10" bundle="1">
+  <node id="a" wh="4"/>
+  <node id="b" wh="4"/>
+  <node id="c" wh="4"/>
+  <node id="d" wh="4"/>
+  <edge from="a" to="b" class="d-arrow"/>
+  <connect>
+  from, to
+  b, d
+  </connect>
+</graph>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_not_contains!(output, "bundle=");
+    assert_contains!(output, r#"<path d="M 7 2 Q 7 7 12 7" class="d-arrow"/>"#);
+    assert_contains!(output, r#"<path d="M 12 7 Q 7 7 2 7"/>"#);
+}
+
+#[test]
+fn test_statemachine() {
+    let input = r##"
+<statemachine id="door" spacing="25">
+[*] -> closed
+closed -> open : open
+open -> closed : close
+closed -> closed : knock
+open -> [*]
+</statemachine>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="door">"#);
+    assert_not_contains!(output, "<statemachine");
+    assert_not_contains!(output, "layout=");
+    // start and end markers
+    assert_contains!(
+        output,
+        r#"<circle id="door-start" cx="0" cy="0" r="2" class="d-fill-black"/>"#
+    );
+    assert_contains!(output, r#"<circle id="door-end" cx="0" cy="75" r="3"/>"#);
+    // states in ranks
+    assert_contains!(
+        output,
+        r#"<rect id="closed" x="-10" y="20" width="20" height="10" rx="2"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="open" x="-10" y="45" width="20" height="10" rx="2"/>"#
+    );
+    // labelled transitions, including a self-transition
+    assert_contains!(
+        output,
+        r#"<line x1="0" y1="30" x2="0" y2="45" class="d-arrow"/>"#
+    );
+    assert_contains!(output, ">open</text>");
+    assert_contains!(
+        output,
+        r#"<polyline points="10 23, 13 23, 13 27, 10 27" class="d-arrow"/>"#
+    );
+    assert_contains!(output, ">knock</text>");
+}
+
+#[test]
+fn test_statemachine_bad() {
+    let input = "<statemachine>a - b</statemachine>";
+    assert!(transform_str_default(input).is_err());
+    let input = "<statemachine>[*] -> [*]</statemachine>";
+    assert!(transform_str_default(input).is_err());
+}
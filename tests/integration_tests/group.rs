@@ -161,3 +161,87 @@ fn test_group_transform_prev() {
     assert_contains!(output, expected1);
     assert_contains!(output, expected2);
 }
+
+#[test]
+fn test_group_nested_transform_bbox() {
+    let input = r##"
+<svg>
+  <config border="0"/>
+  <var d="5"/>
+  <g id="outer" transform="translate(10)">
+    <g transform="translate({{$d}}) scale(2)">
+      <rect wh="10"/>
+    </g>
+  </g>
+  <rect id="z" xy="#outer|h" wh="1"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"viewBox="15 0 21 20""#);
+    assert_contains!(output, r#"id="z" x="35" y="9.5""#);
+
+    let input = r##"
+<svg>
+  <config border="0"/>
+  <g id="outer" transform="translate(20)">
+    <g transform="rotate(90)">
+      <g transform="scale(-1, 1)">
+        <rect wh="10 5"/>
+      </g>
+    </g>
+  </g>
+  <rect id="z" xy="#outer|h" wh="1"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"viewBox="15 -10 6 10""#);
+    assert_contains!(output, r#"id="z" x="20" y="-5.5""#);
+}
+
+#[test]
+fn test_group_fade() {
+    let input = r#"
+<g fade="60%"><rect wh="10"/></g>
+<g id="x" fade="0.5" opacity="0.8"><rect xy="20 0" wh="10"/></g>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<g opacity="0.4"><rect width="10" height="10"/></g>"#
+    );
+    assert_contains!(output, r#"<g id="x" opacity="0.4">"#);
+
+    for input in [
+        r#"<g fade="150%"><rect wh="10"/></g>"#,
+        r#"<g fade="-1"><rect wh="10"/></g>"#,
+        r#"<g fade="lots"><rect wh="10"/></g>"#,
+    ] {
+        assert!(transform_str_default(input).is_err(), "{input}");
+    }
+}
+
+#[test]
+fn test_group_transform_refs() {
+    let input = r##"
+<rect id="other" wh="10"/>
+<g transform="translate(30, 20)">
+  <rect id="a" wh="5"/>
+  <rect id="b" xy="#a|h 5" wh="5"/>
+  <g transform="translate(0, 10)">
+    <rect id="c" wh="5"/>
+    <line id="bc" start="#b@b" end="#c@t"/>
+  </g>
+</g>
+<line id="l1" start="#other@r" end="#a@l"/>
+<polyline id="l2" start="#other@b" end="#c@l"/>
+<rect id="z" xy="#b|h" wh="1"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    // references within a group use the group's coordinates
+    assert_contains!(output, r#"id="b" x="10" y="0""#);
+    assert_contains!(output, r#"id="bc" x1="12.5" y1="-5" x2="2.5" y2="0""#);
+    // references from outside the group use rendered coordinates
+    assert_contains!(output, r#"id="l1" x1="10" y1="5" x2="30" y2="22.5""#);
+    assert_contains!(output, r#"id="l2" points="5 10, 5 32.5, 30 32.5""#);
+    assert_contains!(output, r#"id="z" x="45" y="22""#);
+}
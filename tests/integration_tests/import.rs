@@ -0,0 +1,73 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::{transform_str, transform_str_default, TransformConfig};
+
+const ARTWORK: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 50">
+  <rect id="box" x="10" y="10" width="20" height="10" fill="blue"/>
+  <g id="grp"><circle id="dot" cx="60" cy="30" r="5"/></g>
+</svg>
+"#;
+
+fn import_config() -> (tempfile::TempDir, TransformConfig) {
+    let dir = tempfile::tempdir().expect("could not create tmpdir");
+    std::fs::write(dir.path().join("art.svg"), ARTWORK).expect("write failed");
+    let config = TransformConfig {
+        import_dir: Some(dir.path().to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+    (dir, config)
+}
+
+#[test]
+fn test_import_svg() {
+    let (_dir, config) = import_config();
+    let input = r##"
+<svg>
+<import id="art" src="art.svg" opacity="0.5"/>
+<line id="l" start="#box@r" end="#dot@l"/>
+<rect id="z" xy="#box|v 2" wh="5"/>
+</svg>
+"##;
+    let output = transform_str(input, &config).unwrap();
+    assert_contains!(output, r#"<g id="art" opacity="0.5" class="d-import">"#);
+    assert_contains!(
+        output,
+        r#"<rect id="box" x="10" y="10" width="20" height="10" fill="blue"/>"#
+    );
+    assert_not_contains!(output, "<import");
+    assert_not_contains!(
+        output,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg xmlns"
+    );
+    // imported elements may be referenced
+    assert_contains!(output, r#"id="l" x1="30" y1="15" x2="55" y2="30""#);
+    assert_contains!(output, r#"id="z" x="17.5" y="22""#);
+}
+
+#[test]
+fn test_import_bad() {
+    // imports are disabled by default
+    let input = r#"<svg><import src="art.svg"/></svg>"#;
+    assert!(transform_str_default(input).is_err());
+
+    let (_dir, config) = import_config();
+    assert!(transform_str(r#"<svg><import src="missing.svg"/></svg>"#, &config).is_err());
+    assert!(transform_str(r#"<svg><import/></svg>"#, &config).is_err());
+
+    // imports may not escape the import directory
+    let outer = tempfile::tempdir().expect("could not create tmpdir");
+    let inner = outer.path().join("inner");
+    std::fs::create_dir(&inner).expect("create_dir failed");
+    std::fs::write(outer.path().join("art.svg"), ARTWORK).expect("write failed");
+    let config = TransformConfig {
+        import_dir: Some(inner.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+    let abs = outer.path().join("art.svg");
+    for src in ["../art.svg", "./../art.svg", &abs.to_string_lossy()] {
+        let input = format!(r#"<svg><import src="{src}"/></svg>"#);
+        assert!(transform_str(&input, &config).is_err(), "{src}");
+    }
+    std::fs::write(inner.join("art.svg"), ARTWORK).expect("write failed");
+    assert!(transform_str(r#"<svg><import src="./art.svg"/></svg>"#, &config).is_ok());
+}
@@ -0,0 +1,77 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_key() {
+    let input = r#"
+<key id="ctrl" label="Ctrl" xy="0" wh="16 8"/>
+<key label="C" xy="^|h 2" wh="8"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="ctrl" x="0" y="0" width="16" height="8" rx="1" class="d-fill-lightgrey"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect x="1" y="0.5" width="14" height="5.5" rx="0.5"/>"#
+    );
+    assert_contains!(output, r#"<text x="8" y="3.25" class="d-text">Ctrl</text>"#);
+    // the following key is positioned relative to the whole of the first
+    assert_contains!(
+        output,
+        r#"<rect x="18" y="0" width="8" height="8" rx="1" class="d-fill-lightgrey"/>"#
+    );
+    assert_not_contains!(output, "label=");
+}
+
+#[test]
+fn test_key_style() {
+    let input = r#"<key label="A" xy="0" wh="8" rx="2" class="d-fill-red"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect x="0" y="0" width="8" height="8" rx="2" class="d-fill-red"/>"#
+    );
+    assert_not_contains!(output, "d-fill-lightgrey");
+}
+
+#[test]
+fn test_keyboard() {
+    let input = r#"
+<keyboard id="kb" xy="0" key-wh="8" gap="1">
+Esc _ F1
+Tab:1.5 Q
+</keyboard>
+<rect xy="^|v 2" wh="5"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="kb">"#);
+    assert_contains!(output, r#"<text x="4" y="3.25" class="d-text">Esc</text>"#);
+    assert_contains!(
+        output,
+        r#"<rect x="18" y="0" width="8" height="8" rx="1" class="d-fill-lightgrey"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect x="0" y="9" width="12.5" height="8" rx="1" class="d-fill-lightgrey"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect x="13.5" y="9" width="8" height="8" rx="1" class="d-fill-lightgrey"/>"#
+    );
+    // keyboard bbox is 26x17
+    assert_contains!(output, r#"<rect x="10.5" y="19" width="5" height="5"/>"#);
+    assert_not_contains!(output, "key-wh");
+}
+
+#[test]
+fn test_keyboard_bad() {
+    for input in [
+        r#"<keyboard key-wh="8 8 8">A</keyboard>"#,
+        r#"<keyboard>A:wide</keyboard>"#,
+        r#"<keyboard>A:-1</keyboard>"#,
+    ] {
+        assert!(transform_str_default(input).is_err(), "{input}");
+    }
+}
@@ -0,0 +1,70 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_layout_row() {
+    let input = r##"
+<row id="r" gap="5">
+  <rect id="a" wh="20 10"/>
+  <circle id="b" r="8"/>
+  <rect id="c" wh="10"/>
+  <line start="#a" end="#c"/>
+</row>
+<rect id="z" xy="#c|v 2" wh="5"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="r">"#);
+    assert_not_contains!(output, "gap=");
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="0" y="0" width="20" height="10"/>"#
+    );
+    assert_contains!(output, r#"<circle id="b" cx="33" cy="8" r="8"/>"#);
+    assert_contains!(
+        output,
+        r#"<rect id="c" x="46" y="0" width="10" height="10"/>"#
+    );
+    // placed elements can be referenced
+    assert_contains!(output, r#"<rect id="z" x="48.5" y="12""#);
+}
+
+#[test]
+fn test_layout_col_align() {
+    let input = r##"
+<col gap="2" align="center">
+  <rect id="a" wh="20 10"/>
+  <rect id="b" wh="10 4"/>
+  <rect id="c" xy="50 50" wh="5"/>
+</col>
+<col align="end" equal="true" transform="translate(30)">
+  <rect id="d" wh="10 4"/>
+  <rect id="e" wh="4 10"/>
+</col>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="0" y="0" width="20" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="b" x="5" y="12" width="10" height="4"/>"#
+    );
+    // explicitly positioned children are unchanged
+    assert_contains!(
+        output,
+        r#"<rect id="c" x="50" y="50" width="5" height="5"/>"#
+    );
+    // equal slots are as tall as the tallest child
+    assert_contains!(
+        output,
+        r#"<rect id="d" x="0" y="3" width="10" height="4"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="e" x="6" y="10" width="4" height="10"/>"#
+    );
+
+    let input = r#"<row align="middle"><rect wh="5"/></row>"#;
+    assert!(transform_str_default(input).is_err());
+}
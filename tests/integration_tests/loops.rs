@@ -79,6 +79,33 @@ fn test_loop_while() {
     assert_eq!(output.trim(), expected.trim());
 }
 
+#[test]
+fn test_loop_while_geometric() {
+    // fill a region until the previous element reaches its edge
+    let input = r##"
+<rect id="region" wh="10 2"/>
+<rect xy="0" wh="2" id="first"/>
+<loop while="lt(^~x2, #region~x2)" loop-var="n" start="1"><rect xy="^|h 1" wh="2" id="r$n"/>
+</loop>
+"##;
+    let expected = r#"
+<rect id="first" x="0" y="0" width="2" height="2"/>
+<rect id="r1" x="3" y="0" width="2" height="2"/>
+<rect id="r2" x="6" y="0" width="2" height="2"/>
+<rect id="r3" x="9" y="0" width="2" height="2"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output.trim(), expected.trim());
+
+    // a condition which is never false is stopped by loop-limit
+    let input = r#"
+<config loop-limit="50"/>
+<loop while="gt(^~x2, 0)"><rect xy="^|h" wh="1"/></loop>
+"#;
+    let input = format!(r#"<rect wh="1"/>{input}"#);
+    assert!(transform_str_default(&input).is_err());
+}
+
 #[test]
 fn test_loop_until() {
     let input = r##"
@@ -232,6 +259,31 @@ fn test_loop_limit() {
     assert!(transform_str_default(input).is_err());
 }
 
+#[test]
+fn test_loop_limit_attr() {
+    // per-loop limit overrides loop-limit in either direction
+    let input = r#"
+<config loop-limit="100"/>
+<loop count="200" limit="200"><rect wh="1" xy="0"/></loop>
+"#;
+    assert!(transform_str_default(input).is_ok());
+
+    let input = r#"
+<loop count="20" limit="10"><rect wh="1" xy="0"/></loop>
+"#;
+    let err = transform_str_default(input).unwrap_err().to_string();
+    assert_contains!(err, "Loop count 11 exceeded limit 10");
+    // error identifies the loop's source line
+    assert_contains!(err, "   2: loop count=\"20\" limit=\"10\": Loop count 11");
+
+    let input = r#"
+<for data="1 2 3" var="v" limit="2"><rect wh="$v"/></for>
+"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r#"<loop count="2" limit="x"><rect wh="1"/></loop>"#;
+    assert!(transform_str_default(input).is_err());
+}
+
 #[test]
 fn test_for_loop() {
     let input = r#"
@@ -260,3 +312,85 @@ fn test_for_loop() {
     assert_contains!(output, expected2);
     assert_contains!(output, expected3);
 }
+
+#[test]
+fn test_loop_implicit_vars() {
+    let input = r##"
+<loop count="2"><loop count="3"><rect x="$loop_index" y="$loop_count"/></loop><circle r="$loop_index"/></loop>
+"##;
+    let expected = r#"
+<rect x="0" y="3"/><rect x="1" y="3"/><rect x="2" y="3"/><circle r="0"/><rect x="0" y="3"/><rect x="1" y="3"/><rect x="2" y="3"/><circle r="1"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_eq!(output.trim(), expected.trim());
+
+    let input = r##"
+<for data="'a', 'b'" var="v"><text text="$v:{{$loop_index + 1}}/$loop_count"/></for>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ">a:1/2</text>");
+    assert_contains!(output, ">b:2/2</text>");
+
+    // only defined within loops, and a while loop has no count
+    let input = r##"
+<var i="0"/>
+<for data="'a'" var="v"><loop while="lt($i, 2)"><text text="$loop_index/$loop_count"/><var i="{{$i + 1}}"/></loop></for>
+<rect x="$loop_index" y="$loop_count"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ">0/$loop_count</text>");
+    assert_contains!(output, ">1/$loop_count</text>");
+    assert_contains!(output, r#"<rect x="$loop_index" y="$loop_count"/>"#);
+}
+
+#[test]
+fn test_loop_grid() {
+    let input = r##"<loop grid="3x2"><rect x="$x" y="$y" width="$i"/></loop>"##;
+    let expected = concat!(
+        r#"<rect x="0" y="0" width="0"/><rect x="1" y="0" width="1"/><rect x="2" y="0" width="2"/>"#,
+        r#"<rect x="0" y="1" width="3"/><rect x="1" y="1" width="4"/><rect x="2" y="1" width="5"/>"#
+    );
+    let output = transform_str_default(input).unwrap();
+    assert_eq!(output, expected);
+
+    let input = r##"<var n="2"/><loop count-x="$n"><circle r="{{$x + $loop_count}}"/></loop>"##;
+    let expected = r#"<circle r="2"/><circle r="3"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_eq!(output, expected);
+
+    // grid size is checked against the loop limit before iterating
+    let input = r##"<loop grid="100000x100000"><rect wh="1"/></loop>"##;
+    assert!(transform_str_default(input).is_err());
+    let input = r##"<loop grid="5x5" limit="20"><rect wh="1"/></loop>"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_loop_grid_spacing() {
+    let input = r##"
+<loop grid="2x2" spacing="20 10">
+<rect id="r$i" xy="0" wh="5"/>
+<line xy1="^@r" xy2="10 2.5"/>
+</loop>
+<text xy="#r3@br" text="end"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="r0" x="0" y="0" width="5" height="5"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="r1" x="20" y="0" width="5" height="5"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="r2" x="0" y="10" width="5" height="5"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="r3" x="20" y="10" width="5" height="5"/>"#
+    );
+    assert_contains!(output, r#"<line x1="25" y1="12.5" x2="30" y2="12.5"/>"#);
+    assert_contains!(output, r#"<text x="26" y="16""#);
+}
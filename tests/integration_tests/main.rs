@@ -1,3 +1,4 @@
+mod align;
 mod attr_expansion;
 mod auto_styles;
 mod box_element;
@@ -11,9 +12,13 @@ mod defaults;
 mod error_handling;
 mod eval_locs;
 mod expression;
+mod graph;
 mod group;
 mod if_element;
+mod import;
 mod indent;
+mod key;
+mod layout;
 mod loops;
 mod point;
 mod position;
@@ -22,7 +27,14 @@ mod rel_size;
 mod reuse;
 mod root_svg;
 mod roundtrip;
+mod sanitize;
+mod schematic;
 mod src_line;
+mod stats;
+mod steps;
+mod template_areas;
 mod text_attr;
 mod transform_svg;
+mod tree;
 mod variables;
+mod wave;
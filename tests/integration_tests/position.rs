@@ -330,6 +330,225 @@ fn test_use_bbox() {
     assert_contains!(output, expected2);
 }
 
+#[test]
+fn test_use_scaled() {
+    let input = r##"
+<svg>
+  <defs>
+    <symbol id="sym"><rect xy="5" wh="10 5"/></symbol>
+    <symbol id="vb" viewBox="0 0 10 10"><rect wh="10"/></symbol>
+  </defs>
+  <use id="u1" href="#sym" wh="20"/>
+  <use id="u2" href="#sym" xy="#u1|h 2" wh="20" preserveAspectRatio="none"/>
+  <use href="#vb" x="50" wh="20"/>
+  <rect xy="#u2|v" wh="2"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    // aspect ratio preserved by default
+    assert_contains!(
+        output,
+        r##"<use id="u1" href="#sym" transform="translate(5, 5) scale(2) translate(-5, -5)"/>"##
+    );
+    assert_contains!(
+        output,
+        r##"<use id="u2" href="#sym" transform="translate(27, 0) scale(2, 4) translate(-5, -5)"/>"##
+    );
+    // symbols with viewBox are scaled by the renderer
+    assert_contains!(
+        output,
+        r##"<use href="#vb" x="50" width="20" height="20"/>"##
+    );
+    // scaled bbox is used for positioning
+    assert_contains!(output, r#"<rect x="36" y="20" width="2" height="2"/>"#);
+
+    // no scaling required; left untouched
+    let input = r##"
+<svg>
+  <defs><symbol id="sym"><rect xy="5" wh="10 5"/></symbol></defs>
+  <use href="#sym" xy="30 40" wh="10 5"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r##"<use href="#sym" x="30" y="40" width="10" height="5"/>"##
+    );
+}
+
+#[test]
+fn test_use_auto_aspect() {
+    let input = r##"
+<svg>
+  <defs>
+    <symbol id="vb" viewBox="0 0 20 10"><rect wh="20 10"/></symbol>
+    <rect id="r" wh="4 8"/>
+  </defs>
+  <use id="u1" href="#vb" wh="40 auto"/>
+  <use id="u2" href="#r" xy="50 0" width="auto" height="4"/>
+  <use id="u3" href="#r" xy="60 0" wh="auto"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r##"<use id="u1" href="#vb" width="40" height="20"/>"##
+    );
+    assert_contains!(
+        output,
+        r##"<use id="u2" href="#r" transform="translate(50, 0) scale(0.5) translate(0, 0)"/>"##
+    );
+    assert_contains!(
+        output,
+        r##"<use id="u3" href="#r" x="60" y="0" width="4" height="8"/>"##
+    );
+}
+
+#[test]
+fn test_image_auto_aspect() {
+    let href = "data:image/svg+xml,%3Csvg%20viewBox=%220%200%2020%2010%22/%3E";
+    let input = format!(
+        r#"<image id="i1" href="{href}" wh="auto 5"/><image id="i2" href="{href}" xy="^|h" wh="auto"/>"#
+    );
+    let output = transform_str_default(&input).unwrap();
+    assert_contains!(output, r#"%3E" width="10" height="5"/>"#);
+    assert_contains!(output, r#"x="10" y="-2.5" width="20" height="10"/>"#);
+
+    // image files can only be read if enabled
+    assert!(transform_str_default(r#"<image href="a.png" wh="auto 5"/>"#).is_err());
+
+    // and only from regular files within the import directory
+    let outer = tempfile::tempdir().expect("could not create tmpdir");
+    let inner = outer.path().join("inner");
+    std::fs::create_dir(&inner).expect("create_dir failed");
+    let svg = r#"<svg viewBox="0 0 20 10"><rect/></svg>"#;
+    std::fs::write(outer.path().join("a.svg"), svg).expect("write failed");
+    std::fs::write(inner.join("a.svg"), svg).expect("write failed");
+    let cfg = svgdx::TransformConfig {
+        import_dir: Some(inner.to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+    let output = svgdx::transform_str(r#"<image href="a.svg" wh="auto 5"/>"#, &cfg).unwrap();
+    assert_contains!(output, r#"width="10" height="5""#);
+    let abs = outer.path().join("a.svg");
+    for href in ["../a.svg", &abs.to_string_lossy(), ".", "/dev/zero"] {
+        let input = format!(r#"<image href="{href}" wh="auto 5"/>"#);
+        assert!(svgdx::transform_str(&input, &cfg).is_err(), "{href}");
+    }
+}
+
+#[test]
+fn test_transform_origin() {
+    let input = r##"
+<svg>
+  <rect id="a" xy="10" wh="20 10" transform="rotate(90)" transform-origin="center"/>
+  <rect xy="#a|v" wh="2"/>
+  <rect id="b" xy="0 40" wh="10" transform="scale(2)" transform-origin="100% 100%"/>
+  <circle cxy="#b@tl" r="1"/>
+  <rect xy="0 40" wh="10" transform="scale(2)" transform-origin="0 0"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"transform="translate(20, 15) rotate(90) translate(-20, -15)"/>"#
+    );
+    // rotated bbox is used for positioning
+    assert_contains!(output, r#"<rect x="19" y="25" width="2" height="2"/>"#);
+    assert_contains!(
+        output,
+        r#"transform="translate(10, 50) scale(2) translate(-10, -50)"/>"#
+    );
+    assert_contains!(output, r#"<circle cx="-10" cy="30" r="1"/>"#);
+    // origin at (0, 0) leaves the transform unchanged
+    assert_contains!(
+        output,
+        r#"<rect x="0" y="40" width="10" height="10" transform="scale(2)"/>"#
+    );
+}
+
+#[test]
+fn test_transform_origin_bad() {
+    let input = r#"<rect wh="10" transform="scale(2)" transform-origin="1 2 3"/>"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r#"<rect wh="10" transform="scale(2)" transform-origin="middle"/>"#;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_scale_attr() {
+    let input = r##"
+<svg>
+  <rect id="a" xy="10" wh="20 10" scale="2"/>
+  <rect xy="^|v 1" wh="2"/>
+  <rect id="b" xy="0 40" wh="10" scale="2 0.5" transform-origin="tl"/>
+  <circle cxy="#b@br" r="1"/>
+  <circle cxy="50 50" r="5" scale="1.5" transform="translate(10)"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="10" y="10" width="20" height="10" transform="translate(20, 15) scale(2) translate(-20, -15)"/>"#
+    );
+    // scaled bbox is used for positioning
+    assert_contains!(output, r#"<rect x="19" y="26" width="2" height="2"/>"#);
+    assert_contains!(
+        output,
+        r#"transform="translate(0, 40) scale(2, 0.5) translate(0, -40)"/>"#
+    );
+    assert_contains!(output, r#"<circle cx="20" cy="45" r="1"/>"#);
+    // scale is applied before any existing transform
+    assert_contains!(
+        output,
+        r#"transform="translate(10) translate(50, 50) scale(1.5) translate(-50, -50)"/>"#
+    );
+}
+
+#[test]
+fn test_scale_attr_bad() {
+    for input in [
+        r#"<rect wh="10" scale="x"/>"#,
+        r#"<rect wh="10" scale="1 2 3"/>"#,
+    ] {
+        assert!(transform_str_default(input).is_err(), "{input}");
+    }
+    // `scale` on filter primitives is left alone
+    let input = r#"<feDisplacementMap scale="20"/>"#;
+    assert_contains!(
+        transform_str_default(input).unwrap(),
+        r#"<feDisplacementMap scale="20"/>"#
+    );
+}
+
+#[test]
+fn test_skew_attr() {
+    let input = r##"
+<svg>
+  <rect id="a" xy="0" wh="20 10" skew-x="45"/>
+  <rect xy="#a|h" wh="2"/>
+  <rect xy="0 40" wh="10" skew-y="-30" skew-x="10" transform-origin="bl"/>
+</svg>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="0" y="0" width="20" height="10" transform="translate(10, 5) skewX(45) translate(-10, -5)"/>"#
+    );
+    // skewed bbox is used for positioning
+    assert_contains!(output, r#"<rect x="25" y="4" width="2" height="2"/>"#);
+    assert_contains!(
+        output,
+        r#"transform="translate(0, 50) skewX(10) skewY(-30) translate(0, -50)"/>"#
+    );
+    assert!(transform_str_default(r#"<rect wh="10" skew-x="steep"/>"#).is_err());
+    for angle in ["90", "-90", "270", "89.99", "NaN"] {
+        let input = format!(r#"<rect wh="10" skew-y="{angle}"/>"#);
+        assert!(transform_str_default(&input).is_err(), "{angle}");
+    }
+    assert!(transform_str_default(r#"<rect wh="10" skew-x="180"/>"#).is_ok());
+}
+
 #[test]
 fn test_reuse_bbox() {
     let input = r##"
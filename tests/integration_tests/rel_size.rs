@@ -231,3 +231,27 @@ fn test_rel_size_dwh_anchor() {
     let output = transform_str_default(input).unwrap();
     assert_contains!(output, expected);
 }
+
+#[test]
+fn test_rel_size_parent_ratio() {
+    let input = r#"
+<g wh="40 20"><rect xy="0" width="50%" height="100%"/><rect xy="^|h" wh="25%"/></g>
+"#;
+    let expected =
+        r#"<rect x="0" y="0" width="20" height="20"/><rect x="20" y="7.5" width="10" height="5"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, expected);
+
+    // viewBox takes priority over width / height
+    let input = r#"
+<svg width="100" height="50" viewBox="0 0 10 20"><rect wh="50%"/></svg>
+"#;
+    let expected = r#"<rect width="5" height="10"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, expected);
+
+    // No parent size; left for the renderer to resolve
+    let input = r#"<rect width="50%" height="10"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect width="50%" height="10"/>"#);
+}
@@ -1,5 +1,5 @@
 use assertables::{assert_contains, assert_not_contains};
-use svgdx::transform_str_default;
+use svgdx::{transform_str, transform_str_default, TransformConfig};
 
 #[test]
 fn test_reuse_simple() {
@@ -295,6 +295,35 @@ fn test_nesting_depth_limit() {
     assert!(transform_str_default(&input).is_err());
 }
 
+#[test]
+fn test_reuse_resource_limits() {
+    // Each level doubles the number of generated elements
+    let input = r##"
+<specs>
+<g id="a0"><rect wh="1"/><rect wh="1"/></g>
+<g id="a1"><reuse href="#a0"/><reuse href="#a0"/></g>
+<g id="a2"><reuse href="#a1"/><reuse href="#a1"/></g>
+<g id="a3"><reuse href="#a2"/><reuse href="#a2"/></g>
+</specs>
+<reuse href="#a3"/>
+"##;
+    assert!(transform_str_default(input).is_ok());
+
+    let cfg = TransformConfig {
+        element_limit: 20,
+        ..Default::default()
+    };
+    let err = transform_str(input, &cfg).unwrap_err();
+    assert_eq!(err.to_string(), "Element count 21 exceeded limit 20");
+
+    let cfg = TransformConfig {
+        output_limit: 200,
+        ..Default::default()
+    };
+    let err = transform_str(input, &cfg).unwrap_err();
+    assert_contains!(err.to_string(), "exceeded limit 200");
+}
+
 #[test]
 fn test_reuse_group_rel() {
     let input = r##"
@@ -384,3 +413,109 @@ fn test_reuse_prev() {
     let output = transform_str_default(input).unwrap();
     assert_contains!(output, expected);
 }
+
+#[test]
+fn test_reuse_parent_size() {
+    let input = r##"
+<specs>
+<g id="tmpl"><rect xy="0" wh="{{$parent_width / 2}} $parent_height"/></g>
+</specs>
+<reuse href="#tmpl" width="30" height="4"/>
+"##;
+    let expected = r#"<rect x="0" y="0" width="15" height="4"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, expected);
+}
+
+#[test]
+fn test_reuse_override() {
+    let input = r##"
+<specs>
+<g id="tmpl"><rect wh="20 10" class="bg"/><text class="title" text="Old"/></g>
+</specs>
+<reuse href="#tmpl" override="rect.bg:fill=red; text.title:text='New value'"/>
+<reuse href="#tmpl"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect width="20" height="10" fill="red" class="bg"/>"#
+    );
+    assert_contains!(output, r#"class="d-text title">New value</text>"#);
+    // overrides only apply to the instance they are given on
+    assert_contains!(output, r#"<rect width="20" height="10" class="bg"/>"#);
+    assert_contains!(output, r#"class="d-text title">Old</text>"#);
+}
+
+#[test]
+fn test_reuse_override_invalid() {
+    let input = r##"
+<specs><rect id="tmpl" wh="20 10"/></specs>
+<reuse href="#tmpl" override="rect fill=red"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+
+    // a failed reuse must not leave its attributes available as variables
+    let input = r##"
+<specs>
+<rect id="tmpl" wh="20 10"/>
+<reuse href="#tmpl" override="rect fill=red" w="3"/>
+</specs>
+<rect wh="$w 1"/>
+"##;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_reuse_bake() {
+    let input = r##"
+<specs>
+<g id="tmpl"><rect id="inner" wh="20 10"/><circle xy="^|h 2" r="3"/><path d="M 0 0 h 5"/></g>
+</specs>
+<reuse id="r1" href="#tmpl" x="100" y="50" bake="true"/>
+<line start="#inner@r" end="#r1@b"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<g id="r1" class="tmpl"><rect id="inner" x="100" y="50" width="20" height="10"/>"#
+    );
+    assert_contains!(output, r#"<circle cx="125" cy="55" r="3"/>"#);
+    assert_contains!(
+        output,
+        r#"<path d="M 0 0 h 5" transform="translate(100, 50)"/>"#
+    );
+    // inner elements are referenceable at their baked position
+    assert_contains!(output, r#"<line x1="120" y1="55" x2="114" y2="60"/>"#);
+}
+
+#[test]
+fn test_reuse_bake_transform() {
+    // x/y translation applies before any transform on the instance element
+    let input = r##"
+<specs><rect id="tmpl" wh="4" transform="rotate(45)"/></specs>
+<reuse href="#tmpl" x="10" bake="true"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect x="10" y="0" width="4" height="4" transform="rotate(45)" class="tmpl"/>"#
+    );
+}
+
+#[test]
+fn test_reuse_bake_nested_transform() {
+    // content of a nested transformed group is moved by the group's transform
+    let input = r##"
+<specs>
+<g id="tmpl"><g transform="rotate(10)"><rect wh="4"/></g><rect xy="10 0" wh="2"/></g>
+</specs>
+<reuse href="#tmpl" x="100" bake="true"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<g transform="translate(100, 0) rotate(10)"><rect width="4" height="4"/></g>"#
+    );
+    assert_contains!(output, r#"<rect x="110" y="0" width="2" height="2"/>"#);
+}
@@ -0,0 +1,61 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::{transform_str, transform_str_default, TransformConfig};
+
+fn sanitize_config() -> TransformConfig {
+    TransformConfig {
+        sanitize: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_sanitize_elements() {
+    let input = r#"
+<svg>
+<script>alert(1)</script>
+<foreignObject wh="10"><div xmlns="http://www.w3.org/1999/xhtml">Hi</div></foreignObject>
+<rect wh="10"/>
+</svg>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, "<script>");
+    assert_contains!(output, "<foreignObject");
+
+    let output = transform_str(input, &sanitize_config()).unwrap();
+    assert_not_contains!(output, "script");
+    assert_not_contains!(output, "foreignObject");
+    assert_not_contains!(output, "Hi");
+    assert_contains!(output, r#"<rect width="10" height="10"/>"#);
+}
+
+#[test]
+fn test_sanitize_attributes() {
+    let input = r##"
+<svg>
+<circle id="abc" r="1"/>
+<rect wh="10" onclick="alert(1)" fill="url(#grad)"/>
+<a href="javascript:alert(1)"><circle r="2" style="fill: url('https://example.com/x#y')"/></a>
+<image href="https://example.com/img.png" wh="5"/>
+<use href="#abc"/>
+</svg>
+"##;
+    let output = transform_str(input, &sanitize_config()).unwrap();
+    assert_not_contains!(output, "onclick");
+    assert_not_contains!(output, "javascript");
+    assert_not_contains!(output, "example.com");
+    assert_contains!(output, r##"fill="url(#grad)""##);
+    assert_contains!(output, r##"<use href="#abc""##);
+}
+
+#[test]
+fn test_sanitize_generated() {
+    // Attribute values produced during processing are also checked
+    let input = r#"
+<svg>
+<var url="'https://example.com/'"/>
+<image href="{{$url}}" wh="5"/>
+</svg>
+"#;
+    let output = transform_str(input, &sanitize_config()).unwrap();
+    assert_not_contains!(output, "example.com");
+}
@@ -0,0 +1,67 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_part_pins() {
+    let input = r#"
+<part id="u1" xy="0" wh="20 30" text="U1">
+  <pin name="in" side="l"/>
+  <pin name="out" side="r"/>
+  <pin side="t"/>
+</part>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect id="u1" x="0" y="0" width="20" height="30"/>"#
+    );
+    assert_contains!(output, r#"<line x1="0" y1="15" x2="-3" y2="15"/>"#);
+    assert_contains!(output, r#"<line x1="20" y1="15" x2="23" y2="15"/>"#);
+    assert_contains!(output, r#"<line x1="10" y1="0" x2="10" y2="-3"/>"#);
+    assert_contains!(
+        output,
+        r#"<text x="2" y="15" class="d-text d-text-left d-text-smaller">in</text>"#
+    );
+    assert_not_contains!(output, "<pin");
+}
+
+#[test]
+fn test_part_bad_pin() {
+    let input = r#"<part id="u1" xy="0" wh="20 30"><pin side="x"/></part>"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r#"<part xy="0" wh="20 30"><pin/></part>"#;
+    assert!(transform_str_default(input).is_err());
+}
+
+#[test]
+fn test_net() {
+    let input = r#"
+<net pins="u1.out u2.in" class="d-red"/>
+<part id="u1" xy="0" wh="20 30">
+  <pin name="out" side="r"/>
+</part>
+<part id="u2" xy="40 20" wh="20 20">
+  <pin name="in"/>
+  <pin/>
+</part>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline points="23 15, 30 15, 30 26.667, 37 26.667" class="d-red"/>"#
+    );
+    assert_not_contains!(output, "pins=");
+}
+
+#[test]
+fn test_net_bad() {
+    let part = r#"<part id="u1" xy="0" wh="20 30"><pin name="a"/><pin/></part>"#;
+    for net in [
+        r#"<net pins="u1.a"/>"#,
+        r#"<net pins="u1.a u1-2"/>"#,
+        r#"<net pins="u1.a u1.missing"/>"#,
+    ] {
+        let input = format!("{part}{net}");
+        assert!(transform_str_default(&input).is_err(), "{net}");
+    }
+}
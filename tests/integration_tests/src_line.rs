@@ -77,3 +77,33 @@ fn test_src_line_text() {
     let result = transform_str(input, &meta_config()).unwrap();
     assert_eq!(result, expected);
 }
+
+#[test]
+fn test_src_line_reuse_provenance() {
+    let input = r##"
+<rect id="a" wh="10"/>
+<reuse href="#a" x="5"/>
+<reuse href="#a" x="25"/>
+"##;
+    let result = transform_str(input, &meta_config()).unwrap();
+    let hashes: Vec<_> = result
+        .lines()
+        .filter(|l| l.contains("data-reuse-href=\"#a\""))
+        .map(|l| {
+            assert!(l.contains(r#"data-reuse-src-line="2""#));
+            assert!(l.contains(&format!(r#"data-svgdx-version="{}""#, svgdx::VERSION)));
+            l.split("data-reuse-hash=\"").nth(1).unwrap()[..16].to_owned()
+        })
+        .collect();
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(hashes[0], hashes[1]);
+
+    // Changing the definition changes the hash
+    let input2 = input.replace(r#"wh="10""#, r#"wh="20""#);
+    let result2 = transform_str(input2, &meta_config()).unwrap();
+    assert!(!result2.contains(&hashes[0]));
+
+    // No provenance without metadata
+    let result = transform_str(input, &Default::default()).unwrap();
+    assert!(!result.contains("data-reuse-"));
+}
@@ -0,0 +1,36 @@
+use svgdx::{transform_str_with_stats, TransformConfig};
+
+#[test]
+fn test_stats_counts() {
+    let input = r##"
+<svg>
+<rect xy="#b|h" wh="5"/>
+<rect id="b" wh="5"/>
+<line start="^@r" end="#b@l"/>
+<loop count="3"><circle r="1"/></loop>
+</svg>
+"##;
+    let (output, stats) = transform_str_with_stats(input, &TransformConfig::default()).unwrap();
+    assert!(output.contains("<circle"));
+    // svg, 2 rects, line, loop, 3 circles
+    assert_eq!(stats.elements, 8);
+    assert_eq!(stats.connectors, 1);
+    assert_eq!(stats.deferred_passes, 1);
+    assert!(stats.styles > 0);
+    let phases: Vec<_> = stats.phases.iter().map(|(p, _)| *p).collect();
+    assert_eq!(
+        phases,
+        vec!["parse", "collect", "resolve", "style", "write"]
+    );
+
+    let cfg = TransformConfig {
+        add_auto_styles: false,
+        ..Default::default()
+    };
+    let (_, stats) = transform_str_with_stats(input, &cfg).unwrap();
+    assert_eq!(stats.styles, 0);
+
+    let trace = stats.chrome_trace();
+    assert!(trace.starts_with(r#"[{"name":"parse","cat":"svgdx","ph":"X","ts":0,"#));
+    assert_eq!(trace.matches(r#""ph":"X""#).count(), 5);
+}
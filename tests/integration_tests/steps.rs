@@ -0,0 +1,52 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::{transform_str, transform_str_default, TransformConfig};
+
+const INPUT: &str = r#"
+<svg>
+<rect id="a" wh="10"/>
+<rect xy="^|h 5" wh="10" step="2"/>
+<g step="3"><circle cxy="40 5" r="5"/></g>
+</svg>
+"#;
+
+#[test]
+fn test_step_classes() {
+    let output = transform_str_default(INPUT).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect x="15" y="0" width="10" height="10" class="d-step-2"/>"#
+    );
+    assert_contains!(output, r#"<g class="d-step-3">"#);
+    assert_contains!(
+        output,
+        r#"[data-step="0"] .d-step-2, [data-step="1"] .d-step-2 { display: none; }"#
+    );
+    assert_not_contains!(output, " step=");
+    assert_not_contains!(output, "display=");
+}
+
+#[test]
+fn test_step_config() {
+    let cfg = TransformConfig {
+        step: Some(2),
+        ..Default::default()
+    };
+    let output = transform_str(INPUT, &cfg).unwrap();
+    assert_contains!(
+        output,
+        r#"<rect x="15" y="0" width="10" height="10" class="d-step-2"/>"#
+    );
+    assert_contains!(output, r#"<g display="none" class="d-step-3">"#);
+
+    let input = format!(r#"<config step="1"/>{INPUT}"#);
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"display="none" class="d-step-2"/>"#);
+}
+
+#[test]
+fn test_step_bad() {
+    for step in ["0", "-1", "two"] {
+        let input = format!(r#"<rect wh="1" step="{step}"/>"#);
+        assert!(transform_str_default(input).is_err(), "{step}");
+    }
+}
@@ -0,0 +1,57 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_template_areas() {
+    let input = r##"
+<g id="page" template-areas="header header / nav main" gap="2">
+  <rect id="h" area="header" wh="40 5"/>
+  <rect id="n" area="nav" wh="10 20"/>
+  <rect id="m" area="main" wh="20 30"/>
+</g>
+<rect id="z" xy="#m|h 3" wh="5"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="page">"#);
+    assert_not_contains!(output, "area=");
+    assert_not_contains!(output, "gap=");
+    // header spans both columns, widening them evenly from 10 and 20 to 14 and 24
+    assert_contains!(
+        output,
+        r#"<rect id="h" x="0" y="0" width="40" height="5"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="n" x="2" y="12" width="10" height="20"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="m" x="18" y="7" width="20" height="30"/>"#
+    );
+    // placed elements can be referenced
+    assert_contains!(output, r#"<rect id="z" x="41" y="19.5""#);
+}
+
+#[test]
+fn test_template_areas_empty_cell() {
+    let input = r##"
+<g template-areas="a . / . b">
+  <circle id="a" area="a" r="5"/>
+  <rect id="b" area="b" wh="20 4"/>
+</g>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<circle id="a" cx="5" cy="5" r="5"/>"#);
+    assert_contains!(
+        output,
+        r#"<rect id="b" x="10" y="10" width="20" height="4"/>"#
+    );
+}
+
+#[test]
+fn test_template_areas_bad() {
+    let input = r#"<g template-areas="a b / b b"><rect area="a" wh="5"/></g>"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r#"<g template-areas="a b"><rect area="c" wh="5"/></g>"#;
+    assert!(transform_str_default(input).is_err());
+}
@@ -1,3 +1,4 @@
+use assertables::assert_contains;
 use svgdx::transform_str_default;
 
 #[test]
@@ -614,3 +615,26 @@ fn test_multiline_outside() {
         expected.trim()
     );
 }
+
+#[test]
+fn test_auto_size() {
+    let input = r#"<rect id="a" size="auto" text="Hello"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect id="a" width="11" height="5"/>"#);
+    assert_contains!(
+        output,
+        r#"<text x="5.5" y="2.5" class="d-text">Hello</text>"#
+    );
+
+    // padding, multi-line text and text size classes are taken into account
+    let input = r#"<rect xy="0" size="auto 2" text="two\nlines" class="d-text-large"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"width="17.5" height="13.225""#);
+
+    let input = r#"<rect size="auto" font-size="5" text="{{1+1}}"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"width="5" height="7""#);
+
+    assert!(transform_str_default(r#"<rect size="auto"/>"#).is_err());
+    assert!(transform_str_default(r#"<circle size="auto" text="a"/>"#).is_err());
+}
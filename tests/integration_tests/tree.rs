@@ -0,0 +1,93 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_tree_nested() {
+    let input = r##"
+<tree id="org" gap="4 10" edge-class="d-arrow">
+  <node id="ceo" text="CEO">
+    <node text="CTO">
+      <node wh="10">Dev</node>
+      <node wh="10">Ops</node>
+    </node>
+    <node>CFO</node>
+  </node>
+</tree>
+<rect id="z" xy="#org-4|h 2" wh="5"/>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="org">"#);
+    assert_not_contains!(output, "gap=");
+    assert_not_contains!(output, "edge-class=");
+    assert_not_contains!(output, "<node");
+    // parents are centred over their children
+    assert_contains!(
+        output,
+        r#"<rect id="ceo" x="14" y="0" width="20" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="org-1" x="2" y="20" width="20" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="org-2" x="0" y="40" width="10" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="org-3" x="14" y="40" width="10" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="org-4" x="26" y="20" width="20" height="10"/>"#
+    );
+    // text content is used as a label
+    assert_contains!(output, r#"class="d-text">Ops</text>"#);
+    // connectors from each parent to its children
+    assert_contains!(
+        output,
+        r#"<polyline points="24 10, 24 15, 12 15, 12 20" class="d-arrow"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline points="24 10, 24 15, 36 15, 36 20" class="d-arrow"/>"#
+    );
+    assert_eq!(output.matches("<polyline ").count(), 4);
+    // generated nodes can be referenced
+    assert_contains!(output, r#"<rect id="z" x="48" y="22.5""#);
+}
+
+#[test]
+fn test_tree_edges() {
+    let input = r##"
+<tree direction="LR">
+  <rect id="a" wh="10"/>
+  <rect id="b" wh="10"/>
+  <circle id="c" r="5"/>
+  <edge from="a" to="b"/>
+  <edge from="a" to="c"/>
+  <line start="#c" end="#a"/>
+</tree>
+"##;
+    let output = transform_str_default(input).unwrap();
+    assert_not_contains!(output, "direction=");
+    assert_contains!(
+        output,
+        r#"<rect id="a" x="0" y="10" width="10" height="10"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<rect id="b" x="30" y="0" width="10" height="10"/>"#
+    );
+    assert_contains!(output, r#"<circle id="c" cx="35" cy="25" r="5"/>"#);
+    // edges which would form a cycle are drawn but ignored in the layout
+    assert_eq!(output.matches("<line ").count(), 3);
+}
+
+#[test]
+fn test_tree_bad() {
+    let input = r#"<tree><node><title>x</title></node></tree>"#;
+    assert!(transform_str_default(input).is_err());
+    let input = r#"<tree direction="up"><node/></tree>"#;
+    assert!(transform_str_default(input).is_err());
+}
@@ -249,6 +249,19 @@ fn test_var_scopes() {
     assert_contains!(output, expected5);
 }
 
+#[test]
+fn test_var_container_scope() {
+    // Only group elements introduce a variable scope
+    let input = r#"<defs><var k="7"/></defs><rect wh="$k"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<rect width="7" height="7"/>"#);
+
+    // ...and container attributes are not variables
+    let input = r#"<svg foo="3"><text text="$foo"/></svg>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ">$foo</text>");
+}
+
 #[test]
 fn test_var_closure() {
     // Check that deferred elements carry closure info
@@ -277,3 +290,19 @@ fn test_var_closure() {
     let output = transform_str_default(input).unwrap();
     assert_contains!(output, expected);
 }
+
+#[test]
+fn test_var_doc_theme() {
+    let input = r#"<text text="$doc_theme"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ">default</text>");
+
+    let input = r#"<config theme="dark"/><text text="$doc_theme"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ">dark</text>");
+
+    // explicit variables take priority
+    let input = r#"<var doc_theme="mine"/><text text="$doc_theme"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, ">mine</text>");
+}
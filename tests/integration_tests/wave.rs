@@ -0,0 +1,50 @@
+use assertables::{assert_contains, assert_not_contains};
+use svgdx::transform_str_default;
+
+#[test]
+fn test_wave_clock() {
+    let input = r#"<wave id="clk" signal="clk" pattern="pp" xy="0" period="4" height="2"/>"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(output, r#"<g id="clk">"#);
+    assert_contains!(
+        output,
+        r#"<text x="-3" y="1" class="d-text d-text-right">clk</text>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polyline points="0 2, 0 0, 2 0, 2 2, 4 2, 4 0, 6 0, 6 2, 8 2"/>"#
+    );
+    assert_not_contains!(output, "pattern=");
+    assert_not_contains!(output, "signal=");
+}
+
+#[test]
+fn test_wave_levels_data() {
+    let input = r#"
+<wave pattern="0.1z" xy="10 0"/>
+<wave pattern="x=.=" data="A B" xy="^|v 5"/>
+"#;
+    let output = transform_str_default(input).unwrap();
+    assert_contains!(
+        output,
+        r#"<polyline points="10 5, 30 5, 30 0, 40 0, 40 2.5, 50 2.5"/>"#
+    );
+    // relative positioning uses the waveform, so waves share a time axis
+    assert_contains!(
+        output,
+        r#"<rect x="10" y="10" width="10" height="5" class="d-hatch"/>"#
+    );
+    assert_contains!(
+        output,
+        r#"<polygon points="20 12.5, 21.25 10, 38.75 10, 40 12.5, 38.75 15, 21.25 15"/>"#
+    );
+    assert_contains!(output, r#"<text x="30" y="12.5" class="d-text">A</text>"#);
+    assert_contains!(output, r#"<text x="45" y="12.5" class="d-text">B</text>"#);
+}
+
+#[test]
+fn test_wave_bad_pattern() {
+    assert!(transform_str_default(r#"<wave pattern=".h"/>"#).is_err());
+    assert!(transform_str_default(r#"<wave pattern="hq"/>"#).is_err());
+    assert!(transform_str_default(r#"<wave signal="a"/>"#).is_err());
+}